@@ -0,0 +1,99 @@
+//! Proves and verifies the same program over the BLS12-381 scalar field, Goldilocks and
+//! Goldilocks with challenges from its quadratic extension.
+//!
+//! Run with `cargo bench --bench fields`; each size is proven a few times and the fastest
+//! run is reported.
+
+use ark_bls12_381::Fr;
+use ark_ff::{FftField, PrimeField};
+use std::{
+    collections::HashMap,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+use toyni::{
+    math::goldilocks::{Goldilocks, GoldilocksExt2},
+    options::ProofOptions,
+    prover::StarkProver,
+    verifier::StarkVerifier,
+    vm::{constraints::ConstraintSystem, trace::ExecutionTrace},
+};
+
+const LOG_SIZES: [u32; 3] = [8, 10, 12];
+const RUNS: usize = 3;
+
+/// A counter `x` and its running sum `y`.
+fn trace<F: PrimeField>(height: u64) -> ExecutionTrace<F> {
+    let mut trace = ExecutionTrace::new(height, 2);
+    let mut sum = 0;
+    for i in 0..height {
+        let mut row = HashMap::new();
+        row.insert("x".to_string(), i);
+        row.insert("y".to_string(), sum);
+        trace.insert_column(row).unwrap();
+        sum += i;
+    }
+    trace
+}
+
+fn constraints<F: FftField>() -> ConstraintSystem<F> {
+    let mut constraints = ConstraintSystem::<F>::new();
+    constraints.add_transition_constraint(
+        "increment".to_string(),
+        vec!["x".to_string()],
+        Box::new(|current, next| next["x"] - current["x"] - F::one()),
+    );
+    constraints.add_transition_constraint(
+        "sum".to_string(),
+        vec!["x".to_string(), "y".to_string()],
+        Box::new(|current, next| next["y"] - current["y"] - current["x"]),
+    );
+    constraints.add_boundary_assertion("start".to_string(), "x".to_string(), 0, F::zero());
+    constraints
+}
+
+/// Fastest proving and verification time over `RUNS` runs on a trace of `height` rows.
+fn measure<F: FftField>(height: u64) -> (Duration, Duration) {
+    let trace = &trace::<F::BasePrimeField>(height);
+    let constraints = constraints::<F>();
+    let options = ProofOptions::<F>::new();
+    let verifier =
+        StarkVerifier::with_options(&constraints, trace.height as usize, options.clone());
+    let mut prove = Duration::MAX;
+    let mut verify = Duration::MAX;
+    for _ in 0..RUNS {
+        let started = Instant::now();
+        let proof = StarkProver::with_options(trace, &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        prove = prove.min(started.elapsed());
+
+        let started = Instant::now();
+        assert!(verifier.verify(black_box(&proof)));
+        verify = verify.min(started.elapsed());
+    }
+    (prove, verify)
+}
+
+fn main() {
+    println!(
+        "{:>6} {:>7} {:>12} {:>12}",
+        "rows", "field", "prove", "verify"
+    );
+    for log_size in LOG_SIZES {
+        let measurements = [
+            ("Fr", measure::<Fr>(1 << log_size)),
+            ("Gl", measure::<Goldilocks>(1 << log_size)),
+            ("Gl²", measure::<GoldilocksExt2>(1 << log_size)),
+        ];
+        for (field, (prove, verify)) in measurements {
+            println!(
+                "{:>6} {:>7} {:>12.2?} {:>12.2?}",
+                1 << log_size,
+                field,
+                prove,
+                verify
+            );
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Domains and domain points shared by the phases of proof generation.
+//!
+//! `ProverContext` sets up the trace domain and its extended coset once per proof, over
+//! the proving field and over its base prime field, and caches the points of both along
+//! with the values of the trace domain's vanishing polynomial over the coset. The
+//! composition quotient and the DEEP composition read points and zerofier inputs from
+//! here instead of recomputing powers of the generators row by row, and FRI starts
+//! from the same coset.
+
+use ark_bls12_381::Fr;
+use ark_ff::FftField;
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+
+use crate::error::ToyniError;
+use crate::options::ProofOptions;
+
+/// Precomputed domains for proving a trace of a given length.
+#[derive(Debug, Clone)]
+pub struct ProverContext<F: FftField = Fr> {
+    /// Number of trace rows before the blinding rows
+    trace_len: usize,
+    /// The trace domain `H`, including any blinding rows
+    trace_domain: GeneralEvaluationDomain<F>,
+    /// The coset `H` is extended over
+    extended_domain: GeneralEvaluationDomain<F>,
+    /// `trace_domain` over the base prime field, which the trace is committed over
+    base_trace_domain: GeneralEvaluationDomain<F::BasePrimeField>,
+    /// `extended_domain` over the base prime field
+    base_extended_domain: GeneralEvaluationDomain<F::BasePrimeField>,
+    /// Every point of `trace_domain`, in order
+    trace_points: Vec<F>,
+    /// Every point of `extended_domain`, in order
+    extended_points: Vec<F>,
+    /// `Z_H` over the first `blowup_factor` points of `extended_domain`, after which its
+    /// values repeat
+    vanishing: Vec<F>,
+}
+
+impl<F: FftField> ProverContext<F> {
+    /// Sets up the domains for a trace of `trace_len` rows under `options`, for
+    /// constraints reading `window` consecutive rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::NonPowerOfTwoDomain` if the field has no domain of the trace
+    /// domain or extended domain size
+    pub fn new(
+        options: &ProofOptions<F>,
+        trace_len: usize,
+        window: usize,
+    ) -> Result<Self, ToyniError> {
+        let domain_size = options.trace_domain_size(trace_len, window);
+        let extended_size = domain_size * options.blowup_factor;
+        let (base_trace_domain, base_extended_domain) =
+            domains(domain_size, extended_size, options.coset_offset)?;
+        let (trace_domain, extended_domain) = domains(
+            domain_size,
+            extended_size,
+            F::from_base_prime_field(options.coset_offset),
+        )?;
+
+        let extended_points: Vec<F> = extended_domain.elements().collect();
+        let vanishing = extended_points[..options.blowup_factor]
+            .iter()
+            .map(|x| trace_domain.evaluate_vanishing_polynomial(*x))
+            .collect();
+        Ok(Self {
+            trace_len,
+            trace_domain,
+            extended_domain,
+            base_trace_domain,
+            base_extended_domain,
+            trace_points: trace_domain.elements().collect(),
+            extended_points,
+            vanishing,
+        })
+    }
+
+    /// Number of trace rows before the blinding rows.
+    pub fn trace_len(&self) -> usize {
+        self.trace_len
+    }
+
+    /// The trace domain `H`, including any blinding rows.
+    pub fn trace_domain(&self) -> &GeneralEvaluationDomain<F> {
+        &self.trace_domain
+    }
+
+    /// The coset the trace is extended over.
+    pub fn extended_domain(&self) -> &GeneralEvaluationDomain<F> {
+        &self.extended_domain
+    }
+
+    /// The trace domain over the base prime field.
+    pub fn base_trace_domain(&self) -> &GeneralEvaluationDomain<F::BasePrimeField> {
+        &self.base_trace_domain
+    }
+
+    /// The extended coset over the base prime field.
+    pub fn base_extended_domain(&self) -> &GeneralEvaluationDomain<F::BasePrimeField> {
+        &self.base_extended_domain
+    }
+
+    /// The point at position `index` of the trace domain.
+    pub fn trace_point(&self, index: usize) -> F {
+        self.trace_points[index]
+    }
+
+    /// The point at position `index` of the extended coset.
+    pub fn extended_point(&self, index: usize) -> F {
+        self.extended_points[index]
+    }
+
+    /// The vanishing polynomial of the trace domain at position `index` of the extended
+    /// coset.
+    pub fn vanishing(&self, index: usize) -> F {
+        self.vanishing[index % self.vanishing.len()]
+    }
+
+    /// Bytes held by the cached points and values.
+    pub fn heap_size(&self) -> usize {
+        let len = self.trace_points.len() + self.extended_points.len() + self.vanishing.len();
+        len * size_of::<F>()
+    }
+}
+
+/// A domain of `domain_size` points and its extension of `extended_size` points over
+/// the coset of `coset_offset`.
+fn domains<K: FftField>(
+    domain_size: usize,
+    extended_size: usize,
+    coset_offset: K,
+) -> Result<(GeneralEvaluationDomain<K>, GeneralEvaluationDomain<K>), ToyniError> {
+    let domain = GeneralEvaluationDomain::<K>::new(domain_size)
+        .ok_or(ToyniError::NonPowerOfTwoDomain(domain_size))?;
+    let extended_domain = GeneralEvaluationDomain::<K>::new(extended_size)
+        .and_then(|domain| domain.get_coset(coset_offset))
+        .ok_or(ToyniError::NonPowerOfTwoDomain(extended_size))?;
+    Ok((domain, extended_domain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::goldilocks::GoldilocksExt2;
+    use ark_ff::Field;
+
+    #[test]
+    fn test_cached_points_match_domains() {
+        let options = ProofOptions::default().with_blowup_factor(4);
+        let context = ProverContext::<Fr>::new(&options, 8, 2).unwrap();
+        let trace_domain = context.trace_domain();
+        let extended_domain = context.extended_domain();
+        assert_eq!(trace_domain.size(), 8);
+        assert_eq!(extended_domain.size(), 32);
+        assert_eq!(extended_domain.coset_offset(), options.coset_offset);
+
+        for (i, x) in trace_domain.elements().enumerate() {
+            assert_eq!(context.trace_point(i), x);
+        }
+        for (i, x) in extended_domain.elements().enumerate() {
+            assert_eq!(context.extended_point(i), x);
+            assert_eq!(
+                context.vanishing(i),
+                trace_domain.evaluate_vanishing_polynomial(x)
+            );
+        }
+    }
+
+    #[test]
+    fn test_extension_field_domains() {
+        let options = ProofOptions::<GoldilocksExt2>::new();
+        let context = ProverContext::new(&options, 16, 2).unwrap();
+
+        // The base field domains hold the same points as the extension field domains
+        let base_points = context.base_extended_domain().elements();
+        for (i, x) in base_points.enumerate() {
+            assert_eq!(
+                context.extended_point(i),
+                GoldilocksExt2::from_base_prime_field(x)
+            );
+        }
+        assert_eq!(
+            context.base_trace_domain().size(),
+            context.trace_domain().size()
+        );
+    }
+}
@@ -0,0 +1,517 @@
+//! Canonical, versioned binary encoding of STARK proofs and constraint systems.
+//!
+//! A proof is encoded as the magic bytes `TOYN` and a little-endian `u16` format
+//! version, followed by the proof options, the commitments, the public values, the
+//! out-of-domain frame, the FRI proof and the trace openings, in the order of the
+//! `StarkProof` fields. The frame holds the rows at `z` and `ω·z`, the further rows
+//! window constraints read and the quotient chunks; every trace opening ends with the
+//! auxiliary row and its optional Merkle proof.
+//!
+//! Within the body every integer is a little-endian `u64`, every field element its
+//! canonical big-endian bytes (32 for `Fr`) and every boolean or enum a single byte.
+//! Sequences, byte strings and UTF-8 strings are prefixed by their length as a `u64`.
+//! Decoding rejects unknown versions, non-canonical values and trailing bytes, so every
+//! proof has exactly one encoding.
+//!
+//! A constraint system whose constraints are all expressions is encoded the same way,
+//! opened by the magic bytes `TOYC` and its own format version: the transition, window
+//! and boundary constraints as name, variables and encoded `Expr`, with the position of
+//! each boundary constraint as a tag byte for first, last, absolute or from the end and
+//! a row, followed by the private, auxiliary and challenge names, the periodic columns
+//! and the public cells.
+
+use ark_ff::FftField;
+
+use crate::{
+    error::ToyniError,
+    math::{
+        deep::OodFrame,
+        fri::{FriLayerOpening, FriProof, FriQuery},
+        serde_fr,
+    },
+    merkle::{MerkleCap, MerkleProof},
+    options::{ProofOptions, Soundness},
+    prover::{StarkProof, TraceOpening},
+    vm::{
+        constraints::{BoundaryPosition, ConstraintSystem, PublicCell},
+        expr::Expr,
+    },
+};
+
+/// Magic bytes opening every encoded proof
+pub const PROOF_MAGIC: [u8; 4] = *b"TOYN";
+
+/// Version of the layout written by `StarkProof::to_bytes`
+pub const PROOF_VERSION: u16 = 1;
+
+/// Magic bytes opening every encoded constraint system
+pub const CONSTRAINTS_MAGIC: [u8; 4] = *b"TOYC";
+
+/// Version of the layout written by `ConstraintSystem::to_bytes`
+pub const CONSTRAINTS_VERSION: u16 = 1;
+
+impl<F: FftField> StarkProof<F> {
+    /// Encodes the proof in the canonical binary layout of `PROOF_VERSION`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(&PROOF_MAGIC);
+        writer.bytes.extend_from_slice(&PROOF_VERSION.to_le_bytes());
+        writer.options(&self.options);
+        writer.cap(&self.trace_commitment);
+        writer.sequence(&self.trace_variables, Writer::string);
+        writer.sequence(&self.trace_randomizers, Writer::string);
+        writer.sequence(&self.public_values, |writer, (name, value)| {
+            writer.string(name);
+            writer.field_element(value);
+        });
+        writer.cap(&self.auxiliary_commitment);
+        writer.cap(&self.quotient_commitment);
+        writer.field_elements(&self.ood_frame.current);
+        writer.field_elements(&self.ood_frame.next);
+        writer.sequence(&self.ood_frame.window, |writer, row| {
+            writer.field_elements(row)
+        });
+        writer.field_elements(&self.ood_frame.quotient);
+        writer.cap(&self.deep_commitment);
+        writer.fri_proof(&self.fri_proof);
+        writer.sequence(&self.trace_openings, |writer, openings| {
+            writer.sequence(openings, Writer::trace_opening)
+        });
+        writer.bytes
+    }
+
+    /// Decodes a proof written by `to_bytes`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::UnsupportedProofVersion` for a format version other than
+    /// `PROOF_VERSION`, and `ToyniError::MalformedProof` if the bytes do not follow the
+    /// layout exactly.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ToyniError> {
+        let mut reader = Reader { bytes };
+        if reader.take(PROOF_MAGIC.len())? != PROOF_MAGIC {
+            return Err(malformed("missing magic bytes"));
+        }
+        let version = u16::from_le_bytes(reader.take(2)?.try_into().unwrap());
+        if version != PROOF_VERSION {
+            return Err(ToyniError::UnsupportedProofVersion(version));
+        }
+
+        let proof = StarkProof {
+            options: reader.options()?,
+            trace_commitment: reader.cap()?,
+            trace_variables: reader.sequence(Reader::string)?,
+            trace_randomizers: reader.sequence(Reader::string)?,
+            public_values: reader
+                .sequence(|reader| Ok((reader.string()?, reader.field_element()?)))?,
+            auxiliary_commitment: reader.cap()?,
+            quotient_commitment: reader.cap()?,
+            ood_frame: OodFrame {
+                current: reader.field_elements()?,
+                next: reader.field_elements()?,
+                window: reader.sequence(Reader::field_elements)?,
+                quotient: reader.field_elements()?,
+            },
+            deep_commitment: reader.cap()?,
+            fri_proof: reader.fri_proof()?,
+            trace_openings: reader.sequence(|reader| reader.sequence(Reader::trace_opening))?,
+        };
+        if !reader.bytes.is_empty() {
+            return Err(malformed("trailing bytes after the proof"));
+        }
+        Ok(proof)
+    }
+}
+
+impl<F: FftField> ConstraintSystem<F> {
+    /// Encodes the constraint system in the canonical binary layout of
+    /// `CONSTRAINTS_VERSION`, so a verifier can load it with `from_bytes`.
+    ///
+    /// Degrees and window lengths are not encoded, as they follow from the expressions.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::UnencodableConstraint` naming the first constraint given as
+    /// a closure instead of an expression
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ToyniError> {
+        let expression = |name: &String, expression: &Option<Expr<F>>| {
+            expression
+                .as_ref()
+                .map(Expr::to_bytes)
+                .ok_or_else(|| ToyniError::UnencodableConstraint(name.clone()))
+        };
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(&CONSTRAINTS_MAGIC);
+        writer
+            .bytes
+            .extend_from_slice(&CONSTRAINTS_VERSION.to_le_bytes());
+        writer.integer(self.transition_constraints.len());
+        for constraint in &self.transition_constraints {
+            writer.string(&constraint.name);
+            writer.sequence(&constraint.variables, Writer::string);
+            writer.byte_string(&expression(&constraint.name, &constraint.expression)?);
+        }
+        writer.integer(self.window_constraints.len());
+        for constraint in &self.window_constraints {
+            writer.string(&constraint.name);
+            writer.sequence(&constraint.variables, Writer::string);
+            writer.byte_string(&expression(&constraint.name, &constraint.expression)?);
+        }
+        writer.integer(self.boundary_constraints.len());
+        for constraint in &self.boundary_constraints {
+            writer.string(&constraint.name);
+            let (tag, row) = constraint.position.encode();
+            writer.byte(tag);
+            writer.integer(row as usize);
+            writer.sequence(&constraint.variables, Writer::string);
+            writer.byte_string(&expression(&constraint.name, &constraint.expression)?);
+        }
+        writer.sequence(&self.private_columns, Writer::string);
+        writer.sequence(&self.auxiliary_columns, Writer::string);
+        writer.sequence(&self.challenges, Writer::string);
+        writer.sequence(&self.periodic_columns, |writer, column| {
+            writer.string(&column.name);
+            writer.field_elements(column.values());
+        });
+        writer.sequence(&self.public_cells, |writer, cell| {
+            writer.string(&cell.name);
+            writer.string(&cell.variable);
+            writer.integer(cell.row as usize);
+        });
+        Ok(writer.bytes)
+    }
+
+    /// Decodes a constraint system written by `to_bytes`.
+    ///
+    /// The decoded system holds the same constraints in the same order, so its
+    /// `digest` matches that of the encoded one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::MalformedConstraints` if the bytes do not follow the layout
+    /// of `CONSTRAINTS_VERSION` exactly, and the error of adding a constraint or
+    /// periodic column if an expression or period is invalid, such as
+    /// `ToyniError::InvalidExpression` for an expression nested too deeply, of a degree
+    /// or window beyond any evaluation domain
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ToyniError> {
+        Self::decode(bytes).map_err(|error| match error {
+            ToyniError::MalformedProof(reason) => ToyniError::MalformedConstraints(reason),
+            error => error,
+        })
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, ToyniError> {
+        let mut reader = Reader { bytes };
+        if reader.take(CONSTRAINTS_MAGIC.len())? != CONSTRAINTS_MAGIC {
+            return Err(malformed("missing magic bytes"));
+        }
+        let version = u16::from_le_bytes(reader.take(2)?.try_into().unwrap());
+        if version != CONSTRAINTS_VERSION {
+            return Err(malformed(&format!("unsupported version {}", version)));
+        }
+
+        let mut system = ConstraintSystem::new();
+        let constraint = |reader: &mut Reader| {
+            Ok((
+                reader.string()?,
+                reader.sequence(Reader::string)?,
+                reader.expression()?,
+            ))
+        };
+        for (name, variables, expression) in reader.sequence(constraint)? {
+            system.add_transition_expr(name, variables, expression)?;
+        }
+        for (name, variables, expression) in reader.sequence(constraint)? {
+            system.add_window_expr(name, variables, expression)?;
+        }
+        let boundary = |reader: &mut Reader| {
+            Ok((
+                reader.string()?,
+                reader.position()?,
+                reader.sequence(Reader::string)?,
+                reader.expression()?,
+            ))
+        };
+        for (name, position, variables, expression) in reader.sequence(boundary)? {
+            system.add_boundary_expr_at(name, position, variables, expression)?;
+        }
+        system.private_columns = reader.sequence(Reader::string)?;
+        system.auxiliary_columns = reader.sequence(Reader::string)?;
+        system.challenges = reader.sequence(Reader::string)?;
+        for (name, values) in
+            reader.sequence(|reader| Ok((reader.string()?, reader.field_elements()?)))?
+        {
+            system.add_periodic_column(name, values)?;
+        }
+        system.public_cells = reader.sequence(|reader| {
+            Ok(PublicCell {
+                name: reader.string()?,
+                variable: reader.string()?,
+                row: reader.u64()?,
+            })
+        })?;
+        if !reader.bytes.is_empty() {
+            return Err(malformed("trailing bytes after the constraint system"));
+        }
+        Ok(system)
+    }
+}
+
+fn malformed(reason: &str) -> ToyniError {
+    ToyniError::MalformedProof(reason.to_string())
+}
+
+/// Appends values to an encoding.
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn byte(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn integer(&mut self, value: usize) {
+        self.bytes.extend_from_slice(&(value as u64).to_le_bytes());
+    }
+
+    fn field_element<F: FftField>(&mut self, value: &F) {
+        self.bytes.extend_from_slice(&serde_fr::to_bytes(value));
+    }
+
+    fn byte_string(&mut self, value: &[u8]) {
+        self.integer(value.len());
+        self.bytes.extend_from_slice(value);
+    }
+
+    fn string(&mut self, value: &String) {
+        self.byte_string(value.as_bytes());
+    }
+
+    fn sequence<T>(&mut self, values: &[T], mut write: impl FnMut(&mut Self, &T)) {
+        self.integer(values.len());
+        for value in values {
+            write(self, value);
+        }
+    }
+
+    fn field_elements<F: FftField>(&mut self, values: &[F]) {
+        self.sequence(values, Writer::field_element);
+    }
+
+    fn cap(&mut self, cap: &MerkleCap) {
+        self.sequence(cap, |writer, node| writer.byte_string(node));
+    }
+
+    fn merkle_proof(&mut self, proof: &MerkleProof) {
+        self.sequence(&proof.path, |writer, node| writer.byte_string(node));
+        self.sequence(&proof.position, |writer, position| {
+            writer.integer(*position)
+        });
+    }
+
+    fn options<F: FftField>(&mut self, options: &ProofOptions<F>) {
+        self.integer(options.blowup_factor);
+        self.integer(options.merkle_cap_height);
+        self.integer(options.fri_remainder_size);
+        self.sequence(&options.fri_folding_schedule, |writer, factor| {
+            writer.integer(*factor)
+        });
+        self.integer(options.fri_queries);
+        match options.security_bits {
+            Some(bits) => {
+                self.byte(1);
+                self.integer(bits);
+            }
+            None => self.byte(0),
+        }
+        self.byte(match options.soundness {
+            Soundness::Conjectured => 0,
+            Soundness::Proven => 1,
+        });
+        self.integer(options.grinding_bits);
+        self.integer(options.composition_chunks);
+        self.field_element(&options.coset_offset);
+        self.integer(options.blinding_rows);
+        self.byte(u8::from(options.zk));
+    }
+
+    fn fri_proof<F: FftField>(&mut self, proof: &FriProof<F>) {
+        self.field_elements(&proof.challenges);
+        self.sequence(&proof.layer_caps, Writer::cap);
+        self.sequence(&proof.queries, |writer, query| {
+            writer.sequence(&query.layers, |writer, layer| {
+                writer.integer(layer.index);
+                writer.field_elements(&layer.values);
+                writer.sequence(&layer.proofs, Writer::merkle_proof);
+            })
+        });
+        self.field_elements(&proof.final_poly);
+        self.bytes.extend_from_slice(&proof.pow_nonce.to_le_bytes());
+    }
+
+    fn trace_opening<F: FftField>(&mut self, opening: &TraceOpening<F>) {
+        self.integer(opening.index);
+        self.field_elements(&opening.row);
+        self.byte_string(&opening.salt);
+        self.merkle_proof(&opening.proof);
+        self.field_elements(&opening.quotient);
+        self.merkle_proof(&opening.quotient_proof);
+        self.field_elements(&opening.auxiliary_row);
+        match &opening.auxiliary_proof {
+            Some(proof) => {
+                self.byte(1);
+                self.merkle_proof(proof);
+            }
+            None => self.byte(0),
+        }
+    }
+}
+
+/// Consumes values from the front of an encoding.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ToyniError> {
+        if self.bytes.len() < len {
+            return Err(malformed("unexpected end of input"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, ToyniError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn flag(&mut self) -> Result<bool, ToyniError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(malformed("invalid boolean")),
+        }
+    }
+
+    fn u64(&mut self) -> Result<u64, ToyniError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn integer(&mut self) -> Result<usize, ToyniError> {
+        usize::try_from(self.u64()?).map_err(|_| malformed("integer out of range"))
+    }
+
+    fn field_element<F: FftField>(&mut self) -> Result<F, ToyniError> {
+        serde_fr::decode(self.take(serde_fr::encoded_len::<F>())?).map_err(malformed)
+    }
+
+    fn byte_string(&mut self) -> Result<Vec<u8>, ToyniError> {
+        let len = self.integer()?;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn string(&mut self) -> Result<String, ToyniError> {
+        String::from_utf8(self.byte_string()?).map_err(|_| malformed("invalid UTF-8 string"))
+    }
+
+    /// Reads a length-prefixed sequence. Every item consumes at least one byte, so a
+    /// corrupted length fails on the missing input instead of allocating for it.
+    fn sequence<T>(
+        &mut self,
+        mut read: impl FnMut(&mut Self) -> Result<T, ToyniError>,
+    ) -> Result<Vec<T>, ToyniError> {
+        let len = self.integer()?;
+        if len > self.bytes.len() {
+            return Err(malformed("sequence longer than the input"));
+        }
+        (0..len).map(|_| read(self)).collect()
+    }
+
+    fn field_elements<F: FftField>(&mut self) -> Result<Vec<F>, ToyniError> {
+        self.sequence(Reader::field_element)
+    }
+
+    fn position(&mut self) -> Result<BoundaryPosition, ToyniError> {
+        let tag = self.byte()?;
+        BoundaryPosition::decode(tag, self.u64()?).ok_or(malformed("invalid boundary position"))
+    }
+
+    fn expression<F: FftField>(&mut self) -> Result<Expr<F>, ToyniError> {
+        Expr::from_bytes(&self.byte_string()?)
+    }
+
+    fn cap(&mut self) -> Result<MerkleCap, ToyniError> {
+        self.sequence(Reader::byte_string)
+    }
+
+    fn merkle_proof(&mut self) -> Result<MerkleProof, ToyniError> {
+        Ok(MerkleProof {
+            path: self.sequence(Reader::byte_string)?,
+            position: self.sequence(Reader::integer)?,
+        })
+    }
+
+    fn options<F: FftField>(&mut self) -> Result<ProofOptions<F>, ToyniError> {
+        Ok(ProofOptions {
+            blowup_factor: self.integer()?,
+            merkle_cap_height: self.integer()?,
+            fri_remainder_size: self.integer()?,
+            fri_folding_schedule: self.sequence(Reader::integer)?,
+            fri_queries: self.integer()?,
+            security_bits: match self.flag()? {
+                true => Some(self.integer()?),
+                false => None,
+            },
+            soundness: match self.byte()? {
+                0 => Soundness::Conjectured,
+                1 => Soundness::Proven,
+                _ => return Err(malformed("unknown soundness bound")),
+            },
+            grinding_bits: self.integer()?,
+            composition_chunks: self.integer()?,
+            coset_offset: self.field_element()?,
+            blinding_rows: self.integer()?,
+            zk: self.flag()?,
+            memory_budget: None,
+        })
+    }
+
+    fn fri_proof<F: FftField>(&mut self) -> Result<FriProof<F>, ToyniError> {
+        Ok(FriProof {
+            challenges: self.field_elements()?,
+            layer_caps: self.sequence(Reader::cap)?,
+            queries: self.sequence(|reader| {
+                Ok(FriQuery {
+                    layers: reader.sequence(|reader| {
+                        Ok(FriLayerOpening {
+                            index: reader.integer()?,
+                            values: reader.field_elements()?,
+                            proofs: reader.sequence(Reader::merkle_proof)?,
+                        })
+                    })?,
+                })
+            })?,
+            final_poly: self.field_elements()?,
+            pow_nonce: self.u64()?,
+        })
+    }
+
+    fn trace_opening<F: FftField>(&mut self) -> Result<TraceOpening<F>, ToyniError> {
+        Ok(TraceOpening {
+            index: self.integer()?,
+            row: self.field_elements()?,
+            salt: self.byte_string()?,
+            proof: self.merkle_proof()?,
+            quotient: self.field_elements()?,
+            quotient_proof: self.merkle_proof()?,
+            auxiliary_row: self.field_elements()?,
+            auxiliary_proof: match self.flag()? {
+                true => Some(self.merkle_proof()?),
+                false => None,
+            },
+        })
+    }
+}
@@ -0,0 +1,186 @@
+//! Errors returned by the library instead of panicking on invalid input.
+
+use std::fmt;
+
+use crate::vm::trace::ProgramVariable;
+
+/// Error type shared by trace construction, constraint evaluation and proving.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToyniError {
+    /// The trace holds fewer rows than its declared height
+    TraceTooShort { rows: usize, height: u64 },
+    /// A row was inserted into a trace that already holds `height` rows
+    TraceFull { height: u64 },
+    /// A row does not hold one value per trace column
+    WidthMismatch { expected: u64, actual: usize },
+    /// A column whose length differs from the other columns of the trace
+    ColumnLengthMismatch {
+        column: ProgramVariable,
+        expected: usize,
+        actual: usize,
+    },
+    /// A row whose variables differ from the columns of the trace
+    ColumnSchemaMismatch {
+        missing: Vec<ProgramVariable>,
+        extra: Vec<ProgramVariable>,
+    },
+    /// A row index beyond the rows recorded in the trace
+    RowOutOfBounds { index: u64, rows: usize },
+    /// A variable that is not a column of the trace
+    UnknownVariable(ProgramVariable),
+    /// A column name that is already taken
+    DuplicateColumn(ProgramVariable),
+    /// An interpolation parameter outside `0..=100`
+    InvalidInterpolationParameter(u8),
+    /// A domain size that is not a power of two
+    NonPowerOfTwoDomain(usize),
+    /// Division by the zero polynomial
+    DivisionByZeroPoly,
+    /// Evaluations that do not hold one value per point of their domain
+    DomainMismatch { expected: usize, actual: usize },
+    /// A constraint of higher degree than the blowup factor allows
+    InsufficientBlowup {
+        constraint: String,
+        degree: usize,
+        required: usize,
+        blowup: usize,
+    },
+    /// A composition polynomial of degree not below the size of the extended domain
+    CompositionDegreeTooHigh { degree: usize, extended_size: usize },
+    /// A constraint of higher degree than it was declared with
+    UndeclaredConstraintDegree { constraint: String, declared: usize },
+    /// A constraint that does not hold on the trace
+    UnsatisfiedConstraint(String),
+    /// Proof options that are inconsistent with each other, the trace or the constraints
+    InvalidOptions(String),
+    /// An encoded proof with a format version this library cannot decode
+    UnsupportedProofVersion(u16),
+    /// Bytes that are not a well-formed encoded proof
+    MalformedProof(String),
+    /// Proof generation was cancelled by its observer
+    Cancelled,
+    /// A builder was finished without setting a required field
+    IncompleteBuilder(&'static str),
+    /// An auxiliary column that is missing, undeclared or not one value per trace row
+    InvalidAuxiliaryColumn(ProgramVariable),
+    /// A constraint expression that is malformed or reads columns it was not given
+    InvalidExpression(String),
+    /// Bytes or text that are not a well-formed exported trace
+    MalformedTrace(String),
+    /// A constraint given as a closure, which has no encoding
+    UnencodableConstraint(String),
+    /// Bytes that are not a well-formed encoded constraint system
+    MalformedConstraints(String),
+    /// The out-of-domain point drawn from the oracle lies on an evaluation domain
+    PointOnDomain,
+    /// Reading or writing an exported trace failed
+    Io(String),
+}
+
+impl fmt::Display for ToyniError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToyniError::TraceTooShort { rows, height } => {
+                write!(f, "trace holds {} rows but has height {}", rows, height)
+            }
+            ToyniError::TraceFull { height } => {
+                write!(f, "trace already holds all {} rows", height)
+            }
+            ToyniError::WidthMismatch { expected, actual } => {
+                write!(f, "row has {} values, expected {}", actual, expected)
+            }
+            ToyniError::ColumnLengthMismatch {
+                column,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "column {} has {} values, expected {}",
+                column, actual, expected
+            ),
+            ToyniError::ColumnSchemaMismatch { missing, extra } => write!(
+                f,
+                "row is missing variables {:?} and has unknown variables {:?}",
+                missing, extra
+            ),
+            ToyniError::RowOutOfBounds { index, rows } => {
+                write!(f, "row {} is out of bounds for {} rows", index, rows)
+            }
+            ToyniError::UnknownVariable(variable) => write!(f, "unknown variable {}", variable),
+            ToyniError::DuplicateColumn(column) => {
+                write!(f, "column name {} is already in use", column)
+            }
+            ToyniError::InvalidInterpolationParameter(t) => {
+                write!(f, "interpolation parameter {} is not between 0 and 100", t)
+            }
+            ToyniError::NonPowerOfTwoDomain(size) => {
+                write!(f, "domain size {} is not a power of two", size)
+            }
+            ToyniError::DivisionByZeroPoly => write!(f, "division by the zero polynomial"),
+            ToyniError::DomainMismatch { expected, actual } => {
+                write!(f, "expected {} evaluations, got {}", expected, actual)
+            }
+            ToyniError::InsufficientBlowup {
+                constraint,
+                degree,
+                required,
+                blowup,
+            } => write!(
+                f,
+                "constraint {} of degree {} needs a blowup factor of at least {}, got {}",
+                constraint, degree, required, blowup
+            ),
+            ToyniError::CompositionDegreeTooHigh {
+                degree,
+                extended_size,
+            } => write!(
+                f,
+                "composition degree {} is not below the extended domain size {}",
+                degree, extended_size
+            ),
+            ToyniError::UndeclaredConstraintDegree {
+                constraint,
+                declared,
+            } => write!(
+                f,
+                "constraint {} has a higher degree than its declared degree {}",
+                constraint, declared
+            ),
+            ToyniError::UnsatisfiedConstraint(name) => {
+                write!(f, "constraint {} is not satisfied", name)
+            }
+            ToyniError::InvalidOptions(reason) => write!(f, "invalid proof options: {}", reason),
+            ToyniError::UnsupportedProofVersion(version) => {
+                write!(f, "unsupported proof format version {}", version)
+            }
+            ToyniError::MalformedProof(reason) => write!(f, "malformed proof: {}", reason),
+            ToyniError::Cancelled => write!(f, "proof generation was cancelled"),
+            ToyniError::IncompleteBuilder(field) => write!(f, "builder is missing the {}", field),
+            ToyniError::InvalidAuxiliaryColumn(column) => {
+                write!(
+                    f,
+                    "auxiliary column {} does not match its declaration",
+                    column
+                )
+            }
+            ToyniError::InvalidExpression(reason) => {
+                write!(f, "invalid constraint expression: {}", reason)
+            }
+            ToyniError::MalformedTrace(reason) => write!(f, "malformed trace: {}", reason),
+            ToyniError::UnencodableConstraint(name) => write!(
+                f,
+                "constraint {} is given as a closure and cannot be encoded",
+                name
+            ),
+            ToyniError::MalformedConstraints(reason) => {
+                write!(f, "malformed constraint system: {}", reason)
+            }
+            ToyniError::PointOnDomain => {
+                write!(f, "out-of-domain point lies on the evaluation domains")
+            }
+            ToyniError::Io(reason) => write!(f, "trace I/O failed: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ToyniError {}
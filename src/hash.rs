@@ -0,0 +1,90 @@
+//! Hash backends for Merkle commitments.
+//!
+//! `MerkleTree` is generic over the `Hasher` trait so commitments can be built with
+//! whichever hash function the proof consumer can verify cheaply:
+//!
+//! * `Sha256Hasher` - the default, matches `digest_sha2`
+//! * `Blake3Hasher` - fast native hashing
+//! * `Keccak256Hasher` - cheap to verify on EVM chains
+
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+use std::fmt;
+
+/// Hash function used to build Merkle trees.
+pub trait Hasher: Clone + fmt::Debug {
+    /// Hashes arbitrary bytes into a digest.
+    fn hash(data: &[u8]) -> Vec<u8>;
+
+    /// Hashes the child nodes of one group (two for binary trees) into their parent node.
+    fn merge(children: &[&[u8]]) -> Vec<u8> {
+        Self::hash(&children.concat())
+    }
+}
+
+/// SHA-256 hasher.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Hasher;
+
+impl Hasher for Sha256Hasher {
+    fn hash(data: &[u8]) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        hasher.finalize().to_vec()
+    }
+}
+
+/// BLAKE3 hasher.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake3Hasher;
+
+impl Hasher for Blake3Hasher {
+    fn hash(data: &[u8]) -> Vec<u8> {
+        blake3::hash(data).as_bytes().to_vec()
+    }
+}
+
+/// Keccak-256 hasher (the Ethereum variant, not NIST SHA3-256).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Keccak256Hasher;
+
+impl Hasher for Keccak256Hasher {
+    fn hash(data: &[u8]) -> Vec<u8> {
+        let mut hasher = Keccak256::new();
+        hasher.update(data);
+        hasher.finalize().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashers_are_distinct_and_deterministic() {
+        let data = b"toyni";
+        let digests = [
+            Sha256Hasher::hash(data),
+            Blake3Hasher::hash(data),
+            Keccak256Hasher::hash(data),
+        ];
+        for (i, digest) in digests.iter().enumerate() {
+            assert_eq!(digest.len(), 32);
+            for other in &digests[i + 1..] {
+                assert_ne!(digest, other);
+            }
+        }
+        assert_eq!(Blake3Hasher::hash(data), Blake3Hasher::hash(data));
+    }
+
+    #[test]
+    fn test_keccak256_known_answer() {
+        // Keccak-256 of the empty string
+        let digest = Keccak256Hasher::hash(b"");
+        assert_eq!(
+            digest[..4],
+            [0xc5, 0xd2, 0x46, 0x01],
+            "Keccak256Hasher must use the original Keccak padding"
+        );
+    }
+}
@@ -11,14 +11,30 @@
 //!
 //! * `math` - Mathematical utilities for polynomial operations and FRI protocol
 //! * `vm` - Virtual machine implementation with execution tracing
+//! * `merkle` / `hash` - Merkle commitments over pluggable hash functions
+//! * `transcript` - Fiat-Shamir transcript and random oracle abstraction
+//! * `options` - Proof configuration shared by prover and verifier
+//! * `error` - The `ToyniError` type returned instead of panicking on invalid input
+//! * `encoding` - Canonical, versioned binary encoding of proofs
+//! * `progress` - Progress callbacks and cooperative cancellation for the prover
+//! * `metrics` - Per-phase memory and time reported by the prover
 
 use sha2::{Digest, Sha256};
 
+pub mod error;
+pub mod encoding;
 pub mod math;
+pub mod hash;
 pub mod merkle;
+pub mod options;
 pub mod vm;
 pub mod prover;
+pub mod context;
+pub mod progress;
+pub mod metrics;
 pub mod verifier;
+pub mod transcript;
+mod parallel;
 
 pub fn digest_sha2(data: &[u8]) -> [u8; 32] {
     let mut hasher = Sha256::new();
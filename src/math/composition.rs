@@ -1,24 +1,37 @@
 //! Composition polynomial for STARK proofs.
 //!
-//! Combines trace and constraints into H(x) = T(x) + Z_H(x) * sum(C_i(x)).
+//! Combines trace and constraints into H(x) = T(x) + Z_H(x) * sum(C_i(x)), or, as used by
+//! the prover, into the quotient H(x) = sum((α_i + β_i * x^d_i) * C_i(x) / Z_i(x)) over the
+//! low-degree extension of the trace.
 
 use ark_bls12_381::Fr;
-use ark_ff::{Field, One, Zero};
+use ark_ff::{AdditiveGroup, FftField, Field};
 use ark_poly::{
-    EvaluationDomain, Evaluations, GeneralEvaluationDomain, Polynomial, univariate::DensePolynomial,
+    DenseUVPolynomial, EvaluationDomain, Evaluations, GeneralEvaluationDomain, Polynomial,
+    univariate::DensePolynomial,
 };
+use std::collections::HashMap;
 
-use crate::vm::{constraints::ConstraintSystem, trace::ExecutionTrace};
+use crate::{
+    context::ProverContext,
+    error::ToyniError,
+    math::{eval_poly::EvalPoly, lde::TraceLde},
+    vm::{
+        auxiliary::AuxiliarySegment,
+        constraints::ConstraintSystem,
+        trace::{ExecutionTrace, ProgramVariable},
+    },
+};
 
 /// Polynomial combining trace and constraints for STARK proofs.
-pub struct CompositionPolynomial {
+pub struct CompositionPolynomial<F: FftField = Fr> {
     /// The composed polynomial H(x)
-    polynomial: DensePolynomial<Fr>,
+    polynomial: DensePolynomial<F>,
     /// The evaluation domain
-    domain: GeneralEvaluationDomain<Fr>,
+    domain: GeneralEvaluationDomain<F>,
 }
 
-impl CompositionPolynomial {
+impl<F: FftField> CompositionPolynomial<F> {
     /// Creates composition polynomial from trace and constraints.
     ///
     /// # Arguments
@@ -31,11 +44,11 @@ impl CompositionPolynomial {
     ///
     /// A new composition polynomial that encodes all constraints
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if:
-    /// * The domain size is not a power of 2
-    /// * The trace height is greater than the domain size
+    /// Returns an error if:
+    /// * The trace holds fewer rows than its height
+    /// * The trace height is not a power of 2
     ///
     /// # Security Note
     ///
@@ -44,69 +57,184 @@ impl CompositionPolynomial {
     /// - No random masks are applied
     /// - The composition is not blinded
     pub fn new(
-        trace: &ExecutionTrace,
-        constraints: &ConstraintSystem,
-        domain: GeneralEvaluationDomain<Fr>,
-    ) -> Self {
+        trace: &ExecutionTrace<F::BasePrimeField>,
+        constraints: &ConstraintSystem<F>,
+        domain: GeneralEvaluationDomain<F>,
+    ) -> Result<Self, ToyniError> {
+        trace.validate()?;
+        let original_size = trace.height as usize;
+        if !original_size.is_power_of_two() {
+            return Err(ToyniError::NonPowerOfTwoDomain(original_size));
+        }
+
         // Create constraint polynomial evaluations
-        let mut constraint_evals = vec![Fr::zero(); domain.size()];
+        let mut constraint_evals = vec![F::zero(); domain.size()];
 
         // First, evaluate constraints on the original domain points
-        let original_size = trace.height as usize;
         for (i, eval) in constraint_evals.iter_mut().enumerate().take(original_size) {
-            let current_row = trace.get_column(i as u64);
-            let next_row = trace.get_column(((i + 1) % original_size) as u64);
+            let current_row = constraints.trace_row(trace, i as u64)?;
+            let next_row = constraints.trace_row(trace, ((i + 1) % original_size) as u64)?;
 
             for constraint in &constraints.transition_constraints {
-                let constraint_eval = (constraint.evaluate)(current_row, next_row);
+                let constraint_eval = (constraint.evaluate)(&current_row, &next_row);
                 *eval += constraint_eval;
             }
         }
 
+        // Evaluate window constraints, over windows wrapping around like the transitions
+        for constraint in &constraints.window_constraints {
+            for (i, eval) in constraint_evals.iter_mut().enumerate().take(original_size) {
+                let rows = (i..i + constraint.rows)
+                    .map(|j| constraints.trace_row(trace, (j % original_size) as u64))
+                    .collect::<Result<Vec<_>, _>>()?;
+                *eval += (constraint.evaluate)(&rows);
+            }
+        }
+
         // Evaluate boundary constraints
         for constraint in &constraints.boundary_constraints {
-            let row = trace.get_column(constraint.row);
-            let eval = (constraint.evaluate)(row);
-            constraint_evals[constraint.row as usize] += eval;
+            let index = constraint.row(original_size);
+            let row = constraints.trace_row(trace, index)?;
+            let eval = (constraint.evaluate)(&row);
+            constraint_evals[index as usize] += eval;
         }
 
-        // If we have an extended domain, interpolate the constraint evaluations
-        if domain.size() > original_size {
-            // Create a polynomial from the original evaluations
-            let original_domain = GeneralEvaluationDomain::new(original_size).unwrap();
-            let constraint_poly = Evaluations::from_vec_and_domain(
-                constraint_evals[..original_size].to_vec(),
-                original_domain,
-            )
-            .interpolate();
-
-            // Evaluate this polynomial over the extended domain
-            constraint_evals = domain.fft(&constraint_poly.coeffs);
-        }
+        // Interpolate constraint polynomial sum(C_i(x)) from the original domain points,
+        // which already determine it on an extended domain
+        let constraint_domain = if domain.size() > original_size {
+            GeneralEvaluationDomain::new(original_size).unwrap()
+        } else {
+            domain
+        };
+        constraint_evals.truncate(constraint_domain.size());
+        let constraint_poly = DensePolynomial::from_coefficients_vec(
+            EvalPoly::new(constraint_evals, constraint_domain)?
+                .interpolate()
+                .coefficients,
+        );
 
-        // Interpolate constraint polynomial sum(C_i(x))
-        let constraint_poly =
-            Evaluations::from_vec_and_domain(constraint_evals, domain).interpolate();
-
-        // Create the vanishing polynomial Z_H(x)
-        let mut z_h_evals = vec![Fr::one(); domain.size()];
-        let omega = domain.element(1); // ω is the generator
-        for (i, eval) in z_h_evals.iter_mut().enumerate().take(domain.size()) {
-            let x = domain.element(i);
-            for j in 0..domain.size() {
-                let omega_j = omega.pow([j as u64]);
-                *eval *= x - omega_j;
-            }
-        }
-        let z_h = Evaluations::from_vec_and_domain(z_h_evals, domain).interpolate();
+        // Create the vanishing polynomial Z_H(x) = x^n - 1 in closed form
+        let z_h: DensePolynomial<F> = domain.vanishing_polynomial().into();
 
         // Compute H(x) = Z_H(x) * sum(C_i(x))
         let composition = &z_h * &constraint_poly;
 
-        Self {
+        Ok(Self {
             polynomial: composition,
             domain,
-        }
+        })
+    }
+
+    /// Creates the composition quotient from the low-degree extension of the trace.
+    ///
+    /// Every constraint is evaluated pointwise on the extended rows at `x, ω·x, ...`,
+    /// lifted from the base prime field into `F`, divided by its zerofier and
+    /// degree-adjusted, see `ConstraintSystem::evaluate_composition`. The rows of an
+    /// auxiliary segment and its challenges, the values of every periodic column and the
+    /// public values are added to the rows of the main trace before the constraints read
+    /// them. Columns beyond `variables` are randomizer or mask columns: constraints
+    /// cannot read them, and their combination, see `combine_unconstrained`, is added to
+    /// the composition to hide it.
+    ///
+    /// # Arguments
+    ///
+    /// * `variables` - Names of the trace columns
+    /// * `lde` - Trace columns, followed by any randomizer and mask columns, extended over
+    ///   a coset disjoint from `H`
+    /// * `auxiliary` - The auxiliary segment, extended over the same coset, if any
+    /// * `public_values` - Name and value of every public cell
+    /// * `constraints` - The constraint system defining program rules
+    /// * `context` - The trace domain `H`, including any blinding rows, the coset the
+    ///   trace is extended over and their cached points
+    /// * `alphas`, `betas` - Combination and degree-adjustment coefficients
+    ///
+    /// # Panics
+    ///
+    /// Panics if a zerofier vanishes on the extended coset
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_trace_lde(
+        variables: &[ProgramVariable],
+        lde: &TraceLde<F::BasePrimeField>,
+        auxiliary: Option<&AuxiliarySegment<F>>,
+        public_values: &[(String, F)],
+        constraints: &ConstraintSystem<F>,
+        context: &ProverContext<F>,
+        alphas: &[F],
+        betas: &[F],
+    ) -> Self {
+        let domain_size = context.trace_domain().size();
+        let trace_len = context.trace_len();
+        let adjustments = constraints.degree_adjustments(domain_size, trace_len);
+        let extended_len = context.extended_domain().size();
+        let blowup_factor = extended_len / domain_size;
+        let periodic: Vec<Vec<F>> = constraints
+            .periodic_columns
+            .iter()
+            .map(|column| column.evaluate_over(context.extended_domain(), domain_size))
+            .collect();
+        let lde_row = |row: &[F::BasePrimeField], index: usize| -> HashMap<ProgramVariable, F> {
+            let mut values: HashMap<ProgramVariable, F> = variables
+                .iter()
+                .zip(row)
+                .map(|(variable, value)| (variable.clone(), F::from_base_prime_field(*value)))
+                .collect();
+            if let Some(auxiliary) = auxiliary {
+                auxiliary.extend_row(&mut values, index);
+            }
+            for (column, cycle) in constraints.periodic_columns.iter().zip(&periodic) {
+                values.insert(column.name.clone(), cycle[index % cycle.len()]);
+            }
+            values.extend(public_values.iter().cloned());
+            values
+        };
+
+        let evals = lde.map_windows(constraints.window(), |i, rows| {
+            let window: Vec<HashMap<ProgramVariable, F>> = rows
+                .iter()
+                .enumerate()
+                .map(|(j, row)| lde_row(row, (i + j * blowup_factor) % extended_len))
+                .collect();
+            let constraint_values = constraints.evaluate_rows(&window);
+            let unconstrained: Vec<F> = rows[0][variables.len()..]
+                .iter()
+                .map(|value| F::from_base_prime_field(*value))
+                .collect();
+            let x = context.extended_point(i);
+            let zerofier_invs = constraints.zerofier_inverses(
+                |j| context.trace_point(j),
+                domain_size,
+                trace_len,
+                x,
+                context.vanishing(i),
+            );
+            ConstraintSystem::combine_quotients(
+                &constraint_values,
+                alphas,
+                betas,
+                &zerofier_invs,
+                &adjustments,
+                x,
+            ) + combine_unconstrained(&unconstrained)
+        });
+        Self::from_evaluations(evals, *context.extended_domain())
+    }
+
+    /// Splits the polynomial into `num_chunks` chunks `H_i` with
+    /// `H(x) = sum(x^i * H_i(x^k))`, each of `1/k` of its degree.
+    pub fn split(&self, num_chunks: usize) -> Vec<DensePolynomial<F>> {
+        (0..num_chunks)
+            .map(|i| {
+                DensePolynomial::from_coefficients_vec(
+                    self.polynomial
+                        .coeffs
+                        .iter()
+                        .skip(i)
+                        .step_by(num_chunks)
+                        .copied()
+                        .collect(),
+                )
+            })
+            .collect()
     }
 
     /// Creates polynomial from pre-computed evaluations.
@@ -119,7 +247,7 @@ impl CompositionPolynomial {
     /// # Returns
     ///
     /// A new composition polynomial with the given evaluations
-    pub fn from_evaluations(evals: Vec<Fr>, domain: GeneralEvaluationDomain<Fr>) -> Self {
+    pub fn from_evaluations(evals: Vec<F>, domain: GeneralEvaluationDomain<F>) -> Self {
         let poly = Evaluations::from_vec_and_domain(evals.clone(), domain).interpolate();
         Self {
             polynomial: poly,
@@ -150,7 +278,7 @@ impl CompositionPolynomial {
     /// # Returns
     ///
     /// The value of the polynomial at the given point
-    pub fn evaluate(&self, point: Fr) -> Fr {
+    pub fn evaluate(&self, point: F) -> F {
         self.polynomial.evaluate(&point)
     }
 
@@ -165,7 +293,7 @@ impl CompositionPolynomial {
     /// The current implementation exposes the raw coefficients, which may leak
     /// information about the trace. In a zero-knowledge implementation, these
     /// should be committed to using a Merkle tree.
-    pub fn coefficients(&self) -> &[Fr] {
+    pub fn coefficients(&self) -> &[F] {
         &self.polynomial.coeffs
     }
 
@@ -180,25 +308,52 @@ impl CompositionPolynomial {
     /// The current implementation exposes all evaluations, which may leak
     /// information about the trace. In a zero-knowledge implementation, these
     /// should be committed to using a Merkle tree.
-    pub fn evaluations(&self) -> Vec<Fr> {
-        self.domain.fft(&self.polynomial.coeffs)
+    pub fn evaluations(&self) -> Vec<F> {
+        // The composition can exceed the domain size, so reduce it over the domain
+        // instead of truncating its coefficients
+        self.polynomial
+            .evaluate_over_domain_by_ref(self.domain)
+            .evals
     }
 }
 
+/// Combines the randomizer and mask columns added to the composition unconstrained.
+///
+/// The columns are committed over the base prime field of `F`, so over an extension of
+/// degree `d` the `k`-th column is weighted by the `k mod d`-th basis element of `F`
+/// over it. A mask spanning `d` consecutive columns then covers every coefficient of the
+/// composition. Over a prime field the columns are simply summed.
+pub(crate) fn combine_unconstrained<F: Field>(values: &[F]) -> F {
+    let degree = F::extension_degree() as usize;
+    let basis: Vec<F> = (0..degree)
+        .map(|i| {
+            let coefficients = (0..degree).map(|j| {
+                if i == j {
+                    F::BasePrimeField::ONE
+                } else {
+                    F::BasePrimeField::ZERO
+                }
+            });
+            F::from_base_prime_field_elems(coefficients)
+                .expect("one coefficient per extension degree")
+        })
+        .collect();
+    values
+        .iter()
+        .zip(basis.iter().cycle())
+        .map(|(value, basis)| *value * basis)
+        .sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
+    use ark_ff::{Field, Zero};
 
     #[test]
     fn test_composition_polynomial() {
         // Create a simple trace: x[n] = n
-        let mut trace = ExecutionTrace::new(4, 1);
-        for i in 0..4 {
-            let mut column = HashMap::new();
-            column.insert("x".to_string(), i);
-            trace.insert_column(column);
-        }
+        let trace = ExecutionTrace::from_fn(["x"], 4, |i| [i]).unwrap();
 
         // Create constraint system: x[n] = x[n-1] + 1
         let mut constraints = ConstraintSystem::default();
@@ -208,7 +363,7 @@ mod tests {
             Box::new(|current, next| {
                 let x_current = current.get("x").unwrap();
                 let x_next = next.get("x").unwrap();
-                Fr::from(*x_next) - Fr::from(*x_current + 1)
+                *x_next - (*x_current + Fr::ONE)
             }),
         );
 
@@ -216,7 +371,7 @@ mod tests {
         let domain = GeneralEvaluationDomain::<Fr>::new(4).unwrap();
 
         // Create composition polynomial
-        let comp_poly = CompositionPolynomial::new(&trace, &constraints, domain);
+        let comp_poly = CompositionPolynomial::new(&trace, &constraints, domain).unwrap();
 
         // The composition polynomial should evaluate to zero at all points where:
         // 1. The trace values are correct (x[n] = n)
@@ -226,4 +381,22 @@ mod tests {
             assert!(eval.is_zero());
         }
     }
+
+    #[test]
+    fn test_split_recombines() {
+        let domain = GeneralEvaluationDomain::<Fr>::new(16).unwrap();
+        let evals: Vec<Fr> = (0..16u64).map(|i| Fr::from(i * i + 3)).collect();
+        let comp_poly = CompositionPolynomial::from_evaluations(evals, domain);
+
+        // H(x) = Σ xⁱ·H_i(x^4), each chunk a quarter of the degree
+        let chunks = comp_poly.split(4);
+        assert!(chunks.iter().all(|chunk| chunk.coeffs.len() <= 4));
+        let x = Fr::from(7u64);
+        let x_k = x.pow([4]);
+        let recombined = chunks
+            .iter()
+            .rev()
+            .fold(Fr::zero(), |acc, chunk| acc * x + chunk.evaluate(&x_k));
+        assert_eq!(recombined, comp_poly.evaluate(x));
+    }
 }
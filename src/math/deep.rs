@@ -0,0 +1,233 @@
+//! DEEP (Domain Extension for Eliminating Pretenders) composition.
+//!
+//! After committing to the trace and the quotient chunks, the prover reveals their values
+//! at an out-of-domain point `z` drawn from the transcript: every trace column at `z` and
+//! `ω·z`, including the columns of an auxiliary segment, and every quotient chunk at `zᵏ`. The verifier checks the constraints against
+//! these values alone. Constraints over windows of `w` rows also reveal every column at
+//! `ω²·z, ..., ω^(w-1)·z`. To bind the values to the commitments, FRI runs on
+//!
+//! ```text
+//! D(x) = Σ γ_c·(T_c(x) - T_c(z)) / (x - z) + γ'_c·(T_c(x) - T_c(ω·z)) / (x - ω·z)
+//!      + Σ γ_{c,i}·(T_c(x) - T_c(ωⁱ·z)) / (x - ωⁱ·z)
+//!      + Σ δ_j·(H_j(x) - H_j(zᵏ)) / (x - zᵏ)
+//! ```
+//!
+//! which is only low degree if every revealed value is correct. The verifier recomputes
+//! `D` at the FRI query positions from the opened trace and quotient rows, so it never
+//! handles a full polynomial.
+
+use ark_bls12_381::Fr;
+use ark_ff::FftField;
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+
+use crate::math::inversion::batch_inverse;
+
+/// Values of the committed columns at the out-of-domain point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OodFrame<F: FftField = Fr> {
+    /// Every trace column at `z`, in the order the columns are committed, followed by
+    /// the auxiliary columns
+    pub current: Vec<F>,
+    /// Every trace column at `ω·z`
+    pub next: Vec<F>,
+    /// Every trace column at `ωⁱ·z` for `i ≥ 2`, one row per further row of the longest
+    /// window constraint, empty without window constraints
+    pub window: Vec<Vec<F>>,
+    /// Every quotient chunk `H_j` at `zᵏ`
+    pub quotient: Vec<F>,
+}
+
+impl<F: FftField> OodFrame<F> {
+    /// All values in the order they are absorbed into the transcript.
+    pub fn values(&self) -> Vec<F> {
+        let mut values = [self.current.as_slice(), self.next.as_slice()].concat();
+        values.extend(self.window.iter().flatten());
+        values.extend_from_slice(&self.quotient);
+        values
+    }
+
+    /// The revealed rows at `z, ω·z, ω²·z, ...`, in order.
+    pub fn rows(&self) -> impl Iterator<Item = &Vec<F>> {
+        [&self.current, &self.next].into_iter().chain(&self.window)
+    }
+}
+
+/// Checks that `z` can serve as the out-of-domain point.
+///
+/// The constraint zerofiers must not vanish at `z`, so `z` must lie outside the trace
+/// domain, and none of `z`, `ωⁱ·z` and `zᵏ` may lie on the extended coset, where the DEEP
+/// quotients are evaluated. As `ω` lies in the subgroup the coset is a shift of, `ωⁱ·z`
+/// lies on it exactly if `z` does.
+pub fn is_out_of_domain<F: FftField>(
+    z: F,
+    trace_domain: &GeneralEvaluationDomain<F>,
+    extended_domain: &GeneralEvaluationDomain<F>,
+    num_chunks: usize,
+) -> bool {
+    let extended_len = extended_domain.size() as u64;
+    let coset_power = extended_domain.coset_offset().pow([extended_len]);
+    !trace_domain.evaluate_vanishing_polynomial(z).is_zero()
+        && z.pow([extended_len]) != coset_power
+        && z.pow([extended_len * num_chunks as u64]) != coset_power
+}
+
+/// Random linear combination of the DEEP quotients of every committed column.
+#[derive(Debug, Clone)]
+pub struct DeepComposition<F: FftField = Fr> {
+    /// The points `ωⁱ·z` the trace columns are opened at, one per revealed row, starting
+    /// at the out-of-domain point `z` itself
+    row_points: Vec<F>,
+    /// The point `zᵏ` the quotient chunks are opened at
+    chunk_z: F,
+    /// Values revealed at the out-of-domain points
+    frame: OodFrame<F>,
+    /// `γ_c` for every column, then `γ'_c` for every column, then the coefficients of
+    /// every further revealed row, then `δ_j` for every chunk
+    coefficients: Vec<F>,
+}
+
+impl<F: FftField> DeepComposition<F> {
+    /// Number of combination coefficients for `width` trace columns revealed at
+    /// `num_rows` points and `num_chunks` quotient chunks.
+    pub fn num_coefficients(width: usize, num_rows: usize, num_chunks: usize) -> usize {
+        num_rows * width + num_chunks
+    }
+
+    /// Creates the DEEP composition for the out-of-domain point `z`.
+    ///
+    /// # Arguments
+    ///
+    /// * `z` - The out-of-domain point
+    /// * `trace_generator` - Generator `ω` of the trace domain
+    /// * `frame` - Values of the trace columns and quotient chunks at the out-of-domain points
+    /// * `coefficients` - Combination coefficients, see `num_coefficients`
+    pub fn new(z: F, trace_generator: F, frame: OodFrame<F>, coefficients: Vec<F>) -> Self {
+        let num_chunks = frame.quotient.len();
+        let row_points = std::iter::successors(Some(z), |point| Some(trace_generator * point))
+            .take(frame.rows().count())
+            .collect();
+        Self {
+            row_points,
+            chunk_z: z.pow([num_chunks as u64]),
+            frame,
+            coefficients,
+        }
+    }
+
+    /// Evaluates `D(x)` from the trace, auxiliary and quotient rows at `x`.
+    ///
+    /// The main trace is committed over the base prime field of `F`, so its row is lifted
+    /// into `F` here; the auxiliary row, empty without an auxiliary segment, is over `F`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` is one of the out-of-domain points, see `is_out_of_domain`.
+    pub fn evaluate(
+        &self,
+        x: F,
+        trace_row: &[F::BasePrimeField],
+        auxiliary_row: &[F],
+        quotient_row: &[F],
+    ) -> F {
+        let denominators: Vec<F> = self
+            .row_points
+            .iter()
+            .chain([&self.chunk_z])
+            .map(|point| x - point)
+            .collect();
+        let inverses =
+            batch_inverse(&denominators).expect("Out-of-domain point lies on the extended domain");
+        let (row_invs, chunk_inv) = (
+            &inverses[..self.row_points.len()],
+            inverses[self.row_points.len()],
+        );
+        let width = self.frame.current.len();
+        let (row_coeffs, chunk_coeffs) = self.coefficients.split_at(row_invs.len() * width);
+
+        let values: Vec<F> = trace_row
+            .iter()
+            .map(|value| F::from_base_prime_field(*value))
+            .chain(auxiliary_row.iter().copied())
+            .collect();
+        let trace: F = self
+            .frame
+            .rows()
+            .zip(row_invs)
+            .zip(row_coeffs.chunks(width))
+            .map(|((revealed, inv), gammas)| {
+                let sum: F = values
+                    .iter()
+                    .zip(revealed)
+                    .zip(gammas)
+                    .map(|((value, revealed), gamma)| *gamma * (*value - revealed))
+                    .sum();
+                sum * inv
+            })
+            .sum();
+        let quotient: F = quotient_row
+            .iter()
+            .zip(&self.frame.quotient)
+            .zip(chunk_coeffs)
+            .map(|((value, chunk), delta)| *delta * (*value - chunk) * chunk_inv)
+            .sum();
+        trace + quotient
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::{Field, Zero};
+    use ark_poly::{DenseUVPolynomial, Polynomial, univariate::DensePolynomial};
+
+    #[test]
+    fn test_deep_composition_is_low_degree() {
+        let trace_domain = GeneralEvaluationDomain::<Fr>::new(4).unwrap();
+        let extended_domain = GeneralEvaluationDomain::<Fr>::new(16)
+            .unwrap()
+            .get_coset(Fr::from(7u64))
+            .unwrap();
+        let column = DensePolynomial::from_coefficients_vec((1..5u64).map(Fr::from).collect());
+        let chunk = DensePolynomial::from_coefficients_vec(vec![Fr::from(3u64), Fr::from(5u64)]);
+        let z = Fr::from(11u64);
+        assert!(is_out_of_domain(z, &trace_domain, &extended_domain, 1));
+        assert!(!is_out_of_domain(
+            trace_domain.element(1),
+            &trace_domain,
+            &extended_domain,
+            1
+        ));
+
+        let deep = |frame: OodFrame| {
+            let composition = DeepComposition::new(
+                z,
+                trace_domain.group_gen(),
+                frame,
+                vec![Fr::from(2u64), Fr::from(3u64), Fr::from(4u64)],
+            );
+            let evals: Vec<Fr> = extended_domain
+                .elements()
+                .map(|x| {
+                    composition.evaluate(x, &[column.evaluate(&x)], &[], &[chunk.evaluate(&x)])
+                })
+                .collect();
+            extended_domain.ifft(&evals)
+        };
+
+        // Correct values leave polynomials of degree one less than the columns
+        let frame = OodFrame {
+            current: vec![column.evaluate(&z)],
+            next: vec![column.evaluate(&(trace_domain.group_gen() * z))],
+            window: Vec::new(),
+            quotient: vec![chunk.evaluate(&z)],
+        };
+        assert!(deep(frame.clone())[3..].iter().all(Fr::is_zero));
+
+        // A wrong value turns the quotient into a rational function
+        let wrong = OodFrame {
+            current: vec![frame.current[0] + Fr::ONE],
+            ..frame
+        };
+        assert!(!deep(wrong)[3..].iter().all(Fr::is_zero));
+    }
+}
@@ -2,9 +2,11 @@
 //!
 //! This module provides functionality for working with evaluation domains in the Stark proving system.
 //! It includes functions for creating and extending evaluation domains, as well as operations on domain points.
-use ark_bls12_381::Fr;
+use ark_ff::FftField;
 use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
 
+use crate::math::inversion::batch_inverse;
+
 /// Squares each point in the domain for FRI protocol.
 ///
 /// This operation is used in the FRI protocol to reduce the size of the evaluation domain
@@ -23,7 +25,7 @@ use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
 ///
 /// Panics if the domain size is not even
 ///
-pub fn fold_domain_points(domain_points: Vec<Fr>, domain_size: usize) -> Vec<Fr> {
+pub fn fold_domain_points<F: FftField>(domain_points: Vec<F>, domain_size: usize) -> Vec<F> {
     domain_points
         .iter()
         .take(domain_size / 2)
@@ -44,8 +46,8 @@ pub fn fold_domain_points(domain_points: Vec<Fr>, domain_size: usize) -> Vec<Fr>
 /// # Panics
 ///
 /// Panics if the domain size is not a power of 2
-pub fn get_domain(domain_size: usize) -> GeneralEvaluationDomain<Fr> {
-    GeneralEvaluationDomain::<Fr>::new(domain_size).unwrap()
+pub fn get_domain<F: FftField>(domain_size: usize) -> GeneralEvaluationDomain<F> {
+    GeneralEvaluationDomain::<F>::new(domain_size).unwrap()
 }
 
 /// Creates an extended domain by applying a blowup factor.
@@ -67,19 +69,93 @@ pub fn get_domain(domain_size: usize) -> GeneralEvaluationDomain<Fr> {
 /// Panics if:
 /// * The domain size is not a power of 2
 /// * The resulting extended domain size is not a power of 2
-pub fn get_extended_domain(
+pub fn get_extended_domain<F: FftField>(
     domain_size: usize,
     blowup_factor: usize,
-) -> GeneralEvaluationDomain<Fr> {
-    GeneralEvaluationDomain::<Fr>::new(domain_size * blowup_factor).unwrap()
+) -> GeneralEvaluationDomain<F> {
+    GeneralEvaluationDomain::<F>::new(domain_size * blowup_factor).unwrap()
+}
+
+/// Evaluates the vanishing polynomial `Z_H(x) = x^n - 1` of `domain` over every point
+/// of `coset`.
+///
+/// On a coset `o·⟨g⟩` of size `N` the values `o^n·g^(i·n) - 1` repeat with period
+/// `N / n`, so only one period is computed.
+///
+/// # Panics
+///
+/// Panics if the coset size is not a multiple of the domain size
+pub fn vanishing_polynomial_on_coset<F: FftField>(
+    domain: &GeneralEvaluationDomain<F>,
+    coset: &GeneralEvaluationDomain<F>,
+) -> Vec<F> {
+    assert!(
+        coset.size() % domain.size() == 0,
+        "Coset size must be a multiple of the domain size"
+    );
+    let period = coset.size() / domain.size();
+    let values: Vec<F> = coset
+        .elements()
+        .take(period)
+        .map(|x| domain.evaluate_vanishing_polynomial(x))
+        .collect();
+    values.iter().copied().cycle().take(coset.size()).collect()
 }
 
+/// Evaluates the polynomial with values `evals` over `domain` at `z` with the
+/// barycentric Lagrange formula, without interpolating it.
+///
+/// For a coset `o·⟨g⟩` of size `n` with vanishing polynomial `Z(x) = x^n - o^n`, the
+/// polynomial is `p(z) = Z(z) / (n·o^n) · sum(y_i·x_i / (z - x_i))`, which costs one
+/// batch inversion and O(n) multiplications. Points of the domain return their value.
+///
+/// # Panics
+///
+/// Panics unless `evals` holds one value per point of `domain`
+pub fn barycentric_evaluate<F: FftField>(
+    evals: &[F],
+    domain: &GeneralEvaluationDomain<F>,
+    z: F,
+) -> F {
+    assert_eq!(
+        evals.len(),
+        domain.size(),
+        "Evaluations must cover the domain"
+    );
+    let vanishing = domain.evaluate_vanishing_polynomial(z);
+    if vanishing.is_zero() {
+        let index = domain
+            .elements()
+            .position(|x| x == z)
+            .expect("z lies in the domain");
+        return evals[index];
+    }
+
+    let points: Vec<F> = domain.elements().collect();
+    let differences: Vec<F> = points.iter().map(|x| z - x).collect();
+    let inverses = batch_inverse(&differences).expect("z lies outside the domain");
+    let sum: F = evals
+        .iter()
+        .zip(&points)
+        .zip(&inverses)
+        .map(|((y, x), inverse)| *y * x * inverse)
+        .sum();
+    let scale = domain.size_as_field_element() * domain.coset_offset_pow_size();
+    sum * vanishing
+        * scale
+            .inverse()
+            .expect("domain sizes and offsets are nonzero")
+}
+
+#[cfg(test)]
+use ark_bls12_381::Fr;
+
 #[test]
 fn test_general_evaluation_domain() {
     let original_domain_size = 4;
     let blowup_factor = 8;
-    let domain = get_domain(original_domain_size);
-    let extended_domain = get_extended_domain(original_domain_size, blowup_factor);
+    let domain = get_domain::<Fr>(original_domain_size);
+    let extended_domain = get_extended_domain::<Fr>(original_domain_size, blowup_factor);
 
     // Verify domain sizes
     assert_eq!(domain.size(), 4);
@@ -94,3 +170,41 @@ fn test_general_evaluation_domain() {
         assert_eq!(point, extended_points[i * blowup_factor]);
     }
 }
+
+#[test]
+fn test_vanishing_polynomial_on_coset() {
+    use ark_poly::Polynomial;
+    use ark_poly::univariate::DensePolynomial;
+
+    let domain = get_domain::<Fr>(8);
+    let coset = get_extended_domain(8, 4).get_coset(Fr::from(7u64)).unwrap();
+    let z_h: DensePolynomial<Fr> = domain.vanishing_polynomial().into();
+
+    let values = vanishing_polynomial_on_coset(&domain, &coset);
+    assert_eq!(values.len(), coset.size());
+    for (value, x) in values.iter().zip(coset.elements()) {
+        assert_eq!(*value, z_h.evaluate(&x));
+    }
+}
+
+#[test]
+fn test_barycentric_evaluate() {
+    use ark_poly::univariate::DensePolynomial;
+    use ark_poly::{DenseUVPolynomial, Polynomial};
+
+    let poly = DensePolynomial::from_coefficients_vec((1..=8u64).map(Fr::from).collect());
+    let subgroup = get_domain::<Fr>(8);
+    let coset = get_extended_domain(4, 4).get_coset(Fr::from(7u64)).unwrap();
+    for domain in [subgroup, coset] {
+        let evals = poly.evaluate_over_domain_by_ref(domain).evals;
+
+        // Out-of-domain points match the polynomial, domain points return their value
+        for z in [Fr::from(11u64), Fr::from(123456u64)] {
+            assert_eq!(barycentric_evaluate(&evals, &domain, z), poly.evaluate(&z));
+        }
+        assert_eq!(
+            barycentric_evaluate(&evals, &domain, domain.element(3)),
+            evals[3]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Polynomials in evaluation form.
+//!
+//! `EvalPoly` holds the values of a polynomial over every point of a domain or coset.
+//! Sums, products and quotients are computed pointwise in O(n), and the polynomial is
+//! only interpolated to coefficient form when `interpolate` is called. Pointwise
+//! products are exact as long as the result still has fewer coefficients than the
+//! domain has points; beyond that the values describe the product reduced modulo the
+//! domain's vanishing polynomial.
+
+use ark_bls12_381::Fr;
+use ark_ff::FftField;
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use std::ops;
+
+use crate::error::ToyniError;
+use crate::math::{domain::barycentric_evaluate, inversion::batch_inverse, polynomial::Polynomial};
+
+/// Evaluations of a polynomial over a domain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalPoly<F: FftField = Fr> {
+    /// Value at `domain.element(i)` for every `i`
+    evals: Vec<F>,
+    /// The domain or coset evaluated over
+    domain: GeneralEvaluationDomain<F>,
+}
+
+impl<F: FftField> EvalPoly<F> {
+    /// Wraps the values of a polynomial over `domain`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::DomainMismatch` unless there is one value per domain point
+    pub fn new(evals: Vec<F>, domain: GeneralEvaluationDomain<F>) -> Result<Self, ToyniError> {
+        if evals.len() != domain.size() {
+            return Err(ToyniError::DomainMismatch {
+                expected: domain.size(),
+                actual: evals.len(),
+            });
+        }
+        Ok(Self { evals, domain })
+    }
+
+    /// Evaluates `poly` over `domain` with a single FFT.
+    pub fn from_polynomial(poly: &Polynomial<F>, domain: GeneralEvaluationDomain<F>) -> Self {
+        Self {
+            evals: poly.evaluate_over_domain(&domain),
+            domain,
+        }
+    }
+
+    /// The values, in the order of the domain elements.
+    pub fn evals(&self) -> &[F] {
+        &self.evals
+    }
+
+    /// Consumes the polynomial, returning its values.
+    pub fn into_evals(self) -> Vec<F> {
+        self.evals
+    }
+
+    /// The domain the polynomial is evaluated over.
+    pub fn domain(&self) -> GeneralEvaluationDomain<F> {
+        self.domain
+    }
+
+    /// Evaluates the polynomial at `z` from its values, see `barycentric_evaluate`.
+    pub fn evaluate(&self, z: F) -> F {
+        barycentric_evaluate(&self.evals, &self.domain, z)
+    }
+
+    /// Interpolates the values into coefficient form with an inverse FFT.
+    pub fn interpolate(&self) -> Polynomial<F> {
+        Polynomial::new(self.domain.ifft(&self.evals))
+    }
+
+    /// Adds two polynomials pointwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the polynomials are evaluated over different domains
+    pub fn add(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a + b)
+    }
+
+    /// Subtracts `other` from this polynomial pointwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the polynomials are evaluated over different domains
+    pub fn sub(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a - b)
+    }
+
+    /// Multiplies two polynomials pointwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the polynomials are evaluated over different domains
+    pub fn mul(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a * b)
+    }
+
+    /// Multiplies every value by a scalar.
+    pub fn scale(&self, scalar: F) -> Self {
+        Self {
+            evals: self.evals.iter().map(|value| *value * scalar).collect(),
+            domain: self.domain,
+        }
+    }
+
+    /// Divides this polynomial by `divisor` pointwise, with a single batch inversion.
+    ///
+    /// The values are those of the quotient if `divisor` divides this polynomial and
+    /// the quotient fits the domain.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::DivisionByZeroPoly` if `divisor` vanishes anywhere on the
+    /// domain
+    ///
+    /// # Panics
+    ///
+    /// Panics if the polynomials are evaluated over different domains
+    pub fn divide(&self, divisor: &Self) -> Result<Self, ToyniError> {
+        let inverses = batch_inverse(&divisor.evals).ok_or(ToyniError::DivisionByZeroPoly)?;
+        Ok(self.zip_with(
+            &Self {
+                evals: inverses,
+                domain: divisor.domain,
+            },
+            |a, b| a * b,
+        ))
+    }
+
+    fn zip_with(&self, other: &Self, op: impl Fn(F, F) -> F) -> Self {
+        assert_eq!(
+            self.domain, other.domain,
+            "Pointwise operations need a common domain"
+        );
+        Self {
+            evals: self
+                .evals
+                .iter()
+                .zip(&other.evals)
+                .map(|(a, b)| op(*a, *b))
+                .collect(),
+            domain: self.domain,
+        }
+    }
+}
+
+impl<F: FftField> ops::Add<&EvalPoly<F>> for &EvalPoly<F> {
+    type Output = EvalPoly<F>;
+
+    fn add(self, other: &EvalPoly<F>) -> EvalPoly<F> {
+        EvalPoly::add(self, other)
+    }
+}
+
+impl<F: FftField> ops::Sub<&EvalPoly<F>> for &EvalPoly<F> {
+    type Output = EvalPoly<F>;
+
+    fn sub(self, other: &EvalPoly<F>) -> EvalPoly<F> {
+        EvalPoly::sub(self, other)
+    }
+}
+
+impl<F: FftField> ops::Mul<&EvalPoly<F>> for &EvalPoly<F> {
+    type Output = EvalPoly<F>;
+
+    fn mul(self, other: &EvalPoly<F>) -> EvalPoly<F> {
+        EvalPoly::mul(self, other)
+    }
+}
+
+impl<F: FftField> ops::Mul<F> for &EvalPoly<F> {
+    type Output = EvalPoly<F>;
+
+    fn mul(self, scalar: F) -> EvalPoly<F> {
+        self.scale(scalar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::{AdditiveGroup, Field};
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn test_pointwise_arithmetic() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let domain = GeneralEvaluationDomain::<Fr>::new(16)
+            .unwrap()
+            .get_coset(Fr::from(7u64))
+            .unwrap();
+        let p = Polynomial::<Fr>::random(6, &mut rng);
+        let q = Polynomial::<Fr>::random(5, &mut rng);
+        let p_evals = EvalPoly::from_polynomial(&p, domain);
+        let q_evals = EvalPoly::from_polynomial(&q, domain);
+
+        assert_eq!(p_evals.interpolate(), p);
+        assert_eq!(p_evals.evaluate(Fr::from(5u64)), p.evaluate(Fr::from(5u64)));
+        assert_eq!((&p_evals + &q_evals).interpolate(), &p + &q);
+        assert_eq!((&p_evals - &q_evals).interpolate(), &p - &q);
+        assert_eq!((&p_evals * &q_evals).interpolate(), &p * &q);
+        assert_eq!(
+            (&p_evals * Fr::from(3u64)).interpolate(),
+            p.scale(Fr::from(3u64))
+        );
+
+        // The product divides back into its factor without leaving evaluation form
+        let product = &p_evals * &q_evals;
+        assert_eq!(product.divide(&q_evals).unwrap(), p_evals);
+    }
+
+    #[test]
+    fn test_invalid_evaluations() {
+        let domain = GeneralEvaluationDomain::<Fr>::new(4).unwrap();
+        assert_eq!(
+            EvalPoly::new(vec![Fr::ONE; 3], domain).err(),
+            Some(ToyniError::DomainMismatch {
+                expected: 4,
+                actual: 3
+            })
+        );
+
+        // x - 1 vanishes on the subgroup, so it cannot be divided by there
+        let numerator = EvalPoly::new(vec![Fr::ONE; 4], domain).unwrap();
+        let divisor = EvalPoly::from_polynomial(&Polynomial::new(vec![-Fr::ONE, Fr::ONE]), domain);
+        assert_eq!(
+            numerator.divide(&divisor).err(),
+            Some(ToyniError::DivisionByZeroPoly)
+        );
+        assert_eq!(divisor.evals()[0], Fr::ZERO);
+    }
+}
@@ -0,0 +1,63 @@
+//! The quadratic extension `Fr[X] / (X² - 7)` of the BLS12-381 scalar field.
+//!
+//! Proving over `FrExt2` instead of `Fr` keeps the trace extended, committed and opened
+//! over `Fr`, but draws the out-of-domain point, the constraint and DEEP combination
+//! coefficients and the FRI folding challenges from the extension. The chance of a
+//! challenge hitting one of the points a cheating prover can exploit, such as the
+//! evaluation domain, shrinks with the size of the field, which matters once the domain
+//! is a noticeable fraction of it.
+
+use ark_bls12_381::Fr;
+use ark_ff::{
+    MontFp,
+    fields::{Fp2, Fp2Config},
+};
+
+/// Parameters of the quadratic extension `Fr[X] / (X² - 7)`.
+pub struct FrExt2Config;
+
+impl Fp2Config for FrExt2Config {
+    type Fp = Fr;
+
+    /// 7 generates the multiplicative group of `Fr`, so it is a quadratic non-residue
+    const NONRESIDUE: Fr = MontFp!("7");
+
+    /// `7^((r^i - 1) / 2)` for `i = 0, 1`
+    const FROBENIUS_COEFF_FP2_C1: &'static [Fr] = &[
+        MontFp!("1"),
+        MontFp!("52435875175126190479447740508185965837690552500527637822603658699938581184512"),
+    ];
+}
+
+/// Element of the quadratic extension of the BLS12-381 scalar field.
+pub type FrExt2 = Fp2<FrExt2Config>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::ProofOptions;
+    use ark_ff::{FftField, Field, PrimeField};
+
+    #[test]
+    fn test_quadratic_extension() {
+        let seven = Fr::from(7u64);
+        assert_eq!(Fr::GENERATOR, seven);
+        assert_eq!(seven.pow(Fr::MODULUS_MINUS_ONE_DIV_TWO), -Fr::ONE);
+        assert_eq!(FrExt2Config::FROBENIUS_COEFF_FP2_C1[1], -Fr::ONE);
+
+        let x = FrExt2::new(Fr::from(0u64), Fr::ONE);
+        assert_eq!(x.square(), FrExt2::from(7u64));
+        assert_eq!(FrExt2::TWO_ADICITY, Fr::TWO_ADICITY);
+
+        // The extension adds a further 254 bits to the cap on the security level
+        let options = ProofOptions::<FrExt2>::new().with_fri_queries(400);
+        assert_eq!(options.security_level().conjectured, 400);
+        assert_eq!(
+            ProofOptions::<Fr>::new()
+                .with_fri_queries(400)
+                .security_level()
+                .conjectured,
+            254
+        );
+    }
+}
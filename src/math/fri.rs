@@ -1,36 +1,957 @@
 //! Fast Reed-Solomon Interactive Oracle Proof (FRI) implementation.
 //!
 //! The FRI protocol proves polynomial low-degree by iteratively folding evaluations
-//! and reducing domain size. Each round combines pairs of points using a random challenge.
+//! and reducing domain size. Each round combines cosets of `k` points using a random
+//! challenge, where the folding factor `k` follows the configured schedule and defaults
+//! to 2. Folded layers are Merkle-committed and only opened at the queried positions.
+//!
+//! `fri_prove` runs the commit and query phases for an already committed layer 0,
+//! and `fri_verify` checks the fold relation between consecutive layer openings at
+//! positions sampled from the transcript. Folding stops at a configurable remainder
+//! size; the final layer is sent as a polynomial whose degree the verifier checks
+//! directly, so the verifier never touches a full layer.
+//!
+//! `FriProver` and `FriVerifier` wrap both phases into a standalone low-degree test
+//! that commits layer 0 itself and runs over its own transcript. Several polynomials
+//! can be batched into one FRI instance through a random linear combination.
 
+use crate::digest_sha2;
+use crate::error::ToyniError;
+use crate::hash::Sha256Hasher;
+use crate::math::inversion::batch_inverse;
+use crate::math::serde_fr::{self, to_bytes};
+use crate::merkle::{
+    MerkleCap, MerkleProof, MerkleTree, field_element_leaf, path_length, row_leaf,
+    verify_merkle_proof_with_cap,
+};
+use crate::options::ProofOptions;
+use crate::parallel::into_iter;
+use crate::progress::ProofObserver;
+use crate::transcript::{RandomOracle, Transcript};
 use ark_bls12_381::Fr;
-use ark_ff::Field;
+use ark_ff::FftField;
 use ark_poly::{
-    EvaluationDomain, Evaluations, GeneralEvaluationDomain, univariate::DensePolynomial,
+    DenseUVPolynomial, EvaluationDomain, Evaluations, GeneralEvaluationDomain, Polynomial,
+    univariate::DensePolynomial,
 };
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tracing::{debug, debug_span};
 
-/// Folds evaluations using FRI protocol with challenge beta.
-pub fn fri_fold(evals: &[Fr], beta: Fr) -> Vec<Fr> {
-    assert!(evals.len() % 2 == 0, "Evaluations length must be even");
-    let mut result = Vec::with_capacity(evals.len() / 2);
-    let half = evals.len() / 2;
-    let half_inv = Fr::from(2u64).inverse().unwrap();
+/// Opening of the coset folded into one value of the next FRI layer.
+///
+/// For a layer of size `L` folded by a factor `k`, the coset of `index` holds the
+/// positions `index + t * L / k` for `t` in `0..k`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct FriLayerOpening<F: FftField = Fr> {
+    /// Lowest position of the opened coset within the layer
+    pub index: usize,
+    /// Layer values at every position of the coset
+    #[serde(with = "serde_fr::vec")]
+    pub values: Vec<F>,
+    /// Merkle authentication paths for `values`
+    pub proofs: Vec<MerkleProof>,
+}
+
+/// Openings of all committed intermediate layers for one query position.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct FriQuery<F: FftField = Fr> {
+    /// One opening per committed layer, excluding the final layer
+    pub layers: Vec<FriLayerOpening<F>>,
+}
+
+/// FRI proof for evaluations whose layer 0 is committed by the caller.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct FriProof<F: FftField = Fr> {
+    /// Folding challenge of each round
+    #[serde(with = "serde_fr::vec")]
+    pub challenges: Vec<F>,
+    /// Merkle caps of each folded layer, excluding the final one
+    pub layer_caps: Vec<MerkleCap>,
+    /// Layer openings at the queried positions
+    pub queries: Vec<FriQuery<F>>,
+    /// Coefficients of the polynomial interpolating the final layer
+    #[serde(with = "serde_fr::vec")]
+    pub final_poly: Vec<F>,
+    /// Proof-of-work nonce ground before the query positions were drawn
+    pub pow_nonce: u64,
+}
+
+/// Opening of all batched polynomials at the layer 0 coset of one query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct FriBatchOpening<F: FftField = Fr> {
+    /// Values of every batched polynomial, one row per coset position
+    #[serde(with = "serde_fr::rows")]
+    pub rows: Vec<Vec<F>>,
+    /// Merkle authentication paths for `rows`
+    pub proofs: Vec<MerkleProof>,
+}
+
+/// FRI proof for a random linear combination of several committed polynomials.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct BatchedFriProof<F: FftField = Fr> {
+    /// Merkle cap of the combined evaluations, which form layer 0
+    pub combined_commitment: MerkleCap,
+    /// Openings of the batched polynomials, one per FRI query
+    pub openings: Vec<FriBatchOpening<F>>,
+    /// FRI proof over the combined evaluations
+    pub proof: FriProof<F>,
+}
+
+/// FRI check that rejected a proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FriFailure {
+    /// The proof does not have one challenge per round and one cap per folded layer
+    RoundCount { expected: usize, actual: usize },
+    /// The final polynomial has too many coefficients for the folded degree bound
+    FinalDegree { coefficients: usize, bound: usize },
+    /// A folding challenge differs from the one drawn from the transcript
+    Challenge { round: usize },
+    /// The nonce does not satisfy the proof-of-work
+    ProofOfWork { nonce: u64 },
+    /// The cap of a layer does not have the size implied by the cap height
+    CapSize { layer: usize },
+    /// Without folding rounds, the final polynomial does not reproduce layer 0
+    Remainder,
+    /// The proof does not hold the configured number of queries
+    QueryCount { expected: usize, actual: usize },
+    /// A query does not open one coset per folded layer
+    QueryLayers { query: usize },
+    /// A coset opening is not authenticated by the cap of its layer
+    LayerOpening {
+        query: usize,
+        layer: usize,
+        index: usize,
+    },
+    /// Folding an opened coset does not yield the value opened in the next layer
+    Folding {
+        query: usize,
+        layer: usize,
+        index: usize,
+    },
+}
+
+impl fmt::Display for FriFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FriFailure::RoundCount { expected, actual } => {
+                write!(f, "proof has {} rounds, expected {}", actual, expected)
+            }
+            FriFailure::FinalDegree {
+                coefficients,
+                bound,
+            } => write!(
+                f,
+                "final polynomial has {} coefficients, the bound is {}",
+                coefficients, bound
+            ),
+            FriFailure::Challenge { round } => {
+                write!(
+                    f,
+                    "challenge of round {} does not match the transcript",
+                    round
+                )
+            }
+            FriFailure::ProofOfWork { nonce } => {
+                write!(f, "nonce {} does not satisfy the proof-of-work", nonce)
+            }
+            FriFailure::CapSize { layer } => {
+                write!(f, "cap of layer {} does not match the cap height", layer)
+            }
+            FriFailure::Remainder => {
+                write!(f, "final polynomial does not match the committed layer 0")
+            }
+            FriFailure::QueryCount { expected, actual } => {
+                write!(f, "proof has {} queries, expected {}", actual, expected)
+            }
+            FriFailure::QueryLayers { query } => {
+                write!(f, "query {} does not open every folded layer", query)
+            }
+            FriFailure::LayerOpening {
+                query,
+                layer,
+                index,
+            } => write!(
+                f,
+                "query {} opens coset {} of layer {} with an invalid Merkle proof",
+                query, index, layer
+            ),
+            FriFailure::Folding {
+                query,
+                layer,
+                index,
+            } => write!(
+                f,
+                "query {} folds coset {} of layer {} inconsistently with the next layer",
+                query, index, layer
+            ),
+        }
+    }
+}
+
+/// Domain separator of the transcript used by the standalone FRI prover and verifier
+const FRI_DOMAIN_SEPARATOR: &[u8] = b"toyni-fri";
+
+/// Largest number of proof-of-work bits, those of the 64-bit digest prefix a nonce is
+/// checked against.
+pub const MAX_GRINDING_BITS: usize = 64;
+
+/// Standalone FRI prover proving that evaluations over a domain are low degree.
+#[derive(Debug, Clone)]
+pub struct FriProver<F: FftField = Fr> {
+    /// Merkle cap height, remainder size and query count
+    options: ProofOptions<F>,
+}
+
+impl Default for FriProver {
+    fn default() -> Self {
+        Self::new(ProofOptions::default())
+    }
+}
+
+impl<F: FftField> FriProver<F> {
+    /// Creates a new FRI prover with the given options.
+    pub fn new(options: ProofOptions<F>) -> Self {
+        Self { options }
+    }
+
+    /// Commits to the evaluations and proves they are low degree.
+    ///
+    /// # Arguments
+    ///
+    /// * `evals` - Evaluations of the polynomial over `domain`
+    /// * `domain` - The evaluation domain
+    ///
+    /// # Returns
+    ///
+    /// The Merkle cap of the evaluations and the FRI proof
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of evaluations does not match the domain size
+    pub fn prove(
+        &self,
+        evals: Vec<F>,
+        domain: &GeneralEvaluationDomain<F>,
+    ) -> (MerkleCap, FriProof<F>) {
+        assert_eq!(
+            evals.len(),
+            domain.size(),
+            "Evaluations must cover the domain"
+        );
+        let base_tree = MerkleTree::from_field_elements(&evals);
+        let commitment = base_tree.cap(self.options.merkle_cap_height);
+
+        let mut transcript = Transcript::new(FRI_DOMAIN_SEPARATOR);
+        transcript.absorb(&commitment.concat());
+        let proof = fri_prove(evals, domain, base_tree, &self.options, &mut transcript);
+        (commitment, proof)
+    }
+
+    /// Commits to several evaluation vectors and proves all of them are low degree
+    /// with a single FRI instance.
+    ///
+    /// The vectors are committed row-wise in one tree, combined as `sum(alpha^i * f_i)`
+    /// with a transcript challenge `alpha`, and FRI runs on the combination. Each query
+    /// also opens the batched rows so the verifier can recompute the combined values.
+    ///
+    /// # Returns
+    ///
+    /// The Merkle cap of the batched rows and the batched FRI proof
+    ///
+    /// # Panics
+    ///
+    /// Panics if no evaluations are given or any vector does not cover the domain
+    pub fn prove_batch(
+        &self,
+        evals: Vec<Vec<F>>,
+        domain: &GeneralEvaluationDomain<F>,
+    ) -> (MerkleCap, BatchedFriProof<F>) {
+        assert!(!evals.is_empty(), "At least one polynomial must be batched");
+        assert!(
+            evals.iter().all(|column| column.len() == domain.size()),
+            "Evaluations must cover the domain"
+        );
+        let rows: Vec<Vec<F>> = (0..domain.size())
+            .map(|i| evals.iter().map(|column| column[i]).collect())
+            .collect();
+        let batch_tree = MerkleTree::from_rows(&rows);
+        let commitment = batch_tree.cap(self.options.merkle_cap_height);
+
+        let mut transcript = Transcript::new(FRI_DOMAIN_SEPARATOR);
+        transcript.absorb(&commitment.concat());
+        let proof = fri_prove_batch(&rows, domain, &batch_tree, &self.options, &mut transcript);
+        (commitment, proof)
+    }
+}
+
+/// Combines one row of batched values as `sum(alpha^i * row[i])`.
+fn combine_row<F: FftField>(row: &[F], alpha: F) -> F {
+    row.iter()
+        .rev()
+        .fold(F::zero(), |acc, value| acc * alpha + value)
+}
+
+/// Standalone FRI verifier for proofs produced by `FriProver`.
+#[derive(Debug, Clone)]
+pub struct FriVerifier<F: FftField = Fr> {
+    /// The evaluation domain, possibly a coset
+    domain: GeneralEvaluationDomain<F>,
+    /// The committed polynomial must have fewer than this many coefficients
+    degree_bound: usize,
+    /// Merkle cap height, remainder size and query count
+    options: ProofOptions<F>,
+}
+
+impl<F: FftField> FriVerifier<F> {
+    /// Creates a new FRI verifier.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The evaluation domain used by the prover
+    /// * `degree_bound` - The committed polynomial must have fewer coefficients
+    /// * `options` - The options used by the prover
+    pub fn new(
+        domain: &GeneralEvaluationDomain<F>,
+        degree_bound: usize,
+        options: ProofOptions<F>,
+    ) -> Self {
+        Self {
+            domain: *domain,
+            degree_bound,
+            options,
+        }
+    }
+
+    /// Verifies that `commitment` commits to evaluations of a low-degree polynomial.
+    pub fn verify(&self, commitment: &MerkleCap, proof: &FriProof<F>) -> bool {
+        let mut transcript = Transcript::new(FRI_DOMAIN_SEPARATOR);
+        transcript.absorb(&commitment.concat());
+        fri_verify(
+            commitment,
+            &self.domain,
+            self.degree_bound,
+            proof,
+            &self.options,
+            &mut transcript,
+        )
+    }
+
+    /// Verifies that every polynomial batched under `commitment` is low degree.
+    pub fn verify_batch(&self, commitment: &MerkleCap, proof: &BatchedFriProof<F>) -> bool {
+        let mut transcript = Transcript::new(FRI_DOMAIN_SEPARATOR);
+        transcript.absorb(&commitment.concat());
+        fri_verify_batch(
+            commitment,
+            &self.domain,
+            self.degree_bound,
+            proof,
+            &self.options,
+            &mut transcript,
+        )
+    }
+}
+
+/// Runs batched FRI over rows already committed in `batch_tree`.
+///
+/// The caller must have absorbed the cap of `batch_tree`. The rows are combined as
+/// `sum(alpha^i * row[i])` with a transcript challenge `alpha`, FRI runs on the
+/// combination, and each query also opens the batched rows of its layer 0 coset.
+pub fn fri_prove_batch<F: FftField>(
+    rows: &[Vec<F>],
+    domain: &GeneralEvaluationDomain<F>,
+    batch_tree: &MerkleTree,
+    options: &ProofOptions<F>,
+    oracle: &mut impl RandomOracle,
+) -> BatchedFriProof<F> {
+    let cap_height = options.merkle_cap_height;
+    let alpha = oracle.draw_field_element();
+    let combined: Vec<F> = rows.iter().map(|row| combine_row(row, alpha)).collect();
+    let combined_tree = MerkleTree::from_field_elements(&combined);
+    let combined_commitment = combined_tree.cap(cap_height);
+    oracle.absorb(&combined_commitment.concat());
+
+    let proof = fri_prove(combined, domain, combined_tree, options, oracle);
+    let openings = proof
+        .queries
+        .iter()
+        .map(|query| {
+            let layer = &query.layers[0];
+            let positions = coset_positions(layer.index, domain.size(), layer.values.len());
+            FriBatchOpening {
+                rows: positions.iter().map(|&i| rows[i].clone()).collect(),
+                proofs: positions
+                    .iter()
+                    .map(|&i| batch_tree.get_proof_with_cap(i, cap_height).unwrap())
+                    .collect(),
+            }
+        })
+        .collect();
+
+    BatchedFriProof {
+        combined_commitment,
+        openings,
+        proof,
+    }
+}
+
+/// Verifies a batched FRI proof against the cap of the batched rows.
+///
+/// The oracle must be in the state the prover's was in when calling `fri_prove_batch`.
+pub fn fri_verify_batch<F: FftField>(
+    commitment: &MerkleCap,
+    domain: &GeneralEvaluationDomain<F>,
+    degree_bound: usize,
+    proof: &BatchedFriProof<F>,
+    options: &ProofOptions<F>,
+    oracle: &mut impl RandomOracle,
+) -> bool {
+    let alpha = oracle.draw_field_element();
+    oracle.absorb(&proof.combined_commitment.concat());
+    if !fri_verify(
+        &proof.combined_commitment,
+        domain,
+        degree_bound,
+        &proof.proof,
+        options,
+        oracle,
+    ) {
+        return false;
+    }
+
+    // The opened rows must be authentic and combine to the opened layer 0 values
+    if proof.openings.len() != proof.proof.queries.len() {
+        return false;
+    }
+    proof
+        .openings
+        .iter()
+        .zip(proof.proof.queries.iter())
+        .all(|(opening, query)| {
+            let Some(layer) = query.layers.first() else {
+                return false;
+            };
+            let positions = coset_positions(layer.index, domain.size(), layer.values.len());
+            opening.rows.len() == positions.len()
+                && opening.proofs.len() == positions.len()
+                && positions.iter().enumerate().all(|(t, &position)| {
+                    verify_merkle_proof_with_cap(
+                        row_leaf::<Sha256Hasher>(&opening.rows[t]),
+                        position,
+                        &opening.proofs[t],
+                        commitment,
+                        path_length(domain.size(), 2, options.merkle_cap_height),
+                        2,
+                    ) && combine_row(&opening.rows[t], alpha) == layer.values[t]
+                })
+        })
+}
+
+/// Checks that `H(seed || nonce)` starts with at least `bits` zero bits.
+fn check_pow<F: FftField>(seed: &F, nonce: u64, bits: usize) -> bool {
+    let mut data = to_bytes(seed);
+    data.extend_from_slice(&nonce.to_be_bytes());
+    let digest = digest_sha2(&data);
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(prefix).leading_zeros() as usize >= bits
+}
+
+/// Folding factor of every round for a layer 0 of `domain_size` values.
+///
+/// Factors follow `options.fri_folding_schedule` and default to 2 once it is exhausted.
+/// Folding stops once a layer has at most `options.fri_remainder_size` values, and a
+/// factor is reduced if it would fold past that size.
+pub fn fri_folding_factors<F: FftField>(
+    domain_size: usize,
+    options: &ProofOptions<F>,
+) -> Vec<usize> {
+    let remainder_size = options.fri_remainder_size.max(1);
+    let mut factors = Vec::new();
+    let mut layer_len = domain_size;
+    while layer_len > remainder_size {
+        let factor = options
+            .fri_folding_schedule
+            .get(factors.len())
+            .copied()
+            .unwrap_or(2)
+            .min(layer_len / remainder_size);
+        factors.push(factor);
+        layer_len /= factor;
+    }
+    factors
+}
+
+/// Positions of the coset of `index` in a layer of `layer_len` values folded by `factor`.
+pub fn coset_positions(index: usize, layer_len: usize, factor: usize) -> Vec<usize> {
+    let stride = layer_len / factor;
+    (0..factor).map(|t| index + t * stride).collect()
+}
+
+/// Runs the FRI commit and query phases.
+///
+/// `evals` are the layer 0 evaluations committed by `base_tree`, whose cap must already
+/// have been absorbed into the oracle. Layers are folded until at most
+/// `options.fri_remainder_size` values remain; the final layer is not committed but sent
+/// as the coefficients of its interpolating polynomial.
+///
+/// # Arguments
+///
+/// * `evals` - Layer 0 evaluations over `domain`
+/// * `domain` - Layer 0 evaluation domain, possibly a coset
+/// * `base_tree` - Merkle tree over `evals`
+/// * `options` - Merkle cap height, remainder size and query count
+/// * `oracle` - Source of folding challenges and query positions
+///
+/// # Panics
+///
+/// Panics if `options.grinding_bits` exceeds `MAX_GRINDING_BITS`
+pub fn fri_prove<F: FftField>(
+    evals: Vec<F>,
+    domain: &GeneralEvaluationDomain<F>,
+    base_tree: MerkleTree,
+    options: &ProofOptions<F>,
+    oracle: &mut impl RandomOracle,
+) -> FriProof<F> {
+    match fri_prove_observed(evals, domain, base_tree, options, oracle, &()) {
+        Ok(proof) => proof,
+        Err(error) => panic!("{}", error),
+    }
+}
+
+/// Runs `fri_prove`, reporting every folding round to `observer`.
+///
+/// # Errors
+///
+/// Returns `ToyniError::InvalidOptions` if `options.grinding_bits` exceeds
+/// `MAX_GRINDING_BITS`, as no nonce could satisfy the proof-of-work, and
+/// `ToyniError::Cancelled` if the observer cancels before a folding round.
+pub fn fri_prove_observed<F: FftField>(
+    evals: Vec<F>,
+    domain: &GeneralEvaluationDomain<F>,
+    base_tree: MerkleTree,
+    options: &ProofOptions<F>,
+    oracle: &mut impl RandomOracle,
+    observer: &dyn ProofObserver,
+) -> Result<FriProof<F>, ToyniError> {
+    let cap_height = options.merkle_cap_height;
+    let domain_size = domain.size();
+    assert_eq!(
+        evals.len(),
+        domain_size,
+        "Evaluations must cover the domain"
+    );
+    if options.grinding_bits > MAX_GRINDING_BITS {
+        return Err(ToyniError::InvalidOptions(format!(
+            "{} grinding bits exceed the maximum of {}",
+            options.grinding_bits, MAX_GRINDING_BITS
+        )));
+    }
+    let factors = fri_folding_factors(domain_size, options);
+    let num_rounds = factors.len();
+    let _span = debug_span!("fri_prove", domain_size, rounds = num_rounds).entered();
+    let mut layers = vec![evals];
+    let mut layer_domains = vec![*domain];
+    let mut trees = vec![base_tree];
+    let mut challenges = Vec::with_capacity(num_rounds);
+
+    // Commit phase: fold and commit every layer except the final one
+    for (round, &factor) in factors.iter().enumerate() {
+        if observer.is_cancelled() {
+            return Err(ToyniError::Cancelled);
+        }
+        let beta = oracle.draw_field_element();
+        challenges.push(beta);
+        let folded =
+            fri_fold_by_factor(layers.last().unwrap(), &layer_domains[round], beta, factor);
+        if round + 1 < num_rounds {
+            let tree = MerkleTree::from_field_elements(&folded);
+            oracle.absorb(&tree.cap(cap_height).concat());
+            trees.push(tree);
+        }
+        debug!(round, factor, layer_len = folded.len(), "folded layer");
+        layer_domains.push(shrink_domain(&layer_domains[round], factor));
+        layers.push(folded);
+        observer.on_fri_progress(round + 1, num_rounds);
+    }
+
+    // The final layer is sent as its interpolating polynomial
+    let mut final_poly = layer_domains[num_rounds].ifft(layers.last().unwrap());
+    while final_poly.last().is_some_and(|c| c.is_zero()) {
+        final_poly.pop();
+    }
+    oracle.absorb_field_elements(&final_poly);
+
+    // Grind a proof-of-work nonce so query positions are expensive to bias
+    let pow_nonce = if options.grinding_bits > 0 {
+        let seed = oracle.draw_field_element::<F>();
+        let nonce = (0..)
+            .find(|&nonce| check_pow(&seed, nonce, options.grinding_bits))
+            .unwrap();
+        oracle.absorb(&nonce.to_be_bytes());
+        debug!(nonce, "found proof-of-work nonce");
+        nonce
+    } else {
+        0
+    };
+
+    // Query phase: open the folded coset of every committed layer at the sampled positions
+    let mut queries = Vec::with_capacity(options.num_fri_queries());
+    for _ in 0..options.num_fri_queries() {
+        let mut position = oracle.draw_index(domain_size);
+        let mut openings = Vec::with_capacity(num_rounds);
+        for ((layer, tree), &factor) in layers.iter().zip(trees.iter()).zip(factors.iter()) {
+            let index = position % (layer.len() / factor);
+            let positions = coset_positions(index, layer.len(), factor);
+            openings.push(FriLayerOpening {
+                index,
+                values: positions.iter().map(|&i| layer[i]).collect(),
+                proofs: positions
+                    .iter()
+                    .map(|&i| tree.get_proof_with_cap(i, cap_height).unwrap())
+                    .collect(),
+            });
+            position = index;
+        }
+        queries.push(FriQuery { layers: openings });
+    }
+
+    Ok(FriProof {
+        challenges,
+        layer_caps: trees[1..].iter().map(|tree| tree.cap(cap_height)).collect(),
+        queries,
+        final_poly,
+        pow_nonce,
+    })
+}
+
+/// Verifies a FRI proof against the layer 0 commitment.
+///
+/// Re-derives the folding challenges, then for each sampled position authenticates the
+/// opened cosets and checks that folding them yields the opening in the next layer.
+/// The last fold is checked against the final polynomial, whose degree must be below the
+/// layer 0 bound divided by the folding factor of every round.
+///
+/// # Arguments
+///
+/// * `base_cap` - Merkle cap of layer 0, already absorbed into the oracle
+/// * `domain` - Layer 0 evaluation domain, possibly a coset
+/// * `degree_bound` - Layer 0 must have fewer than `degree_bound` coefficients
+/// * `proof` - The FRI proof
+/// * `options` - Merkle cap height, remainder size and query count, as used by the prover
+/// * `oracle` - Oracle in the same state as the prover's after absorbing `base_cap`
+///
+/// # Returns
+///
+/// `true` if all layer openings are authentic and consistent
+pub fn fri_verify<F: FftField>(
+    base_cap: &MerkleCap,
+    domain: &GeneralEvaluationDomain<F>,
+    degree_bound: usize,
+    proof: &FriProof<F>,
+    options: &ProofOptions<F>,
+    oracle: &mut impl RandomOracle,
+) -> bool {
+    match fri_verify_with_report(base_cap, domain, degree_bound, proof, options, oracle) {
+        Ok(()) => true,
+        Err(failure) => {
+            debug!("FRI check failed: {}", failure);
+            false
+        }
+    }
+}
+
+/// Runs `fri_verify`, naming the first check that fails.
+///
+/// # Errors
+///
+/// Returns the failed check, with the query, layer and coset it concerns.
+pub fn fri_verify_with_report<F: FftField>(
+    base_cap: &MerkleCap,
+    domain: &GeneralEvaluationDomain<F>,
+    degree_bound: usize,
+    proof: &FriProof<F>,
+    options: &ProofOptions<F>,
+    oracle: &mut impl RandomOracle,
+) -> Result<(), FriFailure> {
+    let cap_height = options.merkle_cap_height;
+    let domain_size = domain.size();
+    let factors = fri_folding_factors(domain_size, options);
+    let num_rounds = factors.len();
+    let _span = debug_span!("fri_verify", domain_size, rounds = num_rounds).entered();
+    if proof.challenges.len() != num_rounds
+        || proof.layer_caps.len() != num_rounds.saturating_sub(1)
+    {
+        return Err(FriFailure::RoundCount {
+            expected: num_rounds,
+            actual: proof.challenges.len(),
+        });
+    }
+
+    // The final polynomial must respect the folded degree bound
+    let final_degree_bound = degree_bound.div_ceil(factors.iter().product());
+    if proof.final_poly.len() > final_degree_bound {
+        return Err(FriFailure::FinalDegree {
+            coefficients: proof.final_poly.len(),
+            bound: final_degree_bound,
+        });
+    }
+
+    // Replay the commit phase to re-derive the folding challenges
+    for (round, claimed_beta) in proof.challenges.iter().enumerate() {
+        if oracle.draw_field_element::<F>() != *claimed_beta {
+            return Err(FriFailure::Challenge { round });
+        }
+        if let Some(cap) = proof.layer_caps.get(round) {
+            oracle.absorb(&cap.concat());
+        }
+    }
+    oracle.absorb_field_elements(&proof.final_poly);
+
+    // The nonce must satisfy the proof-of-work before any query position is drawn
+    if options.grinding_bits > 0 {
+        let seed = oracle.draw_field_element::<F>();
+        if !check_pow(&seed, proof.pow_nonce, options.grinding_bits) {
+            return Err(FriFailure::ProofOfWork {
+                nonce: proof.pow_nonce,
+            });
+        }
+        oracle.absorb(&proof.pow_nonce.to_be_bytes());
+    }
+
+    let layer_caps: Vec<&MerkleCap> = std::iter::once(base_cap)
+        .chain(proof.layer_caps.iter())
+        .collect();
+    let mut layer_domains = vec![*domain];
+    for (round, &factor) in factors.iter().enumerate() {
+        layer_domains.push(shrink_domain(&layer_domains[round], factor));
+    }
+
+    // Every cap must have the size implied by the cap height
+    let expected_cap_len = |layer_len: usize| layer_len.min(1 << cap_height);
+    if let Some(layer) = layer_caps
+        .iter()
+        .zip(layer_domains.iter())
+        .position(|(cap, layer_domain)| cap.len() != expected_cap_len(layer_domain.size()))
+    {
+        return Err(FriFailure::CapSize { layer });
+    }
+
+    let final_domain = &layer_domains[num_rounds];
+    let final_poly = DensePolynomial::from_coefficients_slice(&proof.final_poly);
+
+    // Without folding rounds the final polynomial must reproduce layer 0 itself
+    if num_rounds == 0 {
+        let evals = final_domain.fft(&proof.final_poly);
+        if MerkleTree::from_field_elements(&evals).cap(cap_height) != *base_cap {
+            return Err(FriFailure::Remainder);
+        }
+        return Ok(());
+    }
+
+    if proof.queries.len() != options.num_fri_queries() {
+        return Err(FriFailure::QueryCount {
+            expected: options.num_fri_queries(),
+            actual: proof.queries.len(),
+        });
+    }
+    for (query_index, query) in proof.queries.iter().enumerate() {
+        if query.layers.len() != num_rounds {
+            return Err(FriFailure::QueryLayers { query: query_index });
+        }
+        let mut position = oracle.draw_index(domain_size);
+
+        for (round, (beta, opening)) in proof.challenges.iter().zip(query.layers.iter()).enumerate()
+        {
+            let layer_len = layer_domains[round].size();
+            let factor = factors[round];
+            let index = position % (layer_len / factor);
+
+            // Authenticate the opened coset in the current layer
+            let positions = coset_positions(index, layer_len, factor);
+            if opening.index != index
+                || opening.values.len() != factor
+                || opening.proofs.len() != factor
+                || positions.iter().enumerate().any(|(t, &i)| {
+                    !verify_merkle_proof_with_cap(
+                        field_element_leaf(&opening.values[t]),
+                        i,
+                        &opening.proofs[t],
+                        layer_caps[round],
+                        path_length(layer_len, 2, cap_height),
+                        2,
+                    )
+                })
+            {
+                return Err(FriFailure::LayerOpening {
+                    query: query_index,
+                    layer: round,
+                    index,
+                });
+            }
+            let expected_next = fold_coset(&opening.values, index, &layer_domains[round], *beta);
 
-    for i in 0..half {
-        let a = evals[i];
-        let b = evals[i + half];
-        let folded = (a + b) * half_inv + (a - b) * half_inv * beta;
-        result.push(folded);
+            // Value claimed for the same position in the next layer
+            let actual_next = if round + 1 == num_rounds {
+                Some(final_poly.evaluate(&final_domain.element(index)))
+            } else {
+                let next_stride = layer_domains[round + 1].size() / factors[round + 1];
+                query.layers[round + 1]
+                    .values
+                    .get(index / next_stride)
+                    .copied()
+            };
+            if actual_next != Some(expected_next) {
+                return Err(FriFailure::Folding {
+                    query: query_index,
+                    layer: round,
+                    index,
+                });
+            }
+
+            position = index;
+        }
+    }
+
+    Ok(())
+}
+
+/// Domain of the layer obtained by folding `domain` by `factor`.
+///
+/// Folding by `k` raises every point to the power `k`, so the result is the subgroup of
+/// size `|domain| / k` shifted by the coset offset raised to `k`.
+fn shrink_domain<F: FftField>(
+    domain: &GeneralEvaluationDomain<F>,
+    factor: usize,
+) -> GeneralEvaluationDomain<F> {
+    let offset = domain.coset_offset().pow([factor as u64]);
+    GeneralEvaluationDomain::<F>::new(domain.size() / factor)
+        .unwrap()
+        .get_coset(offset)
+        .unwrap()
+}
+
+/// Folds evaluations over `domain` by a power-of-two `factor`.
+///
+/// Folding by `2^m` with challenge `β` is `m` binary folds with challenges
+/// `β, β^2, β^4, ...`, which yields `sum(β^r * f_r)` for
+/// `f(x) = sum(x^r * f_r(x^k))`.
+///
+/// # Panics
+///
+/// Panics if `factor` is not a power of two or does not divide the domain size.
+pub fn fri_fold_by_factor<F: FftField>(
+    evals: &[F],
+    domain: &GeneralEvaluationDomain<F>,
+    beta: F,
+    factor: usize,
+) -> Vec<F> {
+    assert!(
+        factor.is_power_of_two(),
+        "Folding factor must be a power of 2"
+    );
+    let mut layer = evals.to_vec();
+    let mut layer_domain = *domain;
+    let mut challenge = beta;
+    for _ in 0..factor.ilog2() {
+        layer = fri_fold_with_domain(&layer, &layer_domain, challenge);
+        layer_domain = shrink_domain(&layer_domain, 2);
+        challenge.square_in_place();
     }
+    layer
+}
+
+/// Folds the opened coset of `index` in a layer over `domain` into one value.
+///
+/// Performs the binary folds of `fri_fold_by_factor` on the coset values only.
+fn fold_coset<F: FftField>(
+    values: &[F],
+    index: usize,
+    domain: &GeneralEvaluationDomain<F>,
+    beta: F,
+) -> F {
+    let half_inv = F::from(2u64).inverse().unwrap();
+    let mut values = values.to_vec();
+    let mut layer_domain = *domain;
+    let mut challenge = beta;
+    while values.len() > 1 {
+        // Coset position t sits at index + t * stride and pairs with t + half
+        let half = values.len() / 2;
+        let stride = layer_domain.size() / values.len();
+        let points: Vec<F> = (0..half)
+            .map(|t| layer_domain.element(index + t * stride))
+            .collect();
+        let x_invs = batch_inverse(&points).unwrap();
+        values = (0..half)
+            .map(|t| {
+                let (a, b) = (values[t], values[t + half]);
+                (a + b) * half_inv + challenge * (a - b) * half_inv * x_invs[t]
+            })
+            .collect();
+        layer_domain = shrink_domain(&layer_domain, 2);
+        challenge.square_in_place();
+    }
+    values[0]
+}
+
+/// Folds evaluations over `domain` into evaluations over the squared domain.
+///
+/// In arkworks' natural ordering the domain is `offset * g^i`, so `evals[i]` and
+/// `evals[i + half]` are the values at `x` and `-x`. Writing
+/// `f(x) = f_even(x^2) + x * f_odd(x^2)`, the result at index `i` is
+/// `f_even(x_i^2) + β * f_odd(x_i^2)` over the domain of size `half` with offset `offset^2`,
+/// which halves the degree bound.
+///
+/// # Panics
+///
+/// Panics if the number of evaluations does not match the domain size.
+pub fn fri_fold_with_domain<F: FftField>(
+    evals: &[F],
+    domain: &GeneralEvaluationDomain<F>,
+    beta: F,
+) -> Vec<F> {
+    assert_eq!(
+        evals.len(),
+        domain.size(),
+        "Evaluations must cover the domain"
+    );
+    let half = evals.len() / 2;
+    let half_inv = F::from(2u64).inverse().unwrap();
+    let points: Vec<F> = domain.elements().take(half).collect();
+    let x_invs = batch_inverse(&points).unwrap();
+    into_iter!(0..half)
+        .map(|i| {
+            let (a, b) = (evals[i], evals[i + half]);
+            (a + b) * half_inv + beta * (a - b) * half_inv * x_invs[i]
+        })
+        .collect()
+}
 
-    result
+/// Folds evaluations over the subgroup of their size using challenge beta.
+///
+/// Shorthand for `fri_fold_with_domain` without a coset offset.
+pub fn fri_fold<F: FftField>(evals: &[F], beta: F) -> Vec<F> {
+    assert!(
+        evals.len().is_multiple_of(2),
+        "Evaluations length must be even"
+    );
+    let domain = GeneralEvaluationDomain::<F>::new(evals.len())
+        .expect("Evaluations length must be a power of 2");
+    fri_fold_with_domain(evals, &domain, beta)
 }
 
 /// Interpolates polynomial from points using FFT.
-pub fn interpolate_poly(xs: &[Fr], ys: &[Fr]) -> DensePolynomial<Fr> {
+pub fn interpolate_poly<F: FftField>(xs: &[F], ys: &[F]) -> DensePolynomial<F> {
     assert_eq!(xs.len(), ys.len(), "Mismatched lengths");
     let domain =
-        GeneralEvaluationDomain::<Fr>::new(xs.len()).expect("Domain size must be a power of 2");
+        GeneralEvaluationDomain::<F>::new(xs.len()).expect("Domain size must be a power of 2");
     let evals = Evaluations::from_vec_and_domain(ys.to_vec(), domain);
     evals.interpolate()
 }
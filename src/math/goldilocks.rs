@@ -0,0 +1,111 @@
+//! The Goldilocks field `p = 2^64 - 2^32 + 1`.
+//!
+//! Elements fit in a single machine word, so field arithmetic is far cheaper than over
+//! the 255-bit BLS12-381 scalar field, and `p - 1` is divisible by `2^32`, so the field
+//! has FFT domains for traces of up to `2^32` extended rows. Proofs are built over it
+//! from `ConstraintSystem::<Goldilocks>::new()` and `ProofOptions::<Goldilocks>::new()`.
+//!
+//! Challenges are drawn from the field itself, so the security of a Goldilocks proof is
+//! capped at 63 bits regardless of the number of FRI queries, see
+//! `ProofOptions::security_level`. Proving over the quadratic extension `GoldilocksExt2`
+//! instead lifts the cap to 126 bits: the trace is still extended and committed over
+//! Goldilocks, and only the quotient, the DEEP composition and the FRI layers, which
+//! depend on challenges, are computed over the extension.
+
+use ark_ff::{
+    MontFp,
+    fields::{Fp2, Fp2Config, Fp64, MontBackend, MontConfig},
+};
+
+/// Montgomery parameters of the Goldilocks field, with 7 generating its multiplicative
+/// group.
+#[derive(MontConfig)]
+#[modulus = "18446744069414584321"]
+#[generator = "7"]
+pub struct GoldilocksConfig;
+
+/// Element of the Goldilocks field.
+pub type Goldilocks = Fp64<MontBackend<GoldilocksConfig, 1>>;
+
+/// Parameters of the quadratic extension `Goldilocks[X] / (X² - 7)`.
+pub struct GoldilocksExt2Config;
+
+impl Fp2Config for GoldilocksExt2Config {
+    type Fp = Goldilocks;
+
+    /// 7 is a quadratic non-residue modulo the Goldilocks prime
+    const NONRESIDUE: Goldilocks = MontFp!("7");
+
+    /// `7^((p^i - 1) / 2)` for `i = 0, 1`
+    const FROBENIUS_COEFF_FP2_C1: &'static [Goldilocks] =
+        &[MontFp!("1"), MontFp!("18446744069414584320")];
+}
+
+/// Element of the quadratic extension of the Goldilocks field.
+pub type GoldilocksExt2 = Fp2<GoldilocksExt2Config>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{math::domain::get_extended_domain, options::ProofOptions};
+    use ark_ff::{AdditiveGroup, FftField, Field, PrimeField};
+    use ark_poly::EvaluationDomain;
+
+    #[test]
+    fn test_field_parameters() {
+        assert_eq!(Goldilocks::MODULUS.0[0], 0xffff_ffff_0000_0001);
+        assert_eq!(Goldilocks::MODULUS_BIT_SIZE, 64);
+        assert_eq!(Goldilocks::TWO_ADICITY, 32);
+        let root = Goldilocks::TWO_ADIC_ROOT_OF_UNITY;
+        assert_eq!(root.pow([1u64 << 32]), Goldilocks::ONE);
+        assert_ne!(root.pow([1u64 << 31]), Goldilocks::ONE);
+        assert_eq!(
+            -Goldilocks::ONE,
+            Goldilocks::from(u64::MAX - u32::MAX as u64)
+        );
+    }
+
+    #[test]
+    fn test_extended_domain_avoids_subgroup() {
+        let options = ProofOptions::<Goldilocks>::new();
+        let domain = get_extended_domain::<Goldilocks>(1 << 10, options.blowup_factor);
+        assert_eq!(domain.size(), 1 << 11);
+        assert_ne!(
+            options.coset_offset.pow([domain.size() as u64]),
+            Goldilocks::ONE
+        );
+    }
+
+    #[test]
+    fn test_security_is_capped_by_field_size() {
+        let level = ProofOptions::<Goldilocks>::new().security_level();
+        assert_eq!(level.conjectured, 63);
+        assert_eq!(level.proven, 40);
+
+        // Challenges from the extension lift the cap to twice the bits of the base field
+        let options = ProofOptions::<GoldilocksExt2>::new().with_fri_queries(200);
+        assert_eq!(options.security_level().conjectured, 126);
+        assert_eq!(options.security_level().proven, 100);
+    }
+
+    #[test]
+    fn test_quadratic_extension() {
+        let seven = Goldilocks::from(7u64);
+        assert_eq!(
+            seven.pow(Goldilocks::MODULUS_MINUS_ONE_DIV_TWO),
+            -Goldilocks::ONE
+        );
+
+        // X² = 7 and the Frobenius map is conjugation
+        let x = GoldilocksExt2::new(Goldilocks::ZERO, Goldilocks::ONE);
+        assert_eq!(x.square(), GoldilocksExt2::from(7u64));
+        let mut conjugate = GoldilocksExt2::new(Goldilocks::from(3u64), Goldilocks::from(5u64));
+        conjugate.frobenius_map_in_place(1);
+        assert_eq!(
+            conjugate,
+            GoldilocksExt2::new(Goldilocks::from(3u64), -Goldilocks::from(5u64))
+        );
+        assert_eq!(GoldilocksExt2::extension_degree(), 2);
+        assert_eq!(GoldilocksExt2::TWO_ADICITY, 32);
+    }
+}
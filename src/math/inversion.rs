@@ -0,0 +1,54 @@
+//! Batch inversion of field elements.
+//!
+//! A field inversion costs about as much as a hundred multiplications. Montgomery's
+//! trick inverts `n` elements with a single inversion and `3(n - 1)` multiplications:
+//! invert the product of all elements, then peel the individual inverses off it with
+//! the prefix products.
+
+use ark_ff::Field;
+
+/// Inverts every element of `values` with a single field inversion.
+///
+/// # Returns
+///
+/// The inverses in the order of `values`, or `None` if any of them is zero
+pub fn batch_inverse<F: Field>(values: &[F]) -> Option<Vec<F>> {
+    // prefixes[i] holds the product of values[..i]
+    let mut prefixes = Vec::with_capacity(values.len());
+    let mut product = F::one();
+    for value in values {
+        if value.is_zero() {
+            return None;
+        }
+        prefixes.push(product);
+        product *= value;
+    }
+
+    // Walk back from the inverse of the full product, dropping one factor at a time
+    let mut inverse = product.inverse()?;
+    let mut inverses = vec![F::zero(); values.len()];
+    for i in (0..values.len()).rev() {
+        inverses[i] = inverse * prefixes[i];
+        inverse *= values[i];
+    }
+    Some(inverses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::Fr;
+    use ark_ff::AdditiveGroup;
+
+    #[test]
+    fn test_batch_inverse() {
+        let values: Vec<Fr> = (1..=20u64).map(|i| Fr::from(i * i + 3)).collect();
+        let inverses = batch_inverse(&values).unwrap();
+        for (value, inverse) in values.iter().zip(&inverses) {
+            assert_eq!(*inverse, value.inverse().unwrap());
+        }
+
+        assert_eq!(batch_inverse::<Fr>(&[]), Some(vec![]));
+        assert_eq!(batch_inverse(&[Fr::from(2u64), Fr::ZERO, Fr::ONE]), None);
+    }
+}
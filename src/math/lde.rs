@@ -0,0 +1,360 @@
+//! Low-degree extension of the trace columns over the extended coset.
+//!
+//! The extended coset `g·⟨w⟩` of size `b·n` splits into the `b` cosets `g·wʲ·⟨wᵇ⟩` of the
+//! trace domain size `n`, where segment `j` holds the extended positions `j, j + b, ...`.
+//! The successor `ω·x = wᵇ·x` of a point lies in the same segment, one position further,
+//! so constraints can be evaluated one segment at a time.
+//!
+//! `TraceLde` either evaluates every column over the whole coset up front, or keeps only
+//! the column polynomials and recomputes one segment at a time. The streaming variant
+//! never holds more than `n` extended rows, so its memory is bounded by the size of the
+//! trace rather than of its extension.
+//!
+//! The prover extends the trace over the base prime field of the field it proves over,
+//! and only lifts rows into the extension where they meet a challenge.
+//!
+//! The prover builds one `TraceLde` per proof, which the trace commitment, the
+//! composition quotient and the DEEP composition all read. `ExecutionTrace::lde` caches
+//! the extension of a trace's own columns; without blinding rows the prover starts from
+//! that cached extension and only evaluates its randomizer and mask columns.
+
+use ark_bls12_381::Fr;
+use ark_ff::{FftField, Field, PrimeField};
+use ark_poly::{
+    EvaluationDomain, GeneralEvaluationDomain, Polynomial, univariate::DensePolynomial,
+};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::borrow::Cow;
+
+use crate::metrics::rows_size;
+use crate::parallel::{into_iter, iter};
+
+/// Rows of the extension at the positions `first, first + stride, ...` of the extended
+/// coset.
+#[derive(Debug, Clone)]
+pub struct LdeSegment<F: FftField = Fr> {
+    /// Extended position of the first row
+    first: usize,
+    /// Distance between the extended positions of consecutive rows
+    stride: usize,
+    /// Number of rows between a row and its successor `ω·x`
+    next_offset: usize,
+    /// One value per column for every row
+    rows: Vec<Vec<F>>,
+}
+
+impl<F: FftField> LdeSegment<F> {
+    /// Evaluates the columns over `domain`, whose points are the extended positions
+    /// `first, first + stride, ...`.
+    fn evaluate(
+        polynomials: &[DensePolynomial<F>],
+        domain: GeneralEvaluationDomain<F>,
+        first: usize,
+        stride: usize,
+        next_offset: usize,
+    ) -> Self {
+        let columns: Vec<Vec<F>> = iter!(polynomials)
+            .map(|poly| poly.evaluate_over_domain_by_ref(domain).evals)
+            .collect();
+        let rows = into_iter!(0..domain.size())
+            .map(|k| columns.iter().map(|column| column[k]).collect())
+            .collect();
+        Self {
+            first,
+            stride,
+            next_offset,
+            rows,
+        }
+    }
+
+    /// Number of rows in the segment.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether the segment holds no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Position of row `k` in the extended coset.
+    pub fn index(&self, k: usize) -> usize {
+        self.first + k * self.stride
+    }
+
+    /// Row `k`, with one value per column.
+    pub fn row(&self, k: usize) -> &[F] {
+        &self.rows[k]
+    }
+
+    /// Successor of row `k`, i.e. the columns at `ω·x` for the point `x` of row `k`.
+    pub fn next_row(&self, k: usize) -> &[F] {
+        self.window_row(k, 1)
+    }
+
+    /// Row `j` of the window starting at row `k`, i.e. the columns at `ωʲ·x` for the
+    /// point `x` of row `k`.
+    pub fn window_row(&self, k: usize, j: usize) -> &[F] {
+        &self.rows[(k + j * self.next_offset) % self.rows.len()]
+    }
+}
+
+/// Low-degree extension of the trace columns, held in memory or streamed in segments.
+#[derive(Debug, Clone)]
+pub struct TraceLde<F: FftField = Fr> {
+    /// Column polynomials, in the order the columns are committed
+    polynomials: Vec<DensePolynomial<F>>,
+    /// Size `n` of the trace domain
+    trace_domain_size: usize,
+    /// The extended coset
+    extended_domain: GeneralEvaluationDomain<F>,
+    /// All rows of the extension as a single segment, unless streaming
+    rows: Option<LdeSegment<F>>,
+}
+
+impl<F: FftField> TraceLde<F> {
+    /// Extends the column polynomials over `extended_domain`.
+    ///
+    /// # Arguments
+    ///
+    /// * `polynomials` - Column polynomials, in the order the columns are committed
+    /// * `trace_domain_size` - Size `n` of the trace domain, dividing the extended size
+    /// * `extended_domain` - The coset the columns are extended over
+    /// * `streaming` - Recompute segments on demand instead of holding every row
+    pub fn new(
+        polynomials: Vec<DensePolynomial<F>>,
+        trace_domain_size: usize,
+        extended_domain: GeneralEvaluationDomain<F>,
+        streaming: bool,
+    ) -> Self {
+        let blowup_factor = extended_domain.size() / trace_domain_size;
+        let rows = (!streaming)
+            .then(|| LdeSegment::evaluate(&polynomials, extended_domain, 0, 1, blowup_factor));
+        Self {
+            polynomials,
+            trace_domain_size,
+            extended_domain,
+            rows,
+        }
+    }
+
+    /// The extension of these columns followed by `polynomials`, sharing the rows
+    /// evaluated already.
+    ///
+    /// Only the new columns are evaluated over the extended coset. Streaming extensions
+    /// hold no rows to share, so their result is streaming as well.
+    pub fn with_columns(&self, polynomials: Vec<DensePolynomial<F>>) -> Self {
+        let rows = self.rows.as_ref().map(|rows| {
+            let blowup_factor = self.extended_domain.size() / self.trace_domain_size;
+            let added =
+                LdeSegment::evaluate(&polynomials, self.extended_domain, 0, 1, blowup_factor);
+            LdeSegment {
+                rows: rows
+                    .rows
+                    .iter()
+                    .zip(added.rows)
+                    .map(|(row, added)| [row.as_slice(), &added].concat())
+                    .collect(),
+                ..added
+            }
+        });
+        Self {
+            polynomials: [self.polynomials.clone(), polynomials].concat(),
+            trace_domain_size: self.trace_domain_size,
+            extended_domain: self.extended_domain,
+            rows,
+        }
+    }
+
+    /// Column polynomials, in the order the columns are committed.
+    pub fn polynomials(&self) -> &[DensePolynomial<F>] {
+        &self.polynomials
+    }
+
+    /// Number of columns.
+    pub fn width(&self) -> usize {
+        self.polynomials.len()
+    }
+
+    /// The extended coset.
+    pub fn extended_domain(&self) -> &GeneralEvaluationDomain<F> {
+        &self.extended_domain
+    }
+
+    /// Bytes held by the column polynomials and, unless streaming, the extended rows.
+    pub fn heap_size(&self) -> usize {
+        let coefficients: usize = self
+            .polynomials
+            .iter()
+            .map(|poly| size_of_val(poly.coeffs.as_slice()))
+            .sum();
+        coefficients + self.rows.as_ref().map_or(0, |rows| rows_size(&rows.rows))
+    }
+
+    /// Bytes of a segment recomputed while streaming, zero when held in memory.
+    pub fn segment_size(&self) -> usize {
+        match self.rows {
+            Some(_) => 0,
+            None => self.trace_domain_size * (size_of::<Vec<F>>() + self.width() * size_of::<F>()),
+        }
+    }
+
+    /// Whether segments are recomputed on demand.
+    pub fn is_streaming(&self) -> bool {
+        self.rows.is_none()
+    }
+
+    /// The rows of the extension, as a single segment in memory or one coset of the
+    /// trace domain size at a time.
+    pub fn segments(&self) -> impl Iterator<Item = Cow<'_, LdeSegment<F>>> {
+        let num_segments = match self.rows {
+            Some(_) => 1,
+            None => self.extended_domain.size() / self.trace_domain_size,
+        };
+        (0..num_segments).map(move |j| match &self.rows {
+            Some(rows) => Cow::Borrowed(rows),
+            None => {
+                let domain = GeneralEvaluationDomain::<F>::new(self.trace_domain_size)
+                    .and_then(|domain| domain.get_coset(self.extended_domain.element(j)))
+                    .expect("Segments have the size of the trace domain");
+                Cow::Owned(LdeSegment::evaluate(
+                    &self.polynomials,
+                    domain,
+                    j,
+                    num_segments,
+                    1,
+                ))
+            }
+        })
+    }
+
+    /// Applies `f` to every row and its successor, returning the results in extended
+    /// domain order.
+    ///
+    /// `f` receives the extended position, the row and the successor row.
+    pub fn map_rows<T: Send>(&self, f: impl Fn(usize, &[F], &[F]) -> T + Sync) -> Vec<T> {
+        let mut results: Vec<Option<T>> = (0..self.extended_domain.size()).map(|_| None).collect();
+        for segment in self.segments() {
+            let values: Vec<T> = into_iter!(0..segment.len())
+                .map(|k| f(segment.index(k), segment.row(k), segment.next_row(k)))
+                .collect();
+            for (k, value) in values.into_iter().enumerate() {
+                results[segment.index(k)] = Some(value);
+            }
+        }
+        results.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// Applies `f` to every window of `window` consecutive rows, returning the results
+    /// in extended domain order.
+    ///
+    /// `f` receives the extended position and the rows at `x, ω·x, ..., ω^(window-1)·x`.
+    pub fn map_windows<T: Send>(
+        &self,
+        window: usize,
+        f: impl Fn(usize, &[&[F]]) -> T + Sync,
+    ) -> Vec<T> {
+        let mut results: Vec<Option<T>> = (0..self.extended_domain.size()).map(|_| None).collect();
+        for segment in self.segments() {
+            let values: Vec<T> = into_iter!(0..segment.len())
+                .map(|k| {
+                    let rows: Vec<&[F]> = (0..window).map(|j| segment.window_row(k, j)).collect();
+                    f(segment.index(k), &rows)
+                })
+                .collect();
+            for (k, value) in values.into_iter().enumerate() {
+                results[segment.index(k)] = Some(value);
+            }
+        }
+        results.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// Every column evaluated at `x`, a point of an extension of `F` such as the
+    /// out-of-domain point.
+    pub fn evaluate_at<E: Field<BasePrimeField = F>>(&self, x: E) -> Vec<E>
+    where
+        F: PrimeField,
+    {
+        iter!(self.polynomials)
+            .map(|poly| {
+                poly.coeffs.iter().rev().fold(E::ZERO, |acc, coeff| {
+                    acc * x + E::from_base_prime_field(*coeff)
+                })
+            })
+            .collect()
+    }
+
+    /// The row at position `index` of the extended coset.
+    pub fn row(&self, index: usize) -> Vec<F> {
+        match &self.rows {
+            Some(rows) => rows.row(index).to_vec(),
+            None => {
+                let x = self.extended_domain.element(index);
+                self.polynomials
+                    .iter()
+                    .map(|poly| poly.evaluate(&x))
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_poly::DenseUVPolynomial;
+
+    #[test]
+    fn test_streaming_matches_in_memory() {
+        let extended_domain = GeneralEvaluationDomain::<Fr>::new(32)
+            .unwrap()
+            .get_coset(Fr::GENERATOR)
+            .unwrap();
+        // A column of the trace degree and a mask column exceeding the segment size
+        let polynomials = vec![
+            DensePolynomial::from_coefficients_vec((1..=8u64).map(Fr::from).collect()),
+            DensePolynomial::from_coefficients_vec((1..=20u64).map(Fr::from).collect()),
+        ];
+        let in_memory = TraceLde::new(polynomials.clone(), 8, extended_domain, false);
+        let streaming = TraceLde::new(polynomials.clone(), 8, extended_domain, true);
+        assert!(streaming.is_streaming() && !in_memory.is_streaming());
+        assert_eq!(streaming.segments().count(), 4);
+
+        // Every row and successor matches the columns evaluated at x and ω·x
+        let trace_generator = GeneralEvaluationDomain::<Fr>::new(8).unwrap().group_gen();
+        let expected = |i: usize, row: &[Fr], next: &[Fr]| {
+            let x = extended_domain.element(i);
+            polynomials
+                .iter()
+                .zip(row.iter().zip(next))
+                .all(|(poly, (value, next))| {
+                    *value == poly.evaluate(&x) && *next == poly.evaluate(&(trace_generator * x))
+                })
+        };
+        assert!(in_memory.map_rows(expected).into_iter().all(|ok| ok));
+        assert!(streaming.map_rows(expected).into_iter().all(|ok| ok));
+        let window = |i: usize, rows: &[&[Fr]]| {
+            let x = extended_domain.element(i) * trace_generator.pow([2]);
+            rows.len() == 3 && rows[2] == [polynomials[0].evaluate(&x), polynomials[1].evaluate(&x)]
+        };
+        assert!(in_memory.map_windows(3, window).into_iter().all(|ok| ok));
+        assert!(streaming.map_windows(3, window).into_iter().all(|ok| ok));
+        for index in [0, 5, 31] {
+            assert_eq!(streaming.row(index), in_memory.row(index));
+        }
+
+        // Adding columns to an extension matches extending them all at once
+        let first = TraceLde::new(polynomials[..1].to_vec(), 8, extended_domain, false);
+        let combined = first.with_columns(polynomials[1..].to_vec());
+        assert_eq!(combined.width(), 2);
+        assert!(!combined.is_streaming());
+        for index in [0, 5, 31] {
+            assert_eq!(combined.row(index), in_memory.row(index));
+        }
+        assert_eq!(
+            combined.map_rows(|_, row, next| [row, next].concat()),
+            in_memory.map_rows(|_, row, next| [row, next].concat())
+        );
+    }
+}
@@ -4,6 +4,13 @@
 //! including polynomial operations and the FRI (Fast Reed-Solomon Interactive Oracle Proof) protocol.
 
 pub mod composition;
+pub mod deep;
 pub mod domain;
+pub mod eval_poly;
+pub mod fr_ext2;
 pub mod fri;
+pub mod goldilocks;
+pub mod inversion;
+pub mod lde;
 pub mod polynomial;
+pub mod serde_fr;
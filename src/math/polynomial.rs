@@ -1,11 +1,20 @@
 //! Basic polynomial operations over finite fields.
 
 use ark_bls12_381::Fr;
-use ark_ff::{Zero, UniformRand};
-use ark_poly::univariate::DensePolynomial;
-use std::fmt;
+use ark_ff::FftField;
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain, univariate::DensePolynomial};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{fmt, ops};
+
+use crate::error::ToyniError;
+use crate::parallel::iter;
 use rand;
 
+/// Number of coefficients both factors must reach for `Polynomial::multiply` to switch
+/// from the schoolbook algorithm to FFTs.
+const FFT_MULTIPLY_THRESHOLD: usize = 64;
+
 #[derive(Debug, Clone)]
 /// Polynomial with finite field coefficients.
 ///
@@ -16,13 +25,13 @@ use rand;
 ///
 /// * The coefficients vector should not have trailing zeros
 /// * All coefficients should be valid field elements
-pub struct Polynomial {
+pub struct Polynomial<F: FftField = Fr> {
     /// Coefficients in ascending order of power.
     /// The vector must not have trailing zeros.
-    pub coefficients: Vec<Fr>,
+    pub coefficients: Vec<F>,
 }
 
-impl Polynomial {
+impl<F: FftField> Polynomial<F> {
     /// Creates a new polynomial from coefficients.
     ///
     /// # Arguments
@@ -36,7 +45,7 @@ impl Polynomial {
     /// # Note
     ///
     /// Any trailing zeros in the coefficients vector will be removed.
-    pub fn new(mut coefficients: Vec<Fr>) -> Self {
+    pub fn new(mut coefficients: Vec<F>) -> Self {
         // Remove trailing zeros
         while coefficients.last().is_some_and(|&x| x.is_zero()) {
             coefficients.pop();
@@ -68,8 +77,8 @@ impl Polynomial {
     /// # Returns
     ///
     /// The leading coefficient
-    pub fn leading_coefficient(&self) -> Fr {
-        self.coefficients.last().copied().unwrap_or(Fr::zero())
+    pub fn leading_coefficient(&self) -> F {
+        self.coefficients.last().copied().unwrap_or(F::zero())
     }
 
     /// Divides this polynomial by another, returns (quotient, remainder).
@@ -80,16 +89,16 @@ impl Polynomial {
     ///
     /// # Returns
     ///
-    /// Option containing a tuple of (quotient, remainder) if division is possible,
-    /// None if the divisor is zero or empty
+    /// A tuple of (quotient, remainder), or `ToyniError::DivisionByZeroPoly` if the
+    /// divisor is zero or empty
     ///
     /// # Details
     ///
     /// The division is performed using the standard long division algorithm
     /// over the finite field. The remainder will have degree less than the divisor.
-    pub fn divide(&self, divisor: &Polynomial) -> Option<(Polynomial, Polynomial)> {
+    pub fn divide(&self, divisor: &Self) -> Result<(Self, Self), ToyniError> {
         if divisor.coefficients.is_empty() || divisor.leading_coefficient().is_zero() {
-            return None;
+            return Err(ToyniError::DivisionByZeroPoly);
         }
 
         let dividend = self.coefficients.clone();
@@ -98,10 +107,10 @@ impl Polynomial {
 
         // If dividend degree is less than divisor degree, quotient is zero
         if dividend_degree < divisor_degree {
-            return Some((Polynomial::zero(), self.clone()));
+            return Ok((Self::zero(), self.clone()));
         }
 
-        let mut quotient = vec![Fr::zero(); dividend_degree - divisor_degree + 1];
+        let mut quotient = vec![F::zero(); dividend_degree - divisor_degree + 1];
         let mut remainder = dividend.clone();
 
         // Perform long division
@@ -124,13 +133,60 @@ impl Polynomial {
             remainder.pop();
         }
 
-        Some((Polynomial::new(quotient), Polynomial::new(remainder)))
+        Ok((Self::new(quotient), Self::new(remainder)))
+    }
+
+    /// Divides this polynomial by `x - a` using synthetic division, in O(n).
+    ///
+    /// # Returns
+    ///
+    /// A tuple of (quotient, remainder), where the remainder is the value of the
+    /// polynomial at `a`
+    pub fn divide_by_linear(&self, a: F) -> (Self, F) {
+        let mut quotient = vec![F::zero(); self.coefficients.len().saturating_sub(1)];
+        let mut remainder = F::zero();
+        for (i, coeff) in self.coefficients.iter().enumerate().rev() {
+            if i < quotient.len() {
+                quotient[i] = remainder;
+            }
+            remainder = remainder * a + coeff;
+        }
+        (Self::new(quotient), remainder)
+    }
+
+    /// Divides this polynomial by the vanishing polynomial `x^n - 1` of a domain of size
+    /// `n`, in O(n) of the dividend's length.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of (quotient, remainder), or `ToyniError::DivisionByZeroPoly` if `n` is
+    /// zero
+    pub fn divide_by_vanishing(&self, n: usize) -> Result<(Self, Self), ToyniError> {
+        if n == 0 {
+            return Err(ToyniError::DivisionByZeroPoly);
+        }
+        let len = self.coefficients.len();
+        if len <= n {
+            return Ok((Self::zero(), self.clone()));
+        }
+
+        // x^i = x^(i - n) * (x^n - 1) + x^(i - n), so every coefficient at or above x^n
+        // moves to the quotient and folds n places down
+        let mut remainder = self.coefficients.clone();
+        let mut quotient = vec![F::zero(); len - n];
+        for i in (n..len).rev() {
+            quotient[i - n] = remainder[i];
+            let coeff = remainder[i];
+            remainder[i - n] += coeff;
+        }
+        remainder.truncate(n);
+        Ok((Self::new(quotient), Self::new(remainder)))
     }
 
     /// Adds two polynomials.
-    pub fn add(&self, other: &Polynomial) -> Polynomial {
+    pub fn add(&self, other: &Self) -> Self {
         let max_len = std::cmp::max(self.coefficients.len(), other.coefficients.len());
-        let mut result = vec![Fr::zero(); max_len];
+        let mut result = vec![F::zero(); max_len];
 
         for (i, coeff) in result.iter_mut().enumerate().take(self.coefficients.len()) {
             *coeff += self.coefficients[i];
@@ -140,16 +196,93 @@ impl Polynomial {
             *coeff += other.coefficients[i];
         }
 
-        Polynomial::new(result)
+        Self::new(result)
+    }
+
+    /// Subtracts `other` from this polynomial.
+    pub fn sub(&self, other: &Self) -> Self {
+        let max_len = std::cmp::max(self.coefficients.len(), other.coefficients.len());
+        let mut result = vec![F::zero(); max_len];
+
+        for (i, coeff) in result.iter_mut().enumerate().take(self.coefficients.len()) {
+            *coeff += self.coefficients[i];
+        }
+
+        for (i, coeff) in result.iter_mut().enumerate().take(other.coefficients.len()) {
+            *coeff -= other.coefficients[i];
+        }
+
+        Self::new(result)
+    }
+
+    /// Multiplies the polynomial by a scalar.
+    pub fn scale(&self, scalar: F) -> Self {
+        Self::new(self.coefficients.iter().map(|c| *c * scalar).collect())
+    }
+
+    /// Composes two polynomials, computing `p(q(x))` for `p = self` and `q = other`.
+    ///
+    /// Evaluates `p` at `q` with Horner's method, so the cost is `deg(p)` polynomial
+    /// multiplications of growing size.
+    pub fn compose(&self, other: &Self) -> Self {
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(Self::zero(), |acc, &coeff| {
+                &(&acc * other) + &Self::new(vec![coeff])
+            })
+    }
+
+    /// Substitutes `c·x` for `x`, computing `p(c·x)`.
+    ///
+    /// The coefficient of `x^i` is scaled by `c^i`, which moves evaluations between a
+    /// domain and its coset: `p(c·x)` over `⟨w⟩` equals `p` over `c·⟨w⟩`.
+    pub fn shift(&self, c: F) -> Self {
+        let mut power = F::one();
+        Self::new(
+            self.coefficients
+                .iter()
+                .map(|&coeff| {
+                    let scaled = coeff * power;
+                    power *= c;
+                    scaled
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the formal derivative of the polynomial.
+    pub fn derivative(&self) -> Self {
+        Self::new(
+            self.coefficients
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(i, &coeff)| F::from(i as u64) * coeff)
+                .collect(),
+        )
     }
 
     /// Multiplies two polynomials.
-    pub fn multiply(&self, other: &Polynomial) -> Polynomial {
+    ///
+    /// Small factors are multiplied with the O(n²) schoolbook algorithm; once both have
+    /// at least `FFT_MULTIPLY_THRESHOLD` coefficients, the product is interpolated from
+    /// its values over a domain large enough to hold it in O(n log n).
+    pub fn multiply(&self, other: &Self) -> Self {
+        if self.coefficients.len().min(other.coefficients.len()) < FFT_MULTIPLY_THRESHOLD {
+            self.multiply_naive(other)
+        } else {
+            self.multiply_fft(other)
+        }
+    }
+
+    /// Multiplies two polynomials coefficient by coefficient.
+    fn multiply_naive(&self, other: &Self) -> Self {
         if self.coefficients.is_empty() || other.coefficients.is_empty() {
-            return Polynomial::new(vec![]);
+            return Self::new(vec![]);
         }
 
-        let mut result = vec![Fr::zero(); self.degree() + other.degree() + 1];
+        let mut result = vec![F::zero(); self.degree() + other.degree() + 1];
 
         for i in 0..self.coefficients.len() {
             for j in 0..other.coefficients.len() {
@@ -157,7 +290,29 @@ impl Polynomial {
             }
         }
 
-        Polynomial::new(result)
+        Self::new(result)
+    }
+
+    /// Multiplies two polynomials by evaluating both over a power-of-two domain,
+    /// multiplying pointwise and interpolating the product.
+    ///
+    /// Falls back to `multiply_naive` if `F` has no domain of the product size.
+    fn multiply_fft(&self, other: &Self) -> Self {
+        if self.coefficients.is_empty() || other.coefficients.is_empty() {
+            return Self::new(vec![]);
+        }
+        let product_len = self.coefficients.len() + other.coefficients.len() - 1;
+        let Some(domain) = GeneralEvaluationDomain::<F>::new(product_len) else {
+            return self.multiply_naive(other);
+        };
+
+        let mut evals = domain.fft(&self.coefficients);
+        for (value, other) in evals.iter_mut().zip(domain.fft(&other.coefficients)) {
+            *value *= other;
+        }
+        let mut coefficients = domain.ifft(&evals);
+        coefficients.truncate(product_len);
+        Self::new(coefficients)
     }
 
     /// Evaluates the polynomial at point x.
@@ -173,9 +328,9 @@ impl Polynomial {
     /// # Details
     ///
     /// Evaluation is performed using Horner's method for efficiency.
-    pub fn evaluate(&self, x: Fr) -> Fr {
+    pub fn evaluate(&self, x: F) -> F {
         if self.coefficients.is_empty() {
-            return Fr::zero();
+            return F::zero();
         }
 
         let mut result = self.coefficients[self.coefficients.len() - 1];
@@ -185,6 +340,36 @@ impl Polynomial {
         result
     }
 
+    /// Evaluates the polynomial at each of `points`.
+    ///
+    /// Points are evaluated independently with Horner's method, on the rayon thread pool
+    /// with the `parallel` feature. Points that form a subgroup or coset are evaluated
+    /// faster by `evaluate_over_domain`.
+    pub fn evaluate_batch(&self, points: &[F]) -> Vec<F> {
+        iter!(points).map(|&x| self.evaluate(x)).collect()
+    }
+
+    /// Evaluates the polynomial over every element of `domain` with a single FFT.
+    ///
+    /// # Returns
+    ///
+    /// The values at `domain.element(0), domain.element(1), ...`
+    pub fn evaluate_over_domain(&self, domain: &GeneralEvaluationDomain<F>) -> Vec<F> {
+        // Every element satisfies x^n = offset^n, so coefficients past the domain size
+        // fold onto x^(i mod n) with one factor of offset^n per wrap
+        let size = domain.size();
+        let wrap = domain.coset_offset_pow_size();
+        let mut folded = vec![F::zero(); size];
+        let mut factor = F::one();
+        for chunk in self.coefficients.chunks(size) {
+            for (value, &coeff) in folded.iter_mut().zip(chunk) {
+                *value += factor * coeff;
+            }
+            factor *= wrap;
+        }
+        domain.fft(&folded)
+    }
+
     /// Creates a polynomial from a dense polynomial.
     ///
     /// # Arguments
@@ -194,7 +379,7 @@ impl Polynomial {
     /// # Returns
     ///
     /// A new polynomial with the same coefficients
-    pub fn from_dense_poly(poly: DensePolynomial<Fr>) -> Self {
+    pub fn from_dense_poly(poly: DensePolynomial<F>) -> Self {
         Self::new(poly.coeffs)
     }
 
@@ -209,7 +394,7 @@ impl Polynomial {
     ///
     /// A polynomial representing zero
     pub fn zero() -> Self {
-        Self::new(vec![Fr::zero()])
+        Self::new(vec![F::zero()])
     }
 
     /// Creates a random polynomial of given degree.
@@ -225,7 +410,7 @@ impl Polynomial {
     pub fn random(degree: usize, rng: &mut impl rand::Rng) -> Self {
         let mut coefficients = Vec::with_capacity(degree + 1);
         for _ in 0..=degree {
-            coefficients.push(Fr::rand(rng));
+            coefficients.push(F::rand(rng));
         }
         Self::new(coefficients)
     }
@@ -254,12 +439,116 @@ impl Polynomial {
     /// The current implementation exposes the raw coefficients, which may leak
     /// information about the trace. In a zero-knowledge implementation, these
     /// should be committed to using a Merkle tree.
-    pub fn coefficients(&self) -> &[Fr] {
+    pub fn coefficients(&self) -> &[F] {
         &self.coefficients
     }
+
+    /// Coefficients without trailing zeros, in case the public field was set directly.
+    fn trimmed(&self) -> &[F] {
+        let len = self
+            .coefficients
+            .iter()
+            .rposition(|c| !c.is_zero())
+            .map_or(0, |i| i + 1);
+        &self.coefficients[..len]
+    }
 }
 
-impl fmt::Display for Polynomial {
+/// Polynomials are equal if their coefficients agree up to trailing zeros.
+impl<F: FftField> PartialEq for Polynomial<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.trimmed() == other.trimmed()
+    }
+}
+
+impl<F: FftField> Eq for Polynomial<F> {}
+
+/// The coefficient of `x^index`.
+///
+/// Panics if `index` exceeds the length of the coefficient vector, like indexing it.
+impl<F: FftField> ops::Index<usize> for Polynomial<F> {
+    type Output = F;
+
+    fn index(&self, index: usize) -> &F {
+        &self.coefficients[index]
+    }
+}
+
+impl<F: FftField> ops::Neg for &Polynomial<F> {
+    type Output = Polynomial<F>;
+
+    fn neg(self) -> Polynomial<F> {
+        Polynomial::new(self.coefficients.iter().map(|c| -*c).collect())
+    }
+}
+
+impl<F: FftField> ops::Neg for Polynomial<F> {
+    type Output = Polynomial<F>;
+
+    fn neg(self) -> Polynomial<F> {
+        -&self
+    }
+}
+
+/// Implements a binary operator between polynomials for every combination of owned and
+/// borrowed operands, in terms of the inherent method of the same meaning.
+macro_rules! impl_polynomial_op {
+    ($trait:ident, $op:ident, $method:ident) => {
+        impl<F: FftField> ops::$trait<&Polynomial<F>> for &Polynomial<F> {
+            type Output = Polynomial<F>;
+
+            fn $op(self, other: &Polynomial<F>) -> Polynomial<F> {
+                Polynomial::$method(self, other)
+            }
+        }
+
+        impl<F: FftField> ops::$trait<Polynomial<F>> for &Polynomial<F> {
+            type Output = Polynomial<F>;
+
+            fn $op(self, other: Polynomial<F>) -> Polynomial<F> {
+                Polynomial::$method(self, &other)
+            }
+        }
+
+        impl<F: FftField> ops::$trait<&Polynomial<F>> for Polynomial<F> {
+            type Output = Polynomial<F>;
+
+            fn $op(self, other: &Polynomial<F>) -> Polynomial<F> {
+                Polynomial::$method(&self, other)
+            }
+        }
+
+        impl<F: FftField> ops::$trait<Polynomial<F>> for Polynomial<F> {
+            type Output = Polynomial<F>;
+
+            fn $op(self, other: Polynomial<F>) -> Polynomial<F> {
+                Polynomial::$method(&self, &other)
+            }
+        }
+    };
+}
+
+impl_polynomial_op!(Add, add, add);
+impl_polynomial_op!(Sub, sub, sub);
+impl_polynomial_op!(Mul, mul, multiply);
+
+impl<F: FftField> ops::Mul<F> for &Polynomial<F> {
+    type Output = Polynomial<F>;
+
+    fn mul(self, scalar: F) -> Polynomial<F> {
+        self.scale(scalar)
+    }
+}
+
+impl<F: FftField> ops::Mul<F> for Polynomial<F> {
+    type Output = Polynomial<F>;
+
+    fn mul(self, scalar: F) -> Polynomial<F> {
+        self.scale(scalar)
+    }
+}
+
+impl<F: FftField> fmt::Display for Polynomial<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.coefficients.is_empty() {
             return write!(f, "0");
@@ -286,3 +575,150 @@ impl fmt::Display for Polynomial {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::{AdditiveGroup, Field};
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn test_fft_multiply_matches_naive() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for (a_degree, b_degree) in [(0, 0), (3, 70), (63, 63), (64, 64), (100, 300), (255, 256)] {
+            let a = Polynomial::<Fr>::random(a_degree, &mut rng);
+            let b = Polynomial::<Fr>::random(b_degree, &mut rng);
+            let expected = a.multiply_naive(&b);
+            assert_eq!(expected.degree(), a_degree + b_degree);
+            assert_eq!(a.multiply_fft(&b).coefficients, expected.coefficients);
+            assert_eq!(a.multiply(&b).coefficients, expected.coefficients);
+        }
+
+        // Products with the zero polynomial stay zero on both paths
+        let a = Polynomial::<Fr>::random(100, &mut rng);
+        assert!(a.multiply_fft(&Polynomial::zero()).is_zero());
+        assert!(a.multiply_naive(&Polynomial::zero()).is_zero());
+    }
+
+    #[test]
+    fn test_operators() {
+        let p = Polynomial::new(vec![Fr::from(1u64), Fr::from(2u64), Fr::from(3u64)]);
+        let q = Polynomial::new(vec![Fr::from(4u64), Fr::from(5u64)]);
+
+        let sum = Polynomial::new(vec![Fr::from(5u64), Fr::from(7u64), Fr::from(3u64)]);
+        let difference = Polynomial::new(vec![-Fr::from(3u64), -Fr::from(3u64), Fr::from(3u64)]);
+        assert_eq!(&p + &q, sum);
+        assert_eq!(&p - &q, difference);
+        assert_eq!(&p - &p, Polynomial::zero());
+        assert_eq!(-&q + &q, Polynomial::zero());
+        assert_eq!(&p * &q, p.multiply(&q));
+        assert_eq!(p.clone() * Fr::from(2u64), &p + &p);
+        assert_eq!(p[2], Fr::from(3u64));
+
+        // Trailing zeros written to the public field do not affect equality
+        let padded = Polynomial {
+            coefficients: vec![Fr::from(4u64), Fr::from(5u64), Fr::ZERO],
+        };
+        assert_eq!(padded, q);
+        assert_ne!(p, q);
+    }
+
+    #[test]
+    fn test_compose_shift_derivative() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let p = Polynomial::<Fr>::random(6, &mut rng);
+        let q = Polynomial::<Fr>::random(3, &mut rng);
+        let c = Fr::from(7u64);
+
+        let composed = p.compose(&q);
+        assert_eq!(composed.degree(), 18);
+        let shifted = p.shift(c);
+        for x in (0..5u64).map(Fr::from) {
+            assert_eq!(composed.evaluate(x), p.evaluate(q.evaluate(x)));
+            assert_eq!(shifted.evaluate(x), p.evaluate(c * x));
+        }
+        assert_eq!(p.compose(&Polynomial::zero()), Polynomial::new(vec![p[0]]));
+
+        // A shift moves evaluations from the subgroup to its coset
+        let subgroup = GeneralEvaluationDomain::<Fr>::new(8).unwrap();
+        let coset = subgroup.get_coset(c).unwrap();
+        assert_eq!(
+            shifted.evaluate_over_domain(&subgroup),
+            p.evaluate_over_domain(&coset)
+        );
+
+        // d/dx (1 + 2x + 3x^2) = 2 + 6x, and constants vanish
+        let poly = Polynomial::new(vec![Fr::from(1u64), Fr::from(2u64), Fr::from(3u64)]);
+        assert_eq!(
+            poly.derivative(),
+            Polynomial::new(vec![Fr::from(2u64), Fr::from(6u64)])
+        );
+        assert!(Polynomial::new(vec![Fr::from(9u64)]).derivative().is_zero());
+        // Product rule
+        assert_eq!(
+            (&p * &q).derivative(),
+            &(&p.derivative() * &q) + &(&p * &q.derivative())
+        );
+    }
+
+    #[test]
+    fn test_batch_evaluation_matches_horner() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let subgroup = GeneralEvaluationDomain::<Fr>::new(16).unwrap();
+        let coset = subgroup.get_coset(Fr::from(7u64)).unwrap();
+        for degree in [0, 5, 15, 16, 40] {
+            let poly = Polynomial::<Fr>::random(degree, &mut rng);
+            let points: Vec<Fr> = (0..10u64).map(Fr::from).collect();
+            let expected: Vec<Fr> = points.iter().map(|&x| poly.evaluate(x)).collect();
+            assert_eq!(poly.evaluate_batch(&points), expected);
+
+            // Degrees at or above the domain size wrap around the subgroup and the coset
+            for domain in [subgroup, coset] {
+                let expected: Vec<Fr> = domain.elements().map(|x| poly.evaluate(x)).collect();
+                assert_eq!(poly.evaluate_over_domain(&domain), expected);
+            }
+        }
+        assert_eq!(
+            Polynomial::<Fr>::zero().evaluate_over_domain(&coset),
+            vec![Fr::ZERO; 16]
+        );
+        assert!(Polynomial::<Fr>::zero().evaluate_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_fast_division_matches_long_division() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for degree in [0, 1, 7, 64] {
+            let dividend = Polynomial::<Fr>::random(degree, &mut rng);
+
+            let a = Fr::from(5u64);
+            let (quotient, remainder) = dividend.divide_by_linear(a);
+            let (expected, expected_remainder) = dividend
+                .divide(&Polynomial::new(vec![-a, Fr::ONE]))
+                .unwrap();
+            assert_eq!(quotient.coefficients, expected.coefficients);
+            assert_eq!(
+                Polynomial::new(vec![remainder]).coefficients,
+                expected_remainder.coefficients
+            );
+            assert_eq!(remainder, dividend.evaluate(a));
+
+            for n in [1, 4, 16, 128] {
+                let (quotient, remainder) = dividend.divide_by_vanishing(n).unwrap();
+                let mut vanishing = vec![Fr::ZERO; n + 1];
+                vanishing[0] = -Fr::ONE;
+                vanishing[n] = Fr::ONE;
+                let (expected, expected_remainder) =
+                    dividend.divide(&Polynomial::new(vanishing)).unwrap();
+                assert_eq!(quotient.coefficients, expected.coefficients);
+                assert_eq!(remainder.coefficients, expected_remainder.coefficients);
+            }
+        }
+        assert_eq!(
+            Polynomial::<Fr>::random(3, &mut rng)
+                .divide_by_vanishing(0)
+                .err(),
+            Some(ToyniError::DivisionByZeroPoly)
+        );
+    }
+}
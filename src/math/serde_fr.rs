@@ -0,0 +1,126 @@
+//! Serde helpers for field elements.
+//!
+//! Field elements are encoded as their canonical big-endian bytes, and elements of an
+//! extension field as those of their base prime field coefficients one after another.
+//! Decoding rejects encodings that are not reduced modulo the field order, so every
+//! element has exactly one serialized form. Use with `#[serde(with = "crate::math::serde_fr")]`, or
+//! `serde_fr::vec` and `serde_fr::rows` for `Vec<F>` and `Vec<Vec<F>>` fields.
+
+use ark_ff::{BigInteger, Field, PrimeField};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+/// Serializes a field element as its canonical big-endian bytes.
+pub fn serialize<F: Field, S: Serializer>(value: &F, serializer: S) -> Result<S::Ok, S::Error> {
+    to_bytes(value).serialize(serializer)
+}
+
+/// Deserializes a field element from its canonical big-endian bytes.
+pub fn deserialize<'de, F: Field, D: Deserializer<'de>>(deserializer: D) -> Result<F, D::Error> {
+    let bytes = Vec::<u8>::deserialize(deserializer)?;
+    decode(&bytes).map_err(D::Error::custom)
+}
+
+/// Canonical big-endian bytes of a field element.
+pub(crate) fn to_bytes<F: Field>(value: &F) -> Vec<u8> {
+    value
+        .to_base_prime_field_elements()
+        .flat_map(|coefficient| coefficient.into_bigint().to_bytes_be())
+        .collect()
+}
+
+/// Length in bytes of the canonical encoding of an element of `F`.
+pub(crate) fn encoded_len<F: Field>() -> usize {
+    let coefficient_len = F::BasePrimeField::MODULUS.to_bytes_be().len();
+    F::extension_degree() as usize * coefficient_len
+}
+
+/// Decodes canonical big-endian bytes into a field element.
+pub(crate) fn decode<F: Field>(bytes: &[u8]) -> Result<F, &'static str> {
+    if bytes.len() != encoded_len::<F>() {
+        return Err("non-canonical field element encoding");
+    }
+    let coefficients = bytes
+        .chunks(encoded_len::<F::BasePrimeField>())
+        .map(|chunk| {
+            let coefficient = F::BasePrimeField::from_be_bytes_mod_order(chunk);
+            (coefficient.into_bigint().to_bytes_be() == chunk)
+                .then_some(coefficient)
+                .ok_or("non-canonical field element encoding")
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    F::from_base_prime_field_elems(coefficients).ok_or("non-canonical field element encoding")
+}
+
+/// Serde helpers for vectors of field elements.
+pub mod vec {
+    use super::*;
+
+    /// Serializes field elements as a sequence of canonical big-endian byte strings.
+    pub fn serialize<F: Field, S: Serializer>(
+        values: &[F],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        values
+            .iter()
+            .map(to_bytes)
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    /// Deserializes field elements from a sequence of canonical big-endian byte strings.
+    pub fn deserialize<'de, F: Field, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<F>, D::Error> {
+        Vec::<Vec<u8>>::deserialize(deserializer)?
+            .iter()
+            .map(|bytes| decode(bytes).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+/// Serde helpers for rows of field elements.
+pub mod rows {
+    use super::*;
+
+    /// Serializes rows of field elements as nested sequences of canonical byte strings.
+    pub fn serialize<F: Field, S: Serializer>(
+        rows: &[Vec<F>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        rows.iter()
+            .map(|row| row.iter().map(to_bytes).collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    /// Deserializes rows of field elements from nested sequences of canonical byte strings.
+    pub fn deserialize<'de, F: Field, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Vec<F>>, D::Error> {
+        Vec::<Vec<Vec<u8>>>::deserialize(deserializer)?
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|bytes| decode(bytes).map_err(D::Error::custom))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::Fr;
+    use ark_ff::Field;
+
+    #[test]
+    fn test_rejects_non_canonical_encoding() {
+        let value = -Fr::ONE;
+        assert_eq!(decode::<Fr>(&value.into_bigint().to_bytes_be()), Ok(value));
+
+        // The modulus itself reduces to zero but is not the canonical encoding of zero
+        let modulus = Fr::MODULUS.to_bytes_be();
+        assert!(decode::<Fr>(&modulus).is_err());
+    }
+}
@@ -1,51 +1,228 @@
-use sha2::{Digest, Sha256};
+//! Merkle commitments for the trace, quotient and FRI layers.
+//!
+//! Besides the tree itself this module defines how field data becomes leaves:
+//! single evaluations are committed by their canonical big-endian encoding
+//! (`field_element_leaf`) and trace rows by the hash of their concatenated
+//! encodings (`row_leaf`), optionally followed by a random salt (`salted_row_leaf`).
+//!
+//! Parents of leaves are hashed under a different domain tag than parents of internal
+//! nodes, so an internal node can never be opened as a leaf of a shorter path.
 
-#[derive(Debug)]
+use crate::hash::{Hasher, Sha256Hasher};
+use crate::math::serde_fr::to_bytes;
+use crate::metrics::rows_size;
+use ark_ff::Field;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Domain tag of nodes whose children are leaves
+const LEAF_PARENT_TAG: u8 = 0;
+
+/// Domain tag of nodes whose children are internal nodes
+const NODE_PARENT_TAG: u8 = 1;
+
+/// Minimum level size for which parent nodes are hashed in parallel
+#[cfg(feature = "parallel")]
+const PARALLEL_LEVEL_THRESHOLD: usize = 1 << 10;
+
+/// Authentication path for a single leaf.
+///
+/// For a tree of arity `k` every level contributes `k - 1` sibling nodes, listed in
+/// group order with the node on the path itself left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MerkleProof {
     pub path: Vec<Vec<u8>>,
-    pub position: Vec<bool>,
+    /// Position of the node on the path within its group, per level
+    pub position: Vec<usize>,
+}
+
+/// Top level of a Merkle tree committed in place of the single root.
+///
+/// A cap of height `h` holds up to `arity^h` nodes and shortens every authentication
+/// path by `h` levels.
+pub type MerkleCap = Vec<Vec<u8>>;
+
+/// Number of levels in the authentication path of a tree over `leaf_count` leaves with
+/// the given arity, when the top `cap_height` levels are covered by the cap.
+pub fn path_length(leaf_count: usize, arity: usize, cap_height: usize) -> usize {
+    let mut depth = 0;
+    let mut level_len = leaf_count;
+    while level_len > 1 {
+        level_len = level_len.div_ceil(arity);
+        depth += 1;
+    }
+    depth - cap_height.min(depth)
+}
+
+/// Encodes a field element as a Merkle leaf.
+pub fn field_element_leaf<F: Field>(value: &F) -> Vec<u8> {
+    to_bytes(value)
+}
+
+/// Hashes a row of field elements (one value per trace column) into a Merkle leaf.
+pub fn row_leaf<H: Hasher>(row: &[impl Field]) -> Vec<u8> {
+    let bytes: Vec<u8> = row.iter().flat_map(field_element_leaf).collect();
+    H::hash(&bytes)
+}
+
+/// Length in bytes of the random salts appended to hidden rows.
+pub const SALT_LEN: usize = 32;
+
+/// Hashes a row of field elements followed by a salt into a Merkle leaf.
+///
+/// Salting hides rows whose leaf is revealed in an authentication path but which are
+/// never opened. An empty salt gives the same leaf as `row_leaf`.
+pub fn salted_row_leaf<H: Hasher>(row: &[impl Field], salt: &[u8]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = row.iter().flat_map(field_element_leaf).collect();
+    bytes.extend_from_slice(salt);
+    H::hash(&bytes)
+}
+
+/// Authentication data for several leaves at once.
+///
+/// Internal nodes that can be recomputed from the opened leaves are omitted, so
+/// paths of nearby leaves share everything above their common ancestor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleMultiProof {
+    /// Number of leaves in the tree
+    pub leaf_count: usize,
+    /// Number of children per internal node
+    pub arity: usize,
+    /// Sibling nodes not derivable from the opened leaves, level by level
+    pub nodes: Vec<Vec<u8>>,
 }
 
-#[derive(Debug)]
-pub struct MerkleTree {
+/// Merkle tree, generic over the node hash function.
+///
+/// Every internal node hashes `arity` children (2 by default). Higher arities shorten
+/// authentication paths in levels at the cost of more sibling nodes per level. Groups
+/// at the end of a level that are not full are padded by repeating the last node.
+///
+/// The hasher defaults to SHA-256; use `MerkleTree::<H>::from_leaves` to pick another.
+/// Serialized trees do not record the hasher, so they must be decoded with the same `H`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct MerkleTree<H: Hasher = Sha256Hasher> {
     pub leaves: Vec<Vec<u8>>,
     pub levels: Vec<Vec<Vec<u8>>>,
+    /// Number of children per internal node
+    pub arity: usize,
+    #[serde(skip)]
+    _hasher: PhantomData<H>,
 }
 
 impl MerkleTree {
+    /// Builds a binary SHA-256 Merkle tree over the given leaves.
     pub fn new(leaves: Vec<Vec<u8>>) -> Self {
+        Self::from_leaves(leaves)
+    }
+
+    /// Builds a SHA-256 Merkle tree with the given arity over the leaves.
+    pub fn with_arity(leaves: Vec<Vec<u8>>, arity: usize) -> Self {
+        Self::from_leaves_with_arity(leaves, arity)
+    }
+
+    /// Builds a binary SHA-256 Merkle tree with one leaf per field element.
+    pub fn from_field_elements<F: Field>(values: &[F]) -> Self {
+        Self::new(values.iter().map(field_element_leaf).collect())
+    }
+
+    /// Builds a binary SHA-256 Merkle tree with one hashed leaf per row of field elements.
+    pub fn from_rows<F: Field>(rows: &[Vec<F>]) -> Self {
+        Self::new(
+            rows.iter()
+                .map(|row| row_leaf::<Sha256Hasher>(row))
+                .collect(),
+        )
+    }
+
+    /// Builds a binary SHA-256 Merkle tree with one hashed leaf per salted row.
+    pub fn from_salted_rows<F: Field>(rows: &[Vec<F>], salts: &[Vec<u8>]) -> Self {
+        Self::new(
+            rows.iter()
+                .zip(salts)
+                .map(|(row, salt)| salted_row_leaf::<Sha256Hasher>(row, salt))
+                .collect(),
+        )
+    }
+}
+
+impl<H: Hasher> MerkleTree<H> {
+    /// Builds a binary Merkle tree over the given leaves using the hasher `H`.
+    pub fn from_leaves(leaves: Vec<Vec<u8>>) -> Self {
+        Self::from_leaves_with_arity(leaves, 2)
+    }
+
+    /// Builds a Merkle tree with the given arity using the hasher `H`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `arity` is smaller than 2.
+    pub fn from_leaves_with_arity(leaves: Vec<Vec<u8>>, arity: usize) -> Self {
+        assert!(arity >= 2, "Merkle tree arity must be at least 2");
         let mut tree = MerkleTree {
-            leaves: leaves.clone(),
+            leaves,
             levels: Vec::new(),
+            arity,
+            _hasher: PhantomData,
         };
         tree.build_tree();
         tree
     }
 
+    /// Hashes all levels from the leaves up to the root.
+    ///
+    /// With the `parallel` feature, large levels are hashed across the rayon thread pool.
     pub fn build_tree(&mut self) {
         let mut current_level = self.leaves.clone();
         self.levels.push(current_level.clone());
 
         while current_level.len() > 1 {
-            let mut next_level = Vec::new();
-            for i in (0..current_level.len()).step_by(2) {
-                let left = current_level.get(i).unwrap();
-                let right = if i + 1 < current_level.len() {
-                    current_level.get(i + 1).unwrap()
-                } else {
-                    current_level.get(i).unwrap() // Duplicate last node if odd number
-                };
-
-                let mut combined = Vec::new();
-                combined.extend_from_slice(left);
-                combined.extend_from_slice(right);
-                next_level.push(sha_digest(&combined));
-            }
-            current_level = next_level;
+            current_level = self.hash_level(&current_level, self.levels.len() - 1);
             self.levels.push(current_level.clone());
         }
     }
 
+    /// Computes the parent level of `level`, the level at `height` above the leaves.
+    #[cfg(not(feature = "parallel"))]
+    fn hash_level(&self, level: &[Vec<u8>], height: usize) -> Vec<Vec<u8>> {
+        level
+            .chunks(self.arity)
+            .map(|group| hash_group::<H>(group, self.arity, height))
+            .collect()
+    }
+
+    /// Computes the parent level of `level`, the level at `height` above the leaves, in
+    /// parallel for large levels.
+    #[cfg(feature = "parallel")]
+    fn hash_level(&self, level: &[Vec<u8>], height: usize) -> Vec<Vec<u8>> {
+        if level.len() < PARALLEL_LEVEL_THRESHOLD {
+            return level
+                .chunks(self.arity)
+                .map(|group| hash_group::<H>(group, self.arity, height))
+                .collect();
+        }
+        let arity = self.arity;
+        level
+            .par_chunks(arity)
+            .map(|group| hash_group::<H>(group, arity, height))
+            .collect()
+    }
+
+    /// Bytes held by the leaves and levels of the tree, including their `Vec` headers.
+    pub fn heap_size(&self) -> usize {
+        rows_size(&self.leaves)
+            + self
+                .levels
+                .iter()
+                .map(|level| rows_size(level))
+                .sum::<usize>()
+    }
+
     pub fn get_proof(&self, index: usize) -> Option<MerkleProof> {
         if index >= self.leaves.len() {
             return None;
@@ -57,68 +234,414 @@ impl MerkleTree {
 
         // Start from the leaf level
         for level in &self.levels[..self.levels.len() - 1] {
-            let sibling_index = if current_index % 2 == 0 {
-                current_index + 1
-            } else {
-                current_index - 1
-            };
-
-            // If we're at the last node in an odd-sized level, use the node itself as sibling
-            if sibling_index >= level.len() {
-                path.push(level.get(current_index).unwrap().clone());
-                position.push(true); // Treat as if sibling is on the right
-            } else {
-                path.push(level.get(sibling_index).unwrap().clone());
-                position.push(current_index % 2 == 1);
+            let group_start = current_index - current_index % self.arity;
+            for sibling_index in group_start..group_start + self.arity {
+                if sibling_index == current_index {
+                    continue;
+                }
+                // Siblings past the end of the level are padding copies of the last node
+                let sibling = level.get(sibling_index).unwrap_or(level.last().unwrap());
+                path.push(sibling.clone());
             }
-
-            current_index /= 2;
+            position.push(current_index % self.arity);
+            current_index /= self.arity;
         }
 
         Some(MerkleProof { path, position })
     }
 
+    /// Builds a single proof for all leaves at `indices`.
+    ///
+    /// Indices may be unsorted and contain duplicates. Returns `None` if any index
+    /// is out of range.
+    pub fn get_multi_proof(&self, indices: &[usize]) -> Option<MerkleMultiProof> {
+        if indices.iter().any(|&index| index >= self.leaves.len()) {
+            return None;
+        }
+
+        let mut known: Vec<usize> = indices.to_vec();
+        known.sort_unstable();
+        known.dedup();
+
+        let mut nodes = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            let mut groups: Vec<usize> = known.iter().map(|index| index / self.arity).collect();
+            groups.dedup();
+            for group in &groups {
+                let group_start = group * self.arity;
+                // Padding past the end of the level needs no proof data
+                let group_end = (group_start + self.arity).min(level.len());
+                for (sibling_index, node) in
+                    level.iter().enumerate().take(group_end).skip(group_start)
+                {
+                    if known.binary_search(&sibling_index).is_err() {
+                        nodes.push(node.clone());
+                    }
+                }
+            }
+            known = groups;
+        }
+
+        Some(MerkleMultiProof {
+            leaf_count: self.leaves.len(),
+            arity: self.arity,
+            nodes,
+        })
+    }
+
     pub fn root(&self) -> Option<Vec<u8>> {
         self.levels.last().unwrap().first().cloned().to_owned()
     }
+
+    /// Returns the Merkle cap of the given height.
+    ///
+    /// The cap is the level `cap_height` steps below the root, i.e. up to `arity^cap_height`
+    /// nodes. Height 0 is the root itself; heights beyond the tree depth yield the leaves.
+    pub fn cap(&self, cap_height: usize) -> MerkleCap {
+        let depth = self.levels.len() - 1;
+        self.levels[depth - cap_height.min(depth)].clone()
+    }
+
+    /// Builds a proof for a leaf against the cap of the given height.
+    ///
+    /// The top `cap_height` levels are covered by the cap and left out of the path.
+    pub fn get_proof_with_cap(&self, index: usize, cap_height: usize) -> Option<MerkleProof> {
+        let mut proof = self.get_proof(index)?;
+        let depth = self.levels.len() - 1;
+        let path_levels = depth - cap_height.min(depth);
+        proof.path.truncate(path_levels * (self.arity - 1));
+        proof.position.truncate(path_levels);
+        Some(proof)
+    }
+
+    /// Hashes a leaf up along its authentication path.
+    ///
+    /// The tree arity is implied by the number of siblings per level.
+    fn compute_path_root(leaf: Vec<u8>, proof: &MerkleProof) -> Option<Vec<u8>> {
+        let mut current_hash = leaf;
+        if proof.position.is_empty() {
+            return proof.path.is_empty().then_some(current_hash);
+        }
+        if !proof.path.len().is_multiple_of(proof.position.len()) {
+            return None;
+        }
+        let siblings_per_level = proof.path.len() / proof.position.len();
+        if siblings_per_level == 0 {
+            return None;
+        }
+
+        for (height, (siblings, &position)) in proof
+            .path
+            .chunks(siblings_per_level)
+            .zip(proof.position.iter())
+            .enumerate()
+        {
+            if position > siblings_per_level {
+                return None;
+            }
+            let mut children: Vec<&[u8]> = siblings.iter().map(|s| s.as_slice()).collect();
+            children.insert(position, &current_hash);
+            current_hash = merge_children::<H>(&children, height);
+        }
+
+        Some(current_hash)
+    }
+
+    /// Verifies a single-leaf proof against the given root.
+    pub fn verify_proof(leaf: Vec<u8>, proof: &MerkleProof, root: &Vec<u8>) -> bool {
+        Self::compute_path_root(leaf, proof).as_ref() == Some(root)
+    }
+
+    /// Verifies a proof for the leaf at `index` against a Merkle cap.
+    ///
+    /// The path must span `depth` levels of a tree with the given arity, see
+    /// `path_length`, and its positions must match `index`, which also selects the cap
+    /// node the path has to end in.
+    pub fn verify_proof_with_cap(
+        leaf: Vec<u8>,
+        index: usize,
+        proof: &MerkleProof,
+        cap: &MerkleCap,
+        depth: usize,
+        arity: usize,
+    ) -> bool {
+        if arity < 2 || proof.position.len() != depth || proof.path.len() != depth * (arity - 1) {
+            return false;
+        }
+        let mut cap_index = index;
+        for &position in &proof.position {
+            if position != cap_index % arity {
+                return false;
+            }
+            cap_index /= arity;
+        }
+        match (Self::compute_path_root(leaf, proof), cap.get(cap_index)) {
+            (Some(node), Some(cap_node)) => node == *cap_node,
+            _ => false,
+        }
+    }
+
+    /// Verifies that `leaves[i]` sits at `indices[i]` in the tree with the given root.
+    pub fn verify_multi_proof(
+        indices: &[usize],
+        leaves: &[Vec<u8>],
+        proof: &MerkleMultiProof,
+        root: &Vec<u8>,
+    ) -> bool {
+        if indices.len() != leaves.len() || indices.is_empty() {
+            return false;
+        }
+
+        let mut known: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for (&index, leaf) in indices.iter().zip(leaves.iter()) {
+            if index >= proof.leaf_count {
+                return false;
+            }
+            // Duplicate indices must open to the same leaf
+            if let Some(existing) = known.insert(index, leaf.clone())
+                && existing != *leaf
+            {
+                return false;
+            }
+        }
+
+        if proof.arity < 2 {
+            return false;
+        }
+        let mut nodes = proof.nodes.iter();
+        let mut level_len = proof.leaf_count;
+        let mut height = 0;
+        while level_len > 1 {
+            let mut next_level = BTreeMap::new();
+            for &index in known.keys() {
+                let group = index / proof.arity;
+                if next_level.contains_key(&group) {
+                    continue;
+                }
+                let group_start = group * proof.arity;
+                let group_end = (group_start + proof.arity).min(level_len);
+                let mut children = Vec::with_capacity(proof.arity);
+                for child_index in group_start..group_end {
+                    let child = match known.get(&child_index) {
+                        Some(child) => child.clone(),
+                        None => match nodes.next() {
+                            Some(node) => node.clone(),
+                            None => return false,
+                        },
+                    };
+                    children.push(child);
+                }
+                // Pad a trailing group by repeating its last node
+                while children.len() < proof.arity {
+                    children.push(children.last().unwrap().clone());
+                }
+                let children: Vec<&[u8]> = children.iter().map(|c| c.as_slice()).collect();
+                next_level.insert(group, merge_children::<H>(&children, height));
+            }
+            known = next_level;
+            level_len = level_len.div_ceil(proof.arity);
+            height += 1;
+        }
+
+        nodes.next().is_none() && known.get(&0) == Some(root)
+    }
+}
+
+/// Incremental builder for a `MerkleTree` whose leaves arrive one at a time.
+///
+/// Each parent node is hashed exactly once, as soon as its group of children is
+/// complete, so streaming `n` leaves costs the same as building the tree in one go.
+/// `finalize` hashes the partially filled groups on the right edge and yields a tree
+/// identical to `MerkleTree::from_leaves_with_arity` over the same leaves.
+#[derive(Debug, Clone)]
+pub struct MerkleTreeBuilder<H: Hasher = Sha256Hasher> {
+    /// Completed nodes per level, leaves first
+    levels: Vec<Vec<Vec<u8>>>,
+    /// Number of children per internal node
+    arity: usize,
+    _hasher: PhantomData<H>,
 }
 
-pub fn verify_merkle_proof(leaf:  Vec<u8>, proof: &MerkleProof, root: &Vec<u8>) -> bool {
-    let mut current_hash = leaf.clone();
+impl MerkleTreeBuilder {
+    /// Creates an empty builder for a binary SHA-256 tree.
+    pub fn new() -> Self {
+        Self::with_arity(2)
+    }
+}
 
-    for (sibling, is_right) in proof.path.iter().zip(proof.position.iter()) {
-        let mut combined = Vec::new();
-        if *is_right {
-            combined.extend_from_slice(sibling);
-            combined.extend_from_slice(&current_hash);
-        } else {
-            combined.extend_from_slice(&current_hash);
-            combined.extend_from_slice(sibling);
+impl Default for MerkleTreeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: Hasher> MerkleTreeBuilder<H> {
+    /// Creates an empty builder for a tree with the given arity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `arity` is smaller than 2.
+    pub fn with_arity(arity: usize) -> Self {
+        assert!(arity >= 2, "Merkle tree arity must be at least 2");
+        Self {
+            levels: vec![Vec::new()],
+            arity,
+            _hasher: PhantomData,
         }
-        current_hash = sha_digest(&combined);
     }
 
-    current_hash == *root
+    /// Number of leaves pushed so far.
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Returns true if no leaf has been pushed yet.
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// Appends a leaf, hashing every group it completes on the way up.
+    pub fn push_leaf(&mut self, leaf: Vec<u8>) {
+        self.push_node(0, leaf);
+    }
+
+    /// Appends a node to a level and cascades completed groups upwards.
+    fn push_node(&mut self, level: usize, node: Vec<u8>) {
+        self.levels[level].push(node);
+        let len = self.levels[level].len();
+        if len.is_multiple_of(self.arity) {
+            let parent =
+                hash_group::<H>(&self.levels[level][len - self.arity..], self.arity, level);
+            if self.levels.len() == level + 1 {
+                self.levels.push(Vec::new());
+            }
+            self.push_node(level + 1, parent);
+        }
+    }
+
+    /// Hashes the incomplete right edge and returns the finished tree.
+    pub fn finalize(mut self) -> MerkleTree<H> {
+        let mut level = 0;
+        while level + 1 < self.levels.len() || self.levels[level].len() > 1 {
+            let len = self.levels[level].len();
+            let remainder = len % self.arity;
+            if remainder != 0 {
+                let parent =
+                    hash_group::<H>(&self.levels[level][len - remainder..], self.arity, level);
+                if self.levels.len() == level + 1 {
+                    self.levels.push(Vec::new());
+                }
+                self.push_node(level + 1, parent);
+            }
+            level += 1;
+        }
+
+        MerkleTree {
+            leaves: self.levels[0].clone(),
+            levels: self.levels,
+            arity: self.arity,
+            _hasher: PhantomData,
+        }
+    }
+}
+
+/// Hashes one group of sibling nodes at `height` above the leaves, repeating the last
+/// node if the group is not full.
+fn hash_group<H: Hasher>(group: &[Vec<u8>], arity: usize, height: usize) -> Vec<u8> {
+    let children: Vec<&[u8]> = (0..arity)
+        .map(|i| group.get(i).unwrap_or(group.last().unwrap()).as_slice())
+        .collect();
+    merge_children::<H>(&children, height)
+}
+
+/// Hashes the children at `height` above the leaves into their parent, behind the
+/// domain tag of their level.
+fn merge_children<H: Hasher>(children: &[&[u8]], height: usize) -> Vec<u8> {
+    let tag = [if height == 0 {
+        LEAF_PARENT_TAG
+    } else {
+        NODE_PARENT_TAG
+    }];
+    let tagged: Vec<&[u8]> = std::iter::once(&tag[..])
+        .chain(children.iter().copied())
+        .collect();
+    H::merge(&tagged)
+}
+
+/// Verifies a single-leaf proof against a SHA-256 tree root.
+pub fn verify_merkle_proof(leaf: Vec<u8>, proof: &MerkleProof, root: &Vec<u8>) -> bool {
+    MerkleTree::<Sha256Hasher>::verify_proof(leaf, proof, root)
+}
+
+/// Verifies a single-leaf proof spanning `depth` levels against a SHA-256 Merkle cap.
+pub fn verify_merkle_proof_with_cap(
+    leaf: Vec<u8>,
+    index: usize,
+    proof: &MerkleProof,
+    cap: &MerkleCap,
+    depth: usize,
+    arity: usize,
+) -> bool {
+    MerkleTree::<Sha256Hasher>::verify_proof_with_cap(leaf, index, proof, cap, depth, arity)
 }
 
-fn sha_digest(data: &[u8]) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    hasher.update(data);
-    hasher.finalize().to_vec()
+/// Verifies a multi-proof against a SHA-256 tree root.
+pub fn verify_multi_proof(
+    indices: &[usize],
+    leaves: &[Vec<u8>],
+    proof: &MerkleMultiProof,
+    root: &Vec<u8>,
+) -> bool {
+    MerkleTree::<Sha256Hasher>::verify_multi_proof(indices, leaves, proof, root)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hash::{Blake3Hasher, Keccak256Hasher};
+    use ark_bls12_381::Fr;
+
+    fn check_hasher<H: Hasher>() -> Vec<u8> {
+        let leaves: Vec<Vec<u8>> = (0..5u64).map(|i| H::hash(&i.to_le_bytes())).collect();
+        let tree = MerkleTree::<H>::from_leaves(leaves.clone());
+        let root = tree.root().unwrap();
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = tree.get_proof(i).unwrap();
+            assert!(MerkleTree::<H>::verify_proof(leaf.clone(), &proof, &root));
+        }
+        let proof = tree.get_multi_proof(&[1, 4]).unwrap();
+        let opened = vec![leaves[1].clone(), leaves[4].clone()];
+        assert!(MerkleTree::<H>::verify_multi_proof(
+            &[1, 4],
+            &opened,
+            &proof,
+            &root
+        ));
+        root
+    }
+
+    #[test]
+    fn test_merkle_tree_hash_backends() {
+        let roots = [
+            check_hasher::<Sha256Hasher>(),
+            check_hasher::<Blake3Hasher>(),
+            check_hasher::<Keccak256Hasher>(),
+        ];
+        for (i, root) in roots.iter().enumerate() {
+            for other in &roots[i + 1..] {
+                assert_ne!(root, other);
+            }
+        }
+    }
 
     #[test]
     fn test_merkle_proof_verification() {
         // Create leaves
         let leaves = vec![
-            sha_digest(&1u64.to_le_bytes()),
-            sha_digest(&2u64.to_le_bytes()),
-            sha_digest(&3u64.to_le_bytes()),
-            sha_digest(&4u64.to_le_bytes()),
+            Sha256Hasher::hash(&1u64.to_le_bytes()),
+            Sha256Hasher::hash(&2u64.to_le_bytes()),
+            Sha256Hasher::hash(&3u64.to_le_bytes()),
+            Sha256Hasher::hash(&4u64.to_le_bytes()),
         ];
 
         let tree = MerkleTree::new(leaves);
@@ -127,7 +650,7 @@ mod tests {
         // Test proof for each leaf
         for i in 0..4 {
             let proof = tree.get_proof(i).unwrap();
-            let leaf = sha_digest(&(i as u64 + 1).to_le_bytes());
+            let leaf = Sha256Hasher::hash(&(i as u64 + 1).to_le_bytes());
             assert!(verify_merkle_proof(leaf, &proof, &root));
         }
     }
@@ -136,9 +659,9 @@ mod tests {
     fn test_merkle_proof_odd_leaves() {
         // Test with odd number of leaves
         let leaves = vec![
-            sha_digest(&1u64.to_le_bytes()),
-            sha_digest(&2u64.to_le_bytes()),
-            sha_digest(&3u64.to_le_bytes()),
+            Sha256Hasher::hash(&1u64.to_le_bytes()),
+            Sha256Hasher::hash(&2u64.to_le_bytes()),
+            Sha256Hasher::hash(&3u64.to_le_bytes()),
         ];
 
         let tree = MerkleTree::new(leaves);
@@ -147,7 +670,7 @@ mod tests {
         // Test proof for each leaf
         for i in 0..3 {
             let proof = tree.get_proof(i).unwrap();
-            let leaf = sha_digest(&(i as u64 + 1).to_le_bytes());
+            let leaf = Sha256Hasher::hash(&(i as u64 + 1).to_le_bytes());
             assert!(verify_merkle_proof(leaf, &proof, &root));
         }
     }
@@ -155,12 +678,305 @@ mod tests {
     #[test]
     fn test_merkle_proof_single_leaf() {
         // Test with single leaf
-        let leaves = vec![sha_digest(&1u64.to_le_bytes())];
+        let leaves = vec![Sha256Hasher::hash(&1u64.to_le_bytes())];
         let tree = MerkleTree::new(leaves);
         let root = tree.root().unwrap();
 
         let proof = tree.get_proof(0).unwrap();
-        let leaf = sha_digest(&1u64.to_le_bytes());
+        let leaf = Sha256Hasher::hash(&1u64.to_le_bytes());
         assert!(verify_merkle_proof(leaf, &proof, &root));
     }
+
+    #[test]
+    fn test_merkle_multi_proof_verification() {
+        let leaves: Vec<Vec<u8>> = (0..11u64)
+            .map(|i| Sha256Hasher::hash(&i.to_le_bytes()))
+            .collect();
+        let tree = MerkleTree::new(leaves.clone());
+        let root = tree.root().unwrap();
+
+        let index_sets: Vec<Vec<usize>> = vec![
+            vec![0],
+            vec![10],
+            vec![3, 2],
+            vec![0, 5, 9, 10],
+            vec![4, 4, 7],
+            (0..11).collect(),
+        ];
+        for indices in index_sets {
+            let proof = tree.get_multi_proof(&indices).unwrap();
+            let opened: Vec<Vec<u8>> = indices.iter().map(|&i| leaves[i].clone()).collect();
+            assert!(verify_multi_proof(&indices, &opened, &proof, &root));
+        }
+
+        // Opening every leaf needs no extra nodes at all
+        let all: Vec<usize> = (0..11).collect();
+        assert!(tree.get_multi_proof(&all).unwrap().nodes.is_empty());
+        assert!(tree.get_multi_proof(&[11]).is_none());
+    }
+
+    #[test]
+    fn test_merkle_multi_proof_shares_nodes() {
+        let leaves: Vec<Vec<u8>> = (0..16u64)
+            .map(|i| Sha256Hasher::hash(&i.to_le_bytes()))
+            .collect();
+        let tree = MerkleTree::new(leaves.clone());
+        let root = tree.root().unwrap();
+
+        let indices = [0, 1, 2, 3];
+        let proof = tree.get_multi_proof(&indices).unwrap();
+        let individual: usize = indices
+            .iter()
+            .map(|&i| tree.get_proof(i).unwrap().path.len())
+            .sum();
+        assert_eq!(proof.nodes.len(), 2);
+        assert!(proof.nodes.len() < individual);
+
+        // A wrong leaf must be rejected
+        let mut opened: Vec<Vec<u8>> = indices.iter().map(|&i| leaves[i].clone()).collect();
+        opened[2] = Sha256Hasher::hash(b"forged");
+        assert!(!verify_multi_proof(&indices, &opened, &proof, &root));
+    }
+
+    #[test]
+    fn test_merkle_tree_arity() {
+        let leaves: Vec<Vec<u8>> = (0..70u64)
+            .map(|i| Sha256Hasher::hash(&i.to_le_bytes()))
+            .collect();
+        let binary_depth = MerkleTree::new(leaves.clone())
+            .get_proof(0)
+            .unwrap()
+            .position
+            .len();
+
+        for arity in [4, 8] {
+            let tree = MerkleTree::with_arity(leaves.clone(), arity);
+            let root = tree.root().unwrap();
+            assert_ne!(root, MerkleTree::new(leaves.clone()).root().unwrap());
+
+            for i in [0, 5, 63, 69] {
+                let proof = tree.get_proof(i).unwrap();
+                assert!(proof.position.len() < binary_depth);
+                assert_eq!(proof.path.len(), proof.position.len() * (arity - 1));
+                assert!(verify_merkle_proof(leaves[i].clone(), &proof, &root));
+                assert!(!verify_merkle_proof(
+                    leaves[(i + 1) % leaves.len()].clone(),
+                    &proof,
+                    &root
+                ));
+            }
+
+            let indices = [1, 2, 40, 68, 69];
+            let proof = tree.get_multi_proof(&indices).unwrap();
+            let opened: Vec<Vec<u8>> = indices.iter().map(|&i| leaves[i].clone()).collect();
+            assert!(verify_multi_proof(&indices, &opened, &proof, &root));
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_build_matches_serial() {
+        let leaves: Vec<Vec<u8>> = (0..5000u64)
+            .map(|i| Sha256Hasher::hash(&i.to_le_bytes()))
+            .collect();
+        for arity in [2, 4] {
+            let tree = MerkleTree::with_arity(leaves.clone(), arity);
+            let mut level = leaves.clone();
+            for (height, expected) in tree.levels[1..].iter().enumerate() {
+                level = level
+                    .chunks(arity)
+                    .map(|group| hash_group::<Sha256Hasher>(group, arity, height))
+                    .collect();
+                assert_eq!(&level, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_merkle_serde_roundtrip() {
+        let leaves: Vec<Vec<u8>> = (0..6u64)
+            .map(|i| Sha256Hasher::hash(&i.to_le_bytes()))
+            .collect();
+        let tree = MerkleTree::with_arity(leaves.clone(), 4);
+        let root = tree.root().unwrap();
+
+        let proof = tree.get_proof(5).unwrap();
+        let decoded: MerkleProof =
+            serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+        assert_eq!(decoded, proof);
+        assert!(verify_merkle_proof(leaves[5].clone(), &decoded, &root));
+
+        let multi_proof = tree.get_multi_proof(&[0, 3]).unwrap();
+        let decoded: MerkleMultiProof =
+            serde_json::from_str(&serde_json::to_string(&multi_proof).unwrap()).unwrap();
+        assert_eq!(decoded, multi_proof);
+
+        let decoded_tree: MerkleTree =
+            serde_json::from_str(&serde_json::to_string(&tree).unwrap()).unwrap();
+        assert_eq!(decoded_tree.root(), Some(root));
+        assert_eq!(decoded_tree.arity, 4);
+    }
+
+    #[test]
+    fn test_incremental_builder_matches_batch_build() {
+        for arity in [2, 4, 8] {
+            for count in [1usize, 2, 3, 5, 8, 9, 17, 64, 65] {
+                let leaves: Vec<Vec<u8>> = (0..count as u64)
+                    .map(|i| Sha256Hasher::hash(&i.to_le_bytes()))
+                    .collect();
+                let mut builder = MerkleTreeBuilder::<Sha256Hasher>::with_arity(arity);
+                for leaf in &leaves {
+                    builder.push_leaf(leaf.clone());
+                }
+                assert_eq!(builder.len(), count);
+
+                let incremental = builder.finalize();
+                let batch = MerkleTree::with_arity(leaves.clone(), arity);
+                assert_eq!(incremental.levels, batch.levels);
+
+                let proof = incremental.get_proof(count - 1).unwrap();
+                let root = incremental.root().unwrap();
+                assert!(verify_merkle_proof(
+                    leaves[count - 1].clone(),
+                    &proof,
+                    &root
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_merkle_cap() {
+        let leaves: Vec<Vec<u8>> = (0..16u64)
+            .map(|i| Sha256Hasher::hash(&i.to_le_bytes()))
+            .collect();
+        let tree = MerkleTree::new(leaves.clone());
+
+        assert_eq!(tree.cap(0), vec![tree.root().unwrap()]);
+        assert_eq!(tree.cap(2).len(), 4);
+        assert_eq!(tree.cap(10), leaves);
+
+        let cap = tree.cap(2);
+        let depth = path_length(16, 2, 2);
+        assert_eq!(depth, 2);
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = tree.get_proof_with_cap(i, 2).unwrap();
+            assert_eq!(proof.path.len(), 2);
+            assert!(verify_merkle_proof_with_cap(
+                leaf.clone(),
+                i,
+                &proof,
+                &cap,
+                depth,
+                2
+            ));
+            // The same path must not verify for a leaf index under another cap node
+            assert!(!verify_merkle_proof_with_cap(
+                leaf.clone(),
+                (i + 4) % 16,
+                &proof,
+                &cap,
+                depth,
+                2
+            ));
+            // Nor for another depth or arity
+            assert!(!verify_merkle_proof_with_cap(
+                leaf.clone(),
+                i,
+                &proof,
+                &cap,
+                depth + 1,
+                2
+            ));
+            assert!(!verify_merkle_proof_with_cap(
+                leaf.clone(),
+                i,
+                &proof,
+                &cap,
+                depth,
+                3
+            ));
+        }
+        assert_eq!(path_length(70, 4, 0), 4);
+        assert_eq!(path_length(1, 2, 0), 0);
+    }
+
+    #[test]
+    fn test_internal_node_is_not_a_leaf() {
+        let values: Vec<Fr> = (0..8u64).map(Fr::from).collect();
+        let tree = MerkleTree::from_field_elements(&values);
+        let cap = tree.cap(0);
+        let depth = path_length(8, 2, 0);
+
+        // The parent of leaves 2 and 3 with the path of leaf 2 minus its first level
+        // would open as a leaf at index 1 of a tree one level shorter
+        let node = tree.levels[1][1].clone();
+        let mut proof = tree.get_proof(2).unwrap();
+        proof.path.remove(0);
+        proof.position.remove(0);
+        assert!(!verify_merkle_proof_with_cap(
+            node.clone(),
+            1,
+            &proof,
+            &cap,
+            depth,
+            2
+        ));
+        // Even without the depth check, leaf parents and node parents hash apart
+        assert!(!verify_merkle_proof(node, &proof, &tree.root().unwrap()));
+    }
+
+    #[test]
+    fn test_field_element_leaves() {
+        let values: Vec<Fr> = (0..8u64).map(Fr::from).collect();
+        let tree = MerkleTree::from_field_elements(&values);
+        let root = tree.root().unwrap();
+        let proof = tree.get_proof(5).unwrap();
+        assert!(verify_merkle_proof(
+            field_element_leaf(&values[5]),
+            &proof,
+            &root
+        ));
+        assert!(!verify_merkle_proof(
+            field_element_leaf(&values[4]),
+            &proof,
+            &root
+        ));
+
+        // Rows hash all their columns, so reordering columns changes the leaf
+        let rows = vec![vec![Fr::from(1u64), Fr::from(2u64)]; 4];
+        let tree = MerkleTree::from_rows(&rows);
+        assert_eq!(tree.leaves[0], row_leaf::<Sha256Hasher>(&rows[0]));
+        assert_ne!(
+            tree.leaves[0],
+            row_leaf::<Sha256Hasher>(&[Fr::from(2u64), Fr::from(1u64)])
+        );
+    }
+
+    #[test]
+    fn test_salted_row_leaves() {
+        let rows = vec![vec![Fr::from(1u64), Fr::from(2u64)]; 4];
+        let row = &rows[0];
+        assert_eq!(
+            salted_row_leaf::<Sha256Hasher>(row, &[]),
+            row_leaf::<Sha256Hasher>(row)
+        );
+
+        // Equal rows with different salts get different leaves
+        let salts: Vec<Vec<u8>> = (0..4u8).map(|i| vec![i; SALT_LEN]).collect();
+        let tree = MerkleTree::from_salted_rows(&rows, &salts);
+        assert_ne!(tree.leaves[0], tree.leaves[1]);
+        let proof = tree.get_proof(2).unwrap();
+        let root = tree.root().unwrap();
+        assert!(verify_merkle_proof(
+            salted_row_leaf::<Sha256Hasher>(row, &salts[2]),
+            &proof,
+            &root
+        ));
+        assert!(!verify_merkle_proof(
+            row_leaf::<Sha256Hasher>(row),
+            &proof,
+            &root
+        ));
+    }
 }
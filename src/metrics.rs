@@ -0,0 +1,120 @@
+//! Resource usage reported by `StarkProver::generate_proof_with_metrics`.
+//!
+//! Memory is accounted from the sizes of the buffers the prover holds — the trace
+//! extension, the quotient and DEEP evaluations, the FRI layers and the Merkle trees —
+//! rather than measured from the allocator, so it is an approximation that ignores
+//! allocator overhead and short-lived temporaries.
+
+use std::time::{Duration, Instant};
+
+use crate::progress::ProofPhase;
+use crate::prover::ProvingStrategy;
+
+/// Resource usage of one proving phase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhaseMetrics {
+    /// The phase measured
+    pub phase: ProofPhase,
+    /// Largest number of bytes held by the prover during the phase
+    pub peak_memory: usize,
+    /// Wall-clock time spent in the phase
+    pub duration: Duration,
+}
+
+/// Resource usage of a whole proof generation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofMetrics {
+    /// How the trace extension was held
+    pub strategy: ProvingStrategy,
+    /// Every phase, in the order it ran
+    pub phases: Vec<PhaseMetrics>,
+}
+
+impl ProofMetrics {
+    /// Largest number of bytes held by the prover during any phase.
+    pub fn peak_memory(&self) -> usize {
+        self.phases
+            .iter()
+            .map(|phase| phase.peak_memory)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Metrics of `phase`, if it ran.
+    pub fn phase(&self, phase: ProofPhase) -> Option<&PhaseMetrics> {
+        self.phases.iter().find(|metrics| metrics.phase == phase)
+    }
+}
+
+/// Tracks the bytes held by the prover and records the peak of every phase.
+#[derive(Debug)]
+pub(crate) struct MetricsRecorder {
+    /// Bytes currently held
+    live: usize,
+    /// Largest value of `live` since the current phase started
+    peak: usize,
+    /// Start of the current phase
+    started: Instant,
+    /// Completed phases
+    phases: Vec<PhaseMetrics>,
+}
+
+impl Default for MetricsRecorder {
+    fn default() -> Self {
+        Self {
+            live: 0,
+            peak: 0,
+            started: Instant::now(),
+            phases: Vec::new(),
+        }
+    }
+}
+
+impl MetricsRecorder {
+    /// Starts measuring a new phase.
+    pub(crate) fn start_phase(&mut self) {
+        self.peak = self.live;
+        self.started = Instant::now();
+    }
+
+    /// Records the metrics of `phase`, which just completed.
+    pub(crate) fn end_phase(&mut self, phase: ProofPhase) {
+        self.phases.push(PhaseMetrics {
+            phase,
+            peak_memory: self.peak,
+            duration: self.started.elapsed(),
+        });
+    }
+
+    /// Accounts for a buffer of `bytes` that stays alive.
+    pub(crate) fn hold(&mut self, bytes: usize) {
+        self.live += bytes;
+        self.peak = self.peak.max(self.live);
+    }
+
+    /// Accounts for a buffer of `bytes` being dropped.
+    pub(crate) fn release(&mut self, bytes: usize) {
+        self.live = self.live.saturating_sub(bytes);
+    }
+
+    /// Accounts for a temporary buffer of `bytes`, dropped within the phase.
+    pub(crate) fn touch(&mut self, bytes: usize) {
+        self.hold(bytes);
+        self.release(bytes);
+    }
+
+    /// The metrics of every recorded phase.
+    pub(crate) fn finish(self, strategy: ProvingStrategy) -> ProofMetrics {
+        ProofMetrics {
+            strategy,
+            phases: self.phases,
+        }
+    }
+}
+
+/// Bytes held by `rows`, including their `Vec` headers.
+pub(crate) fn rows_size<T>(rows: &[Vec<T>]) -> usize {
+    rows.iter()
+        .map(|row| size_of::<Vec<T>>() + size_of_val(row.as_slice()))
+        .sum()
+}
@@ -0,0 +1,734 @@
+//! Proof configuration shared by the prover and the verifier.
+//!
+//! Both sides must use the same options: they change the shape of the commitments
+//! and openings in a `StarkProof`.
+
+use ark_bls12_381::Fr;
+use ark_ff::{FftField, Field, PrimeField};
+
+use crate::math::{
+    fri::{MAX_GRINDING_BITS, fri_folding_factors},
+    serde_fr::encoded_len,
+};
+
+/// Length in bytes of an encoded integer or length prefix
+const WORD_LEN: usize = 8;
+/// Length in bytes of a SHA-256 Merkle node
+const DIGEST_LEN: usize = 32;
+/// Size in bytes of a `Vec` header, paid by every row and Merkle node held by the prover
+const VEC_LEN: usize = std::mem::size_of::<Vec<u8>>();
+/// Rows of the out-of-domain frame without window constraints, at `z` and `ω·z`
+const TRANSITION_WINDOW: usize = 2;
+
+/// Bound used to derive the FRI query count from a security target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Soundness {
+    /// Conjectured list-decoding bound: each query contributes `log2(blowup)` bits.
+    #[default]
+    Conjectured,
+    /// Proven Johnson bound: each query contributes `log2(blowup) / 2` bits.
+    Proven,
+}
+
+impl Soundness {
+    /// Number of FRI queries needed to reach `security_bits` bits.
+    ///
+    /// Grinding bits are subtracted from the target before dividing by the bits
+    /// contributed per query.
+    pub fn required_queries(
+        &self,
+        security_bits: usize,
+        blowup_factor: usize,
+        grinding_bits: usize,
+    ) -> usize {
+        let remaining_bits = security_bits.saturating_sub(grinding_bits) as f64;
+        (remaining_bits / self.bits_per_query(blowup_factor)).ceil() as usize
+    }
+
+    /// Bits of security contributed by each FRI query.
+    fn bits_per_query(&self, blowup_factor: usize) -> f64 {
+        let log_blowup = blowup_factor.ilog2() as f64;
+        match self {
+            Soundness::Conjectured => log_blowup,
+            Soundness::Proven => log_blowup / 2.0,
+        }
+    }
+}
+
+/// Security of a proof under both soundness bounds, in bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SecurityLevel {
+    /// Bits under the conjectured list-decoding bound
+    pub conjectured: usize,
+    /// Bits under the proven Johnson bound
+    pub proven: usize,
+}
+
+impl SecurityLevel {
+    /// Bits under `soundness`.
+    pub fn bits(&self, soundness: Soundness) -> usize {
+        match soundness {
+            Soundness::Conjectured => self.conjectured,
+            Soundness::Proven => self.proven,
+        }
+    }
+}
+
+/// Parameters of the STARK proving system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofOptions<F: FftField = Fr> {
+    /// Ratio between the low-degree extension domain and the trace domain.
+    ///
+    /// Must be a power of two of at least 2 and at least the degree of every constraint.
+    pub blowup_factor: usize,
+    /// Height of the Merkle caps committed for the trace, quotient and FRI layers.
+    ///
+    /// Height 0 commits to single roots. Each extra level doubles the commitment size
+    /// and removes one node from every authentication path.
+    pub merkle_cap_height: usize,
+    /// FRI folding stops once a layer has at most this many values.
+    ///
+    /// The final layer is sent as a polynomial, so larger remainders trade
+    /// folding rounds for final polynomial coefficients. At least one round must remain,
+    /// as the trace is opened at the layer 0 positions FRI queries.
+    pub fri_remainder_size: usize,
+    /// Folding factor of each FRI round, e.g. `[4, 4, 2, 2]`.
+    ///
+    /// Factors must be powers of two; rounds beyond the schedule fold by 2. Larger
+    /// factors save commitments at the cost of wider openings.
+    pub fri_folding_schedule: Vec<usize>,
+    /// Number of positions at which the FRI layers are opened, unless derived from
+    /// `security_bits`
+    pub fri_queries: usize,
+    /// Target security level; when set, the query count is derived from it
+    pub security_bits: Option<usize>,
+    /// Soundness bound used to derive the query count from `security_bits`
+    pub soundness: Soundness,
+    /// Proof-of-work bits the prover must grind before FRI query positions are drawn
+    pub grinding_bits: usize,
+    /// Number of chunks `H_i` the composition polynomial is split into, so that
+    /// `H(x) = sum(x^i * H_i(x^k))` and each chunk has `1/k` of its degree.
+    pub composition_chunks: usize,
+    /// Offset `g` of the coset `g·H` over which the trace is extended and the quotient
+    /// and FRI layers are evaluated.
+    ///
+    /// Must lie outside the extended subgroup `H`, so the vanishing polynomial of the
+    /// trace domain never evaluates to zero on the coset. It is taken from the base prime
+    /// field of `F`, over which the trace is extended.
+    pub coset_offset: F::BasePrimeField,
+    /// Number of random rows appended to the trace before it is extended.
+    ///
+    /// Constraints are not enforced on blinding rows, so up to this many opened values
+    /// of each trace column are statistically independent of the witness. The trace
+    /// domain grows to the next power of two that fits them.
+    pub blinding_rows: usize,
+    /// Zero-knowledge mode.
+    ///
+    /// Appends a blinding row per opened value of each trace column, salts its
+    /// Merkle leaves and masks the committed quotient with a random polynomial, so
+    /// openings and authentication paths reveal nothing about the witness.
+    pub zk: bool,
+    /// Approximate number of bytes the prover may use.
+    ///
+    /// When the estimated memory of holding the whole trace extension exceeds the
+    /// budget, the prover streams the extension instead, unless a strategy was chosen
+    /// explicitly. Only the prover reads it: it is neither encoded in proofs nor
+    /// compared by the verifier.
+    pub memory_budget: Option<usize>,
+}
+
+impl Default for ProofOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: FftField> ProofOptions<F> {
+    /// Creates the default options over `F`, extending over the coset of its generator.
+    ///
+    /// `Default` is only implemented over `Fr`, so `ProofOptions::default()` keeps
+    /// inferring the BLS12-381 scalar field.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            blowup_factor: 2,
+            merkle_cap_height: 0,
+            fri_remainder_size: 4,
+            fri_folding_schedule: Vec::new(),
+            fri_queries: 80,
+            security_bits: None,
+            soundness: Soundness::default(),
+            grinding_bits: 0,
+            composition_chunks: 1,
+            coset_offset: F::BasePrimeField::GENERATOR,
+            blinding_rows: 0,
+            zk: false,
+            memory_budget: None,
+        }
+    }
+
+    /// Number of FRI queries, derived from the security target when one is set.
+    pub fn num_fri_queries(&self) -> usize {
+        match self.security_bits {
+            Some(bits) => {
+                self.soundness
+                    .required_queries(bits, self.blowup_factor, self.grinding_bits)
+            }
+            None => self.fri_queries,
+        }
+    }
+
+    /// Security of proofs generated with these options.
+    ///
+    /// Every FRI query contributes the bits of the soundness bound and grinding adds its
+    /// bits on top. Both are capped by the size of the field the challenges are drawn
+    /// from, which bounds the chance of a challenge hitting a bad value.
+    pub fn security_level(&self) -> SecurityLevel {
+        let queries = self.num_fri_queries() as f64;
+        let field_bits =
+            F::extension_degree() as usize * (F::BasePrimeField::MODULUS_BIT_SIZE - 1) as usize;
+        let bits = |soundness: Soundness| {
+            let query_bits = (queries * soundness.bits_per_query(self.blowup_factor)) as usize;
+            (query_bits + self.grinding_bits).min(field_bits)
+        };
+        SecurityLevel {
+            conjectured: bits(Soundness::Conjectured),
+            proven: bits(Soundness::Proven),
+        }
+    }
+
+    /// Sets the ratio between the extended domain and the trace domain.
+    pub fn with_blowup_factor(mut self, blowup_factor: usize) -> Self {
+        self.blowup_factor = blowup_factor;
+        self
+    }
+
+    /// Sets the Merkle cap height.
+    pub fn with_merkle_cap_height(mut self, merkle_cap_height: usize) -> Self {
+        self.merkle_cap_height = merkle_cap_height;
+        self
+    }
+
+    /// Sets the size at which FRI folding stops.
+    pub fn with_fri_remainder_size(mut self, fri_remainder_size: usize) -> Self {
+        self.fri_remainder_size = fri_remainder_size;
+        self
+    }
+
+    /// Sets the FRI stopping point by the maximum degree of the final polynomial.
+    ///
+    /// Folding stops once a layer holds at most `(degree + 1) * blowup_factor` values,
+    /// rounded up to a power of two, so the blowup factor must be set first.
+    pub fn with_fri_max_remainder_degree(mut self, degree: usize) -> Self {
+        self.fri_remainder_size = ((degree + 1) * self.blowup_factor).next_power_of_two();
+        self
+    }
+
+    /// Maximum degree of the final FRI polynomial for honest proofs.
+    pub fn fri_max_remainder_degree(&self) -> usize {
+        (self.fri_remainder_size / self.blowup_factor).saturating_sub(1)
+    }
+
+    /// Number of blinding rows appended to the trace under constraints reading `window`
+    /// consecutive rows, see `ConstraintSystem::window`.
+    ///
+    /// In zero-knowledge mode this is raised to one row per opened value of each trace
+    /// column: the values at the `window` points `z, ω·z, …` of the out-of-domain frame
+    /// and the layer 0 coset of every FRI query.
+    pub fn num_blinding_rows(&self, window: usize) -> usize {
+        if self.zk {
+            let coset_size = self.fri_folding_schedule.first().copied().unwrap_or(2);
+            self.blinding_rows
+                .max(window + self.num_fri_queries() * coset_size)
+        } else {
+            self.blinding_rows
+        }
+    }
+
+    /// Number of columns the zero-knowledge mask takes up in the trace commitment.
+    ///
+    /// The trace is committed over the base prime field of `F`, so the mask spends one
+    /// column on each of its coefficients over it, and none without zero-knowledge.
+    pub fn num_mask_columns(&self) -> usize {
+        if self.zk {
+            F::extension_degree() as usize
+        } else {
+            0
+        }
+    }
+
+    /// Size of the trace domain for a trace of `trace_len` rows under constraints
+    /// reading `window` consecutive rows, including the blinding rows.
+    pub fn trace_domain_size(&self, trace_len: usize, window: usize) -> usize {
+        (trace_len + self.num_blinding_rows(window)).next_power_of_two()
+    }
+
+    /// Estimated length of `StarkProof::to_bytes` for a trace of `trace_len` rows and
+    /// `width` committed columns, including randomizer columns.
+    ///
+    /// Assumes valid options, no public cells, no window constraints, SHA-256
+    /// commitments and a final FRI polynomial of full degree; the characters of column
+    /// names are not counted.
+    pub fn estimate_proof_size(&self, trace_len: usize, width: usize) -> usize {
+        self.estimate_proof_size_with_auxiliary(trace_len, width, 0)
+    }
+
+    /// Estimated length of `StarkProof::to_bytes` like `estimate_proof_size`, for a
+    /// proof that also commits `auxiliary_width` auxiliary columns.
+    pub fn estimate_proof_size_with_auxiliary(
+        &self,
+        trace_len: usize,
+        width: usize,
+        auxiliary_width: usize,
+    ) -> usize {
+        let extended_len =
+            self.trace_domain_size(trace_len, TRANSITION_WINDOW) * self.blowup_factor;
+        let factors = fri_folding_factors(extended_len, self);
+        let queries = self.num_fri_queries();
+        let chunks = self.composition_chunks;
+        let field_len = encoded_len::<F>();
+        let base_len = encoded_len::<F::BasePrimeField>();
+        let sequence = |len: usize, item_len: usize| WORD_LEN + len * item_len;
+        // Trees over single field elements keep them as leaves, see `field_element_leaf`
+        let node = |level: usize, leaf_len: usize| {
+            WORD_LEN + if level == 0 { leaf_len } else { DIGEST_LEN }
+        };
+        let cap = |leaves: usize, leaf_len: usize| {
+            let level = self.path_levels(leaves);
+            sequence(
+                leaves.min(1 << self.merkle_cap_height),
+                node(level, leaf_len),
+            )
+        };
+        let path = |leaves: usize, leaf_len: usize| {
+            let levels = self.path_levels(leaves);
+            let nodes: usize = (0..levels).map(|level| node(level, leaf_len)).sum();
+            WORD_LEN + nodes + sequence(levels, WORD_LEN)
+        };
+
+        let header = 6;
+        let options = 7 * WORD_LEN
+            + sequence(self.fri_folding_schedule.len(), WORD_LEN)
+            + 1
+            + self.security_bits.map_or(0, |_| WORD_LEN)
+            + 1
+            + base_len
+            + 1;
+        let auxiliary_cap = if auxiliary_width > 0 {
+            cap(extended_len, DIGEST_LEN)
+        } else {
+            WORD_LEN
+        };
+        let commitments =
+            2 * cap(extended_len, DIGEST_LEN) + auxiliary_cap + cap(extended_len, field_len);
+        let column_names = 2 * WORD_LEN + width * WORD_LEN;
+        let public_values = WORD_LEN;
+        // The rows at z and ω·z, an empty sequence of further window rows and the chunks
+        let ood_frame = 2 * sequence(width + auxiliary_width, field_len)
+            + WORD_LEN
+            + sequence(chunks, field_len);
+
+        let mut layer_caps = WORD_LEN;
+        let mut query_len = WORD_LEN;
+        let mut layer_len = extended_len;
+        for (round, &factor) in factors.iter().enumerate() {
+            if round > 0 {
+                layer_caps += cap(layer_len, field_len);
+            }
+            query_len += WORD_LEN
+                + sequence(factor, field_len)
+                + sequence(factor, path(layer_len, field_len));
+            layer_len /= factor;
+        }
+        let final_poly = sequence(layer_len / self.blowup_factor.max(1), field_len);
+        let fri = sequence(factors.len(), field_len)
+            + layer_caps
+            + sequence(queries, query_len)
+            + final_poly
+            + WORD_LEN;
+
+        let salt_len = if self.zk { DIGEST_LEN } else { 0 };
+        let opening = WORD_LEN
+            + sequence(width, base_len)
+            + sequence(salt_len, 1)
+            + path(extended_len, DIGEST_LEN)
+            + sequence(chunks, field_len)
+            + path(extended_len, DIGEST_LEN)
+            + sequence(auxiliary_width, field_len)
+            + 1
+            + if auxiliary_width > 0 {
+                path(extended_len, DIGEST_LEN)
+            } else {
+                0
+            };
+        let coset_size = factors.first().copied().unwrap_or_default();
+        let openings = sequence(queries, sequence(coset_size, opening));
+
+        header + options + commitments + column_names + public_values + ood_frame + fri + openings
+    }
+
+    /// Estimated number of hash evaluations the verifier spends on Merkle authentication
+    /// for a trace of `trace_len` rows without window constraints.
+    ///
+    /// Counts the hashed trace and quotient leaves and every node recomputed along the
+    /// opened paths; transcript and proof-of-work hashes are negligible in comparison.
+    pub fn estimate_verifier_hashes(&self, trace_len: usize) -> usize {
+        let extended_len =
+            self.trace_domain_size(trace_len, TRANSITION_WINDOW) * self.blowup_factor;
+        let factors = fri_folding_factors(extended_len, self);
+
+        let mut query_hashes = 0;
+        let mut layer_len = extended_len;
+        for &factor in &factors {
+            query_hashes += factor * self.path_levels(layer_len);
+            layer_len /= factor;
+        }
+        // The trace and quotient rows opened for every position of the layer 0 coset
+        let coset_size = factors.first().copied().unwrap_or_default();
+        query_hashes += coset_size * 2 * (1 + self.path_levels(extended_len));
+
+        self.num_fri_queries() * query_hashes
+    }
+
+    /// Rough peak memory in bytes of proving a trace of `trace_len` rows and `width`
+    /// committed columns without window constraints, including randomizer and mask
+    /// columns.
+    ///
+    /// Counts the trace polynomials and their extension, which the streaming strategy
+    /// holds one segment of the trace domain size at a time, as well as the salts, the
+    /// quotient chunks, the DEEP composition, the FRI layers and their Merkle trees.
+    pub fn estimate_prover_memory(&self, trace_len: usize, width: usize, streaming: bool) -> usize {
+        let domain_size = self.trace_domain_size(trace_len, TRANSITION_WINDOW);
+        let extended_len = domain_size * self.blowup_factor;
+        let field_len = encoded_len::<F>();
+        let base_len = encoded_len::<F::BasePrimeField>();
+        let row = |len: usize| VEC_LEN + len * field_len;
+        let node = VEC_LEN + DIGEST_LEN;
+        // Leaves are stored twice and the internal nodes add as many again
+        let tree = 3 * extended_len * node;
+
+        // The trace is extended over the base prime field
+        let polynomials = width * domain_size * base_len;
+        let extension =
+            if streaming { domain_size } else { extended_len } * (VEC_LEN + width * base_len);
+        let salts = extended_len * (VEC_LEN + if self.zk { DIGEST_LEN } else { 0 });
+        let chunks = self.composition_chunks;
+        let quotient = extended_len * (row(chunks) + (chunks + 2) * field_len);
+        // The DEEP composition and the FRI layers folded from it
+        let fri = 2 * extended_len * field_len;
+        // Points of both domains cached by the prover context
+        let context = (domain_size + extended_len + self.blowup_factor) * field_len;
+
+        polynomials + extension + salts + quotient + fri + context + 4 * tree
+    }
+
+    /// Number of nodes on an authentication path in a binary tree over `leaves` leaves.
+    fn path_levels(&self, leaves: usize) -> usize {
+        let depth = leaves.max(1).ilog2() as usize;
+        depth - self.merkle_cap_height.min(depth)
+    }
+
+    /// Checks that the options are consistent with a trace of `trace_len` rows under
+    /// constraints reading `window` consecutive rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first inconsistency found.
+    pub fn validate(&self, trace_len: usize, window: usize) -> Result<(), String> {
+        if !trace_len.is_power_of_two() {
+            return Err(format!("trace length {} is not a power of two", trace_len));
+        }
+        if !self.blowup_factor.is_power_of_two() || self.blowup_factor < 2 {
+            return Err(format!(
+                "blowup factor {} is not a power of two of at least 2",
+                self.blowup_factor
+            ));
+        }
+        if self.num_fri_queries() == 0 {
+            return Err("at least one FRI query is required".to_string());
+        }
+        if self.grinding_bits > MAX_GRINDING_BITS {
+            return Err(format!(
+                "{} grinding bits exceed the maximum of {}",
+                self.grinding_bits, MAX_GRINDING_BITS
+            ));
+        }
+        let domain_size = self.trace_domain_size(trace_len, window);
+        let extended_len = domain_size * self.blowup_factor;
+        if !self.fri_remainder_size.is_power_of_two()
+            || self.fri_remainder_size < self.blowup_factor
+            || self.fri_remainder_size >= extended_len
+        {
+            return Err(format!(
+                "FRI remainder size {} must be a power of two of at least the blowup factor {} and below the extended domain size {}",
+                self.fri_remainder_size, self.blowup_factor, extended_len
+            ));
+        }
+        if let Some(factor) = self
+            .fri_folding_schedule
+            .iter()
+            .find(|factor| !factor.is_power_of_two() || **factor < 2)
+        {
+            return Err(format!(
+                "FRI folding factor {} is not a power of two of at least 2",
+                factor
+            ));
+        }
+        if !self.composition_chunks.is_power_of_two() || self.composition_chunks > domain_size {
+            return Err(format!(
+                "composition chunk count {} must be a power of two of at most the trace domain size {}",
+                self.composition_chunks, domain_size
+            ));
+        }
+        if self.coset_offset.pow([extended_len as u64]) == F::BasePrimeField::ONE {
+            return Err("coset offset lies inside the extended subgroup".to_string());
+        }
+        Ok(())
+    }
+
+    /// Sets the folding factor of each FRI round.
+    pub fn with_fri_folding_schedule(mut self, fri_folding_schedule: Vec<usize>) -> Self {
+        self.fri_folding_schedule = fri_folding_schedule;
+        self
+    }
+
+    /// Sets the number of FRI queries.
+    pub fn with_fri_queries(mut self, fri_queries: usize) -> Self {
+        self.fri_queries = fri_queries;
+        self
+    }
+
+    /// Derives the FRI query count from a target security level and soundness bound.
+    pub fn with_security_level(mut self, security_bits: usize, soundness: Soundness) -> Self {
+        self.security_bits = Some(security_bits);
+        self.soundness = soundness;
+        self
+    }
+
+    /// Sets the number of proof-of-work grinding bits.
+    pub fn with_grinding_bits(mut self, grinding_bits: usize) -> Self {
+        self.grinding_bits = grinding_bits;
+        self
+    }
+
+    /// Sets the number of chunks the composition polynomial is split into.
+    pub fn with_composition_chunks(mut self, composition_chunks: usize) -> Self {
+        self.composition_chunks = composition_chunks;
+        self
+    }
+
+    /// Sets the number of random blinding rows appended to the trace.
+    pub fn with_blinding_rows(mut self, blinding_rows: usize) -> Self {
+        self.blinding_rows = blinding_rows;
+        self
+    }
+
+    /// Enables or disables zero-knowledge mode.
+    pub fn with_zk(mut self, zk: bool) -> Self {
+        self.zk = zk;
+        self
+    }
+
+    /// Sets the approximate memory budget of the prover in bytes.
+    pub fn with_memory_budget(mut self, memory_budget: usize) -> Self {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
+
+    /// The options recorded in a proof, without the prover-only memory budget.
+    pub fn proof_options(&self) -> Self {
+        Self {
+            memory_budget: None,
+            ..self.clone()
+        }
+    }
+
+    /// Sets the offset of the low-degree extension coset.
+    pub fn with_coset_offset(mut self, coset_offset: F::BasePrimeField) -> Self {
+        self.coset_offset = coset_offset;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::Field;
+
+    #[test]
+    fn test_query_count_from_security_level() {
+        // Blowup 2 gives one conjectured bit or half a proven bit per query
+        assert_eq!(Soundness::Conjectured.required_queries(100, 2, 0), 100);
+        assert_eq!(Soundness::Proven.required_queries(100, 2, 0), 200);
+        assert_eq!(Soundness::Conjectured.required_queries(100, 8, 0), 34);
+        assert_eq!(Soundness::Conjectured.required_queries(100, 8, 20), 27);
+
+        let options = ProofOptions::default();
+        assert_eq!(options.num_fri_queries(), options.fri_queries);
+        let options = options
+            .with_grinding_bits(16)
+            .with_security_level(96, Soundness::Proven);
+        assert_eq!(options.num_fri_queries(), 160);
+
+        // A larger blowup contributes more bits per query
+        let options = options.with_blowup_factor(8);
+        assert_eq!(options.num_fri_queries(), 54);
+    }
+
+    #[test]
+    fn test_blowup_and_query_validation() {
+        let valid = |options: ProofOptions| options.validate(8, 2).is_ok();
+        assert!(valid(ProofOptions::default().with_blowup_factor(4)));
+        assert!(!valid(ProofOptions::default().with_blowup_factor(3)));
+        assert!(!valid(ProofOptions::default().with_blowup_factor(1)));
+        assert!(!valid(ProofOptions::default().with_fri_queries(0)));
+        assert!(valid(ProofOptions::default().with_grinding_bits(64)));
+        assert!(!valid(ProofOptions::default().with_grinding_bits(65)));
+
+        // The remainder must hold at least one blowup's worth of values
+        let options = ProofOptions::default().with_blowup_factor(8);
+        assert!(!valid(options.clone()));
+        let options = options.with_fri_max_remainder_degree(0);
+        assert_eq!(options.fri_remainder_size, 8);
+        assert!(valid(options));
+    }
+
+    #[test]
+    fn test_fri_remainder_validation() {
+        let options = ProofOptions::default().with_fri_max_remainder_degree(7);
+        assert_eq!(options.fri_remainder_size, 16);
+        assert_eq!(options.fri_max_remainder_degree(), 7);
+        assert!(options.validate(16, 2).is_ok());
+        let valid = |options: ProofOptions| options.validate(16, 2).is_ok();
+
+        // The remainder must leave a folding round and cannot undercut the blowup
+        assert!(options.validate(8, 2).is_err());
+        assert!(options.validate(4, 2).is_err());
+        assert!(!valid(options.clone().with_fri_remainder_size(1)));
+        assert!(!valid(options.clone().with_fri_remainder_size(12)));
+        assert!(!valid(options.clone().with_coset_offset(Fr::ONE)));
+
+        // Folding factors must be powers of two of at least 2
+        assert!(valid(options.clone().with_fri_folding_schedule(vec![4, 2])));
+        assert!(!valid(options.clone().with_fri_folding_schedule(vec![3])));
+        assert!(!valid(options.clone().with_fri_folding_schedule(vec![1])));
+
+        // Composition chunks must be a power of two up to the trace length
+        assert!(valid(options.clone().with_composition_chunks(4)));
+        assert!(!valid(options.clone().with_composition_chunks(3)));
+        assert!(!valid(options.clone().with_composition_chunks(32)));
+
+        // Blinding rows grow the trace domain to the next power of two
+        let options = options.with_blinding_rows(3);
+        assert_eq!(options.trace_domain_size(16, 2), 32);
+        assert!(valid(options.clone().with_composition_chunks(32)));
+        assert!(!valid(options.clone().with_fri_remainder_size(64)));
+
+        // Zero-knowledge mode needs enough blinding rows to hide the out-of-domain values
+        // and the layer 0 coset of every FRI query
+        let options = options.with_zk(true);
+        assert_eq!(options.num_blinding_rows(2), 2 + 2 * options.fri_queries);
+        assert_eq!(
+            options
+                .clone()
+                .with_fri_folding_schedule(vec![4])
+                .num_blinding_rows(2),
+            2 + 4 * options.fri_queries
+        );
+        assert_eq!(options.trace_domain_size(16, 2), 256);
+        // and every further row window constraints reveal
+        assert_eq!(options.num_blinding_rows(5), 5 + 2 * options.fri_queries);
+    }
+
+    #[test]
+    fn test_cost_estimates() {
+        let options = ProofOptions::default().with_fri_queries(32);
+        let size = options.estimate_proof_size(64, 2);
+        let hashes = options.estimate_verifier_hashes(64);
+
+        // Every query opens a coset per FRI round and the trace and quotient rows at layer 0
+        assert_eq!(
+            hashes,
+            32 * ((2 * 7 + 2 * 6 + 2 * 5 + 2 * 4 + 2 * 3) + 2 * 2 * 8)
+        );
+
+        // Wider traces only grow the opened rows and the out-of-domain frame
+        assert_eq!(
+            options.estimate_proof_size(64, 3) - size,
+            8 + 2 * 32 + 32 * 2 * 32
+        );
+        assert_eq!(options.estimate_verifier_hashes(64), hashes);
+
+        // Halving the queries roughly halves both estimates
+        let fewer = options.clone().with_fri_queries(16);
+        assert!(fewer.estimate_proof_size(64, 2) < size * 6 / 10);
+        assert_eq!(fewer.estimate_verifier_hashes(64), hashes / 2);
+
+        // A cap removes its levels from every authentication path
+        let capped = options.clone().with_merkle_cap_height(2);
+        assert!(capped.estimate_verifier_hashes(64) < hashes);
+        assert!(capped.estimate_proof_size(64, 2) < size);
+
+        // A larger blowup needs fewer queries for the same security level
+        let secure = |blowup: usize| {
+            ProofOptions::default()
+                .with_blowup_factor(blowup)
+                .with_fri_max_remainder_degree(1)
+                .with_security_level(64, Soundness::Conjectured)
+        };
+        assert!(secure(8).estimate_proof_size(64, 2) < secure(2).estimate_proof_size(64, 2));
+        assert!(secure(8).estimate_verifier_hashes(64) < secure(2).estimate_verifier_hashes(64));
+    }
+
+    #[test]
+    fn test_security_level() {
+        // 80 queries at blowup 2 give one conjectured or half a proven bit each
+        let level = ProofOptions::default().security_level();
+        assert_eq!(
+            level,
+            SecurityLevel {
+                conjectured: 80,
+                proven: 40
+            }
+        );
+        assert_eq!(level.bits(Soundness::Proven), 40);
+
+        // Grinding adds its bits to both bounds
+        let options = ProofOptions::default()
+            .with_blowup_factor(8)
+            .with_fri_queries(27)
+            .with_grinding_bits(20);
+        assert_eq!(options.security_level().conjectured, 27 * 3 + 20);
+        assert_eq!(options.security_level().proven, 40 + 20);
+
+        // A derived query count reaches at least the requested level
+        for soundness in [Soundness::Conjectured, Soundness::Proven] {
+            for blowup in [2, 4, 8, 16] {
+                let options = ProofOptions::default()
+                    .with_blowup_factor(blowup)
+                    .with_security_level(100, soundness);
+                assert!(options.security_level().bits(soundness) >= 100);
+            }
+        }
+
+        // No number of queries exceeds the bits of the field
+        let options = ProofOptions::default().with_fri_queries(1000);
+        assert_eq!(options.security_level().conjectured, 254);
+    }
+
+    #[test]
+    fn test_prover_memory_estimate() {
+        let options = ProofOptions::default().with_blowup_factor(8);
+        let in_memory = options.estimate_prover_memory(1 << 10, 8, false);
+        let streaming = options.estimate_prover_memory(1 << 10, 8, true);
+
+        // Streaming holds one segment of the extension instead of all eight
+        let row = VEC_LEN + 8 * encoded_len::<Fr>();
+        assert_eq!(in_memory - streaming, 7 * (1 << 10) * row);
+
+        // The budget stays with the prover
+        let budgeted = options.clone().with_memory_budget(streaming);
+        assert_eq!(budgeted.memory_budget, Some(streaming));
+        assert_eq!(budgeted.proof_options(), options);
+    }
+}
@@ -0,0 +1,30 @@
+//! Iteration helpers that run on the rayon thread pool with the `parallel` feature.
+//!
+//! Each macro expands to the sequential iterator by default and to its rayon
+//! counterpart with the feature enabled, so call sites stick to the adaptors both
+//! provide (`map`, `enumerate`, `zip`, `sum`, `collect`, ...) and compile either way.
+//! Modules using them import `rayon::prelude::*` behind the same feature.
+
+/// Iterates over a collection by reference.
+macro_rules! iter {
+    ($collection:expr) => {{
+        #[cfg(feature = "parallel")]
+        let iter = $collection.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let iter = $collection.iter();
+        iter
+    }};
+}
+
+/// Iterates over a collection or range by value.
+macro_rules! into_iter {
+    ($collection:expr) => {{
+        #[cfg(feature = "parallel")]
+        let iter = $collection.into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let iter = $collection.into_iter();
+        iter
+    }};
+}
+
+pub(crate) use {into_iter, iter};
@@ -0,0 +1,72 @@
+//! Progress reporting and cooperative cancellation for long-running proofs.
+//!
+//! A `ProofObserver` attached with `StarkProver::with_observer` is notified when each
+//! phase of proof generation starts and ends and after every FRI folding round. The
+//! prover polls `is_cancelled` between phases and rounds and stops with
+//! `ToyniError::Cancelled` once it returns true.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Phases of proof generation, in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProofPhase {
+    /// Low-degree extension and commitment of the trace columns
+    TraceCommitment,
+    /// Constraint evaluation over the extended domain and commitment of the quotient chunks
+    QuotientCommitment,
+    /// Out-of-domain evaluation and commitment of the DEEP composition
+    DeepComposition,
+    /// FRI folding, proof-of-work and query phase
+    Fri,
+    /// Opening of the trace and quotient rows at the queried positions
+    TraceOpenings,
+}
+
+/// Receives progress notifications from the prover.
+///
+/// All methods have empty defaults, so observers only implement what they need. They take
+/// `&self` and observers must be `Sync`, so that a proof can be generated on another
+/// thread or task: observers that record progress use thread-safe interior mutability.
+pub trait ProofObserver: Sync {
+    /// Called before `phase` starts.
+    fn on_phase_start(&self, _phase: ProofPhase) {}
+
+    /// Called after `phase` completed.
+    fn on_phase_end(&self, _phase: ProofPhase) {}
+
+    /// Called after each FRI folding round with the number of rounds folded so far and
+    /// the total number of rounds, so `folded * 100 / total` is the percentage done.
+    fn on_fri_progress(&self, _folded: usize, _total: usize) {}
+
+    /// Whether the prover should stop at the next phase or FRI round.
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// Observes nothing and never cancels.
+impl ProofObserver for () {}
+
+/// Cancellation flag that can be set from another thread while a proof is generated.
+#[derive(Debug, Default)]
+pub struct CancellationToken {
+    cancelled: AtomicBool,
+}
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every prover observing this token to stop.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl ProofObserver for CancellationToken {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
@@ -18,180 +18,943 @@
 //! - `StarkProver`: Generates proofs from execution traces
 //! - `StarkVerifier`: Verifies proofs using FRI and Merkle commitments
 
-use crate::digest_sha2;
-use crate::math::fri::fri_fold;
+use crate::context::ProverContext;
+use crate::error::ToyniError;
+use crate::hash::Sha256Hasher;
+use crate::math::composition::CompositionPolynomial;
+use crate::math::deep::{DeepComposition, OodFrame, is_out_of_domain};
+use crate::math::fri::{FriProof, coset_positions, fri_prove_observed};
+use crate::math::lde::TraceLde;
 use crate::math::polynomial::Polynomial as ToyniPolynomial;
-use crate::merkle::{MerkleTree};
-use crate::vm::{constraints::ConstraintSystem, trace::ExecutionTrace};
+use crate::merkle::{MerkleCap, MerkleProof, MerkleTree, SALT_LEN, salted_row_leaf};
+use crate::metrics::{MetricsRecorder, ProofMetrics, rows_size};
+use crate::options::{ProofOptions, SecurityLevel};
+use crate::parallel::{into_iter, iter};
+use crate::progress::{ProofObserver, ProofPhase};
+use crate::transcript::{RandomOracle, Transcript, absorb_public_inputs};
+use crate::vm::{
+    air::{Air, Constraints},
+    auxiliary::{AuxiliarySegment, AuxiliaryTrace, MainColumns, build_auxiliary_columns},
+    constraints::ConstraintSystem,
+    table::{TraceTable, fill_columns},
+    trace::{PaddingStrategy, ProgramVariable},
+};
 use ark_bls12_381::Fr;
-use ark_ff::{BigInteger, PrimeField, UniformRand};
-use ark_poly::DenseUVPolynomial;
-use ark_poly::{EvaluationDomain, GeneralEvaluationDomain, univariate::DensePolynomial};
-use num_bigint::BigUint;
-use num_traits::ToPrimitive;
-use rand::thread_rng;
+use ark_ff::{FftField, Field, UniformRand};
+use ark_poly::{DenseUVPolynomial, EvaluationDomain, Polynomial, univariate::DensePolynomial};
+use rand::Rng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    pin::{Pin, pin},
+    task::{Context, Poll, Waker},
+};
+use tracing::{Instrument, debug, debug_span};
 
-/// Number of random challenges for verifier spot checks
-const VERIFIER_QUERIES: usize = 80;
+/// Extension of the trace columns over the base prime field of `F`, the salt of every
+/// extended row and the Merkle tree over them.
+type CommittedTrace<F> = (
+    TraceLde<<F as Field>::BasePrimeField>,
+    Vec<Vec<u8>>,
+    MerkleTree,
+);
+
+/// Opening of the trace and quotient rows at one position of the extended domain.
+///
+/// The verifier recomputes the DEEP composition at the position from both rows and
+/// compares it with the layer 0 value opened by FRI.
+#[derive(Debug, Clone)]
+pub struct TraceOpening<F: FftField = Fr> {
+    /// Position in the extended domain
+    pub index: usize,
+    /// Trace row over the base prime field of `F`, in the order of
+    /// `StarkProof::trace_variables` followed by `StarkProof::trace_randomizers` and, in
+    /// zero-knowledge mode, the columns of the quotient mask
+    pub row: Vec<F::BasePrimeField>,
+    /// Salt hashed into the leaf of `row`, empty unless zero-knowledge is enabled
+    pub salt: Vec<u8>,
+    /// Merkle authentication path for `row`
+    pub proof: MerkleProof,
+    /// Value of every quotient chunk
+    pub quotient: Vec<F>,
+    /// Merkle authentication path for `quotient`
+    pub quotient_proof: MerkleProof,
+    /// Auxiliary row in the order of `ConstraintSystem::auxiliary_columns`, empty
+    /// without an auxiliary segment
+    pub auxiliary_row: Vec<F>,
+    /// Merkle authentication path for `auxiliary_row`, salted with `salt`
+    pub auxiliary_proof: Option<MerkleProof>,
+}
 
 /// STARK proof containing all components needed for verification.
 ///
 /// The proof consists of:
-/// - Quotient polynomial evaluations
-/// - FRI protocol layers and challenges
-/// - Merkle commitments for each FRI layer
-/// - Random challenges for spot checks
+/// - Merkle cap of the low-degree extended trace
+/// - Merkle cap of the auxiliary segment, if the constraints declare one
+/// - Merkle cap of the quotient chunk evaluations over the extended domain
+/// - Trace and quotient values at an out-of-domain point
+/// - FRI proof that the DEEP composition of both is low degree
+/// - Trace and quotient openings at the FRI query positions
+///
+/// Its size and verification time are polylogarithmic in the trace length.
 #[derive(Debug)]
-pub struct StarkProof {
-    /// Quotient polynomial evaluations over the extended domain
-    pub quotient_eval_domain: Vec<Fr>,
-    /// FRI protocol layers with folded evaluations
-    pub fri_layers: Vec<Vec<Fr>>,
-    /// Random challenges for FRI folding
-    pub fri_challenges: Vec<Fr>,
-    /// Combined constraint polynomial
-    pub combined_constraint: ToyniPolynomial,
-    /// Quotient polynomial from division
-    pub quotient_poly: ToyniPolynomial,
-    /// Merkle trees for each FRI layer's commitments
-    pub folding_commitment_trees: Vec<MerkleTree>,
-    /// Fiat-Shamir random challenges for spot checks
-    pub verifier_random_challenges: Vec<Fr>,
+pub struct StarkProof<F: FftField = Fr> {
+    /// Merkle cap of the trace rows evaluated over the extended domain
+    pub trace_commitment: MerkleCap,
+    /// Names of the trace columns, in the order they are committed
+    pub trace_variables: Vec<ProgramVariable>,
+    /// Names of the randomizer columns, committed after the trace columns
+    pub trace_randomizers: Vec<ProgramVariable>,
+    /// Name and value of every public cell, in the order of
+    /// `ConstraintSystem::public_cells`
+    pub public_values: Vec<(String, F)>,
+    /// Merkle cap of the auxiliary rows evaluated over the extended domain, empty
+    /// without an auxiliary segment
+    pub auxiliary_commitment: MerkleCap,
+    /// Merkle cap of the rows of quotient chunks `H_i`, `H(x) = Σ xⁱ·H_i(xᵏ)`, evaluated
+    /// over the extended domain
+    pub quotient_commitment: MerkleCap,
+    /// Trace and quotient chunk values at the out-of-domain point
+    pub ood_frame: OodFrame<F>,
+    /// Merkle cap of the DEEP composition evaluated over the extended domain
+    pub deep_commitment: MerkleCap,
+    /// FRI proof over the DEEP composition
+    pub fri_proof: FriProof<F>,
+    /// Trace and quotient rows opened at the layer 0 coset of every FRI query
+    pub trace_openings: Vec<Vec<TraceOpening<F>>>,
+    /// Options the proof was generated with, checked against the verifier's
+    pub options: ProofOptions<F>,
+}
+
+impl<F: FftField> StarkProof<F> {
+    /// Value of the public cell `name`, if the proof reveals it.
+    ///
+    /// Only meaningful once the proof verified against constraints declaring the cell.
+    pub fn public_value(&self, name: &str) -> Option<F> {
+        self.public_values
+            .iter()
+            .find(|(cell, _)| cell == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// Security of the proof under both soundness bounds, derived from the options it
+    /// embeds, which the verifier checks against its own.
+    pub fn security_level(&self) -> SecurityLevel {
+        self.options.security_level()
+    }
+}
+
+/// Builder for a `StarkProver`, started with `StarkProver::builder`.
+///
+/// The trace and constraints are required; every other setting keeps the default of
+/// `StarkProver::new` unless set.
+pub struct StarkProverBuilder<'a, F: FftField = Fr> {
+    trace: Option<&'a dyn TraceTable<F::BasePrimeField>>,
+    constraints: Option<&'a ConstraintSystem<F>>,
+    options: ProofOptions<F>,
+    public_inputs: Vec<F>,
+    observer: Option<&'a dyn ProofObserver>,
+    strategy: Option<ProvingStrategy>,
+    padding: PaddingStrategy<F::BasePrimeField>,
+    auxiliary: Option<&'a dyn AuxiliaryTrace<F>>,
+}
+
+impl<F: FftField> Default for StarkProverBuilder<'_, F> {
+    fn default() -> Self {
+        Self {
+            trace: None,
+            constraints: None,
+            options: ProofOptions::new(),
+            public_inputs: Vec::new(),
+            observer: None,
+            strategy: None,
+            padding: PaddingStrategy::default(),
+            auxiliary: None,
+        }
+    }
+}
+
+impl<'a, F: FftField> StarkProverBuilder<'a, F> {
+    /// Sets the execution trace to prove.
+    pub fn trace(mut self, trace: &'a dyn TraceTable<F::BasePrimeField>) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Sets the constraint system the trace must satisfy.
+    pub fn constraints(mut self, constraints: &'a ConstraintSystem<F>) -> Self {
+        self.constraints = Some(constraints);
+        self
+    }
+
+    /// Sets the proof options.
+    pub fn options(mut self, options: ProofOptions<F>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the public inputs, see `StarkProver::with_public_inputs`.
+    pub fn public_inputs(mut self, public_inputs: Vec<F>) -> Self {
+        self.public_inputs = public_inputs;
+        self
+    }
+
+    /// Sets the progress observer, see `StarkProver::with_observer`.
+    pub fn observer(mut self, observer: &'a dyn ProofObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Sets the proving strategy, see `StarkProver::with_strategy`.
+    pub fn strategy(mut self, strategy: ProvingStrategy) -> Self {
+        self.strategy = Some(strategy);
+        self
+    }
+
+    /// Sets the padding strategy, see `StarkProver::with_padding`.
+    pub fn padding(mut self, padding: PaddingStrategy<F::BasePrimeField>) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets the builder of the auxiliary segment, see `StarkProver::with_auxiliary_trace`.
+    pub fn auxiliary_trace(mut self, auxiliary: &'a dyn AuxiliaryTrace<F>) -> Self {
+        self.auxiliary = Some(auxiliary);
+        self
+    }
+
+    /// Builds the prover.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::IncompleteBuilder` if the trace or the constraints are
+    /// missing.
+    pub fn build(self) -> Result<StarkProver<'a, F>, ToyniError> {
+        let trace = self.trace.ok_or(ToyniError::IncompleteBuilder("trace"))?;
+        let constraints = self
+            .constraints
+            .ok_or(ToyniError::IncompleteBuilder("constraints"))?;
+        Ok(StarkProver {
+            trace,
+            constraints: Constraints::Borrowed(constraints),
+            options: self.options,
+            public_inputs: self.public_inputs,
+            observer: self.observer.unwrap_or(&()),
+            strategy: self.strategy,
+            padding: self.padding,
+            auxiliary: self.auxiliary,
+        })
+    }
+}
+
+/// How the prover holds the low-degree extension of the trace.
+///
+/// Both strategies produce proofs of the same shape; they only trade memory for time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProvingStrategy {
+    /// Evaluate every column over the extended domain once and keep all rows in memory
+    #[default]
+    InMemory,
+    /// Keep only the column polynomials and recompute the extension one segment of the
+    /// trace domain size at a time whenever the rows are needed.
+    ///
+    /// Peak memory no longer grows with the blowup factor times the trace width, at the
+    /// cost of extending the trace three times and evaluating opened rows directly.
+    Streaming,
 }
 
 /// STARK prover component that generates proofs from execution traces.
 ///
 /// The prover:
-/// 1. Interpolates constraints into polynomials
-/// 2. Constructs the composition polynomial
-/// 3. Performs FRI folding with Merkle commitments
-/// 4. Generates random challenges for verification
-pub struct StarkProver<'a> {
-    /// Execution trace to prove
-    trace: &'a ExecutionTrace,
+/// 1. Commits to the low-degree extension of the trace
+/// 2. Evaluates the constraints and their quotients over the extended domain
+/// 3. Reveals the trace and quotient at an out-of-domain point
+/// 4. Performs FRI folding of the DEEP composition with Merkle commitments
+/// 5. Opens the trace and quotient at the FRI query positions
+pub struct StarkProver<'a, F: FftField = Fr> {
+    /// Execution trace to prove, filled into columns when the trace is committed
+    trace: &'a dyn TraceTable<F::BasePrimeField>,
     /// Constraint system defining program rules
-    constraints: &'a ConstraintSystem,
+    constraints: Constraints<'a, F>,
+    /// Proof configuration
+    options: ProofOptions<F>,
+    /// Public inputs of the statement, absorbed into the transcript before any commitment
+    public_inputs: Vec<F>,
+    /// Receives progress notifications and may cancel proof generation
+    observer: &'a dyn ProofObserver,
+    /// Whether the trace extension is held in memory or streamed in segments, chosen
+    /// from the memory budget unless set explicitly
+    strategy: Option<ProvingStrategy>,
+    /// How a trace whose height is not a power of two is padded before proving
+    padding: PaddingStrategy<F::BasePrimeField>,
+    /// Builds the auxiliary columns declared by the constraints, if any
+    auxiliary: Option<&'a dyn AuxiliaryTrace<F>>,
 }
 
-impl<'a> StarkProver<'a> {
+impl<'a, F: FftField> StarkProver<'a, F> {
     /// Creates a new STARK prover for the given trace and constraints.
     ///
     /// # Arguments
     ///
     /// * `trace` - The execution trace to prove
     /// * `constraints` - The constraint system defining program rules
-    pub fn new(trace: &'a ExecutionTrace, constraints: &'a ConstraintSystem) -> Self {
-        Self { trace, constraints }
+    pub fn new(
+        trace: &'a dyn TraceTable<F::BasePrimeField>,
+        constraints: &'a ConstraintSystem<F>,
+    ) -> Self {
+        Self::with_options(trace, constraints, ProofOptions::new())
+    }
+
+    /// Creates a new STARK prover with explicit proof options.
+    pub fn with_options(
+        trace: &'a dyn TraceTable<F::BasePrimeField>,
+        constraints: &'a ConstraintSystem<F>,
+        options: ProofOptions<F>,
+    ) -> Self {
+        Self {
+            trace,
+            constraints: Constraints::Borrowed(constraints),
+            options,
+            public_inputs: Vec::new(),
+            observer: &(),
+            strategy: None,
+            padding: PaddingStrategy::default(),
+            auxiliary: None,
+        }
+    }
+
+    /// Creates a prover for the statement `public_inputs` of `air`, with the
+    /// constraint system, options and transcript inputs the AIR derives from it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::ColumnSchemaMismatch` if the trace columns differ from those
+    /// the AIR declares
+    pub fn from_air<A: Air<F>>(
+        air: &A,
+        trace: &'a dyn TraceTable<F::BasePrimeField>,
+        public_inputs: &A::PublicInputs,
+    ) -> Result<Self, ToyniError> {
+        air.check_trace(trace)?;
+        Ok(Self {
+            trace,
+            constraints: Constraints::Owned(Box::new(air.constraint_system(public_inputs))),
+            options: air.options(),
+            public_inputs: air.public_inputs(public_inputs),
+            observer: &(),
+            strategy: None,
+            padding: PaddingStrategy::default(),
+            auxiliary: None,
+        })
+    }
+
+    /// Starts building a prover one setting at a time.
+    pub fn builder() -> StarkProverBuilder<'a, F> {
+        StarkProverBuilder::default()
+    }
+
+    /// Binds the proof to public inputs, which the verifier must supply as well.
+    ///
+    /// The inputs are absorbed into the transcript before the trace commitment, so every
+    /// challenge depends on them and a proof does not verify against other inputs.
+    pub fn with_public_inputs(mut self, public_inputs: Vec<F>) -> Self {
+        self.public_inputs = public_inputs;
+        self
+    }
+
+    /// Sets how the low-degree extension of the trace is held while proving, overriding
+    /// the choice made from `ProofOptions::memory_budget`.
+    pub fn with_strategy(mut self, strategy: ProvingStrategy) -> Self {
+        self.strategy = Some(strategy);
+        self
+    }
+
+    /// How the low-degree extension of the trace is held while proving.
+    ///
+    /// Unless set explicitly, the extension is held in memory if its estimated footprint
+    /// fits the memory budget, and streamed otherwise.
+    pub fn strategy(&self) -> ProvingStrategy {
+        if let Some(strategy) = self.strategy {
+            return strategy;
+        }
+        let Some(budget) = self.options.memory_budget else {
+            return ProvingStrategy::InMemory;
+        };
+        let trace_len = self.trace.length().next_power_of_two();
+        let width =
+            self.trace.width() + self.trace.randomizers().len() + self.options.num_mask_columns();
+        if self.options.estimate_prover_memory(trace_len, width, false) <= budget {
+            ProvingStrategy::InMemory
+        } else {
+            ProvingStrategy::Streaming
+        }
+    }
+
+    /// Sets how a trace whose height is not a power of two is padded before proving,
+    /// repeating its last row by default.
+    ///
+    /// The trace itself is left untouched; proofs of it verify against
+    /// `ExecutionTrace::padded_height` rows.
+    pub fn with_padding(mut self, padding: PaddingStrategy<F::BasePrimeField>) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Builds the auxiliary columns declared with `ConstraintSystem::set_auxiliary_segment`
+    /// with `auxiliary`, after the main trace is committed and the challenges are drawn.
+    pub fn with_auxiliary_trace(mut self, auxiliary: &'a dyn AuxiliaryTrace<F>) -> Self {
+        self.auxiliary = Some(auxiliary);
+        self
+    }
+
+    /// Reports the progress of proof generation to `observer`, which can also cancel it.
+    pub fn with_observer(mut self, observer: &'a dyn ProofObserver) -> Self {
+        self.observer = observer;
+        self
     }
 
     /// Generates a STARK proof for the execution trace.
     ///
     /// The proof generation process:
-    /// 1. Interpolates all constraints into polynomials
-    /// 2. Combines constraints into a single polynomial
-    /// 3. Generates random polynomial for zero-knowledge
-    /// 4. Multiplies combined constraint by random polynomial
-    /// 5. Divides by the vanishing polynomial to get quotient
-    /// 6. Performs FRI folding with Merkle commitments
-    /// 7. Generates random challenges for verification
+    /// 1. Commits to the low-degree extension of the trace
+    /// 2. Draws the constraint combination coefficients from the transcript
+    /// 3. Evaluates every constraint pointwise on the extended trace rows
+    /// 4. Divides each constraint by its own zerofier and adjusts its degree
+    /// 5. Splits the quotient into chunks and commits to their evaluations
+    /// 6. Reveals every trace column and chunk at an out-of-domain point `z`
+    /// 7. Runs FRI on the DEEP composition and opens the trace and quotient rows at the
+    ///    queried positions
     ///
     /// # Returns
     ///
     /// A `StarkProof` containing all components needed for verification
-    pub fn generate_proof(&self) -> StarkProof {
-        let trace_len = self.trace.height as usize;
-        let domain = GeneralEvaluationDomain::<Fr>::new(trace_len).unwrap();
-        let extended_domain = GeneralEvaluationDomain::<Fr>::new(trace_len * 2).unwrap();
-
-        // Interpolate all constraints into polynomials
-        let constraint_polys = self.constraints.interpolate_all_constraints(self.trace);
-
-        // Combine all constraints into a single polynomial
-        let mut combined_constraint = ToyniPolynomial::zero();
-        for poly in constraint_polys {
-            combined_constraint = combined_constraint.add(&poly);
+    ///
+    /// # Errors
+    ///
+    /// See [`StarkProver::generate_proof_with_oracle`].
+    pub fn generate_proof(&self) -> Result<StarkProof<F>, ToyniError> {
+        self.generate_proof_with_oracle(&mut Transcript::default())
+    }
+
+    /// Generates a STARK proof, drawing every challenge from the given oracle.
+    ///
+    /// The verifier must replay the proof against an oracle in the same initial state.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::TraceTooShort` if the trace holds fewer rows than its height,
+    /// any error of `ExecutionTrace::pad` if the trace cannot be padded, and
+    /// `ToyniError::InvalidOptions` if the proof options are inconsistent with the
+    /// trace length, the blowup factor is too small for the degree of the constraints,
+    /// private boundary values would be left unblinded, a periodic column does not repeat
+    /// within the trace, or the constraints declare an
+    /// auxiliary segment the prover has no builder for. Returns any error of
+    /// `AuxiliaryTrace::build` and `ToyniError::InvalidAuxiliaryColumn` if the auxiliary
+    /// columns do not match their declaration. Returns `ToyniError::Cancelled` if
+    /// the observer cancels proof generation, and `ToyniError::PointOnDomain` if the
+    /// out-of-domain point drawn from the oracle lies on the trace domain or the extended
+    /// coset, which a hashing transcript does with negligible probability.
+    pub fn generate_proof_with_oracle(
+        &self,
+        oracle: &mut impl RandomOracle,
+    ) -> Result<StarkProof<F>, ToyniError> {
+        block_on(self.prove(oracle)).map(|(proof, _)| proof)
+    }
+
+    /// Generates a STARK proof and reports the strategy, peak memory and duration of
+    /// every proving phase.
+    ///
+    /// # Errors
+    ///
+    /// See [`StarkProver::generate_proof_with_oracle`].
+    pub fn generate_proof_with_metrics(&self) -> Result<(StarkProof<F>, ProofMetrics), ToyniError> {
+        block_on(self.prove(&mut Transcript::default()))
+    }
+
+    /// Generates a STARK proof without blocking an async executor between phases.
+    ///
+    /// Each phase still runs to completion once started, but the future yields to the
+    /// executor after every phase. Dropping the future abandons the proof at the next
+    /// phase boundary; an observer such as a `CancellationToken` can also cancel it from
+    /// another task.
+    ///
+    /// # Errors
+    ///
+    /// See [`StarkProver::generate_proof_with_oracle`].
+    pub async fn generate_proof_async(&self) -> Result<StarkProof<F>, ToyniError> {
+        let (proof, _) = self.prove(&mut Transcript::default()).await?;
+        Ok(proof)
+    }
+
+    /// Runs every phase of proof generation, yielding after each one.
+    async fn prove(
+        &self,
+        oracle: &mut impl RandomOracle,
+    ) -> Result<(StarkProof<F>, ProofMetrics), ToyniError> {
+        let trace_len = self.trace.length().next_power_of_two();
+        self.prove_phases(oracle, trace_len)
+            .instrument(debug_span!("prove", trace_len))
+            .await
+    }
+
+    async fn prove_phases(
+        &self,
+        oracle: &mut impl RandomOracle,
+        trace_len: usize,
+    ) -> Result<(StarkProof<F>, ProofMetrics), ToyniError> {
+        let window = self.constraints.window();
+        self.options
+            .validate(trace_len, window)
+            .map_err(ToyniError::InvalidOptions)?;
+        self.constraints
+            .validate_blowup(self.options.blowup_factor)?;
+        self.constraints.validate_composition_degree(
+            self.options.trace_domain_size(trace_len, window),
+            trace_len,
+            self.options.blowup_factor,
+        )?;
+        self.constraints
+            .validate_declared_degrees(&self.trace.variables())?;
+        self.constraints
+            .validate_private_columns(self.options.num_blinding_rows(window))
+            .map_err(ToyniError::InvalidOptions)?;
+        self.constraints
+            .validate_periodic_columns(trace_len)
+            .map_err(ToyniError::InvalidOptions)?;
+        self.constraints
+            .validate_windows(trace_len)
+            .map_err(ToyniError::InvalidOptions)?;
+        self.constraints
+            .validate_boundaries(trace_len)
+            .map_err(ToyniError::InvalidOptions)?;
+        self.constraints
+            .validate_challenges()
+            .map_err(ToyniError::InvalidOptions)?;
+        if !self.constraints.auxiliary_columns.is_empty() && self.auxiliary.is_none() {
+            return Err(ToyniError::InvalidOptions(
+                "the constraints declare auxiliary columns but no auxiliary trace was given"
+                    .to_string(),
+            ));
         }
+        let context = ProverContext::new(&self.options, trace_len, window)?;
+        let domain = *context.trace_domain();
+        let extended_domain = *context.extended_domain();
+        let extended_len = extended_domain.size();
+        let field_bytes = |len: usize| len * size_of::<F>();
+        let strategy = self.strategy();
+        let mut metrics = MetricsRecorder::default();
+        metrics.hold(context.heap_size());
+
+        // Bind the statement, then commit to the low-degree extended trace before drawing
+        // any challenge
+        self.start_phase(ProofPhase::TraceCommitment, &mut metrics)?;
+        oracle.absorb(&self.constraints.digest(&self.trace.variables()));
+        absorb_public_inputs(oracle, &self.public_inputs);
+        let cap_height = self.options.merkle_cap_height;
+        let columns = fill_columns(self.trace, &self.padding)?;
+        if trace_len > self.trace.length() {
+            debug!(
+                rows = self.trace.length(),
+                padding = trace_len - self.trace.length(),
+                "padded trace"
+            );
+        }
+        let variables = self.trace.variables();
+
+        // The public values are part of the statement, bound like the public inputs
+        let public_values = self.constraints.public_values(&variables, &columns)?;
+        let values: Vec<F> = public_values.iter().map(|(_, value)| *value).collect();
+        absorb_public_inputs(oracle, &values);
+        let (trace_lde, salts, trace_tree) = self.commit_trace(&context, &columns, strategy)?;
+        metrics.hold(trace_lde.heap_size() + rows_size(&salts) + trace_tree.heap_size());
+        metrics.touch(trace_lde.segment_size());
+        let trace_commitment = trace_tree.cap(cap_height);
+        oracle.absorb(&trace_commitment.concat());
+        debug!(
+            columns = trace_lde.width(),
+            extended_len,
+            streaming = trace_lde.is_streaming(),
+            "committed the trace extension"
+        );
 
-        // Generate random polynomial for zero-knowledge
-        let mut rng = thread_rng();
-        let random_poly = ToyniPolynomial::random(extended_domain.size() - 1, &mut rng);
-        
-        // Multiply combined constraint by random polynomial
-        let masked_constraint = combined_constraint.mul(&random_poly);
-
-        // Evaluate masked constraint over extended domain
-        let c_evals: Vec<Fr> = extended_domain
-            .elements()
-            .map(|x| masked_constraint.evaluate(x))
+        // Draw the challenges only now, so the main columns cannot depend on them
+        let auxiliary = self.commit_auxiliary(&context, &variables, &columns, &salts, oracle)?;
+        drop(columns);
+        let auxiliary_commitment = match &auxiliary {
+            Some((segment, tree)) => {
+                metrics.hold(segment.lde.heap_size() + tree.heap_size());
+                let cap = tree.cap(cap_height);
+                oracle.absorb(&cap.concat());
+                debug!(
+                    columns = segment.lde.width(),
+                    "committed the auxiliary segment"
+                );
+                cap
+            }
+            None => Vec::new(),
+        };
+        let auxiliary_segment = auxiliary.as_ref().map(|(segment, _)| segment);
+        self.end_phase(ProofPhase::TraceCommitment, &mut metrics)
+            .await;
+
+        // Draw random alphas so violations cannot cancel, and degree-adjustment betas
+        self.start_phase(ProofPhase::QuotientCommitment, &mut metrics)?;
+        let num_constraints = self.constraints.num_constraints();
+        let alphas: Vec<F> = (0..num_constraints)
+            .map(|_| oracle.draw_field_element())
+            .collect();
+        let betas: Vec<F> = (0..num_constraints)
+            .map(|_| oracle.draw_field_element())
             .collect();
 
-        // Interpolate constraint polynomial from evaluations
-        let c_poly = DensePolynomial::from_coefficients_slice(&extended_domain.ifft(&c_evals));
-        let c_poly = ToyniPolynomial::from_dense_poly(c_poly);
+        // Build the composition quotient pointwise on the extended trace rows
+        let composition = CompositionPolynomial::from_trace_lde(
+            &variables,
+            &trace_lde,
+            auxiliary_segment,
+            &public_values,
+            &self.constraints,
+            &context,
+            &alphas,
+            &betas,
+        );
+        metrics.touch(trace_lde.segment_size() + field_bytes(extended_len));
+        metrics.hold(field_bytes(composition.coefficients().len()));
 
-        // Create vanishing polynomial
-        let z_poly = ToyniPolynomial::from_dense_poly(domain.vanishing_polynomial().into());
+        // Split the quotient into chunks of 1/k of its degree and commit to their rows
+        let chunks = composition.split(self.options.composition_chunks);
+        let chunk_evals: Vec<Vec<F>> = iter!(chunks)
+            .map(|chunk| extended_domain.fft(&chunk.coeffs))
+            .collect();
+        metrics.hold(field_bytes(
+            chunks.iter().map(|chunk| chunk.coeffs.len()).sum(),
+        ));
+        metrics.touch(rows_size(&chunk_evals));
+        let rows: Vec<Vec<F>> = into_iter!(0..extended_len)
+            .map(|i| chunk_evals.iter().map(|column| column[i]).collect())
+            .collect();
+        drop(chunk_evals);
+        let quotient_tree = MerkleTree::from_rows(&rows);
+        metrics.hold(rows_size(&rows) + quotient_tree.heap_size());
+        let quotient_commitment = quotient_tree.cap(cap_height);
+        oracle.absorb(&quotient_commitment.concat());
+        debug!(
+            degree = composition.degree(),
+            chunks = chunks.len(),
+            "committed the quotient chunks"
+        );
+        self.end_phase(ProofPhase::QuotientCommitment, &mut metrics)
+            .await;
 
-        // Divide to get quotient polynomial
-        let (quotient_poly, _) = c_poly.divide(&z_poly).unwrap();
+        // Reveal every committed column at the out-of-domain point z
+        self.start_phase(ProofPhase::DeepComposition, &mut metrics)?;
+        let z: F = oracle.draw_field_element();
+        if !is_out_of_domain(z, &domain, &extended_domain, chunks.len()) {
+            return Err(ToyniError::PointOnDomain);
+        }
+        let trace_generator = domain.group_gen();
+        let evaluate_at = |x: F| {
+            let mut values = trace_lde.evaluate_at(x);
+            if let Some(segment) = auxiliary_segment {
+                values.extend(segment.evaluate_at(x));
+            }
+            values
+        };
+        let ood_frame = OodFrame {
+            current: evaluate_at(z),
+            next: evaluate_at(trace_generator * z),
+            window: (2..self.constraints.window())
+                .map(|j| evaluate_at(trace_generator.pow([j as u64]) * z))
+                .collect(),
+            quotient: chunks
+                .iter()
+                .map(|chunk| chunk.evaluate(&z.pow([chunks.len() as u64])))
+                .collect(),
+        };
+        oracle.absorb_field_elements(&ood_frame.values());
 
-        // Evaluate quotient polynomial over extended domain
-        let mut q_evals: Vec<Fr> = extended_domain
-            .elements()
-            .map(|x| quotient_poly.evaluate(x))
+        // Bind the revealed values to the commitments through the DEEP composition
+        let width = ood_frame.current.len();
+        let num_coefficients =
+            DeepComposition::<F>::num_coefficients(width, self.constraints.window(), chunks.len());
+        let coefficients = (0..num_coefficients)
+            .map(|_| oracle.draw_field_element())
             .collect();
+        let deep = DeepComposition::new(z, trace_generator, ood_frame.clone(), coefficients);
+        let auxiliary_row =
+            |i: usize| auxiliary_segment.map_or_else(Vec::new, |segment| segment.lde.row(i));
+        let deep_evals = trace_lde.map_rows(|i, row, _| {
+            deep.evaluate(context.extended_point(i), row, &auxiliary_row(i), &rows[i])
+        });
+        let deep_tree = MerkleTree::from_field_elements(&deep_evals);
+        metrics.touch(trace_lde.segment_size());
+        let deep_size = field_bytes(deep_evals.len()) + deep_tree.heap_size();
+        metrics.hold(deep_size);
+        let deep_commitment = deep_tree.cap(cap_height);
+        oracle.absorb(&deep_commitment.concat());
+        self.end_phase(ProofPhase::DeepComposition, &mut metrics)
+            .await;
 
-        // Perform FRI folding with Merkle commitments
-        let mut fri_layers = vec![q_evals.clone()];
-        let mut fri_challenges = Vec::new();
-        let mut folding_commitment_trees: Vec<MerkleTree> = Vec::new();
-
-        while q_evals.len() > 4 {
-            let beta = Fr::rand(&mut thread_rng());
-            fri_challenges.push(beta);
-            q_evals = fri_fold(&q_evals, beta);
-            
-            // Create Merkle tree for this FRI layer
-            let folding_step_merkle_tree = MerkleTree::new(
-                q_evals
-                    .clone()
-                    .iter()
-                    .map(|x| x.into_bigint().to_bytes_be())
-                    .collect(),
-            );
-            folding_commitment_trees.push(folding_step_merkle_tree);
-            fri_layers.push(q_evals.clone());
+        // Prove the DEEP composition is low degree and open both trees where FRI queried it.
+        // The folded layers and their trees together are smaller than layer 0.
+        self.start_phase(ProofPhase::Fri, &mut metrics)?;
+        metrics.touch(deep_size);
+        let fri_proof = fri_prove_observed(
+            deep_evals,
+            &extended_domain,
+            deep_tree,
+            &self.options,
+            oracle,
+            self.observer,
+        )?;
+        metrics.release(deep_size);
+        self.end_phase(ProofPhase::Fri, &mut metrics).await;
+        self.start_phase(ProofPhase::TraceOpenings, &mut metrics)?;
+        let trace_openings = fri_proof
+            .queries
+            .iter()
+            .map(|query| {
+                let layer = &query.layers[0];
+                coset_positions(layer.index, extended_len, layer.values.len())
+                    .into_iter()
+                    .map(|index| TraceOpening {
+                        index,
+                        row: trace_lde.row(index),
+                        salt: salts[index].clone(),
+                        proof: trace_tree.get_proof_with_cap(index, cap_height).unwrap(),
+                        quotient: rows[index].clone(),
+                        quotient_proof: quotient_tree
+                            .get_proof_with_cap(index, cap_height)
+                            .unwrap(),
+                        auxiliary_row: auxiliary_row(index),
+                        auxiliary_proof: auxiliary
+                            .as_ref()
+                            .map(|(_, tree)| tree.get_proof_with_cap(index, cap_height).unwrap()),
+                    })
+                    .collect()
+            })
+            .collect();
+        self.end_phase(ProofPhase::TraceOpenings, &mut metrics)
+            .await;
+
+        let proof = StarkProof {
+            trace_commitment,
+            trace_variables: variables,
+            trace_randomizers: self.trace.randomizers(),
+            public_values,
+            auxiliary_commitment,
+            quotient_commitment,
+            ood_frame,
+            deep_commitment,
+            fri_proof,
+            trace_openings,
+            options: self.options.proof_options(),
+        };
+        Ok((proof, metrics.finish(strategy)))
+    }
+
+    /// Notifies the observer that `phase` starts, unless it cancelled proof generation.
+    fn start_phase(
+        &self,
+        phase: ProofPhase,
+        metrics: &mut MetricsRecorder,
+    ) -> Result<(), ToyniError> {
+        if self.observer.is_cancelled() {
+            debug!(?phase, "proof generation cancelled");
+            return Err(ToyniError::Cancelled);
         }
+        metrics.start_phase();
+        self.observer.on_phase_start(phase);
+        Ok(())
+    }
 
-        // Generate random challenges for verification
-        let proof_hash = digest_sha2(&[0; 32]);
-        let proof_hash_u32: Vec<u32> = proof_hash
-            .chunks(4)
-            .map(|chunk| {
-                let mut buf = [0u8; 4];
-                buf.copy_from_slice(chunk);
-                u32::from_be_bytes(buf)
+    /// Records the metrics of `phase`, notifies the observer that it completed and
+    /// yields to the executor.
+    async fn end_phase(&self, phase: ProofPhase, metrics: &mut MetricsRecorder) {
+        metrics.end_phase(phase);
+        self.observer.on_phase_end(phase);
+        YieldNow::default().await;
+    }
+
+    /// Low-degree extends every trace column and commits to the extended rows.
+    ///
+    /// The columns, filled from the trace table in sorted variable order and padded to a
+    /// power of two, are padded with random blinding rows up to the trace domain size,
+    /// followed by the randomizer columns filled with fresh randomness and, in
+    /// zero-knowledge mode, the quotient mask; each leaf is the hash of one row of the
+    /// extension, i.e. all column values at one extended domain point, salted in
+    /// zero-knowledge mode. With the streaming strategy the rows are hashed one segment
+    /// at a time.
+    ///
+    /// Without blinding rows the trace columns extend to the same rows as the trace
+    /// table's own extension, see `TraceTable::lde`. Unless streaming, the prover then
+    /// reuses that cached extension, shared with `ExecutionTrace::commit`, and only
+    /// extends the randomizer and mask columns.
+    ///
+    /// Every column lives in the base prime field of `F`, so the mask takes one column
+    /// per coefficient of `F` over it, see `ProofOptions::num_mask_columns`.
+    ///
+    /// # Returns
+    ///
+    /// The extension of the columns, the salt of every row and the Merkle tree over the
+    /// hashed rows
+    fn commit_trace(
+        &self,
+        context: &ProverContext<F>,
+        columns: &[Vec<F::BasePrimeField>],
+        strategy: ProvingStrategy,
+    ) -> Result<CommittedTrace<F>, ToyniError> {
+        let domain = *context.base_trace_domain();
+        let extended_domain = *context.base_extended_domain();
+        let domain_size = domain.size();
+        let trace_len = context.trace_len();
+        let streaming = strategy == ProvingStrategy::Streaming;
+        let cached = if streaming || domain_size != trace_len {
+            None
+        } else {
+            self.trace
+                .lde(self.options.blowup_factor, self.options.coset_offset)
+                .transpose()?
+        };
+        let mut polynomials: Vec<DensePolynomial<F::BasePrimeField>> = match cached {
+            Some(_) => Vec::new(),
+            None => iter!(columns)
+                .map(|column| {
+                    let mut rng = rand::thread_rng();
+                    let mut values = column.clone();
+                    values.resize_with(domain_size, || F::BasePrimeField::rand(&mut rng));
+                    DensePolynomial::from_coefficients_vec(domain.ifft(&values))
+                })
+                .collect(),
+        };
+        let randomizers: Vec<DensePolynomial<F::BasePrimeField>> =
+            into_iter!(self.trace.randomizers())
+                .map(|_| {
+                    let mut rng = rand::thread_rng();
+                    let values: Vec<F::BasePrimeField> = (0..domain_size)
+                        .map(|_| F::BasePrimeField::rand(&mut rng))
+                        .collect();
+                    DensePolynomial::from_coefficients_vec(domain.ifft(&values))
+                })
+                .collect();
+        polynomials.extend(randomizers);
+
+        let mut rng = rand::thread_rng();
+
+        // The mask is a random polynomial up to the composition degree, committed before
+        // the constraint coefficients are drawn so it cannot cancel a violation
+        let mask_degree = self.constraints.composition_degree(domain_size, trace_len);
+        for _ in 0..self.options.num_mask_columns() {
+            let mask = ToyniPolynomial::<F::BasePrimeField>::random(mask_degree, &mut rng);
+            polynomials.push(DensePolynomial::from_coefficients_slice(
+                mask.coefficients(),
+            ));
+        }
+
+        let lde = match cached {
+            Some(cached) => cached.with_columns(polynomials),
+            None => TraceLde::new(polynomials, domain_size, extended_domain, streaming),
+        };
+
+        let salt_len = if self.options.zk { SALT_LEN } else { 0 };
+        let salts: Vec<Vec<u8>> = (0..extended_domain.size())
+            .map(|_| (0..salt_len).map(|_| rng.r#gen()).collect())
+            .collect();
+        let leaves = lde.map_rows(|i, row, _| salted_row_leaf::<Sha256Hasher>(row, &salts[i]));
+        let tree = MerkleTree::new(leaves);
+
+        Ok((lde, salts, tree))
+    }
+
+    /// Draws the challenges, builds the auxiliary columns from the main columns and
+    /// commits to their extension.
+    ///
+    /// The columns are padded with random blinding rows like the main trace, and each
+    /// row is salted with the salt of the main trace row at the same position. The
+    /// extension is held in memory whatever the proving strategy.
+    ///
+    /// # Returns
+    ///
+    /// The auxiliary segment and the Merkle tree over its hashed rows, or `None` if the
+    /// constraints declare no auxiliary columns
+    fn commit_auxiliary(
+        &self,
+        context: &ProverContext<F>,
+        variables: &[ProgramVariable],
+        columns: &[Vec<F::BasePrimeField>],
+        salts: &[Vec<u8>],
+        oracle: &mut impl RandomOracle,
+    ) -> Result<Option<(AuxiliarySegment<F>, MerkleTree)>, ToyniError> {
+        let Some(auxiliary) = self.auxiliary else {
+            return Ok(None);
+        };
+        if self.constraints.auxiliary_columns.is_empty() {
+            return Ok(None);
+        }
+        let challenges: HashMap<ProgramVariable, F> = self
+            .constraints
+            .challenges
+            .iter()
+            .map(|challenge| (challenge.clone(), oracle.draw_field_element()))
+            .collect();
+        let main = MainColumns::new(variables, columns);
+        let auxiliary_columns = build_auxiliary_columns(
+            auxiliary,
+            &main,
+            &challenges,
+            &self.constraints.auxiliary_columns,
+        )?;
+
+        let domain = *context.trace_domain();
+        let domain_size = domain.size();
+        let polynomials: Vec<DensePolynomial<F>> = into_iter!(auxiliary_columns)
+            .map(|mut values| {
+                let mut rng = rand::thread_rng();
+                values.resize_with(domain_size, || F::rand(&mut rng));
+                DensePolynomial::from_coefficients_vec(domain.ifft(&values))
             })
             .collect();
-        let proof_hash_int = BigUint::from_slice(&proof_hash_u32);
-        let mut verifier_random_challenges = Vec::new();
-
-        for _ in 0..VERIFIER_QUERIES {
-            let verifier_index = proof_hash_int.clone() + BigUint::from(1u32);
-            let verifier_index_field = verifier_index % BigUint::from(extended_domain.size());
-            let random_interactive_challenge =
-                extended_domain.element(verifier_index_field.to_usize().unwrap());
-            verifier_random_challenges.push(random_interactive_challenge);
+        let lde = TraceLde::new(polynomials, domain_size, *context.extended_domain(), false);
+        let leaves = lde.map_rows(|i, row, _| salted_row_leaf::<Sha256Hasher>(row, &salts[i]));
+        let segment = AuxiliarySegment {
+            variables: self.constraints.auxiliary_columns.clone(),
+            challenges,
+            lde,
+        };
+        Ok(Some((segment, MerkleTree::new(leaves))))
+    }
+}
+
+/// Future that is pending once, waking its task straight away.
+#[derive(Default)]
+struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
         }
+        self.yielded = true;
+        context.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
 
-        StarkProof {
-            quotient_eval_domain: fri_layers[0].clone(),
-            fri_layers,
-            fri_challenges,
-            combined_constraint,
-            quotient_poly,
-            folding_commitment_trees,
-            verifier_random_challenges,
+/// Drives a proving future to completion on the current thread.
+///
+/// The prover only ever waits on `YieldNow`, which is ready when polled again, so
+/// polling in a loop never spins.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
         }
     }
-}
\ No newline at end of file
+}
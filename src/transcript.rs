@@ -0,0 +1,205 @@
+//! Fiat-Shamir transcript and random oracle abstraction.
+//!
+//! Every challenge used by the prover and verifier (FRI folding factors, spot-check
+//! positions, ...) is drawn through the `RandomOracle` trait. The default `Transcript`
+//! derives challenges from a SHA-256 hash chain over everything absorbed so far, while
+//! `MockOracle` returns scripted values so tests can exercise specific challenge paths.
+
+use ark_bls12_381::Fr;
+use ark_ff::{AdditiveGroup, BigInteger, Field, PrimeField};
+use std::collections::VecDeque;
+
+use crate::{digest_sha2, math::serde_fr::to_bytes};
+
+/// Source of verifier challenges.
+///
+/// Implementations must be deterministic: a prover and a verifier absorbing the same
+/// data in the same order must draw the same challenges.
+pub trait RandomOracle {
+    /// Absorbs prover messages (commitments, evaluations) into the oracle state.
+    fn absorb(&mut self, data: &[u8]);
+
+    /// Draws a field element challenge.
+    fn draw_field_element<F: Field>(&mut self) -> F;
+
+    /// Draws an index in `0..bound`.
+    fn draw_index(&mut self, bound: usize) -> usize;
+
+    /// Absorbs a slice of field elements using their big-endian byte encoding.
+    fn absorb_field_elements<F: Field>(&mut self, elements: &[F]) {
+        for element in elements {
+            self.absorb(&to_bytes(element));
+        }
+    }
+}
+
+/// Absorbs the public inputs of a statement, preceded by their number.
+///
+/// Nothing is absorbed without public inputs, so such proofs replay the same transcript
+/// as before public inputs existed.
+pub(crate) fn absorb_public_inputs<F: Field>(oracle: &mut impl RandomOracle, public_inputs: &[F]) {
+    if !public_inputs.is_empty() {
+        oracle.absorb(&(public_inputs.len() as u64).to_le_bytes());
+        oracle.absorb_field_elements(public_inputs);
+    }
+}
+
+/// Maps uniformly random bytes to a field element.
+///
+/// Every base prime field coefficient of the element is reduced from 32 bytes drawn from
+/// `bytes`, so elements of a degree `d` extension consume `d` draws.
+pub(crate) fn field_element_from_bytes<F: Field>(mut bytes: impl FnMut() -> [u8; 32]) -> F {
+    let coefficients =
+        (0..F::extension_degree()).map(|_| F::BasePrimeField::from_le_bytes_mod_order(&bytes()));
+    F::from_base_prime_field_elems(coefficients).expect("one coefficient per extension degree")
+}
+
+/// SHA-256 based Fiat-Shamir transcript.
+///
+/// The state is a hash chain: absorbing replaces the state with `H(state || data)`,
+/// and each squeeze hashes the state together with a counter that is reset on absorb.
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    /// Current hash chain state
+    state: [u8; 32],
+    /// Number of squeezes since the last absorb
+    counter: u64,
+}
+
+impl Transcript {
+    /// Creates a new transcript bound to a domain separator.
+    pub fn new(domain_separator: &[u8]) -> Self {
+        Self {
+            state: digest_sha2(domain_separator),
+            counter: 0,
+        }
+    }
+
+    /// Squeezes 32 pseudo-random bytes from the current state.
+    fn squeeze(&mut self) -> [u8; 32] {
+        let mut data = self.state.to_vec();
+        data.extend_from_slice(&self.counter.to_le_bytes());
+        self.counter += 1;
+        digest_sha2(&data)
+    }
+}
+
+impl Default for Transcript {
+    fn default() -> Self {
+        Self::new(b"toyni-stark")
+    }
+}
+
+impl RandomOracle for Transcript {
+    fn absorb(&mut self, data: &[u8]) {
+        let mut input = self.state.to_vec();
+        input.extend_from_slice(data);
+        self.state = digest_sha2(&input);
+        self.counter = 0;
+    }
+
+    fn draw_field_element<F: Field>(&mut self) -> F {
+        field_element_from_bytes(|| self.squeeze())
+    }
+
+    fn draw_index(&mut self, bound: usize) -> usize {
+        assert!(bound > 0, "Index bound must be positive");
+        let bytes = self.squeeze();
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&bytes[..8]);
+        (u64::from_le_bytes(buf) % bound as u64) as usize
+    }
+}
+
+/// Random oracle returning scripted challenges, for tests.
+///
+/// Field elements and indices are returned in the order they were scripted and the
+/// script wraps around once exhausted. Absorbed data is recorded but otherwise ignored.
+/// Scripted elements drawn as a different field are reduced coefficient by coefficient
+/// from their canonical bytes, with missing coefficients set to zero.
+#[derive(Debug, Clone, Default)]
+pub struct MockOracle<F: Field = Fr> {
+    /// Scripted field element challenges
+    field_elements: VecDeque<F>,
+    /// Scripted index challenges
+    indices: VecDeque<usize>,
+    /// Everything absorbed so far
+    pub absorbed: Vec<Vec<u8>>,
+}
+
+impl<F: Field> MockOracle<F> {
+    /// Creates a mock oracle with the given field element and index scripts.
+    pub fn new(field_elements: Vec<F>, indices: Vec<usize>) -> Self {
+        Self {
+            field_elements: field_elements.into(),
+            indices: indices.into(),
+            absorbed: Vec::new(),
+        }
+    }
+}
+
+impl<F: Field> RandomOracle for MockOracle<F> {
+    fn absorb(&mut self, data: &[u8]) {
+        self.absorbed.push(data.to_vec());
+    }
+
+    fn draw_field_element<G: Field>(&mut self) -> G {
+        let element = self
+            .field_elements
+            .pop_front()
+            .expect("MockOracle has no scripted field elements");
+        self.field_elements.push_back(element);
+        let coefficients = element
+            .to_base_prime_field_elements()
+            .map(|coefficient| {
+                G::BasePrimeField::from_le_bytes_mod_order(&coefficient.into_bigint().to_bytes_le())
+            })
+            .chain(std::iter::repeat(G::BasePrimeField::ZERO))
+            .take(G::extension_degree() as usize);
+        G::from_base_prime_field_elems(coefficients).expect("one coefficient per extension degree")
+    }
+
+    fn draw_index(&mut self, bound: usize) -> usize {
+        let index = self
+            .indices
+            .pop_front()
+            .expect("MockOracle has no scripted indices");
+        self.indices.push_back(index);
+        index % bound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript_is_deterministic() {
+        let mut a = Transcript::default();
+        let mut b = Transcript::default();
+        a.absorb(b"commitment");
+        b.absorb(b"commitment");
+        assert_eq!(a.draw_field_element::<Fr>(), b.draw_field_element::<Fr>());
+        assert_eq!(a.draw_index(1024), b.draw_index(1024));
+    }
+
+    #[test]
+    fn test_transcript_depends_on_absorbed_data() {
+        let mut a = Transcript::default();
+        let mut b = Transcript::default();
+        a.absorb(b"commitment a");
+        b.absorb(b"commitment b");
+        assert_ne!(a.draw_field_element::<Fr>(), b.draw_field_element::<Fr>());
+    }
+
+    #[test]
+    fn test_mock_oracle_replays_script() {
+        let mut oracle = MockOracle::new(vec![Fr::from(3u64), Fr::from(5u64)], vec![7]);
+        oracle.absorb(b"ignored");
+        assert_eq!(oracle.draw_field_element::<Fr>(), Fr::from(3u64));
+        assert_eq!(oracle.draw_field_element::<Fr>(), Fr::from(5u64));
+        assert_eq!(oracle.draw_field_element::<Fr>(), Fr::from(3u64));
+        assert_eq!(oracle.draw_index(4), 3);
+        assert_eq!(oracle.absorbed.len(), 1);
+    }
+}
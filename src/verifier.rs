@@ -1,43 +1,104 @@
 use ark_bls12_381::Fr;
-use ark_ff::{BigInteger, PrimeField};
+use ark_ff::FftField;
 use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use std::{collections::HashMap, fmt};
+use tracing::{debug, debug_span};
 
-use crate::{math::polynomial::Polynomial, merkle::verify_merkle_proof, prover::StarkProof, vm::constraints::ConstraintSystem};
+use crate::{
+    error::ToyniError,
+    hash::Sha256Hasher,
+    math::{
+        composition::combine_unconstrained,
+        deep::{DeepComposition, is_out_of_domain},
+        fri::{FriFailure, coset_positions, fri_verify_with_report},
+    },
+    merkle::{path_length, row_leaf, salted_row_leaf, verify_merkle_proof_with_cap},
+    options::ProofOptions,
+    prover::StarkProof,
+    transcript::{RandomOracle, Transcript, absorb_public_inputs},
+    vm::{
+        air::{Air, Constraints},
+        constraints::ConstraintSystem,
+        trace::ProgramVariable,
+    },
+};
 
 /// STARK verifier component that verifies proofs.
 ///
 /// The verifier:
-/// 1. Checks FRI folding consistency with Merkle proofs
-/// 2. Verifies constraint satisfaction at random points
-/// 3. Ensures all commitments are valid
-pub struct StarkVerifier<'a> {
+/// 1. Checks the constraints at an out-of-domain point against the revealed values
+/// 2. Checks FRI folding consistency of the DEEP composition with Merkle proofs
+/// 3. Recomputes the DEEP composition from the opened trace, auxiliary and quotient rows
+pub struct StarkVerifier<'a, F: FftField = Fr> {
     /// Constraint system defining program rules
-    #[allow(unused)]
-    constraints: &'a ConstraintSystem,
+    constraints: Constraints<'a, F>,
     /// Length of execution trace
     trace_len: usize,
+    /// Proof configuration, must match the prover's
+    options: ProofOptions<F>,
+    /// Public inputs the proof must have been generated for
+    public_inputs: Vec<F>,
 }
 
-impl<'a> StarkVerifier<'a> {
+impl<'a, F: FftField> StarkVerifier<'a, F> {
     /// Creates a new STARK verifier for the given constraints and trace length.
     ///
     /// # Arguments
     ///
     /// * `constraints` - The constraint system defining program rules
     /// * `trace_len` - The length of the execution trace
-    pub fn new(constraints: &'a ConstraintSystem, trace_len: usize) -> Self {
+    pub fn new(constraints: &'a ConstraintSystem<F>, trace_len: usize) -> Self {
+        Self::with_options(constraints, trace_len, ProofOptions::new())
+    }
+
+    /// Creates a new STARK verifier with explicit proof options.
+    pub fn with_options(
+        constraints: &'a ConstraintSystem<F>,
+        trace_len: usize,
+        options: ProofOptions<F>,
+    ) -> Self {
         Self {
-            constraints,
+            constraints: Constraints::Borrowed(constraints),
             trace_len,
+            options,
+            public_inputs: Vec::new(),
         }
     }
 
+    /// Creates a verifier for the statement `public_inputs` of `air` over traces of
+    /// `trace_len` rows, with the constraint system, options and transcript inputs the
+    /// AIR derives from it.
+    pub fn from_air<A: Air<F>>(air: &A, trace_len: usize, public_inputs: &A::PublicInputs) -> Self {
+        Self {
+            constraints: Constraints::Owned(Box::new(air.constraint_system(public_inputs))),
+            trace_len,
+            options: air.options(),
+            public_inputs: air.public_inputs(public_inputs),
+        }
+    }
+
+    /// Starts building a verifier one setting at a time.
+    pub fn builder() -> StarkVerifierBuilder<'a, F> {
+        StarkVerifierBuilder::default()
+    }
+
+    /// Sets the public inputs the prover bound its proofs to.
+    ///
+    /// A batch verified with `verify_batch` must share them.
+    pub fn with_public_inputs(mut self, public_inputs: Vec<F>) -> Self {
+        self.public_inputs = public_inputs;
+        self
+    }
+
     /// Verifies a STARK proof.
     ///
     /// The verification process:
-    /// 1. Checks FRI folding consistency with Merkle proofs
-    /// 2. Verifies constraint satisfaction at random points
-    /// 3. Ensures all commitments are valid
+    /// 1. Checks the constraints at an out-of-domain point against the revealed values
+    /// 2. Checks FRI folding consistency of the DEEP composition with Merkle proofs
+    /// 3. Recomputes the DEEP composition from the opened trace and quotient rows
+    ///
+    /// The verifier only touches commitments, openings and out-of-domain values, never a
+    /// full polynomial.
     ///
     /// # Arguments
     ///
@@ -45,74 +106,641 @@ impl<'a> StarkVerifier<'a> {
     ///
     /// # Returns
     ///
-    /// `true` if the proof is valid, `false` otherwise
-    pub fn verify(&self, proof: &StarkProof) -> bool {
-        let domain = GeneralEvaluationDomain::<Fr>::new(self.trace_len).unwrap();
-        let extended_domain = GeneralEvaluationDomain::<Fr>::new(self.trace_len * 2).unwrap();
-        let z_poly = Polynomial::from_dense_poly(domain.vanishing_polynomial().into());
+    /// `true` if the proof is valid, `false` otherwise. The reason for a rejection is
+    /// emitted as a `tracing` event at debug level.
+    pub fn verify(&self, proof: &StarkProof<F>) -> bool {
+        self.verify_with_oracle(proof, &mut Transcript::default())
+    }
+
+    /// Verifies a STARK proof, re-deriving the FRI challenges from the given oracle.
+    ///
+    /// The oracle must start in the same state as the one used by the prover.
+    /// Query positions are drawn from it once every commitment has been absorbed, and
+    /// each trace opening must sit at a position drawn for FRI.
+    pub fn verify_with_oracle(
+        &self,
+        proof: &StarkProof<F>,
+        oracle: &mut impl RandomOracle,
+    ) -> bool {
+        let _span = debug_span!("verify", trace_len = self.trace_len).entered();
+        match self.check(proof, oracle) {
+            Ok(()) => {
+                debug!("proof verified");
+                true
+            }
+            Err(failure) => {
+                debug!("{}", failure);
+                false
+            }
+        }
+    }
+
+    /// Verifies a STARK proof, describing the check that rejected it.
+    ///
+    /// Runs the same checks as `verify`, but instead of a bare `false` the report names
+    /// the first check that failed, down to the FRI query, layer and coset or the trace
+    /// opening concerned. Useful when debugging a custom constraint system.
+    pub fn verify_with_report(&self, proof: &StarkProof<F>) -> VerificationReport {
+        let _span = debug_span!("verify", trace_len = self.trace_len).entered();
+        VerificationReport {
+            failure: self.check(proof, &mut Transcript::default()).err(),
+        }
+    }
+
+    /// Sets up the domains and checks a single proof.
+    fn check(
+        &self,
+        proof: &StarkProof<F>,
+        oracle: &mut impl RandomOracle,
+    ) -> Result<(), VerificationFailure> {
+        let domains = self.domains()?;
+        self.verify_proof(&domains, proof, oracle, &mut EqualityChecks::Immediate)
+    }
+
+    /// Verifies several proofs of the same statement shape at once.
+    ///
+    /// The options are validated and the domains set up once for the whole batch. Every
+    /// proof replays its own transcript, but instead of checking the out-of-domain and
+    /// DEEP equalities one by one, the verifier combines them across all proofs with
+    /// fresh random weights and checks the combination once. A false equality survives
+    /// the combination with probability `1/|F|`.
+    ///
+    /// # Returns
+    ///
+    /// `true` if every proof is valid, including for an empty batch
+    pub fn verify_batch(&self, proofs: &[StarkProof<F>]) -> bool {
+        let _span = debug_span!(
+            "verify_batch",
+            trace_len = self.trace_len,
+            proofs = proofs.len()
+        )
+        .entered();
+        let domains = match self.domains() {
+            Ok(domains) => domains,
+            Err(failure) => {
+                debug!("{}", failure);
+                return false;
+            }
+        };
+        let mut checks = EqualityChecks::Batched(F::ZERO);
+        for (i, proof) in proofs.iter().enumerate() {
+            if let Err(failure) =
+                self.verify_proof(&domains, proof, &mut Transcript::default(), &mut checks)
+            {
+                debug!(proof = i, "proof rejected: {}", failure);
+                return false;
+            }
+        }
+        if !checks.holds() {
+            debug!("Combined out-of-domain and DEEP checks failed");
+            return false;
+        }
+        debug!("proofs verified");
+        true
+    }
+
+    /// Validates the options against the constraints and sets up the trace domain and its
+    /// extension.
+    fn domains(&self) -> Result<VerifierDomains<F>, VerificationFailure> {
+        let window = self.constraints.window();
+        self.options
+            .validate(self.trace_len, window)
+            .and_then(|()| {
+                let domain_size = self.options.trace_domain_size(self.trace_len, window);
+                let blowup_factor = self.options.blowup_factor;
+                self.constraints
+                    .validate_blowup(blowup_factor)
+                    .and_then(|()| {
+                        self.constraints.validate_composition_degree(
+                            domain_size,
+                            self.trace_len,
+                            blowup_factor,
+                        )
+                    })
+                    .map_err(|error| error.to_string())
+            })
+            .and_then(|()| {
+                self.constraints
+                    .validate_private_columns(self.options.num_blinding_rows(window))
+            })
+            .and_then(|()| self.constraints.validate_periodic_columns(self.trace_len))
+            .and_then(|()| self.constraints.validate_windows(self.trace_len))
+            .and_then(|()| self.constraints.validate_boundaries(self.trace_len))
+            .and_then(|()| self.constraints.validate_challenges())
+            .map_err(VerificationFailure::InvalidOptions)?;
+        let domain_size = self.options.trace_domain_size(self.trace_len, window);
+        let extended_domain = GeneralEvaluationDomain::<F>::new(
+            domain_size * self.options.blowup_factor,
+        )
+        .and_then(|domain| domain.get_coset(F::from_base_prime_field(self.options.coset_offset)));
+        match (
+            GeneralEvaluationDomain::<F>::new(domain_size),
+            extended_domain,
+        ) {
+            (Some(domain), Some(extended_domain)) => Ok(VerifierDomains {
+                domain,
+                extended_domain,
+            }),
+            _ => Err(VerificationFailure::InvalidOptions(
+                "the field has no domain of the extended size".to_string(),
+            )),
+        }
+    }
+
+    /// Checks a single proof against domains set up by `domains`.
+    ///
+    /// Structural and Merkle checks reject immediately; the out-of-domain and DEEP
+    /// equalities go through `checks`.
+    fn verify_proof(
+        &self,
+        domains: &VerifierDomains<F>,
+        proof: &StarkProof<F>,
+        oracle: &mut impl RandomOracle,
+        checks: &mut EqualityChecks<F>,
+    ) -> Result<(), VerificationFailure> {
+        if proof.options != self.options.proof_options() {
+            return Err(VerificationFailure::OptionsMismatch);
+        }
+        let VerifierDomains {
+            domain,
+            extended_domain,
+        } = domains;
+        let domain_size = domain.size();
+
+        let cap_height = self.options.merkle_cap_height;
 
-        // FRI folding consistency check with Merkle proof verification
-        let mut current_layer = &proof.quotient_eval_domain;
-        for (i, ((beta, next_layer), merkle_tree)) in proof
-            .fri_challenges
+        // The claimed trace columns lay out every row the constraints are evaluated on
+        self.constraints
+            .validate_trace_columns(&proof.trace_variables)
+            .map_err(VerificationFailure::TraceVariables)?;
+
+        // Bind the statement the verifier expects: challenges drawn after a proof for other
+        // constraints or public inputs no longer match the prover's
+        oracle.absorb(&self.constraints.digest(&proof.trace_variables));
+        absorb_public_inputs(oracle, &self.public_inputs);
+
+        // The proof must publish a value for every public cell, in order
+        let public_names = proof.public_values.iter().map(|(name, _)| name);
+        if !public_names.eq(self.constraints.public_cells.iter().map(|cell| &cell.name)) {
+            return Err(VerificationFailure::PublicValues);
+        }
+        let public_values: Vec<F> = proof
+            .public_values
             .iter()
-            .zip(proof.fri_layers.iter().skip(1))
-            .zip(proof.folding_commitment_trees.iter())
+            .map(|(_, value)| *value)
+            .collect();
+        absorb_public_inputs(oracle, &public_values);
+
+        // The trace commitment must have the size implied by the cap height
+        oracle.absorb(&proof.trace_commitment.concat());
+        let cap_size = extended_domain.size().min(1 << cap_height);
+        if proof.trace_commitment.len() != cap_size {
+            return Err(VerificationFailure::TraceCommitmentSize);
+        }
+
+        // Draw the challenges of the auxiliary segment and absorb its commitment, which
+        // must be empty if the constraints declare none
+        let auxiliary_width = self.constraints.auxiliary_columns.len();
+        let challenges: HashMap<ProgramVariable, F> = if auxiliary_width > 0 {
+            let challenges = self
+                .constraints
+                .challenges
+                .iter()
+                .map(|challenge| (challenge.clone(), oracle.draw_field_element()))
+                .collect();
+            oracle.absorb(&proof.auxiliary_commitment.concat());
+            if proof.auxiliary_commitment.len() != cap_size {
+                return Err(VerificationFailure::TraceCommitmentSize);
+            }
+            challenges
+        } else if proof.auxiliary_commitment.is_empty() {
+            HashMap::new()
+        } else {
+            return Err(VerificationFailure::TraceCommitmentSize);
+        };
+
+        // Re-derive the constraint combination coefficients
+        let num_constraints = self.constraints.num_constraints();
+        let alphas: Vec<F> = (0..num_constraints)
+            .map(|_| oracle.draw_field_element())
+            .collect();
+        let betas: Vec<F> = (0..num_constraints)
+            .map(|_| oracle.draw_field_element())
+            .collect();
+
+        oracle.absorb(&proof.quotient_commitment.concat());
+        let num_chunks = self.options.composition_chunks;
+        let num_variables = proof.trace_variables.len();
+        let main_width =
+            num_variables + proof.trace_randomizers.len() + self.options.num_mask_columns();
+        let width = main_width + auxiliary_width;
+        let frame = &proof.ood_frame;
+        let window = self.constraints.window();
+        if frame.rows().count() != window
+            || frame.rows().any(|row| row.len() != width)
+            || frame.quotient.len() != num_chunks
+        {
+            return Err(VerificationFailure::FrameShape);
+        }
+
+        // Check the constraints at the out-of-domain point z against the revealed values
+        let z: F = oracle.draw_field_element();
+        if !is_out_of_domain(z, domain, extended_domain, num_chunks) {
+            return Err(VerificationFailure::PointOnDomain);
+        }
+        oracle.absorb_field_elements(&frame.values());
+        // Periodic columns are not committed, the verifier evaluates them itself
+        let row_map = |row: &[F], x: F| -> HashMap<ProgramVariable, F> {
+            let mut values: HashMap<ProgramVariable, F> = proof
+                .trace_variables
+                .iter()
+                .cloned()
+                .zip(row.iter().copied())
+                .collect();
+            values.extend(
+                self.constraints
+                    .auxiliary_columns
+                    .iter()
+                    .cloned()
+                    .zip(row[main_width..].iter().copied()),
+            );
+            values.extend(challenges.clone());
+            values.extend(proof.public_values.iter().cloned());
+            values.extend(
+                self.constraints
+                    .periodic_columns
+                    .iter()
+                    .map(|column| (column.name.clone(), column.evaluate_at(x, domain_size))),
+            );
+            values
+        };
+
+        // Recombine the chunks as Σ zⁱ·H_i(zᵏ)
+        let q_eval = frame
+            .quotient
+            .iter()
+            .rev()
+            .fold(F::ZERO, |acc, chunk| acc * z + chunk);
+        let rows: Vec<HashMap<ProgramVariable, F>> = frame
+            .rows()
+            .enumerate()
+            .map(|(j, row)| row_map(row, domain.group_gen().pow([j as u64]) * z))
+            .collect();
+        let constraint_values = self.constraints.evaluate_rows(&rows);
+        let c_eval = self.constraints.evaluate_composition(
+            &constraint_values,
+            &alphas,
+            &betas,
+            domain,
+            self.trace_len,
+            z,
+        );
+
+        // Randomizer columns and the zero-knowledge mask are added to the composition
+        // unconstrained
+        let r_eval = combine_unconstrained(&frame.current[num_variables..main_width]);
+
+        if !checks.check(q_eval, c_eval + r_eval) {
+            return Err(VerificationFailure::OutOfDomain);
+        }
+
+        // The DEEP composition is low degree only if the revealed values are correct. Trace
+        // columns have fewer than n coefficients, the mask as many as the composition and
+        // every chunk 1/k of them, and every DEEP quotient one less.
+        let coefficients = (0..DeepComposition::<F>::num_coefficients(width, window, num_chunks))
+            .map(|_| oracle.draw_field_element())
+            .collect();
+        let deep = DeepComposition::new(z, domain.group_gen(), frame.clone(), coefficients);
+        let composition_len = self
+            .constraints
+            .composition_degree(domain_size, self.trace_len)
+            + 1;
+        let trace_len_bound = if self.options.zk {
+            composition_len.max(domain_size)
+        } else {
+            domain_size
+        };
+        let degree_bound = trace_len_bound.max(composition_len.div_ceil(num_chunks)) - 1;
+        oracle.absorb(&proof.deep_commitment.concat());
+        fri_verify_with_report(
+            &proof.deep_commitment,
+            extended_domain,
+            degree_bound,
+            &proof.fri_proof,
+            &self.options,
+            oracle,
+        )
+        .map_err(VerificationFailure::Fri)?;
+
+        // Recompute the DEEP composition from the trace and quotient rows FRI queried
+        let path_depth = path_length(extended_domain.size(), 2, cap_height);
+        if proof.trace_openings.len() != proof.fri_proof.queries.len() {
+            return Err(VerificationFailure::OpeningCount);
+        }
+        for (query_index, (openings, query)) in proof
+            .trace_openings
+            .iter()
+            .zip(&proof.fri_proof.queries)
             .enumerate()
         {
-            let n = current_layer.len();
-            let half_n = n / 2;
-
-            // Verify each point in the next layer was correctly folded and committed
-            for j in 0..half_n {
-                let x = current_layer[j];
-                let neg_x = current_layer[j + half_n];
-
-                // f_next(x) = (f(x) + f(-x) + β * (f(x) - f(-x))) / 2
-                let expected_next = (x + neg_x + *beta * (x - neg_x)) / Fr::from(2u64);
-                let actual_next = next_layer[j];
-
-                // Verify the actual value matches the expected folded value
-                if expected_next != actual_next {
-                    println!("❌ FRI folding failed at layer {}, position {}", i, j);
-                    println!("Expected: {:?}", expected_next);
-                    println!("Actual: {:?}", actual_next);
-                    return false;
+            let Some(layer) = query.layers.first() else {
+                return Err(VerificationFailure::OpeningCoverage { query: query_index });
+            };
+            let positions =
+                coset_positions(layer.index, extended_domain.size(), layer.values.len());
+            if openings.len() != positions.len() {
+                return Err(VerificationFailure::OpeningCoverage { query: query_index });
+            }
+            for ((opening, position), value) in openings.iter().zip(positions).zip(&layer.values) {
+                let auxiliary_valid = match &opening.auxiliary_proof {
+                    Some(auxiliary_proof) => {
+                        auxiliary_width > 0
+                            && verify_merkle_proof_with_cap(
+                                salted_row_leaf::<Sha256Hasher>(
+                                    &opening.auxiliary_row,
+                                    &opening.salt,
+                                ),
+                                position,
+                                auxiliary_proof,
+                                &proof.auxiliary_commitment,
+                                path_depth,
+                                2,
+                            )
+                    }
+                    None => auxiliary_width == 0,
+                };
+                if opening.index != position
+                    || opening.row.len() != main_width
+                    || opening.auxiliary_row.len() != auxiliary_width
+                    || !auxiliary_valid
+                    || opening.quotient.len() != num_chunks
+                    || !verify_merkle_proof_with_cap(
+                        salted_row_leaf::<Sha256Hasher>(&opening.row, &opening.salt),
+                        position,
+                        &opening.proof,
+                        &proof.trace_commitment,
+                        path_depth,
+                        2,
+                    )
+                    || !verify_merkle_proof_with_cap(
+                        row_leaf::<Sha256Hasher>(&opening.quotient),
+                        position,
+                        &opening.quotient_proof,
+                        &proof.quotient_commitment,
+                        path_depth,
+                        2,
+                    )
+                {
+                    return Err(VerificationFailure::TraceOpening {
+                        query: query_index,
+                        position,
+                    });
                 }
-
-                // Get the Merkle proof for this position
-                let proof = merkle_tree.get_proof(j).expect("Merkle proof should exist");
-                let root = merkle_tree.root().expect("Merkle root should exist");
-
-                // Verify the value is properly committed in the Merkle tree
-                let value_bytes = actual_next.into_bigint().to_bytes_be();
-                if !verify_merkle_proof(value_bytes, &proof, &root) {
-                    println!(
-                        "❌ Merkle proof verification failed at layer {}, position {}",
-                        i, j
-                    );
-                    return false;
+                let x = extended_domain.element(position);
+                let deep_value =
+                    deep.evaluate(x, &opening.row, &opening.auxiliary_row, &opening.quotient);
+                if !checks.check(deep_value, *value) {
+                    return Err(VerificationFailure::DeepComposition {
+                        query: query_index,
+                        position,
+                    });
                 }
             }
-            current_layer = next_layer;
         }
 
-        // Verify constraint satisfaction at random points
-        for _i in proof.verifier_random_challenges.iter() {
-            let random_interactive_challenge =
-                extended_domain.element(rand::random::<usize>() % extended_domain.size());
-            let q_eval = proof.quotient_poly.evaluate(random_interactive_challenge);
-            let z_eval = z_poly.evaluate(random_interactive_challenge);
-            let c_eval = proof
-                .combined_constraint
-                .evaluate(random_interactive_challenge);
+        Ok(())
+    }
+}
 
-            if q_eval * z_eval != c_eval {
-                println!("❌ Spot check failed: Q(x₀)*Z(x₀) ≠ C(x₀)");
-                return false;
+/// Builder for a `StarkVerifier`, started with `StarkVerifier::builder`.
+///
+/// The constraints and trace length are required; the options default to those of
+/// `StarkVerifier::new` and there are no public inputs unless set.
+pub struct StarkVerifierBuilder<'a, F: FftField = Fr> {
+    constraints: Option<&'a ConstraintSystem<F>>,
+    trace_len: Option<usize>,
+    options: ProofOptions<F>,
+    public_inputs: Vec<F>,
+}
+
+impl<F: FftField> Default for StarkVerifierBuilder<'_, F> {
+    fn default() -> Self {
+        Self {
+            constraints: None,
+            trace_len: None,
+            options: ProofOptions::new(),
+            public_inputs: Vec::new(),
+        }
+    }
+}
+
+impl<'a, F: FftField> StarkVerifierBuilder<'a, F> {
+    /// Sets the constraint system the proven trace satisfies.
+    pub fn constraints(mut self, constraints: &'a ConstraintSystem<F>) -> Self {
+        self.constraints = Some(constraints);
+        self
+    }
+
+    /// Sets the length of the proven trace.
+    pub fn trace_len(mut self, trace_len: usize) -> Self {
+        self.trace_len = Some(trace_len);
+        self
+    }
+
+    /// Sets the proof options, which must match the prover's.
+    pub fn options(mut self, options: ProofOptions<F>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the public inputs, see `StarkVerifier::with_public_inputs`.
+    pub fn public_inputs(mut self, public_inputs: Vec<F>) -> Self {
+        self.public_inputs = public_inputs;
+        self
+    }
+
+    /// Builds the verifier.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::IncompleteBuilder` if the constraints or the trace length
+    /// are missing.
+    pub fn build(self) -> Result<StarkVerifier<'a, F>, ToyniError> {
+        let constraints = self
+            .constraints
+            .ok_or(ToyniError::IncompleteBuilder("constraints"))?;
+        let trace_len = self
+            .trace_len
+            .ok_or(ToyniError::IncompleteBuilder("trace length"))?;
+        Ok(StarkVerifier {
+            constraints: Constraints::Borrowed(constraints),
+            trace_len,
+            options: self.options,
+            public_inputs: self.public_inputs,
+        })
+    }
+}
+
+/// Outcome of `StarkVerifier::verify_with_report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// The first check that failed, or `None` if the proof is valid
+    pub failure: Option<VerificationFailure>,
+}
+
+impl VerificationReport {
+    /// Whether every check passed.
+    pub fn is_valid(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.failure {
+            None => write!(f, "proof verified"),
+            Some(failure) => write!(f, "proof rejected: {}", failure),
+        }
+    }
+}
+
+/// Verifier check that rejected a proof.
+///
+/// Queries are numbered in the order FRI drew them and positions index the extended
+/// coset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationFailure {
+    /// The verifier's options are inconsistent with the trace length or the constraints
+    InvalidOptions(String),
+    /// The proof was generated with other options than the verifier's
+    OptionsMismatch,
+    /// The trace or auxiliary commitment does not have the size implied by the cap height,
+    /// or an auxiliary commitment comes with constraints that declare no auxiliary segment
+    TraceCommitmentSize,
+    /// The proof does not publish exactly one value per public cell of the constraints
+    PublicValues,
+    /// The trace columns named by the proof repeat a column or miss one the constraints
+    /// read
+    TraceVariables(String),
+    /// The out-of-domain frame does not hold a value per committed column and chunk
+    FrameShape,
+    /// The out-of-domain point drawn from the transcript lies on an evaluation domain
+    PointOnDomain,
+    /// The revealed values do not satisfy `Q(z) = Σ (αᵢ + βᵢ·z^dᵢ)·Cᵢ(z)/Zᵢ(z) + Σ Rⱼ(z)`.
+    ///
+    /// The identity combines every constraint with random weights, so it cannot point at
+    /// a single one. An honest prover only fails it on a trace that violates a
+    /// constraint, which `ConstraintSystem::check` names.
+    OutOfDomain,
+    /// The FRI proof of the DEEP composition is invalid
+    Fri(FriFailure),
+    /// The proof does not hold one set of trace openings per FRI query
+    OpeningCount,
+    /// The trace openings of a query do not cover its layer 0 coset
+    OpeningCoverage { query: usize },
+    /// A trace, auxiliary or quotient row is not authenticated by its commitment
+    TraceOpening { query: usize, position: usize },
+    /// The DEEP composition of the opened rows differs from the FRI layer 0 value
+    DeepComposition { query: usize, position: usize },
+}
+
+impl fmt::Display for VerificationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationFailure::InvalidOptions(reason) => {
+                write!(f, "invalid proof options: {}", reason)
+            }
+            VerificationFailure::OptionsMismatch => {
+                write!(f, "proof options do not match the verifier's")
+            }
+            VerificationFailure::TraceCommitmentSize => {
+                write!(
+                    f,
+                    "trace or auxiliary commitment size does not match the cap height"
+                )
+            }
+            VerificationFailure::PublicValues => {
+                write!(f, "public values do not match the public cells")
+            }
+            VerificationFailure::TraceVariables(reason) => {
+                write!(f, "trace columns do not match the constraints: {}", reason)
+            }
+            VerificationFailure::FrameShape => {
+                write!(
+                    f,
+                    "out-of-domain frame does not match the committed columns"
+                )
+            }
+            VerificationFailure::PointOnDomain => {
+                write!(f, "out-of-domain point lies on the evaluation domains")
             }
+            VerificationFailure::OutOfDomain => write!(
+                f,
+                "out-of-domain check failed: Q(z) ≠ Σ (αᵢ + βᵢ·z^dᵢ)·Cᵢ(z)/Zᵢ(z) + Σ Rⱼ(z)"
+            ),
+            VerificationFailure::Fri(failure) => write!(f, "FRI check failed: {}", failure),
+            VerificationFailure::OpeningCount => {
+                write!(f, "number of trace openings does not match the FRI queries")
+            }
+            VerificationFailure::OpeningCoverage { query } => {
+                write!(
+                    f,
+                    "trace openings of query {} do not cover its coset",
+                    query
+                )
+            }
+            VerificationFailure::TraceOpening { query, position } => write!(
+                f,
+                "opening of query {} at position {} does not match the trace, auxiliary or \
+                 quotient commitment",
+                query, position
+            ),
+            VerificationFailure::DeepComposition { query, position } => write!(
+                f,
+                "DEEP composition of query {} at position {} does not match the FRI layer 0 \
+                 value",
+                query, position
+            ),
         }
+    }
+}
 
-        true
+/// Domains shared by every proof checked by one verifier.
+struct VerifierDomains<F: FftField> {
+    /// Trace domain, including the blinding rows
+    domain: GeneralEvaluationDomain<F>,
+    /// Coset the trace and quotient are extended over
+    extended_domain: GeneralEvaluationDomain<F>,
+}
+
+/// Equality checks of the verifier, either enforced one at a time or combined across a
+/// batch of proofs.
+enum EqualityChecks<F: FftField> {
+    /// Every equality must hold when it is checked
+    Immediate,
+    /// Sum of the differences of every equality so far, each with a random weight
+    Batched(F),
+}
+
+impl<F: FftField> EqualityChecks<F> {
+    /// Checks `lhs == rhs`, or defers it into the batched combination.
+    ///
+    /// Returns `false` only for an immediate check that fails.
+    fn check(&mut self, lhs: F, rhs: F) -> bool {
+        match self {
+            EqualityChecks::Immediate => lhs == rhs,
+            EqualityChecks::Batched(sum) => {
+                *sum += F::rand(&mut rand::thread_rng()) * (lhs - rhs);
+                true
+            }
+        }
     }
-}
\ No newline at end of file
+
+    /// Whether every equality checked so far holds.
+    fn holds(&self) -> bool {
+        match self {
+            EqualityChecks::Immediate => true,
+            EqualityChecks::Batched(sum) => sum.is_zero(),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Algebraic intermediate representations.
+//!
+//! An `Air` describes a computation once: the columns of its trace, its constraints,
+//! the boundary assertions a statement's public inputs fix, how those inputs are bound
+//! into the transcript and the options it is proved with. `StarkProver::from_air` and
+//! `StarkVerifier::from_air` build the constraint system from it, so prover and
+//! verifier cannot drift apart the way two inline `ConstraintSystem`s can.
+
+use ark_bls12_381::Fr;
+use ark_ff::FftField;
+use std::ops::Deref;
+
+use crate::error::ToyniError;
+use crate::options::ProofOptions;
+use crate::vm::{constraints::ConstraintSystem, table::TraceTable, trace::ProgramVariable};
+
+/// A computation the prover can prove and the verifier can check, for the statements
+/// described by `PublicInputs`.
+pub trait Air<F: FftField = Fr> {
+    /// Inputs of a statement, such as the claimed output of a program
+    type PublicInputs;
+
+    /// Names of the trace columns, in sorted order.
+    fn trace_columns(&self) -> Vec<ProgramVariable>;
+
+    /// Number of trace columns.
+    fn trace_width(&self) -> usize {
+        self.trace_columns().len()
+    }
+
+    /// Adds the constraints every trace of the computation satisfies, independent of
+    /// the statement.
+    fn add_constraints(&self, constraints: &mut ConstraintSystem<F>);
+
+    /// Adds the boundary assertions a statement fixes, none by default.
+    fn add_assertions(
+        &self,
+        _constraints: &mut ConstraintSystem<F>,
+        _public_inputs: &Self::PublicInputs,
+    ) {
+    }
+
+    /// Field elements the proof of a statement is bound to, see
+    /// `StarkProver::with_public_inputs`; none by default.
+    fn public_inputs(&self, _public_inputs: &Self::PublicInputs) -> Vec<F> {
+        Vec::new()
+    }
+
+    /// Options the computation is proved with, the defaults of `ProofOptions::new`
+    /// unless overridden.
+    fn options(&self) -> ProofOptions<F> {
+        ProofOptions::new()
+    }
+
+    /// The constraint system of a statement, holding the constraints followed by the
+    /// assertions.
+    fn constraint_system(&self, public_inputs: &Self::PublicInputs) -> ConstraintSystem<F> {
+        let mut constraints = ConstraintSystem::new();
+        self.add_constraints(&mut constraints);
+        self.add_assertions(&mut constraints, public_inputs);
+        constraints
+    }
+
+    /// Checks that `trace` holds exactly the columns of the computation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::ColumnSchemaMismatch` listing the columns the trace lacks
+    /// and those the computation does not declare
+    fn check_trace(&self, trace: &dyn TraceTable<F::BasePrimeField>) -> Result<(), ToyniError> {
+        let columns = self.trace_columns();
+        let variables = trace.variables();
+        let missing: Vec<ProgramVariable> = columns
+            .iter()
+            .filter(|column| !variables.contains(column))
+            .cloned()
+            .collect();
+        let extra: Vec<ProgramVariable> = variables
+            .iter()
+            .filter(|variable| !columns.contains(variable))
+            .cloned()
+            .collect();
+        if !missing.is_empty() || !extra.is_empty() {
+            return Err(ToyniError::ColumnSchemaMismatch { missing, extra });
+        }
+        Ok(())
+    }
+}
+
+/// A constraint system the prover or verifier borrows from the caller or built from an
+/// `Air` itself.
+pub(crate) enum Constraints<'a, F: FftField> {
+    Borrowed(&'a ConstraintSystem<F>),
+    Owned(Box<ConstraintSystem<F>>),
+}
+
+impl<F: FftField> Deref for Constraints<'_, F> {
+    type Target = ConstraintSystem<F>;
+
+    fn deref(&self) -> &ConstraintSystem<F> {
+        match self {
+            Constraints::Borrowed(constraints) => constraints,
+            Constraints::Owned(constraints) => constraints,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::trace::ExecutionTrace;
+    use ark_ff::{AdditiveGroup, Field};
+
+    /// Counts up from zero over four rows to a claimed final value
+    struct Counter;
+
+    impl Air for Counter {
+        type PublicInputs = u64;
+
+        fn trace_columns(&self) -> Vec<ProgramVariable> {
+            vec!["x".to_string()]
+        }
+
+        fn add_constraints(&self, constraints: &mut ConstraintSystem) {
+            constraints.add_transition_constraint(
+                "increment".to_string(),
+                self.trace_columns(),
+                Box::new(|current, next| next["x"] - current["x"] - Fr::ONE),
+            );
+        }
+
+        fn add_assertions(&self, constraints: &mut ConstraintSystem, last: &u64) {
+            constraints.add_boundary_assertion("start".to_string(), "x".to_string(), 0, Fr::ZERO);
+            constraints.add_boundary_assertion(
+                "end".to_string(),
+                "x".to_string(),
+                3,
+                Fr::from(*last),
+            );
+        }
+    }
+
+    #[test]
+    fn test_air_constraint_system() {
+        let system = Counter.constraint_system(&3);
+        assert_eq!(system.transition_constraints.len(), 1);
+        assert_eq!(system.boundary_constraints.len(), 2);
+        assert_eq!(Counter.trace_width(), 1);
+
+        let trace = ExecutionTrace::from_fn(["x"], 4, |i| [i]).unwrap();
+        assert!(Counter.check_trace(&trace).is_ok());
+        assert!(system.is_satisfied(&trace).unwrap());
+        assert!(!Counter.constraint_system(&2).is_satisfied(&trace).unwrap());
+
+        let wide = ExecutionTrace::from_fn(["x", "y"], 4, |i| [i, i]).unwrap();
+        assert!(matches!(
+            Counter.check_trace(&wide),
+            Err(ToyniError::ColumnSchemaMismatch { missing, extra })
+                if missing.is_empty() && extra == ["y"]
+        ));
+    }
+}
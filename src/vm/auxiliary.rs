@@ -0,0 +1,223 @@
+//! Auxiliary trace segment built with verifier randomness.
+//!
+//! Permutation and lookup arguments need columns that depend on random challenges: a
+//! running product `Π (γ - aᵢ) / (γ - bᵢ)` only shows that `b` permutes `a` if `γ` is
+//! drawn after both columns are fixed. The constraint system declares such columns and
+//! the names of their challenges with `ConstraintSystem::set_auxiliary_segment`. The
+//! prover commits the main trace, draws the challenges from the transcript, builds the
+//! auxiliary columns from the main columns with an `AuxiliaryTrace` and commits them
+//! as a second segment. Constraints read the auxiliary columns and challenges by name,
+//! like main trace columns.
+
+use ark_bls12_381::Fr;
+use ark_ff::FftField;
+use ark_poly::Polynomial;
+use std::collections::HashMap;
+
+use crate::error::ToyniError;
+use crate::math::lde::TraceLde;
+use crate::vm::trace::ProgramVariable;
+
+/// The main trace columns an auxiliary segment is built from, padded to a power of
+/// two rows.
+pub struct MainColumns<'a, F: FftField = Fr> {
+    /// Names of the columns, in the order of `columns`
+    variables: &'a [ProgramVariable],
+    /// Values of every column
+    columns: &'a [Vec<F>],
+    /// Prefix of the names columns are looked up by, see `within`
+    prefix: String,
+}
+
+impl<'a, F: FftField> MainColumns<'a, F> {
+    /// Wraps columns of equal length with their names.
+    pub fn new(variables: &'a [ProgramVariable], columns: &'a [Vec<F>]) -> Self {
+        Self {
+            variables,
+            columns,
+            prefix: String::new(),
+        }
+    }
+
+    /// The same columns, looked up by their name within `namespace`, so that the
+    /// builder of a merged module reads `namespace/c` as `c`, see
+    /// `ConstraintSystem::merge`.
+    pub fn within(&self, namespace: &str) -> Self {
+        Self {
+            variables: self.variables,
+            columns: self.columns,
+            prefix: format!("{}{}/", self.prefix, namespace),
+        }
+    }
+
+    /// Number of rows of every column.
+    pub fn num_rows(&self) -> usize {
+        self.columns.first().map_or(0, Vec::len)
+    }
+
+    /// The values of the column named `variable`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::UnknownVariable` if the main trace has no such column
+    pub fn column(&self, variable: &str) -> Result<&'a [F], ToyniError> {
+        let variable = format!("{}{}", self.prefix, variable);
+        self.variables
+            .iter()
+            .position(|name| *name == variable)
+            .map(|index| self.columns[index].as_slice())
+            .ok_or(ToyniError::UnknownVariable(variable))
+    }
+}
+
+/// Builds the auxiliary columns declared by a constraint system.
+///
+/// Builders must be `Sync`, like the trace table, so that a proof can be generated on
+/// another thread.
+pub trait AuxiliaryTrace<F: FftField = Fr>: Sync {
+    /// Computes every auxiliary column from the main columns and the challenges drawn
+    /// after committing them, keyed by the names passed to
+    /// `ConstraintSystem::set_auxiliary_segment`.
+    ///
+    /// Every column must hold one value per row of `main`.
+    fn build(
+        &self,
+        main: &MainColumns<'_, F::BasePrimeField>,
+        challenges: &HashMap<ProgramVariable, F>,
+    ) -> Result<HashMap<ProgramVariable, Vec<F>>, ToyniError>;
+}
+
+impl<F: FftField, T: AuxiliaryTrace<F> + ?Sized> AuxiliaryTrace<F> for &T {
+    /// Builds the columns of the referenced builder, so builders of different types
+    /// combine in a `Vec<&dyn AuxiliaryTrace<F>>`.
+    fn build(
+        &self,
+        main: &MainColumns<'_, F::BasePrimeField>,
+        challenges: &HashMap<ProgramVariable, F>,
+    ) -> Result<HashMap<ProgramVariable, Vec<F>>, ToyniError> {
+        (**self).build(main, challenges)
+    }
+}
+
+impl<F: FftField, T: AuxiliaryTrace<F>> AuxiliaryTrace<F> for Vec<T> {
+    /// Builds the columns of every builder, such as several permutation arguments.
+    fn build(
+        &self,
+        main: &MainColumns<'_, F::BasePrimeField>,
+        challenges: &HashMap<ProgramVariable, F>,
+    ) -> Result<HashMap<ProgramVariable, Vec<F>>, ToyniError> {
+        let mut columns = HashMap::new();
+        for auxiliary in self {
+            columns.extend(auxiliary.build(main, challenges)?);
+        }
+        Ok(columns)
+    }
+}
+
+/// Builds the auxiliary columns and orders them as `variables`.
+///
+/// # Errors
+///
+/// Returns any error of `AuxiliaryTrace::build`, and
+/// `ToyniError::InvalidAuxiliaryColumn` if a column is missing, undeclared or not of
+/// the length of the main columns
+pub fn build_auxiliary_columns<F: FftField>(
+    auxiliary: &dyn AuxiliaryTrace<F>,
+    main: &MainColumns<'_, F::BasePrimeField>,
+    challenges: &HashMap<ProgramVariable, F>,
+    variables: &[ProgramVariable],
+) -> Result<Vec<Vec<F>>, ToyniError> {
+    let mut columns = auxiliary.build(main, challenges)?;
+    if let Some(column) = columns.keys().find(|column| !variables.contains(column)) {
+        return Err(ToyniError::InvalidAuxiliaryColumn(column.clone()));
+    }
+    variables
+        .iter()
+        .map(|variable| match columns.remove(variable) {
+            Some(column) if column.len() == main.num_rows() => Ok(column),
+            _ => Err(ToyniError::InvalidAuxiliaryColumn(variable.clone())),
+        })
+        .collect()
+}
+
+/// An auxiliary segment as committed by the prover.
+pub struct AuxiliarySegment<F: FftField = Fr> {
+    /// Names of the auxiliary columns, in the order they are committed
+    pub variables: Vec<ProgramVariable>,
+    /// Value of every challenge the columns were built with
+    pub challenges: HashMap<ProgramVariable, F>,
+    /// Extension of the auxiliary columns over the extended coset, held in memory
+    pub lde: TraceLde<F>,
+}
+
+impl<F: FftField> AuxiliarySegment<F> {
+    /// Adds the auxiliary values at position `index` of the extended coset and every
+    /// challenge to a row of the main trace.
+    pub fn extend_row(&self, row: &mut HashMap<ProgramVariable, F>, index: usize) {
+        row.extend(self.variables.iter().cloned().zip(self.lde.row(index)));
+        row.extend(self.challenges.clone());
+    }
+
+    /// Every auxiliary column evaluated at `x`, such as the out-of-domain point.
+    pub fn evaluate_at(&self, x: F) -> Vec<F> {
+        self.lde
+            .polynomials()
+            .iter()
+            .map(|poly| poly.evaluate(&x))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::{AdditiveGroup, Field};
+
+    /// Running sum of `x` shifted by the challenge `c`.
+    struct ShiftedSum;
+
+    impl AuxiliaryTrace for ShiftedSum {
+        fn build(
+            &self,
+            main: &MainColumns<'_, Fr>,
+            challenges: &HashMap<ProgramVariable, Fr>,
+        ) -> Result<HashMap<ProgramVariable, Vec<Fr>>, ToyniError> {
+            let sum = main
+                .column("x")?
+                .iter()
+                .scan(Fr::ZERO, |sum, x| {
+                    *sum += *x + challenges["c"];
+                    Some(*sum)
+                })
+                .collect();
+            Ok(HashMap::from([("sum".to_string(), sum)]))
+        }
+    }
+
+    #[test]
+    fn test_build_auxiliary_columns() {
+        let variables = vec!["x".to_string()];
+        let columns = vec![vec![Fr::ONE, Fr::from(2u64)]];
+        let main = MainColumns::new(&variables, &columns);
+        let challenges = HashMap::from([("c".to_string(), Fr::from(10u64))]);
+
+        let auxiliary =
+            build_auxiliary_columns(&ShiftedSum, &main, &challenges, &["sum".to_string()]);
+        assert_eq!(auxiliary, Ok(vec![vec![Fr::from(11u64), Fr::from(23u64)]]));
+        let builders: Vec<&dyn AuxiliaryTrace> = vec![&ShiftedSum];
+        assert_eq!(
+            build_auxiliary_columns(&builders, &main, &challenges, &["sum".to_string()]),
+            auxiliary
+        );
+
+        // Every declared column must be built, and only those
+        assert_eq!(
+            build_auxiliary_columns(&ShiftedSum, &main, &challenges, &["product".to_string()]),
+            Err(ToyniError::InvalidAuxiliaryColumn("sum".to_string()))
+        );
+        assert_eq!(
+            main.column("y").err(),
+            Some(ToyniError::UnknownVariable("y".to_string()))
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Constraint modules over disjoint column groups.
+//!
+//! A VM's constraints split into chiplets, such as an ALU, a hasher and a memory, that
+//! each constrain their own columns. A `Chiplet` writes its constraints against short
+//! local column names, and `ConstraintSystem::add_chiplet` merges them into the
+//! system of the whole VM under the chiplet's name: column `c` of chiplet `alu`
+//! becomes the trace column `alu/c` and constraint `n` becomes `alu/n`. Chiplets thus
+//! never clash over names and can be developed and tested on their own.
+
+use ark_bls12_381::Fr;
+use ark_ff::FftField;
+use std::collections::HashMap;
+
+use crate::error::ToyniError;
+use crate::vm::{
+    auxiliary::{AuxiliaryTrace, MainColumns},
+    constraints::ConstraintSystem,
+    trace::ProgramVariable,
+};
+
+/// The name `namespace/name` of a chiplet's column or constraint in the merged system.
+pub fn namespaced(namespace: &str, name: &str) -> ProgramVariable {
+    format!("{}/{}", namespace, name)
+}
+
+/// A constraint module over its own group of columns.
+pub trait Chiplet<F: FftField = Fr> {
+    /// Name the chiplet's columns and constraints are prefixed with.
+    fn name(&self) -> String;
+
+    /// Local names of the chiplet's trace columns.
+    fn columns(&self) -> Vec<ProgramVariable>;
+
+    /// Adds the chiplet's constraints over its local column names.
+    fn add_constraints(&self, constraints: &mut ConstraintSystem<F>);
+
+    /// Names of the chiplet's trace columns in the merged system.
+    fn trace_columns(&self) -> Vec<ProgramVariable> {
+        let name = self.name();
+        self.columns()
+            .iter()
+            .map(|column| namespaced(&name, column))
+            .collect()
+    }
+
+    /// The chiplet's constraints on their own, over its local column names.
+    fn constraint_system(&self) -> ConstraintSystem<F> {
+        let mut constraints = ConstraintSystem::new();
+        self.add_constraints(&mut constraints);
+        constraints
+    }
+}
+
+/// Builder of a merged module's auxiliary columns, see `ConstraintSystem::merge`.
+///
+/// The wrapped builder reads main columns and challenges by the module's local names
+/// and returns its columns under them; the adapter renames both into the namespace.
+pub struct Namespaced<'a, F: FftField = Fr> {
+    /// Namespace the module was merged under
+    namespace: String,
+    /// Builder of the module's auxiliary columns
+    auxiliary: &'a dyn AuxiliaryTrace<F>,
+}
+
+impl<'a, F: FftField> Namespaced<'a, F> {
+    /// Adapts the builder of a module merged under `namespace`.
+    pub fn new(namespace: impl Into<String>, auxiliary: &'a dyn AuxiliaryTrace<F>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            auxiliary,
+        }
+    }
+}
+
+impl<F: FftField> AuxiliaryTrace<F> for Namespaced<'_, F> {
+    fn build(
+        &self,
+        main: &MainColumns<'_, F::BasePrimeField>,
+        challenges: &HashMap<ProgramVariable, F>,
+    ) -> Result<HashMap<ProgramVariable, Vec<F>>, ToyniError> {
+        let prefix = namespaced(&self.namespace, "");
+        let local = challenges
+            .iter()
+            .filter_map(|(name, value)| {
+                name.strip_prefix(&prefix)
+                    .map(|local| (local.to_string(), *value))
+            })
+            .collect();
+        let columns = self
+            .auxiliary
+            .build(&main.within(&self.namespace), &local)?;
+        Ok(columns
+            .into_iter()
+            .map(|(name, column)| (namespaced(&self.namespace, &name), column))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::trace::ExecutionTrace;
+    use ark_ff::{AdditiveGroup, Field};
+
+    /// Counts up by one from zero
+    struct Counter;
+
+    impl Chiplet for Counter {
+        fn name(&self) -> String {
+            "counter".to_string()
+        }
+
+        fn columns(&self) -> Vec<ProgramVariable> {
+            vec!["x".to_string()]
+        }
+
+        fn add_constraints(&self, constraints: &mut ConstraintSystem) {
+            constraints.add_transition_constraint(
+                "increment".to_string(),
+                self.columns(),
+                Box::new(|current, next| next["x"] - current["x"] - Fr::ONE),
+            );
+            constraints.assert_first("x", Fr::ZERO);
+        }
+    }
+
+    /// Doubles from one
+    struct Doubler;
+
+    impl Chiplet for Doubler {
+        fn name(&self) -> String {
+            "doubler".to_string()
+        }
+
+        fn columns(&self) -> Vec<ProgramVariable> {
+            vec!["x".to_string()]
+        }
+
+        fn add_constraints(&self, constraints: &mut ConstraintSystem) {
+            let x = crate::vm::dsl::col("x");
+            constraints.transition(x.next() - 2 * x.cur());
+            constraints.assert_first("x", Fr::ONE);
+        }
+    }
+
+    #[test]
+    fn test_chiplets() {
+        let mut system = ConstraintSystem::default();
+        system.add_chiplet(&Counter).unwrap();
+        system.add_chiplet(&Doubler).unwrap();
+        let names: Vec<&str> = system
+            .transition_constraints
+            .iter()
+            .map(|constraint| constraint.name.as_str())
+            .collect();
+        assert_eq!(names, ["counter/increment", "doubler/transition/0"]);
+        assert_eq!(Doubler.trace_columns(), ["doubler/x"]);
+        assert_eq!(system.boundary_constraints[1].variables, ["doubler/x"]);
+
+        let trace =
+            ExecutionTrace::from_fn(["counter/x", "doubler/x"], 4, |i| [i, 1 << i]).unwrap();
+        assert!(system.is_satisfied(&trace).unwrap());
+        let swapped =
+            ExecutionTrace::from_fn(["counter/x", "doubler/x"], 4, |i| [1 << i, i]).unwrap();
+        assert!(!system.is_satisfied(&swapped).unwrap());
+    }
+}
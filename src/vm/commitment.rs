@@ -0,0 +1,197 @@
+//! Merkle commitments to execution traces.
+//!
+//! `ExecutionTrace::commit` low-degree extends the trace columns, hashes every extended
+//! row into a leaf and builds the Merkle tree over the leaves in one call. The returned
+//! `TraceCommitment` keeps the extension and the tree, so rows can be opened against
+//! the root afterwards without recomputing either.
+//!
+//! Leaves are the unsalted `row_leaf` of the columns in sorted variable order, which is
+//! what the prover commits to when zero-knowledge and blinding rows are disabled and the
+//! trace has no randomizer columns. With SHA-256, the root then equals the
+//! `trace_commitment` of a proof with a cap height of zero.
+
+use ark_bls12_381::Fr;
+use ark_ff::PrimeField;
+use std::sync::Arc;
+
+use crate::error::ToyniError;
+use crate::hash::{Hasher, Sha256Hasher};
+use crate::math::lde::TraceLde;
+use crate::merkle::{MerkleCap, MerkleProof, MerkleTree, path_length, row_leaf};
+use crate::vm::trace::{ExecutionTrace, ProgramVariable};
+
+/// Commitment to the low-degree extension of a trace, with the data to open its rows.
+#[derive(Debug, Clone)]
+pub struct TraceCommitment<F: PrimeField = Fr, H: Hasher = Sha256Hasher> {
+    /// Names of the committed columns, in the order of every row
+    variables: Vec<ProgramVariable>,
+    /// Extension of the columns, shared with the trace's cache
+    lde: Arc<TraceLde<F>>,
+    /// Merkle tree with one hashed leaf per extended row
+    tree: MerkleTree<H>,
+}
+
+/// One extended row of a committed trace and its authentication path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRowOpening<F: PrimeField = Fr> {
+    /// Position in the extended domain
+    pub index: usize,
+    /// Value of every column at the position, in the order of
+    /// `TraceCommitment::variables`
+    pub row: Vec<F>,
+    /// Merkle authentication path for `row`
+    pub proof: MerkleProof,
+}
+
+impl<F: PrimeField> ExecutionTrace<F> {
+    /// Commits to the extension of the trace over the coset of `coset` with `blowup`
+    /// times as many points, hashing rows with `H`.
+    ///
+    /// The extension comes from `lde`, so it is shared with earlier and later calls
+    /// with the same blowup and coset.
+    ///
+    /// # Errors
+    ///
+    /// See [`ExecutionTrace::lde`].
+    pub fn commit<H: Hasher>(
+        &self,
+        blowup: usize,
+        coset: F,
+    ) -> Result<TraceCommitment<F, H>, ToyniError> {
+        let lde = self.lde(blowup, coset)?;
+        let leaves = lde.map_rows(|_, row, _| row_leaf::<H>(row));
+        Ok(TraceCommitment {
+            variables: self.variables(),
+            lde,
+            tree: MerkleTree::from_leaves(leaves),
+        })
+    }
+}
+
+impl<F: PrimeField, H: Hasher> TraceCommitment<F, H> {
+    /// Root of the Merkle tree over the extended rows.
+    pub fn root(&self) -> Vec<u8> {
+        self.tree.root().unwrap()
+    }
+
+    /// Merkle cap of the given height, as published in a `StarkProof`.
+    pub fn cap(&self, cap_height: usize) -> MerkleCap {
+        self.tree.cap(cap_height)
+    }
+
+    /// Names of the committed columns, in the order of every row.
+    pub fn variables(&self) -> &[ProgramVariable] {
+        &self.variables
+    }
+
+    /// The committed extension.
+    pub fn lde(&self) -> &TraceLde<F> {
+        &self.lde
+    }
+
+    /// The Merkle tree over the extended rows.
+    pub fn tree(&self) -> &MerkleTree<H> {
+        &self.tree
+    }
+
+    /// Number of committed rows, i.e. the size of the extended domain.
+    pub fn len(&self) -> usize {
+        self.tree.leaves.len()
+    }
+
+    /// Whether no row is committed.
+    pub fn is_empty(&self) -> bool {
+        self.tree.leaves.is_empty()
+    }
+
+    /// Opens the extended row at `index` against the cap of height `cap_height`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::RowOutOfBounds` if `index` is not in the extended domain
+    pub fn open(&self, index: usize, cap_height: usize) -> Result<TraceRowOpening<F>, ToyniError> {
+        let proof =
+            self.tree
+                .get_proof_with_cap(index, cap_height)
+                .ok_or(ToyniError::RowOutOfBounds {
+                    index: index as u64,
+                    rows: self.len(),
+                })?;
+        Ok(TraceRowOpening {
+            index,
+            row: self.lde.row(index),
+            proof,
+        })
+    }
+}
+
+impl<F: PrimeField> TraceRowOpening<F> {
+    /// Checks the row at `index` against the cap of height `cap_height` of a
+    /// commitment to `rows` extended rows built with `H`.
+    pub fn verify<H: Hasher>(&self, cap: &MerkleCap, rows: usize, cap_height: usize) -> bool {
+        MerkleTree::<H>::verify_proof_with_cap(
+            row_leaf::<H>(&self.row),
+            self.index,
+            &self.proof,
+            cap,
+            path_length(rows, 2, cap_height),
+            2,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+    use ark_ff::{FftField, Field};
+    use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+
+    #[test]
+    fn test_commit_and_open() {
+        let trace = ExecutionTrace::<Fr>::from_fn(["x", "y"], 4, |i| [i, i * i]).unwrap();
+        let commitment = trace.commit::<Sha256Hasher>(4, Fr::GENERATOR).unwrap();
+        assert_eq!(commitment.len(), 16);
+        assert_eq!(commitment.variables(), ["x", "y"]);
+        assert_eq!(commitment.cap(0), vec![commitment.root()]);
+
+        // Every opened row holds the extension at its point and verifies against the root
+        let domain = GeneralEvaluationDomain::<Fr>::new(16)
+            .unwrap()
+            .get_coset(Fr::GENERATOR)
+            .unwrap();
+        let root = commitment.root();
+        let cap = commitment.cap(2);
+        for index in 0..commitment.len() {
+            let opening = commitment.open(index, 2).unwrap();
+            assert_eq!(
+                opening.row,
+                commitment.lde().evaluate_at(domain.element(index))
+            );
+            assert!(opening.verify::<Sha256Hasher>(&cap, 16, 2));
+            assert!(!opening.verify::<Blake3Hasher>(&cap, 16, 2));
+            assert!(!opening.verify::<Sha256Hasher>(&cap, 32, 2));
+
+            let mut tampered = opening.clone();
+            tampered.row[1] += Fr::ONE;
+            assert!(!tampered.verify::<Sha256Hasher>(&cap, 16, 2));
+            let mut moved = opening.clone();
+            moved.index ^= 1;
+            assert!(!moved.verify::<Sha256Hasher>(&cap, 16, 2));
+        }
+        assert_eq!(
+            commitment.open(16, 0).err(),
+            Some(ToyniError::RowOutOfBounds {
+                index: 16,
+                rows: 16
+            })
+        );
+
+        // The hasher and the trace values both change the root
+        let blake = trace.commit::<Blake3Hasher>(4, Fr::GENERATOR).unwrap();
+        assert_ne!(blake.root(), root);
+        let other = ExecutionTrace::<Fr>::from_fn(["x", "y"], 4, |i| [i + 1, i * i]).unwrap();
+        let other = other.commit::<Sha256Hasher>(4, Fr::GENERATOR).unwrap();
+        assert_ne!(other.root(), root);
+    }
+}
@@ -2,69 +2,581 @@
 //!
 //! Defines and evaluates constraints over execution traces, including transition
 //! constraints between consecutive rows and boundary constraints at specific rows.
+//! Constraints are evaluated on field-valued rows, so the same evaluators apply to
+//! the trace itself and to its low-degree extension.
 
 use ark_bls12_381::Fr;
-use ark_ff::{AdditiveGroup, Zero};
-use ark_poly::{EvaluationDomain, Evaluations, GeneralEvaluationDomain};
-use std::collections::HashMap;
+use ark_ff::{FftField, PrimeField};
+use ark_poly::{
+    DenseUVPolynomial, EvaluationDomain, Evaluations, GeneralEvaluationDomain,
+    Polynomial as ArkPolynomial, univariate::DensePolynomial,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Arc,
+};
+use tracing::debug;
 
+use crate::digest_sha2;
+use crate::error::ToyniError;
+use crate::math::inversion::batch_inverse;
 use crate::math::polynomial::Polynomial as ToyniPolynomial;
+use crate::math::serde_fr::to_bytes;
+use crate::transcript::field_element_from_bytes;
+use crate::vm::chiplet::{Chiplet, namespaced};
+use crate::vm::dsl::{Term, challenge, col};
+use crate::vm::expr::{Expr, MAX_DEPTH};
+use crate::vm::lookup::LookupArgument;
+use crate::vm::permutation::PermutationArgument;
+use crate::vm::range::RangeCheck;
 use crate::vm::trace::{ExecutionTrace, ProgramVariable};
 
 /// Type alias for transition constraint evaluation function
-type TransitionEvaluator =
-    Box<dyn Fn(&HashMap<ProgramVariable, u64>, &HashMap<ProgramVariable, u64>) -> Fr>;
+///
+/// Evaluators are shared across threads when the `parallel` feature evaluates rows
+/// concurrently, so they must be `Send + Sync`.
+type TransitionEvaluator<F> =
+    Box<dyn Fn(&HashMap<ProgramVariable, F>, &HashMap<ProgramVariable, F>) -> F + Send + Sync>;
 
 /// Type alias for boundary constraint evaluation function
-type BoundaryEvaluator = Box<dyn Fn(&HashMap<ProgramVariable, u64>) -> Fr>;
+type BoundaryEvaluator<F> = Box<dyn Fn(&HashMap<ProgramVariable, F>) -> F + Send + Sync>;
+
+/// Type alias for window constraint evaluation function, given consecutive rows
+type WindowEvaluator<F> = Box<dyn Fn(&[HashMap<ProgramVariable, F>]) -> F + Send + Sync>;
 
 /// Constraint between consecutive execution trace rows.
-pub struct TransitionConstraint {
+pub struct TransitionConstraint<F: FftField = Fr> {
     /// Constraint name for debugging
     pub name: String,
     /// Variables used in constraint
     pub variables: Vec<ProgramVariable>,
+    /// Degree of the constraint in the trace columns, e.g. 2 for `y = x²`
+    pub degree: usize,
     /// Function evaluating constraint
-    pub evaluate: TransitionEvaluator,
+    pub evaluate: TransitionEvaluator<F>,
+    /// Expression the evaluator was built from, see `add_transition_expr`
+    pub expression: Option<Expr<F>>,
+}
+
+/// Constraint over a window of consecutive execution trace rows.
+///
+/// A window of `rows` rows starting at every row but the last `rows - 1`, e.g.
+/// `x[n+2] = x[n+1] + x[n]` over windows of three rows.
+pub struct WindowConstraint<F: FftField = Fr> {
+    /// Constraint name for debugging
+    pub name: String,
+    /// Variables used in constraint
+    pub variables: Vec<ProgramVariable>,
+    /// Number of consecutive rows the constraint reads
+    pub rows: usize,
+    /// Degree of the constraint in the trace columns
+    pub degree: usize,
+    /// Function evaluating constraint on the rows of a window, in order
+    pub evaluate: WindowEvaluator<F>,
+    /// Expression the evaluator was built from, see `add_window_expr`
+    pub expression: Option<Expr<F>>,
+}
+
+/// Row a boundary constraint holds at, resolved against the length of the trace
+/// after any padding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoundaryPosition {
+    /// The first row
+    First,
+    /// The last row
+    Last,
+    /// The row at the given index
+    Absolute(u64),
+    /// The row the given number of rows before the last
+    FromEnd(u64),
+}
+
+impl BoundaryPosition {
+    /// Index of the row in a trace of `trace_len` rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position lies before the first row, see `fits`.
+    pub fn row(&self, trace_len: usize) -> u64 {
+        match self {
+            BoundaryPosition::First => 0,
+            BoundaryPosition::Absolute(row) => *row,
+            BoundaryPosition::Last => trace_len as u64 - 1,
+            BoundaryPosition::FromEnd(rows) => trace_len as u64 - 1 - rows,
+        }
+    }
+
+    /// Whether the position lies within a trace of `trace_len` rows.
+    pub fn fits(&self, trace_len: usize) -> bool {
+        match self {
+            BoundaryPosition::First | BoundaryPosition::Last => trace_len > 0,
+            BoundaryPosition::Absolute(row) | BoundaryPosition::FromEnd(row) => {
+                *row < trace_len as u64
+            }
+        }
+    }
+}
+
+impl BoundaryPosition {
+    /// A tag byte, 0 to 3 in the order of the variants, and the row or row count of
+    /// the position, zero for `First` and `Last`.
+    pub(crate) fn encode(&self) -> (u8, u64) {
+        match self {
+            BoundaryPosition::First => (0, 0),
+            BoundaryPosition::Last => (1, 0),
+            BoundaryPosition::Absolute(row) => (2, *row),
+            BoundaryPosition::FromEnd(rows) => (3, *rows),
+        }
+    }
+
+    /// Decodes a position from its tag and row as returned by `encode`.
+    pub(crate) fn decode(tag: u8, row: u64) -> Option<Self> {
+        match (tag, row) {
+            (0, 0) => Some(BoundaryPosition::First),
+            (1, 0) => Some(BoundaryPosition::Last),
+            (2, row) => Some(BoundaryPosition::Absolute(row)),
+            (3, rows) => Some(BoundaryPosition::FromEnd(rows)),
+            _ => None,
+        }
+    }
+}
+
+impl From<u64> for BoundaryPosition {
+    fn from(row: u64) -> Self {
+        BoundaryPosition::Absolute(row)
+    }
+}
+
+impl fmt::Display for BoundaryPosition {
+    /// Writes an absolute position as its row and a relative one as `first`, `last` or
+    /// `last-n`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoundaryPosition::First => write!(f, "first"),
+            BoundaryPosition::Last => write!(f, "last"),
+            BoundaryPosition::Absolute(row) => write!(f, "{}", row),
+            BoundaryPosition::FromEnd(rows) => write!(f, "last-{}", rows),
+        }
+    }
 }
 
 /// Constraint at specific execution trace row.
-pub struct BoundaryConstraint {
+pub struct BoundaryConstraint<F: FftField = Fr> {
     /// Constraint name for debugging
     pub name: String,
     /// Row where constraint must hold
-    pub row: u64,
+    pub position: BoundaryPosition,
     /// Variables used in constraint
     pub variables: Vec<ProgramVariable>,
     /// Function evaluating constraint
-    pub evaluate: BoundaryEvaluator,
+    pub evaluate: BoundaryEvaluator<F>,
+    /// Expression the evaluator was built from, see `add_boundary_expr`
+    pub expression: Option<Expr<F>>,
+}
+
+impl<F: FftField> BoundaryConstraint<F> {
+    /// Row the constraint holds at in a trace of `trace_len` rows.
+    pub fn row(&self, trace_len: usize) -> u64 {
+        self.position.row(trace_len)
+    }
+}
+
+/// A constraint that does not hold on a trace, reported by `ConstraintSystem::check`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation<F: FftField = Fr> {
+    /// Name of the violated constraint
+    pub constraint: String,
+    /// Row the constraint was evaluated at, the current row of a transition
+    pub row: u64,
+    /// Values of the constraint's variables on the row
+    pub current: Vec<(ProgramVariable, F)>,
+    /// Values of the constraint's variables on the next row, empty for a boundary
+    /// constraint
+    pub next: Vec<(ProgramVariable, F)>,
+    /// Nonzero value the constraint evaluates to
+    pub value: F,
+}
+
+impl<F: FftField> fmt::Display for Violation<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "constraint {} violated at row {}: evaluates to {}",
+            self.constraint, self.row, self.value
+        )?;
+        let current = self
+            .current
+            .iter()
+            .map(|(variable, value)| format!("{} = {}", variable, value));
+        let next = self
+            .next
+            .iter()
+            .map(|(variable, value)| format!("next {} = {}", variable, value));
+        let values: Vec<String> = current.chain(next).collect();
+        if !values.is_empty() {
+            write!(f, " with {}", values.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Column repeating a short pattern of values, e.g. the round constants of a hash.
+///
+/// Row `i` holds `values[i % period]`. With `Q` interpolating the pattern over the
+/// subgroup of order `period`, the column polynomial over a trace domain of size `n` is
+/// `Q(x^(n / period))`, so prover and verifier both evaluate it from the pattern alone
+/// and it is never committed.
+pub struct PeriodicColumn<F: FftField = Fr> {
+    /// Column name constraints read the values by
+    pub name: ProgramVariable,
+    /// Values of one period
+    values: Vec<F>,
+    /// Interpolant `Q` of the values over the subgroup of order `period`
+    polynomial: DensePolynomial<F>,
+}
+
+impl<F: FftField> PeriodicColumn<F> {
+    /// Creates a periodic column from the values of one period.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::NonPowerOfTwoDomain` if the period is not a power of two
+    pub fn new(name: ProgramVariable, values: Vec<F>) -> Result<Self, ToyniError> {
+        let period = values.len();
+        let domain = match period.is_power_of_two() {
+            true => GeneralEvaluationDomain::<F>::new(period),
+            false => None,
+        }
+        .ok_or(ToyniError::NonPowerOfTwoDomain(period))?;
+        let polynomial = DensePolynomial::from_coefficients_vec(domain.ifft(&values));
+        Ok(Self {
+            name,
+            values,
+            polynomial,
+        })
+    }
+
+    /// Creates the selector column that is one at the rows `n` with
+    /// `n mod period == offset` and zero at every other row.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::NonPowerOfTwoDomain` if the period is not a power of two
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is not below `period`.
+    pub fn selector(
+        name: ProgramVariable,
+        period: usize,
+        offset: usize,
+    ) -> Result<Self, ToyniError> {
+        assert!(offset < period, "The selected row lies outside the period");
+        let values = (0..period)
+            .map(|row| if row == offset { F::ONE } else { F::ZERO })
+            .collect();
+        Self::new(name, values)
+    }
+
+    /// Values of one period.
+    pub fn values(&self) -> &[F] {
+        &self.values
+    }
+
+    /// Number of rows after which the values repeat.
+    pub fn period(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Value of the column at trace row `row`.
+    pub fn value(&self, row: usize) -> F {
+        self.values[row % self.period()]
+    }
+
+    /// Evaluates the column polynomial over a trace domain of size `domain_size` at `x`.
+    pub fn evaluate_at(&self, x: F, domain_size: usize) -> F {
+        self.polynomial
+            .evaluate(&x.pow([(domain_size / self.period()) as u64]))
+    }
+
+    /// Evaluates the column polynomial over the extended coset of a trace domain of
+    /// size `domain_size`.
+    ///
+    /// The evaluations repeat every `period` times the blowup factor points, so only
+    /// those are returned; the value at point `i` is at index `i % len`.
+    pub fn evaluate_over(
+        &self,
+        extended_domain: &GeneralEvaluationDomain<F>,
+        domain_size: usize,
+    ) -> Vec<F> {
+        let cycle =
+            (extended_domain.size() / domain_size * self.period()).min(extended_domain.size());
+        (0..cycle)
+            .map(|i| self.evaluate_at(extended_domain.element(i), domain_size))
+            .collect()
+    }
+}
+
+/// Trace cell whose value is revealed alongside the proof, see
+/// `ConstraintSystem::add_public_cell`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicCell {
+    /// Name the value is published under and constraints read it by
+    pub name: String,
+    /// Column holding the value
+    pub variable: ProgramVariable,
+    /// Row holding the value
+    pub row: u64,
 }
 
 /// System holding all program constraints.
-#[derive(Default)]
-pub struct ConstraintSystem {
+///
+/// The system is `Send + Sync`: evaluators are `Send + Sync` closures and everything
+/// else is plain data, so one system can be shared by the threads of a parallel
+/// prover, or by several provers and verifiers running at once.
+pub struct ConstraintSystem<F: FftField = Fr> {
     /// Constraints between consecutive rows
-    pub transition_constraints: Vec<TransitionConstraint>,
+    pub transition_constraints: Vec<TransitionConstraint<F>>,
+    /// Constraints over windows of consecutive rows, see `add_window_constraint`
+    pub window_constraints: Vec<WindowConstraint<F>>,
     /// Constraints at specific rows
-    pub boundary_constraints: Vec<BoundaryConstraint>,
+    pub boundary_constraints: Vec<BoundaryConstraint<F>>,
+    /// Columns holding private boundary values, see `add_private_boundary_constraint`
+    pub private_columns: Vec<ProgramVariable>,
+    /// Columns of the auxiliary trace segment, see `set_auxiliary_segment`
+    pub auxiliary_columns: Vec<ProgramVariable>,
+    /// Names of the challenges the auxiliary segment is built with
+    pub challenges: Vec<ProgramVariable>,
+    /// Columns of repeating values, see `add_periodic_column`
+    pub periodic_columns: Vec<PeriodicColumn<F>>,
+    /// Cells revealed alongside the proof, see `add_public_cell`
+    pub public_cells: Vec<PublicCell>,
+}
+
+impl Default for ConstraintSystem {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl ConstraintSystem {
-    /// Adds transition constraint to system.
+impl<F: FftField> ConstraintSystem<F> {
+    /// Creates an empty constraint system over `F`; `ConstraintSystem::default()` is
+    /// the one over `Fr`.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            transition_constraints: Vec::new(),
+            window_constraints: Vec::new(),
+            boundary_constraints: Vec::new(),
+            private_columns: Vec::new(),
+            auxiliary_columns: Vec::new(),
+            challenges: Vec::new(),
+            periodic_columns: Vec::new(),
+            public_cells: Vec::new(),
+        }
+    }
+
+    /// Adds linear transition constraint to system.
     #[allow(clippy::type_complexity)]
     pub fn add_transition_constraint(
         &mut self,
         name: String,
         variables: Vec<ProgramVariable>,
-        evaluate: TransitionEvaluator,
+        evaluate: TransitionEvaluator<F>,
     ) {
+        self.add_transition_constraint_with_degree(name, variables, 1, evaluate);
+    }
+
+    /// Adds transition constraint of the given degree in the trace columns to system.
+    #[allow(clippy::type_complexity)]
+    pub fn add_transition_constraint_with_degree(
+        &mut self,
+        name: String,
+        variables: Vec<ProgramVariable>,
+        degree: usize,
+        evaluate: TransitionEvaluator<F>,
+    ) {
+        self.transition_constraints.push(TransitionConstraint {
+            name,
+            variables,
+            degree,
+            evaluate,
+            expression: None,
+        });
+    }
+
+    /// Adds transition constraint given as an expression in `variables` to system.
+    ///
+    /// Column `i` of the expression is `variables[i]`, read from the current row at
+    /// offset 0 and from the next at offset 1. The degree is computed from the
+    /// expression, which is kept on the constraint and covered by `digest`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InvalidExpression` if the expression reads a column beyond
+    /// `variables` or a row beyond the next, nests deeper than `expr::MAX_DEPTH` or has
+    /// a degree no evaluation domain of the field can hold
+    pub fn add_transition_expr(
+        &mut self,
+        name: String,
+        variables: Vec<ProgramVariable>,
+        expression: Expr<F>,
+    ) -> Result<(), ToyniError> {
+        check_expression(&expression, &variables, 1)?;
+        let columns = variables.clone();
+        let evaluator = expression.clone();
         self.transition_constraints.push(TransitionConstraint {
             name,
             variables,
+            degree: expression.degree().max(1),
+            evaluate: Box::new(move |current, next| {
+                evaluator.evaluate_with(&|i, offset| match offset {
+                    0 => current[&columns[i]],
+                    _ => next[&columns[i]],
+                })
+            }),
+            expression: Some(expression),
+        });
+        Ok(())
+    }
+
+    /// Adds constraint of the given degree over every window of `rows` consecutive rows
+    /// to system.
+    ///
+    /// The evaluator receives the rows of a window in order, so a window of two rows is
+    /// a transition constraint. The constraint holds on every window starting at a row
+    /// but the last `rows - 1`. Every column is revealed at as many points as the
+    /// longest window reads, so long windows enlarge the proof.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rows` is zero.
+    #[allow(clippy::type_complexity)]
+    pub fn add_window_constraint(
+        &mut self,
+        name: String,
+        variables: Vec<ProgramVariable>,
+        rows: usize,
+        degree: usize,
+        evaluate: WindowEvaluator<F>,
+    ) {
+        assert!(rows > 0, "A window holds at least one row");
+        self.window_constraints.push(WindowConstraint {
+            name,
+            variables,
+            rows,
+            degree,
             evaluate,
+            expression: None,
         });
     }
 
+    /// Adds constraint given as an expression in `variables` over windows of
+    /// consecutive rows to system.
+    ///
+    /// Column `i` of the expression is `variables[i]`, read from the row at its offset
+    /// into the window, so the window spans one row more than the largest offset.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InvalidExpression` if the expression reads a column beyond
+    /// `variables` or a window longer than any evaluation domain of the field, nests
+    /// deeper than `expr::MAX_DEPTH` or has a degree no such domain can hold
+    pub fn add_window_expr(
+        &mut self,
+        name: String,
+        variables: Vec<ProgramVariable>,
+        expression: Expr<F>,
+    ) -> Result<(), ToyniError> {
+        check_expression(&expression, &variables, max_domain_size::<F>() - 1)?;
+        let columns = variables.clone();
+        let evaluator = expression.clone();
+        self.window_constraints.push(WindowConstraint {
+            name,
+            variables,
+            rows: expression
+                .max_offset()
+                .checked_add(1)
+                .ok_or_else(|| ToyniError::InvalidExpression("the window overflows".to_string()))?,
+            degree: expression.degree().max(1),
+            evaluate: Box::new(move |rows| {
+                evaluator.evaluate_with(&|i, offset| rows[offset][&columns[i]])
+            }),
+            expression: Some(expression),
+        });
+        Ok(())
+    }
+
+    /// Adds constraint written as a `Term` of the `dsl` frontend to system, named
+    /// `transition/{i}` for the `i`-th constraint of the system over rows.
+    ///
+    /// A term reading only the current and next row becomes a transition constraint,
+    /// one reading further rows a window constraint over as many rows.
+    pub fn transition(&mut self, term: Term<F>) -> &mut Self {
+        let name = format!(
+            "transition/{}",
+            self.transition_constraints.len() + self.window_constraints.len()
+        );
+        let (variables, expression) = term.into_parts();
+        if expression.max_offset() <= 1 {
+            self.add_transition_expr(name, variables, expression)
+        } else {
+            self.add_window_expr(name, variables, expression)
+        }
+        .expect("A term reads exactly its own variables");
+        self
+    }
+
+    /// Adds boundary constraint written as a `Term` of the `dsl` frontend at `position`
+    /// to system, named `boundary/{i}` for the `i`-th boundary constraint.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InvalidExpression` if the term reads a row after the
+    /// current one or is not linear
+    pub fn boundary(
+        &mut self,
+        position: BoundaryPosition,
+        term: Term<F>,
+    ) -> Result<&mut Self, ToyniError> {
+        let name = format!("boundary/{}", self.boundary_constraints.len());
+        let (variables, expression) = term.into_parts();
+        self.add_boundary_expr_at(name, position, variables, expression)?;
+        Ok(self)
+    }
+
+    /// Number of consecutive rows the constraints read together, at least the two of a
+    /// transition.
+    ///
+    /// Every committed column is revealed at this many consecutive points `ωʲ·z` of the
+    /// out-of-domain point `z`.
+    pub fn window(&self) -> usize {
+        self.window_constraints
+            .iter()
+            .map(|constraint| constraint.rows)
+            .fold(2, usize::max)
+    }
+
+    /// Checks that every window constraint fits into a trace of `trace_len` rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first window longer than the trace.
+    pub fn validate_windows(&self, trace_len: usize) -> Result<(), String> {
+        match self
+            .window_constraints
+            .iter()
+            .find(|constraint| constraint.rows > trace_len)
+        {
+            Some(constraint) => Err(format!(
+                "window constraint {} reads {} rows, but the trace holds {}",
+                constraint.name, constraint.rows, trace_len
+            )),
+            None => Ok(()),
+        }
+    }
+
     /// Adds boundary constraint to system.
     #[allow(clippy::type_complexity)]
     pub fn add_boundary_constraint(
@@ -72,113 +584,1814 @@ impl ConstraintSystem {
         name: String,
         row: u64,
         variables: Vec<ProgramVariable>,
-        evaluate: BoundaryEvaluator,
+        evaluate: BoundaryEvaluator<F>,
+    ) {
+        self.add_boundary_constraint_at(name, row.into(), variables, evaluate);
+    }
+
+    /// Adds boundary constraint at a position that may be relative to the end of the
+    /// trace to system.
+    ///
+    /// Relative positions are resolved against the trace length the prover and
+    /// verifier use, which is that of the padded trace.
+    #[allow(clippy::type_complexity)]
+    pub fn add_boundary_constraint_at(
+        &mut self,
+        name: String,
+        position: BoundaryPosition,
+        variables: Vec<ProgramVariable>,
+        evaluate: BoundaryEvaluator<F>,
     ) {
         self.boundary_constraints.push(BoundaryConstraint {
             name,
-            row,
+            position,
             variables,
             evaluate,
+            expression: None,
+        });
+    }
+
+    /// Adds boundary constraint given as an expression in `variables` to system.
+    ///
+    /// Column `i` of the expression is `variables[i]` on `row`. Boundary constraints
+    /// are divided by a single-point divisor and must be linear, so the expression may
+    /// only read the current row and must have degree at most 1.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InvalidExpression` if the expression reads a column beyond
+    /// `variables`, reads the next row or is not linear
+    pub fn add_boundary_expr(
+        &mut self,
+        name: String,
+        row: u64,
+        variables: Vec<ProgramVariable>,
+        expression: Expr<F>,
+    ) -> Result<(), ToyniError> {
+        self.add_boundary_expr_at(name, row.into(), variables, expression)
+    }
+
+    /// Adds boundary constraint given as an expression at a position that may be
+    /// relative to the end of the trace to system, see `add_boundary_expr` and
+    /// `add_boundary_constraint_at`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InvalidExpression` if the expression reads a column beyond
+    /// `variables`, reads the next row or is not linear
+    pub fn add_boundary_expr_at(
+        &mut self,
+        name: String,
+        position: BoundaryPosition,
+        variables: Vec<ProgramVariable>,
+        expression: Expr<F>,
+    ) -> Result<(), ToyniError> {
+        check_expression(&expression, &variables, 0)?;
+        if expression.degree() > 1 {
+            return Err(ToyniError::InvalidExpression(format!(
+                "boundary constraint {} has degree {}",
+                name,
+                expression.degree()
+            )));
+        }
+        let columns = variables.clone();
+        let evaluator = expression.clone();
+        self.boundary_constraints.push(BoundaryConstraint {
+            name,
+            position,
+            variables,
+            evaluate: Box::new(move |row| evaluator.evaluate_with(&|i, _| row[&columns[i]])),
+            expression: Some(expression),
+        });
+        Ok(())
+    }
+
+    /// Adds boundary constraint asserting `variable == value` at `row`.
+    ///
+    /// The constraint `T(x) - value` is linear in the trace column, so its quotient by
+    /// the single-point divisor `x - g^row` is a polynomial whenever the assertion holds.
+    pub fn add_boundary_assertion(
+        &mut self,
+        name: String,
+        variable: ProgramVariable,
+        row: u64,
+        value: F,
+    ) {
+        self.add_boundary_expr(
+            name,
+            row,
+            vec![variable],
+            Expr::current(0) - Expr::Const(value),
+        )
+        .expect("The assertion is linear in its one column");
+    }
+
+    /// Asserts that `column` holds `value` at `row`, under the name `column[row]`.
+    ///
+    /// Like `add_boundary_assertion`, the constraint `T(x) - value` is divided by the
+    /// single-point zerofier `x - g^row`. The value is a constant of the constraint's
+    /// expression and so covered by `digest`: a verifier asserting another value
+    /// rejects the proof, which makes asserted values public inputs of the statement.
+    pub fn assert_eq(&mut self, row: u64, column: impl Into<ProgramVariable>, value: F) {
+        self.assert_at(row.into(), column, value);
+    }
+
+    /// Asserts that `column` holds `value` at `position`, under the name
+    /// `column[position]`, see `assert_eq`.
+    pub fn assert_at(
+        &mut self,
+        position: BoundaryPosition,
+        column: impl Into<ProgramVariable>,
+        value: F,
+    ) {
+        let column = column.into();
+        self.add_boundary_expr_at(
+            format!("{}[{}]", column, position),
+            position,
+            vec![column],
+            Expr::current(0) - Expr::Const(value),
+        )
+        .expect("The assertion is linear in its one column");
+    }
+
+    /// Asserts that `column` holds `value` at the first row, see `assert_eq`.
+    pub fn assert_first(&mut self, column: impl Into<ProgramVariable>, value: F) {
+        self.assert_at(BoundaryPosition::First, column, value);
+    }
+
+    /// Asserts that `column` holds `value` at the last row, see `assert_eq`.
+    ///
+    /// The last row is that of the padded trace. Padding repeats the last row unless
+    /// the prover is given another `PaddingStrategy`, so the value usually stays that
+    /// of the last row the trace was built with.
+    pub fn assert_last(&mut self, column: impl Into<ProgramVariable>, value: F) {
+        self.assert_at(BoundaryPosition::Last, column, value);
+    }
+
+    /// Adds boundary constraint asserting `variable` at `row` equals a private value.
+    ///
+    /// The value is held in `value_column`, which the trace must fill with it on every
+    /// row (see `ExecutionTrace::add_constant_column`). A transition constraint keeps
+    /// the column constant and the boundary constraint ties `variable` to it, so the
+    /// value never appears in the constraints or the proof. The column is only hidden
+    /// when the trace has blinding rows: without them its extension is the constant.
+    pub fn add_private_boundary_constraint(
+        &mut self,
+        name: String,
+        variable: ProgramVariable,
+        row: u64,
+        value_column: ProgramVariable,
+    ) {
+        self.add_transition_expr(
+            format!("{}_constant", name),
+            vec![value_column.clone()],
+            Expr::next(0) - Expr::current(0),
+        )
+        .expect("The constraint is linear in its one column");
+        self.add_boundary_expr(
+            name,
+            row,
+            vec![variable, value_column.clone()],
+            Expr::current(0) - Expr::current(1),
+        )
+        .expect("The constraint is linear in its two columns");
+        self.private_columns.push(value_column);
+    }
+
+    /// Declares the value of `variable` at `row` public under `name`.
+    ///
+    /// The prover reveals the value in `StarkProof::public_values` and binds it into the
+    /// transcript before committing to the trace. A boundary constraint named `name`
+    /// ties the cell to the revealed value, which constraints can also read by `name`
+    /// from both rows, so a proof only verifies if the trace holds the value it
+    /// publishes. Callers then compare the published value with the one they expect.
+    pub fn add_public_cell(&mut self, name: String, variable: ProgramVariable, row: u64) {
+        self.add_boundary_expr(
+            name.clone(),
+            row,
+            vec![variable.clone(), name.clone()],
+            Expr::current(0) - Expr::current(1),
+        )
+        .expect("The constraint is linear in the cell and its value");
+        self.public_cells.push(PublicCell {
+            name,
+            variable,
+            row,
+        });
+    }
+
+    /// Declares the first `rows` values of `variable` public, as cells named
+    /// `variable[row]`, see `add_public_cell`.
+    ///
+    /// Every cell adds a boundary constraint and a value to the proof, so whole columns
+    /// only suit short traces or short prefixes.
+    pub fn add_public_column(&mut self, variable: ProgramVariable, rows: u64) {
+        for row in 0..rows {
+            self.add_public_cell(format!("{}[{}]", variable, row), variable.clone(), row);
+        }
+    }
+
+    /// Values of the public cells in `columns`, named by `variables`, in the order of
+    /// `public_cells`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::UnknownVariable` if a public cell is not in a column and
+    /// `ToyniError::RowOutOfBounds` if its row is not in the trace
+    pub fn public_values(
+        &self,
+        variables: &[ProgramVariable],
+        columns: &[Vec<F::BasePrimeField>],
+    ) -> Result<Vec<(String, F)>, ToyniError> {
+        self.public_cells
+            .iter()
+            .map(|cell| {
+                let column = variables
+                    .iter()
+                    .position(|variable| *variable == cell.variable)
+                    .ok_or_else(|| ToyniError::UnknownVariable(cell.variable.clone()))?;
+                let value =
+                    columns[column]
+                        .get(cell.row as usize)
+                        .ok_or(ToyniError::RowOutOfBounds {
+                            index: cell.row,
+                            rows: columns[column].len(),
+                        })?;
+                Ok((cell.name.clone(), F::from_base_prime_field(*value)))
+            })
+            .collect()
+    }
+
+    /// Declares an auxiliary trace segment of `columns`, built with the random
+    /// `challenges` once the main trace is committed.
+    ///
+    /// Constraints read auxiliary columns by name like main trace columns, and every
+    /// challenge by name from both rows, where it holds the value drawn from the
+    /// transcript. The prover builds the columns with an `AuxiliaryTrace`, see
+    /// `StarkProver::with_auxiliary_trace`. Replaces any segment declared before.
+    ///
+    /// Challenges are drawn right before the segment is committed, so a segment without
+    /// columns may not declare any, see `validate_challenges`.
+    pub fn set_auxiliary_segment(
+        &mut self,
+        columns: Vec<ProgramVariable>,
+        challenges: Vec<ProgramVariable>,
+    ) {
+        self.auxiliary_columns = columns;
+        self.challenges = challenges;
+    }
+
+    /// Adds a permutation argument that the rows of the columns `right` permute those
+    /// of `left` to system, see `PermutationArgument`.
+    ///
+    /// Appends the running product column and the challenges of the argument to the
+    /// auxiliary segment, so other auxiliary columns are declared with
+    /// `set_auxiliary_segment` before. Adds the transition constraint `name/product`,
+    /// the boundary constraints `name/first` and `name/last` on the product, and
+    /// `name/last/j` for every column pair, as the last rows of both sets must agree.
+    /// The returned argument builds its column for the prover.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InvalidExpression` if the sets are empty or of different
+    /// widths and `ToyniError::DuplicateColumn` if the system already has an argument
+    /// of this name
+    pub fn add_permutation_argument(
+        &mut self,
+        name: String,
+        left: Vec<ProgramVariable>,
+        right: Vec<ProgramVariable>,
+    ) -> Result<PermutationArgument, ToyniError> {
+        let argument = PermutationArgument::new(name, left, right)?;
+        let column = argument.column();
+        if self.auxiliary_columns.contains(&column) {
+            return Err(ToyniError::DuplicateColumn(column));
+        }
+        self.auxiliary_columns.push(column.clone());
+        self.challenges.extend(argument.challenges());
+
+        let z = col(&column);
+        let product = z.next() * argument.fingerprint(&argument.right)
+            - z.cur() * argument.fingerprint(&argument.left);
+        let (variables, expression) = product.into_parts();
+        self.add_transition_expr(format!("{}/product", argument.name), variables, expression)?;
+        for (suffix, position) in [
+            ("first", BoundaryPosition::First),
+            ("last", BoundaryPosition::Last),
+        ] {
+            self.add_boundary_expr_at(
+                format!("{}/{}", argument.name, suffix),
+                position,
+                vec![column.clone()],
+                Expr::current(0) - Expr::Const(F::ONE),
+            )?;
+        }
+        for (j, (left, right)) in argument.left.iter().zip(&argument.right).enumerate() {
+            self.add_boundary_expr_at(
+                format!("{}/last/{}", argument.name, j),
+                BoundaryPosition::Last,
+                vec![left.clone(), right.clone()],
+                Expr::current(0) - Expr::current(1),
+            )?;
+        }
+        Ok(argument)
+    }
+
+    /// Constrains `column` to values in `[0, 2^bits)` by decomposing it into bits, see
+    /// `RangeCheck`.
+    ///
+    /// The trace holds bit `j` of every value in the column `column/bit/j`, see
+    /// `RangeCheck::decompose`. Adds a single-row constraint of degree 2 named after
+    /// every bit column that keeps it boolean, and the linear single-row constraint
+    /// `column/range` recomposing the value from its bits.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InvalidOptions` if `bits` is zero or `2^bits` exceeds the
+    /// field modulus, so the recomposition could wrap around
+    pub fn range_check(
+        &mut self,
+        column: impl Into<ProgramVariable>,
+        bits: usize,
+    ) -> Result<RangeCheck, ToyniError> {
+        let check = RangeCheck {
+            column: column.into(),
+            bits,
+        };
+        let modulus_bits = F::BasePrimeField::MODULUS_BIT_SIZE as usize;
+        if bits == 0 || bits >= modulus_bits {
+            return Err(ToyniError::InvalidOptions(format!(
+                "range check of {} over {} bits, where the field allows 1 to {}",
+                check.column,
+                bits,
+                modulus_bits - 1
+            )));
+        }
+        let two = F::from(2u64);
+        let mut recomposed = col(&check.column).cur::<F>();
+        for (j, name) in check.bit_columns().into_iter().enumerate() {
+            let bit = col(&name).cur::<F>();
+            let (variables, expression) = (bit.clone() * (bit.clone() - 1)).into_parts();
+            self.add_window_expr(name, variables, expression)?;
+            recomposed = recomposed - Term::constant(two.pow([j as u64])) * bit;
+        }
+        let (variables, expression) = recomposed.into_parts();
+        self.add_window_expr(format!("{}/range", check.column), variables, expression)?;
+        Ok(check)
+    }
+
+    /// Adds a lookup argument that every value of `column` appears in `table` to
+    /// system, see `LookupArgument`.
+    ///
+    /// Declares the table as the periodic column `name/table`, so it may hold at most
+    /// as many rows as the trace, and appends the columns `name/h` and `name/s` and the
+    /// challenge `name/gamma` to the auxiliary segment. The trace holds the
+    /// multiplicities in the column `name/multiplicity`, see
+    /// `LookupArgument::multiplicities`. Adds the single-row constraint `name/h`, of
+    /// degree 3, the transition constraint `name/s` and the boundary constraints
+    /// `name/first` and `name/last` on the sum. The returned argument builds its
+    /// columns for the prover.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InvalidOptions` if the table is empty and
+    /// `ToyniError::DuplicateColumn` if the system already has an argument of this name
+    pub fn add_lookup_argument(
+        &mut self,
+        name: String,
+        column: ProgramVariable,
+        table: Vec<F>,
+    ) -> Result<LookupArgument<F>, ToyniError> {
+        let argument = LookupArgument::new(name, column, table)?;
+        let columns = argument.columns();
+        if let Some(column) = columns
+            .iter()
+            .find(|column| self.auxiliary_columns.contains(column))
+        {
+            return Err(ToyniError::DuplicateColumn(column.clone()));
+        }
+        self.add_periodic_column(argument.table_column(), argument.table.clone())?;
+        self.auxiliary_columns.extend(columns.iter().cloned());
+        self.challenges.push(argument.challenge());
+
+        let (h, s) = (col(&columns[0]), col(&columns[1]));
+        let gamma = challenge(argument.challenge());
+        let value = gamma.clone() - col(&argument.column).cur();
+        let entry = gamma - col(argument.table_column()).cur();
+        let term = h.cur() * value.clone() * entry.clone() - entry
+            + col(argument.multiplicity_column()).cur() * value;
+        let (variables, expression) = term.into_parts();
+        self.add_window_expr(columns[0].clone(), variables, expression)?;
+        let (variables, expression) = (s.next() - s.cur() - h.cur()).into_parts();
+        self.add_transition_expr(columns[1].clone(), variables, expression)?;
+        let (variables, expression) = s.cur::<F>().into_parts();
+        self.add_boundary_expr_at(
+            format!("{}/first", argument.name),
+            BoundaryPosition::First,
+            variables,
+            expression,
+        )?;
+        let (variables, expression) = (s.cur() + h.cur()).into_parts();
+        self.add_boundary_expr_at(
+            format!("{}/last", argument.name),
+            BoundaryPosition::Last,
+            variables,
+            expression,
+        )?;
+        Ok(argument)
+    }
+
+    /// Merges the constraints of `chiplet` into system under its name, see `merge`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::DuplicateColumn` if system already declares one of the
+    /// chiplet's auxiliary, challenge, periodic or private columns
+    pub fn add_chiplet(&mut self, chiplet: &dyn Chiplet<F>) -> Result<(), ToyniError> {
+        self.merge(&chiplet.name(), chiplet.constraint_system())
+    }
+
+    /// Merges the constraints and column declarations of `module` into system under
+    /// `namespace`, see `Chiplet`.
+    ///
+    /// Every column, challenge, periodic column and public cell `c` of the module
+    /// becomes `namespace/c` and every constraint `n` becomes `namespace/n`, so modules
+    /// over disjoint column groups can be written independently and combined. The
+    /// module's auxiliary columns and challenges are appended to the auxiliary
+    /// segment; its `AuxiliaryTrace` is adapted with `Namespaced`. Expression
+    /// constraints are registered again over the renamed variables, while closures keep
+    /// reading the module's names from rows that are renamed back for them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::DuplicateColumn` if system already declares one of the
+    /// renamed auxiliary, challenge, periodic or private columns
+    pub fn merge(
+        &mut self,
+        namespace: &str,
+        module: ConstraintSystem<F>,
+    ) -> Result<(), ToyniError> {
+        let rename = |name: &str| namespaced(namespace, name);
+        let declared = module
+            .auxiliary_columns
+            .iter()
+            .chain(&module.challenges)
+            .chain(module.periodic_columns.iter().map(|column| &column.name))
+            .chain(&module.private_columns)
+            .map(|name| rename(name));
+        let existing: Vec<&ProgramVariable> = self
+            .auxiliary_columns
+            .iter()
+            .chain(&self.challenges)
+            .chain(self.periodic_columns.iter().map(|column| &column.name))
+            .chain(&self.private_columns)
+            .collect();
+        if let Some(column) = declared.clone().find(|column| existing.contains(&column)) {
+            return Err(ToyniError::DuplicateColumn(column));
+        }
+
+        // Every name a closure of the module may read, with the name it is read by in
+        // the merged system
+        let mut local: Vec<ProgramVariable> = module
+            .transition_constraints
+            .iter()
+            .flat_map(|constraint| &constraint.variables)
+            .chain(
+                module
+                    .window_constraints
+                    .iter()
+                    .flat_map(|constraint| &constraint.variables),
+            )
+            .chain(
+                module
+                    .boundary_constraints
+                    .iter()
+                    .flat_map(|constraint| &constraint.variables),
+            )
+            .chain(&module.auxiliary_columns)
+            .chain(&module.challenges)
+            .chain(module.periodic_columns.iter().map(|column| &column.name))
+            .chain(module.public_cells.iter().map(|cell| &cell.name))
+            .cloned()
+            .collect();
+        local.sort();
+        local.dedup();
+        let names: Arc<Vec<(ProgramVariable, ProgramVariable)>> = Arc::new(
+            local
+                .into_iter()
+                .map(|name| (rename(&name), name))
+                .collect(),
+        );
+        let localize = move |row: &HashMap<ProgramVariable, F>| -> HashMap<ProgramVariable, F> {
+            names
+                .iter()
+                .filter_map(|(global, local)| row.get(global).map(|value| (local.clone(), *value)))
+                .collect()
+        };
+        let localize = Arc::new(localize);
+        let renamed = |variables: &[ProgramVariable]| -> Vec<ProgramVariable> {
+            variables.iter().map(|variable| rename(variable)).collect()
+        };
+
+        for constraint in module.transition_constraints {
+            let name = rename(&constraint.name);
+            let variables = renamed(&constraint.variables);
+            match constraint.expression {
+                Some(expression) => self.add_transition_expr(name, variables, expression)?,
+                None => {
+                    let (evaluate, localize) = (constraint.evaluate, localize.clone());
+                    self.add_transition_constraint_with_degree(
+                        name,
+                        variables,
+                        constraint.degree,
+                        Box::new(move |current, next| {
+                            evaluate(&localize(current), &localize(next))
+                        }),
+                    );
+                }
+            }
+        }
+        for constraint in module.window_constraints {
+            let name = rename(&constraint.name);
+            let variables = renamed(&constraint.variables);
+            match constraint.expression {
+                Some(expression) => self.add_window_expr(name, variables, expression)?,
+                None => {
+                    let (evaluate, localize) = (constraint.evaluate, localize.clone());
+                    self.add_window_constraint(
+                        name,
+                        variables,
+                        constraint.rows,
+                        constraint.degree,
+                        Box::new(move |rows| {
+                            evaluate(&rows.iter().map(|row| localize(row)).collect::<Vec<_>>())
+                        }),
+                    );
+                }
+            }
+        }
+        for constraint in module.boundary_constraints {
+            let name = rename(&constraint.name);
+            let variables = renamed(&constraint.variables);
+            match constraint.expression {
+                Some(expression) => {
+                    self.add_boundary_expr_at(name, constraint.position, variables, expression)?
+                }
+                None => {
+                    let (evaluate, localize) = (constraint.evaluate, localize.clone());
+                    self.add_boundary_constraint_at(
+                        name,
+                        constraint.position,
+                        variables,
+                        Box::new(move |row| evaluate(&localize(row))),
+                    );
+                }
+            }
+        }
+
+        self.private_columns
+            .extend(renamed(&module.private_columns));
+        self.auxiliary_columns
+            .extend(renamed(&module.auxiliary_columns));
+        self.challenges.extend(renamed(&module.challenges));
+        for mut column in module.periodic_columns {
+            column.name = rename(&column.name);
+            self.periodic_columns.push(column);
+        }
+        self.public_cells
+            .extend(module.public_cells.into_iter().map(|cell| PublicCell {
+                name: rename(&cell.name),
+                variable: rename(&cell.variable),
+                row: cell.row,
+            }));
+        Ok(())
+    }
+
+    /// Declares a column `name` whose rows repeat `values`, which constraints read by
+    /// name like trace columns.
+    ///
+    /// The column is not part of the trace: the prover and the verifier both evaluate
+    /// it from `values`, so it costs neither a commitment nor an opening. Its degree
+    /// counts like a trace column's in the degree of a constraint.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::NonPowerOfTwoDomain` if the number of values is not a power
+    /// of two
+    pub fn add_periodic_column(
+        &mut self,
+        name: ProgramVariable,
+        values: Vec<F>,
+    ) -> Result<(), ToyniError> {
+        self.periodic_columns
+            .push(PeriodicColumn::new(name, values)?);
+        Ok(())
+    }
+
+    /// Adds transition constraint of the given degree to system that only holds at the
+    /// rows `n` with `n mod period == offset`, such as the last round of a hash.
+    ///
+    /// The constraint is multiplied by a selector, a periodic column named
+    /// `selector/{name}` that is one at the selected rows and zero elsewhere, so it is
+    /// registered with one degree more than `degree`. As a transition constraint it
+    /// does not hold at the last row, even if selected.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::NonPowerOfTwoDomain` if the period is not a power of two
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is not below `period`.
+    #[allow(clippy::type_complexity)]
+    pub fn add_periodic_constraint(
+        &mut self,
+        name: String,
+        variables: Vec<ProgramVariable>,
+        period: usize,
+        offset: usize,
+        degree: usize,
+        evaluate: TransitionEvaluator<F>,
+    ) -> Result<(), ToyniError> {
+        let selector = PeriodicColumn::selector(format!("selector/{}", name), period, offset)?;
+        let column = selector.name.clone();
+        self.periodic_columns.push(selector);
+        self.add_transition_constraint_with_degree(
+            name,
+            variables,
+            degree + 1,
+            Box::new(move |current, next| current[&column] * evaluate(current, next)),
+        );
+        Ok(())
+    }
+
+    /// Starts a group of constraints that only apply at the rows where the trace column
+    /// `selector` is one, such as the constraints of one instruction of a VM.
+    ///
+    /// The first group of a selector also adds the constraint `boolean/{selector}`,
+    /// `s·(s - 1) = 0` on every row, so the selector is either zero or one. A trace
+    /// selects one instruction per row by holding one selector column per instruction.
+    pub fn add_selector_group(&mut self, selector: ProgramVariable) -> SelectorGroup<'_, F> {
+        let name = format!("boolean/{}", selector);
+        if !self
+            .window_constraints
+            .iter()
+            .any(|constraint| constraint.name == name)
+        {
+            let s = Expr::current(0);
+            self.add_window_expr(
+                name,
+                vec![selector.clone()],
+                s.clone() * (s - Expr::Const(F::ONE)),
+            )
+            .expect("The constraint reads its one column");
+        }
+        SelectorGroup {
+            system: self,
+            selector,
+        }
+    }
+
+    /// Adds transition constraint given as an expression in `variables` to system that
+    /// only holds at the rows `n` with `n mod period == offset`, like
+    /// `add_periodic_constraint`.
+    ///
+    /// The expression is multiplied by the selector column, which becomes column
+    /// `variables.len()` of the registered expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::NonPowerOfTwoDomain` if the period is not a power of two
+    /// and `ToyniError::InvalidExpression` if the expression reads a column beyond
+    /// `variables` or a row beyond the next
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is not below `period`.
+    pub fn add_periodic_expr(
+        &mut self,
+        name: String,
+        mut variables: Vec<ProgramVariable>,
+        period: usize,
+        offset: usize,
+        expression: Expr<F>,
+    ) -> Result<(), ToyniError> {
+        check_expression(&expression, &variables, 1)?;
+        let selector = PeriodicColumn::selector(format!("selector/{}", name), period, offset)?;
+        let column = Expr::current(variables.len());
+        variables.push(selector.name.clone());
+        self.periodic_columns.push(selector);
+        self.add_transition_expr(name, variables, column * expression)
+    }
+
+    /// Checks that every variable an expression reads as a challenge is declared as one,
+    /// as a column read as a challenge would not count towards the degree, and that
+    /// challenges come with an auxiliary segment, as they are drawn only before its
+    /// commitment.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first challenge declared without auxiliary columns
+    /// or constraint reading an undeclared challenge.
+    pub fn validate_challenges(&self) -> Result<(), String> {
+        if self.auxiliary_columns.is_empty()
+            && let Some(challenge) = self.challenges.first()
+        {
+            return Err(format!(
+                "challenge {} is declared without auxiliary columns, so it is never drawn",
+                challenge
+            ));
+        }
+        let transitions = self.transition_constraints.iter().map(|constraint| {
+            (
+                &constraint.name,
+                &constraint.variables,
+                &constraint.expression,
+            )
+        });
+        let windows = self.window_constraints.iter().map(|constraint| {
+            (
+                &constraint.name,
+                &constraint.variables,
+                &constraint.expression,
+            )
+        });
+        let boundaries = self.boundary_constraints.iter().map(|constraint| {
+            (
+                &constraint.name,
+                &constraint.variables,
+                &constraint.expression,
+            )
         });
+        for (name, variables, expression) in transitions.chain(windows).chain(boundaries) {
+            let Some(expression) = expression else {
+                continue;
+            };
+            if let Some(i) = expression
+                .challenges()
+                .into_iter()
+                .find(|i| !self.challenges.contains(&variables[*i]))
+            {
+                return Err(format!(
+                    "constraint {} reads {} as a challenge, which is not declared",
+                    name, variables[i]
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that `columns` names every trace column the constraints read exactly once.
+    ///
+    /// Variables of a constraint that are auxiliary columns, challenges, periodic
+    /// columns or public values are supplied besides the trace columns and need not be
+    /// listed. Evaluators index their rows by the declared variables, so the verifier
+    /// runs this before evaluating any constraint on a row laid out by the columns a
+    /// proof claims.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first repeated column or missing variable.
+    pub fn validate_trace_columns(&self, columns: &[ProgramVariable]) -> Result<(), String> {
+        let mut listed = HashSet::new();
+        if let Some(column) = columns.iter().find(|column| !listed.insert(*column)) {
+            return Err(format!("trace column {} is listed twice", column));
+        }
+        let supplied = |variable: &ProgramVariable| {
+            listed.contains(variable)
+                || self.auxiliary_columns.contains(variable)
+                || self.challenges.contains(variable)
+                || self
+                    .periodic_columns
+                    .iter()
+                    .any(|column| column.name == *variable)
+                || self.public_cells.iter().any(|cell| cell.name == *variable)
+        };
+        let variables = self
+            .transition_constraints
+            .iter()
+            .map(|constraint| (&constraint.name, &constraint.variables))
+            .chain(
+                self.window_constraints
+                    .iter()
+                    .map(|constraint| (&constraint.name, &constraint.variables)),
+            )
+            .chain(
+                self.boundary_constraints
+                    .iter()
+                    .map(|constraint| (&constraint.name, &constraint.variables)),
+            );
+        for (name, variables) in variables {
+            if let Some(variable) = variables.iter().find(|variable| !supplied(variable)) {
+                return Err(format!(
+                    "constraint {} reads {}, which is not a trace column",
+                    name, variable
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that every boundary constraint lies within a trace of `trace_len` rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first boundary constraint beyond the trace.
+    pub fn validate_boundaries(&self, trace_len: usize) -> Result<(), String> {
+        match self
+            .boundary_constraints
+            .iter()
+            .find(|constraint| !constraint.position.fits(trace_len))
+        {
+            Some(constraint) => Err(format!(
+                "boundary constraint {} at row {} lies outside the trace of {} rows",
+                constraint.name, constraint.position, trace_len
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that every periodic column repeats within a trace of `trace_len` rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first periodic column whose period exceeds the
+    /// trace length.
+    pub fn validate_periodic_columns(&self, trace_len: usize) -> Result<(), String> {
+        match self
+            .periodic_columns
+            .iter()
+            .find(|column| column.period() > trace_len)
+        {
+            Some(column) => Err(format!(
+                "periodic column {} has a period of {} rows, more than the trace length {}",
+                column.name,
+                column.period(),
+                trace_len
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Row `row` of the trace, with the value of every periodic column at that row and
+    /// of every public cell.
+    pub fn trace_row(
+        &self,
+        trace: &ExecutionTrace<F::BasePrimeField>,
+        row: u64,
+    ) -> Result<HashMap<ProgramVariable, F>, ToyniError> {
+        let mut values = trace.get_field_column(row)?;
+        values.extend(
+            self.periodic_columns
+                .iter()
+                .map(|column| (column.name.clone(), column.value(row as usize))),
+        );
+        for cell in &self.public_cells {
+            let value = trace.get(cell.row, &cell.variable)?;
+            values.insert(cell.name.clone(), F::from_base_prime_field(value));
+        }
+        Ok(values)
+    }
+
+    /// The `rows` rows starting at `row`, as window constraints read them.
+    fn window_rows(
+        &self,
+        trace: &ExecutionTrace<F::BasePrimeField>,
+        row: u64,
+        rows: usize,
+    ) -> Result<Vec<HashMap<ProgramVariable, F>>, ToyniError> {
+        (row..row + rows as u64)
+            .map(|i| self.trace_row(trace, i))
+            .collect()
+    }
+
+    /// Checks that private boundary values stay hidden in proofs with the given number
+    /// of blinding rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first private value column left unblinded.
+    pub fn validate_private_columns(&self, blinding_rows: usize) -> Result<(), String> {
+        match self.private_columns.first() {
+            Some(column) if blinding_rows == 0 => Err(format!(
+                "private value column {} needs blinding rows to stay hidden",
+                column
+            )),
+            _ => Ok(()),
+        }
     }
 
     /// Evaluates all constraints on trace.
-    pub fn evaluate(&self, trace: &ExecutionTrace) -> Vec<Fr> {
+    ///
+    /// Every violated constraint is reported as a `tracing` event at debug level; `check`
+    /// describes each violation with the values involved.
+    /// Constraints reading auxiliary columns or challenges can only be evaluated by the
+    /// prover, which adds them to the rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::TraceTooShort` if rows are missing and
+    /// `ToyniError::InvalidOptions` if a boundary constraint lies outside the trace,
+    /// which includes every boundary constraint of an empty trace
+    pub fn evaluate(
+        &self,
+        trace: &ExecutionTrace<F::BasePrimeField>,
+    ) -> Result<Vec<F>, ToyniError> {
+        trace.validate()?;
+        self.validate_boundaries(trace.height as usize)
+            .map_err(ToyniError::InvalidOptions)?;
         let mut evaluations = Vec::new();
 
         // Evaluate transition constraints
-        for i in 0..trace.height - 1 {
-            let current_row = trace.get_column(i);
-            let next_row = trace.get_column(i + 1);
+        for i in 0..trace.height.saturating_sub(1) {
+            let current_row = self.trace_row(trace, i)?;
+            let next_row = self.trace_row(trace, i + 1)?;
 
             for constraint in &self.transition_constraints {
-                let eval = (constraint.evaluate)(current_row, next_row);
+                let eval = (constraint.evaluate)(&current_row, &next_row);
+                if !eval.is_zero() {
+                    debug!(constraint = %constraint.name, row = i, "transition constraint violated");
+                }
+                evaluations.push(eval);
+            }
+        }
+
+        // Evaluate window constraints
+        for constraint in &self.window_constraints {
+            for i in 0..(trace.height + 1).saturating_sub(constraint.rows as u64) {
+                let rows = self.window_rows(trace, i, constraint.rows)?;
+                let eval = (constraint.evaluate)(&rows);
+                if !eval.is_zero() {
+                    debug!(constraint = %constraint.name, row = i, "window constraint violated");
+                }
                 evaluations.push(eval);
             }
         }
 
         // Evaluate boundary constraints
         for constraint in &self.boundary_constraints {
-            let row = trace.get_column(constraint.row);
-            let eval = (constraint.evaluate)(row);
+            let index = constraint.row(trace.height as usize);
+            let row = self.trace_row(trace, index)?;
+            let eval = (constraint.evaluate)(&row);
+            if !eval.is_zero() {
+                debug!(constraint = %constraint.name, row = index, "boundary constraint violated");
+            }
             evaluations.push(eval);
         }
 
-        evaluations
+        Ok(evaluations)
+    }
+
+    /// Checks if all constraints are satisfied.
+    pub fn is_satisfied(
+        &self,
+        trace: &ExecutionTrace<F::BasePrimeField>,
+    ) -> Result<bool, ToyniError> {
+        Ok(self.check(trace)?.is_empty())
+    }
+
+    /// Checks every constraint on trace and describes each violation.
+    ///
+    /// Transition constraints are checked on every row but the last, window constraints
+    /// on every row a whole window starts at, boundary constraints on their row. Each
+    /// violation names the constraint and row and carries the values of the
+    /// constraint's variables with the nonzero evaluation, in the order of
+    /// `interpolate_all_constraints` and by row.
+    ///
+    /// # Errors
+    ///
+    /// See `evaluate`.
+    pub fn check(
+        &self,
+        trace: &ExecutionTrace<F::BasePrimeField>,
+    ) -> Result<Vec<Violation<F>>, ToyniError> {
+        trace.validate()?;
+        self.validate_boundaries(trace.height as usize)
+            .map_err(ToyniError::InvalidOptions)?;
+        let values = |variables: &[ProgramVariable], row: &HashMap<ProgramVariable, F>| {
+            variables
+                .iter()
+                .filter_map(|variable| row.get(variable).map(|value| (variable.clone(), *value)))
+                .collect()
+        };
+        let mut violations = Vec::new();
+        for constraint in &self.transition_constraints {
+            for i in 0..trace.height.saturating_sub(1) {
+                let current = self.trace_row(trace, i)?;
+                let next = self.trace_row(trace, i + 1)?;
+                let value = (constraint.evaluate)(&current, &next);
+                if !value.is_zero() {
+                    violations.push(Violation {
+                        constraint: constraint.name.clone(),
+                        row: i,
+                        current: values(&constraint.variables, &current),
+                        next: values(&constraint.variables, &next),
+                        value,
+                    });
+                }
+            }
+        }
+        for constraint in &self.window_constraints {
+            for i in 0..(trace.height + 1).saturating_sub(constraint.rows as u64) {
+                let rows = self.window_rows(trace, i, constraint.rows)?;
+                let value = (constraint.evaluate)(&rows);
+                if !value.is_zero() {
+                    violations.push(Violation {
+                        constraint: constraint.name.clone(),
+                        row: i,
+                        current: values(&constraint.variables, &rows[0]),
+                        next: rows
+                            .get(1)
+                            .map_or_else(Vec::new, |next| values(&constraint.variables, next)),
+                        value,
+                    });
+                }
+            }
+        }
+        for constraint in &self.boundary_constraints {
+            let index = constraint.row(trace.height as usize);
+            let row = self.trace_row(trace, index)?;
+            let value = (constraint.evaluate)(&row);
+            if !value.is_zero() {
+                violations.push(Violation {
+                    constraint: constraint.name.clone(),
+                    row: index,
+                    current: values(&constraint.variables, &row),
+                    next: Vec::new(),
+                    value,
+                });
+            }
+        }
+        Ok(violations)
+    }
+
+    /// Interpolates transition constraint as polynomial.
+    pub fn interpolate_transition_constraint(
+        &self,
+        trace: &ExecutionTrace<F::BasePrimeField>,
+        constraint: &TransitionConstraint<F>,
+    ) -> Result<ToyniPolynomial<F>, ToyniError> {
+        let domain = trace_domain(trace)?;
+
+        let mut evaluations = vec![F::zero(); trace.height as usize];
+        for i in 0..trace.height.saturating_sub(1) {
+            let current_row = self.trace_row(trace, i)?;
+            let next_row = self.trace_row(trace, i + 1)?;
+            evaluations[i as usize] = (constraint.evaluate)(&current_row, &next_row);
+        }
+
+        let evals = Evaluations::from_vec_and_domain(evaluations, domain);
+        Ok(ToyniPolynomial::from_dense_poly(evals.interpolate()))
+    }
+
+    /// Interpolates window constraint as polynomial, zero where no whole window starts.
+    pub fn interpolate_window_constraint(
+        &self,
+        trace: &ExecutionTrace<F::BasePrimeField>,
+        constraint: &WindowConstraint<F>,
+    ) -> Result<ToyniPolynomial<F>, ToyniError> {
+        let domain = trace_domain(trace)?;
+
+        let mut evaluations = vec![F::zero(); trace.height as usize];
+        for i in 0..(trace.height + 1).saturating_sub(constraint.rows as u64) {
+            evaluations[i as usize] =
+                (constraint.evaluate)(&self.window_rows(trace, i, constraint.rows)?);
+        }
+
+        let evals = Evaluations::from_vec_and_domain(evaluations, domain);
+        Ok(ToyniPolynomial::from_dense_poly(evals.interpolate()))
+    }
+
+    /// Interpolates boundary constraint as polynomial.
+    ///
+    /// The interpolant is zero on every row but the asserted one, so it is not
+    /// divisible by `Z_H` for a nonzero target; see `interpolate_boundary_quotient`.
+    pub fn interpolate_boundary_constraint(
+        &self,
+        trace: &ExecutionTrace<F::BasePrimeField>,
+        constraint: &BoundaryConstraint<F>,
+    ) -> Result<ToyniPolynomial<F>, ToyniError> {
+        let domain = trace_domain(trace)?;
+
+        let mut evaluations = vec![F::zero(); trace.height as usize];
+        let row = constraint.row(trace.height as usize);
+        evaluations[row as usize] = (constraint.evaluate)(&self.trace_row(trace, row)?);
+
+        let evals = Evaluations::from_vec_and_domain(evaluations, domain);
+        Ok(ToyniPolynomial::from_dense_poly(evals.interpolate()))
+    }
+
+    /// Interpolates the quotient `C(x) / (x - g^row)` of a boundary constraint.
+    ///
+    /// The constraint is evaluated on every trace row rather than only the asserted one,
+    /// so for a constraint linear in the row, e.g. `T(x) - v`, the interpolant is the
+    /// constraint polynomial itself and not an indicator of the asserted row.
+    ///
+    /// # Returns
+    ///
+    /// The quotient, or `ToyniError::UnsatisfiedConstraint` if the constraint does not
+    /// hold at its row
+    pub fn interpolate_boundary_quotient(
+        &self,
+        trace: &ExecutionTrace<F::BasePrimeField>,
+        constraint: &BoundaryConstraint<F>,
+    ) -> Result<ToyniPolynomial<F>, ToyniError> {
+        let domain = trace_domain(trace)?;
+
+        let evaluations = (0..trace.height)
+            .map(|i| Ok((constraint.evaluate)(&self.trace_row(trace, i)?)))
+            .collect::<Result<Vec<F>, ToyniError>>()?;
+        let constraint_poly = ToyniPolynomial::from_dense_poly(
+            Evaluations::from_vec_and_domain(evaluations, domain).interpolate(),
+        );
+
+        let (quotient, remainder) = constraint_poly
+            .divide_by_linear(domain.element(constraint.row(trace.height as usize) as usize));
+        if !remainder.is_zero() {
+            return Err(ToyniError::UnsatisfiedConstraint(constraint.name.clone()));
+        }
+        Ok(quotient)
+    }
+
+    /// Number of constraints, i.e. the number of polynomials returned by
+    /// `interpolate_all_constraints`.
+    pub fn num_constraints(&self) -> usize {
+        self.transition_constraints.len()
+            + self.window_constraints.len()
+            + self.boundary_constraints.len()
+    }
+
+    /// Evaluates the zerofier of every constraint at `x`, in the order of
+    /// `interpolate_all_constraints`.
+    ///
+    /// The first `trace_len` rows of `domain` hold the trace, any further rows are
+    /// blinding rows. Transition constraints do not apply at the last trace row or the
+    /// blinding rows, so their zerofier is `Z_H(x) / Π(x - g^i)` over `i ≥ trace_len - 1`,
+    /// i.e. `Z_H(x) / (x - g^(n-1))` without blinding. A window of `k` rows does not
+    /// start at the last `k - 1` trace rows either, so the product runs over
+    /// `i ≥ trace_len - k + 1`. A boundary constraint at row `i` only vanishes at `g^i`,
+    /// so its zerofier is `x - g^i`.
+    pub fn evaluate_zerofiers(
+        &self,
+        domain: &GeneralEvaluationDomain<F>,
+        trace_len: usize,
+        x: F,
+    ) -> Vec<F> {
+        let vanishing = domain.evaluate_vanishing_polynomial(x);
+        let window_zerofier = |rows: usize| {
+            let excluded_rows: F = (trace_len + 1 - rows..domain.size())
+                .map(|i| x - domain.element(i))
+                .product();
+            vanishing / excluded_rows
+        };
+        let transition_zerofier = window_zerofier(2);
+        let transitions = self
+            .transition_constraints
+            .iter()
+            .map(|_| transition_zerofier);
+        let windows = self
+            .window_constraints
+            .iter()
+            .map(|constraint| window_zerofier(constraint.rows));
+        let boundaries = self
+            .boundary_constraints
+            .iter()
+            .map(|constraint| x - domain.element(constraint.row(trace_len) as usize));
+        transitions.chain(windows).chain(boundaries).collect()
+    }
+
+    /// Inverts the zerofier of every constraint at `x`, in the order of
+    /// `interpolate_all_constraints`, see `evaluate_zerofiers`.
+    ///
+    /// `root(i)` is the `i`-th point of the trace domain of `domain_size` points and
+    /// `vanishing` its vanishing polynomial at `x`, so callers evaluating many points
+    /// can look both up instead of recomputing them. The transition zerofiers share one
+    /// inverse, as do window zerofiers of the same length, and all inverses together
+    /// take a single field inversion.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` is a root of one of the zerofiers.
+    pub fn zerofier_inverses(
+        &self,
+        root: impl Fn(usize) -> F,
+        domain_size: usize,
+        trace_len: usize,
+        x: F,
+        vanishing: F,
+    ) -> Vec<F> {
+        // Products of the excluded rows, for a window of every length up to the longest
+        let window = self.window();
+        let mut excluded_rows = vec![F::ONE; window + 1];
+        excluded_rows[1] = (trace_len..domain_size).map(|i| x - root(i)).product();
+        for rows in 2..=window {
+            excluded_rows[rows] = excluded_rows[rows - 1] * (x - root(trace_len + 1 - rows));
+        }
+        let denominators: Vec<F> = std::iter::once(vanishing)
+            .chain(
+                self.boundary_constraints
+                    .iter()
+                    .map(|constraint| x - root(constraint.row(trace_len) as usize)),
+            )
+            .collect();
+        let inverses = batch_inverse(&denominators).expect("x is not a root of any zerofier");
+        let transition_inverse = excluded_rows[2] * inverses[0];
+        self.transition_constraints
+            .iter()
+            .map(|_| transition_inverse)
+            .chain(
+                self.window_constraints
+                    .iter()
+                    .map(|constraint| excluded_rows[constraint.rows] * inverses[0]),
+            )
+            .chain(inverses[1..].iter().copied())
+            .collect()
+    }
+
+    /// Evaluates every constraint on a row and its successors, in the order of
+    /// `interpolate_all_constraints`.
+    ///
+    /// `rows` holds `window` consecutive rows. Transition constraints read the first two,
+    /// window constraints as many as they span and boundary constraints only the first.
+    /// On the low-degree extension the rows are the trace column values at `ωʲ·x`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rows` holds fewer rows than `window`.
+    pub fn evaluate_rows(&self, rows: &[HashMap<ProgramVariable, F>]) -> Vec<F> {
+        let transitions = self
+            .transition_constraints
+            .iter()
+            .map(|constraint| (constraint.evaluate)(&rows[0], &rows[1]));
+        let windows = self
+            .window_constraints
+            .iter()
+            .map(|constraint| (constraint.evaluate)(&rows[..constraint.rows]));
+        let boundaries = self
+            .boundary_constraints
+            .iter()
+            .map(|constraint| (constraint.evaluate)(&rows[0]));
+        transitions.chain(windows).chain(boundaries).collect()
+    }
+
+    /// `window` pseudo-random rows derived from `label`, see `sample_row`.
+    fn sample_rows(
+        &self,
+        columns: &[ProgramVariable],
+        label: &str,
+    ) -> Vec<HashMap<ProgramVariable, F>> {
+        (0..self.window())
+            .map(|row| match row {
+                0 => self.sample_row(columns, &format!("{}current", label)),
+                1 => self.sample_row(columns, &format!("{}next", label)),
+                _ => self.sample_row(columns, &format!("{}row-{}", label, row)),
+            })
+            .collect()
+    }
+
+    /// A pseudo-random row derived from `label` and the column names, holding a value
+    /// for every trace column in `columns`, auxiliary column, challenge, periodic column
+    /// and public value.
+    fn sample_row(&self, columns: &[ProgramVariable], label: &str) -> HashMap<ProgramVariable, F> {
+        columns
+            .iter()
+            .chain(&self.auxiliary_columns)
+            .chain(&self.challenges)
+            .chain(self.periodic_columns.iter().map(|column| &column.name))
+            .chain(self.public_cells.iter().map(|cell| &cell.name))
+            .map(|column| {
+                let seed = format!("toyni-constraints/{}/{}", label, column);
+                let mut digest = digest_sha2(seed.as_bytes());
+                let value = field_element_from_bytes(|| {
+                    let bytes = digest;
+                    digest = digest_sha2(&digest);
+                    bytes
+                });
+                (column.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Digest of a canonical encoding of the constraint system over the given columns.
+    ///
+    /// The prover absorbs it into the transcript before any commitment and the verifier
+    /// recomputes it from its own constraint system, so a proof only verifies against
+    /// the constraints it was generated for. The encoding covers the kind, name,
+    /// variables, degree, row and expression, if any, of every constraint in order, the
+    /// private columns, the auxiliary columns and challenges, the name and values of
+    /// every periodic column, and the name, column and row of every public cell.
+    /// Evaluators given as closures cannot be encoded, so it also covers their values on
+    /// a pseudo-random row pair derived from the column names: distinct low-degree
+    /// constraints agree there with negligible probability.
+    pub fn digest(&self, columns: &[ProgramVariable]) -> [u8; 32] {
+        fn push_str(bytes: &mut Vec<u8>, value: &str) {
+            bytes.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(value.as_bytes());
+        }
+        fn push_variables(bytes: &mut Vec<u8>, variables: &[ProgramVariable]) {
+            bytes.extend_from_slice(&(variables.len() as u64).to_le_bytes());
+            for variable in variables {
+                push_str(bytes, variable);
+            }
+        }
+        fn push_position(bytes: &mut Vec<u8>, position: &BoundaryPosition) {
+            let (tag, row) = position.encode();
+            bytes.push(tag);
+            bytes.extend_from_slice(&row.to_le_bytes());
+        }
+        fn push_expression<F: FftField>(bytes: &mut Vec<u8>, expression: &Option<Expr<F>>) {
+            match expression {
+                Some(expression) => {
+                    let encoded = expression.to_bytes();
+                    bytes.push(1);
+                    bytes.extend_from_slice(&(encoded.len() as u64).to_le_bytes());
+                    bytes.extend_from_slice(&encoded);
+                }
+                None => bytes.push(0),
+            }
+        }
+        let mut bytes = b"toyni-constraints".to_vec();
+        bytes.extend_from_slice(&(self.transition_constraints.len() as u64).to_le_bytes());
+        for constraint in &self.transition_constraints {
+            push_str(&mut bytes, &constraint.name);
+            push_variables(&mut bytes, &constraint.variables);
+            bytes.extend_from_slice(&(constraint.degree as u64).to_le_bytes());
+            push_expression(&mut bytes, &constraint.expression);
+        }
+        bytes.extend_from_slice(&(self.boundary_constraints.len() as u64).to_le_bytes());
+        for constraint in &self.boundary_constraints {
+            push_str(&mut bytes, &constraint.name);
+            push_variables(&mut bytes, &constraint.variables);
+            push_position(&mut bytes, &constraint.position);
+            push_expression(&mut bytes, &constraint.expression);
+        }
+        push_variables(&mut bytes, &self.private_columns);
+        push_variables(&mut bytes, &self.auxiliary_columns);
+        push_variables(&mut bytes, &self.challenges);
+        bytes.extend_from_slice(&(self.periodic_columns.len() as u64).to_le_bytes());
+        for column in &self.periodic_columns {
+            push_str(&mut bytes, &column.name);
+            bytes.extend_from_slice(&(column.period() as u64).to_le_bytes());
+            for value in &column.values {
+                bytes.extend_from_slice(&to_bytes(value));
+            }
+        }
+        bytes.extend_from_slice(&(self.public_cells.len() as u64).to_le_bytes());
+        for cell in &self.public_cells {
+            push_str(&mut bytes, &cell.name);
+            push_str(&mut bytes, &cell.variable);
+            bytes.extend_from_slice(&cell.row.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(self.window_constraints.len() as u64).to_le_bytes());
+        for constraint in &self.window_constraints {
+            push_str(&mut bytes, &constraint.name);
+            push_variables(&mut bytes, &constraint.variables);
+            bytes.extend_from_slice(&(constraint.rows as u64).to_le_bytes());
+            bytes.extend_from_slice(&(constraint.degree as u64).to_le_bytes());
+            push_expression(&mut bytes, &constraint.expression);
+        }
+        for value in self.evaluate_rows(&self.sample_rows(columns, "")) {
+            bytes.extend_from_slice(&to_bytes(&value));
+        }
+        digest_sha2(&bytes)
+    }
+
+    /// Largest degree of any constraint in the trace columns.
+    ///
+    /// Boundary constraints are linear in the row, so this is at least 1.
+    pub fn max_degree(&self) -> usize {
+        self.transition_constraints
+            .iter()
+            .map(|constraint| constraint.degree)
+            .chain(
+                self.window_constraints
+                    .iter()
+                    .map(|constraint| constraint.degree),
+            )
+            .fold(1, usize::max)
     }
 
-    /// Checks if all constraints are satisfied.
-    pub fn is_satisfied(&self, trace: &ExecutionTrace) -> bool {
-        self.evaluate(trace).iter().all(|&x| x == Fr::ZERO)
+    /// Smallest blowup factor the constraints can be proved with, the largest
+    /// constraint degree rounded up to a power of two.
+    pub fn required_blowup(&self) -> usize {
+        self.max_degree().next_power_of_two()
     }
 
-    /// Interpolates transition constraint as polynomial.
-    pub fn interpolate_transition_constraint(
+    /// Checks that the low-degree extension is large enough for every constraint.
+    ///
+    /// A constraint of degree `d` has degree `d * (n - 1)` on the extended trace, which
+    /// is only determined by its evaluations if `d` is at most the blowup factor.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InsufficientBlowup` naming the constraint of highest degree
+    /// and the blowup factor it requires
+    pub fn validate_blowup(&self, blowup_factor: usize) -> Result<(), ToyniError> {
+        let highest = self
+            .transition_constraints
+            .iter()
+            .map(|constraint| (&constraint.name, constraint.degree))
+            .chain(
+                self.window_constraints
+                    .iter()
+                    .map(|constraint| (&constraint.name, constraint.degree)),
+            )
+            .max_by_key(|(_, degree)| *degree);
+        match highest {
+            Some((name, degree)) if degree > blowup_factor => Err(ToyniError::InsufficientBlowup {
+                constraint: name.clone(),
+                degree,
+                required: self.required_blowup(),
+                blowup: blowup_factor,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the composition polynomial over a trace domain of `domain_size` rows,
+    /// `trace_len` of them trace rows, is determined by its values on the extended
+    /// domain.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::CompositionDegreeTooHigh` if the composition degree is not
+    /// below the size of the extended domain
+    pub fn validate_composition_degree(
         &self,
-        trace: &ExecutionTrace,
-        constraint: &TransitionConstraint,
-    ) -> ToyniPolynomial {
-        let domain = GeneralEvaluationDomain::<Fr>::new(trace.height as usize)
-            .expect("Trace height must be a power of 2");
+        domain_size: usize,
+        trace_len: usize,
+        blowup_factor: usize,
+    ) -> Result<(), ToyniError> {
+        let degree = self.composition_degree(domain_size, trace_len);
+        let extended_size = domain_size * blowup_factor;
+        if degree >= extended_size {
+            return Err(ToyniError::CompositionDegreeTooHigh {
+                degree,
+                extended_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that no constraint has a higher degree than it was added with.
+    ///
+    /// Degrees of expression constraints are computed, but those of closures are
+    /// declared by the caller, and a closure of higher degree than declared yields a
+    /// composition polynomial beyond the degree bound FRI checks. Every closure is
+    /// evaluated along a pseudo-random line through the trace and auxiliary columns,
+    /// derived from `columns` as in `digest`, where it is a polynomial of its true
+    /// degree; its finite difference of one order above the declared degree vanishes
+    /// only if that degree is not exceeded, up to negligible probability.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::UndeclaredConstraintDegree` for the first constraint of
+    /// higher degree than declared, where boundary constraints are declared linear
+    pub fn validate_declared_degrees(&self, columns: &[ProgramVariable]) -> Result<(), ToyniError> {
+        let base = self.sample_rows(columns, "base-");
+        let step = self.sample_rows(columns, "step-");
+        // Challenges, periodic columns and public values are constants to the constraints
+        let varying: Vec<&ProgramVariable> =
+            columns.iter().chain(&self.auxiliary_columns).collect();
+        let point = |row: usize, t: F| -> HashMap<ProgramVariable, F> {
+            let mut values = base[row].clone();
+            for &column in &varying {
+                values.insert(column.clone(), base[row][column] + t * step[row][column]);
+            }
+            values
+        };
+        let exceeds = |declared: usize, evaluate: &dyn Fn(F) -> F| {
+            // The (d + 1)-th forward difference of a polynomial of degree at most d is zero
+            let mut differences: Vec<F> = (0..=declared as u64 + 1)
+                .map(|t| evaluate(F::from(t)))
+                .collect();
+            for order in 1..differences.len() {
+                for i in 0..differences.len() - order {
+                    differences[i] = differences[i + 1] - differences[i];
+                }
+            }
+            !differences[0].is_zero()
+        };
 
-        let mut evaluations = vec![Fr::zero(); trace.height as usize];
-        for i in 0..trace.height - 1 {
-            let current_row = trace.get_column(i);
-            let next_row = trace.get_column(i + 1);
-            evaluations[i as usize] = (constraint.evaluate)(current_row, next_row);
+        let transitions = self
+            .transition_constraints
+            .iter()
+            .filter(|constraint| constraint.expression.is_none())
+            .map(|constraint| {
+                let evaluate = |t: F| (constraint.evaluate)(&point(0, t), &point(1, t));
+                (
+                    &constraint.name,
+                    constraint.degree,
+                    exceeds(constraint.degree, &evaluate),
+                )
+            });
+        let windows = self
+            .window_constraints
+            .iter()
+            .filter(|constraint| constraint.expression.is_none())
+            .map(|constraint| {
+                let evaluate = |t: F| {
+                    let rows: Vec<_> = (0..constraint.rows).map(|row| point(row, t)).collect();
+                    (constraint.evaluate)(&rows)
+                };
+                (
+                    &constraint.name,
+                    constraint.degree,
+                    exceeds(constraint.degree, &evaluate),
+                )
+            });
+        let boundaries = self
+            .boundary_constraints
+            .iter()
+            .filter(|constraint| constraint.expression.is_none())
+            .map(|constraint| {
+                let evaluate = |t: F| (constraint.evaluate)(&point(0, t));
+                (&constraint.name, 1, exceeds(1, &evaluate))
+            });
+        match transitions
+            .chain(windows)
+            .chain(boundaries)
+            .find(|(_, _, exceeds)| *exceeds)
+        {
+            Some((name, declared, _)) => Err(ToyniError::UndeclaredConstraintDegree {
+                constraint: name.clone(),
+                declared,
+            }),
+            None => Ok(()),
         }
+    }
 
-        let evals = Evaluations::from_vec_and_domain(evaluations, domain);
-        ToyniPolynomial::from_dense_poly(evals.interpolate())
+    /// Degree bound of every constraint quotient `Cᵢ / Zᵢ`, in the order of
+    /// `interpolate_all_constraints`.
+    ///
+    /// Trace columns over a domain of `n` rows have degree below `n`, and the transition
+    /// zerofier for `m = trace_len` rows has degree `m - 1`, so a transition constraint
+    /// of degree `d` has a quotient of degree at most `d * (n - 1) - (m - 1)`, and one
+    /// over windows of `k` rows `d * (n - 1) - (m - k + 1)`. Boundary constraints are
+    /// linear in the row, so their quotients have degree at most `n - 2`.
+    pub fn quotient_degrees(&self, domain_size: usize, trace_len: usize) -> Vec<usize> {
+        let transitions = self.transition_constraints.iter().map(|constraint| {
+            (constraint.degree * (domain_size - 1)).saturating_sub(trace_len - 1)
+        });
+        let windows = self.window_constraints.iter().map(|constraint| {
+            (constraint.degree * (domain_size - 1)).saturating_sub(trace_len + 1 - constraint.rows)
+        });
+        let boundaries = self
+            .boundary_constraints
+            .iter()
+            .map(|_| domain_size.saturating_sub(2));
+        transitions.chain(windows).chain(boundaries).collect()
     }
 
-    /// Interpolates boundary constraint as polynomial.
-    pub fn interpolate_boundary_constraint(
-        &self,
-        trace: &ExecutionTrace,
-        constraint: &BoundaryConstraint,
-    ) -> ToyniPolynomial {
-        let domain = GeneralEvaluationDomain::<Fr>::new(trace.height as usize)
-            .expect("Trace height must be a power of 2");
+    /// Target degree of the composition polynomial, the largest quotient degree and at
+    /// least `n - 1` for a domain of `n` rows.
+    pub fn composition_degree(&self, domain_size: usize, trace_len: usize) -> usize {
+        self.quotient_degrees(domain_size, trace_len)
+            .into_iter()
+            .fold(domain_size - 1, usize::max)
+    }
 
-        let mut evaluations = vec![Fr::zero(); trace.height as usize];
-        evaluations[constraint.row as usize] =
-            (constraint.evaluate)(trace.get_column(constraint.row));
+    /// Degree `dᵢ` by which every constraint quotient is raised to the composition
+    /// degree.
+    pub fn degree_adjustments(&self, domain_size: usize, trace_len: usize) -> Vec<usize> {
+        let target = self.composition_degree(domain_size, trace_len);
+        self.quotient_degrees(domain_size, trace_len)
+            .into_iter()
+            .map(|degree| target - degree)
+            .collect()
+    }
 
-        let evals = Evaluations::from_vec_and_domain(evaluations, domain);
-        ToyniPolynomial::from_dense_poly(evals.interpolate())
+    /// Evaluates the composition `Σ (αᵢ + βᵢ·x^dᵢ)·Cᵢ(x) / Zᵢ(x)` at a point outside the
+    /// trace domain, given the constraint values `Cᵢ(x)` from `evaluate_rows` and the
+    /// number of trace rows before the blinding rows.
+    ///
+    /// The degree-adjustment factors lift every quotient to the composition degree, so
+    /// the composition has a single known degree bound for FRI.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` is a root of one of the zerofiers.
+    pub fn evaluate_composition(
+        &self,
+        constraint_values: &[F],
+        alphas: &[F],
+        betas: &[F],
+        domain: &GeneralEvaluationDomain<F>,
+        trace_len: usize,
+        x: F,
+    ) -> F {
+        let zerofier_invs = self.zerofier_inverses(
+            |i| domain.element(i),
+            domain.size(),
+            trace_len,
+            x,
+            domain.evaluate_vanishing_polynomial(x),
+        );
+        let adjustments = self.degree_adjustments(domain.size(), trace_len);
+        Self::combine_quotients(
+            constraint_values,
+            alphas,
+            betas,
+            &zerofier_invs,
+            &adjustments,
+            x,
+        )
+    }
+
+    /// Combines the constraint values at `x` into the composition, given the inverse of
+    /// every zerofier from `zerofier_inverses` and the `degree_adjustments`.
+    ///
+    /// This is `evaluate_composition` for callers evaluating many points, which compute
+    /// the degree adjustments once and the zerofier inverses from cached domain points.
+    pub fn combine_quotients(
+        constraint_values: &[F],
+        alphas: &[F],
+        betas: &[F],
+        zerofier_invs: &[F],
+        adjustments: &[usize],
+        x: F,
+    ) -> F {
+        constraint_values
+            .iter()
+            .zip(alphas.iter().zip(betas))
+            .zip(zerofier_invs)
+            .zip(adjustments)
+            .map(|(((value, (alpha, beta)), zerofier_inv), adjustment)| {
+                let factor = *alpha + *beta * x.pow([*adjustment as u64]);
+                factor * value * zerofier_inv
+            })
+            .sum()
     }
 
     /// Interpolates all constraints as polynomials.
-    pub fn interpolate_all_constraints(&self, trace: &ExecutionTrace) -> Vec<ToyniPolynomial> {
+    ///
+    /// The trace height must be a power of two; pad other traces first with
+    /// `ExecutionTrace::pad`.
+    pub fn interpolate_all_constraints(
+        &self,
+        trace: &ExecutionTrace<F::BasePrimeField>,
+    ) -> Result<Vec<ToyniPolynomial<F>>, ToyniError> {
         let mut polys = Vec::new();
 
         for constraint in &self.transition_constraints {
-            polys.push(self.interpolate_transition_constraint(trace, constraint));
+            polys.push(self.interpolate_transition_constraint(trace, constraint)?);
+        }
+
+        for constraint in &self.window_constraints {
+            polys.push(self.interpolate_window_constraint(trace, constraint)?);
         }
 
         for constraint in &self.boundary_constraints {
-            polys.push(self.interpolate_boundary_constraint(trace, constraint));
+            polys.push(self.interpolate_boundary_constraint(trace, constraint)?);
+        }
+
+        Ok(polys)
+    }
+}
+
+/// Constraints that only apply at the rows where a selector column is one, see
+/// `ConstraintSystem::add_selector_group`.
+pub struct SelectorGroup<'a, F: FftField = Fr> {
+    /// System the constraints are added to
+    system: &'a mut ConstraintSystem<F>,
+    /// Column selecting the rows the constraints apply at
+    selector: ProgramVariable,
+}
+
+impl<F: FftField> SelectorGroup<'_, F> {
+    /// Adds linear transition constraint to the group.
+    #[allow(clippy::type_complexity)]
+    pub fn add_transition_constraint(
+        &mut self,
+        name: String,
+        variables: Vec<ProgramVariable>,
+        evaluate: TransitionEvaluator<F>,
+    ) -> &mut Self {
+        self.add_transition_constraint_with_degree(name, variables, 1, evaluate)
+    }
+
+    /// Adds transition constraint of the given degree to the group.
+    ///
+    /// The constraint is multiplied by the selector on the current row, so it is
+    /// registered with one degree more than `degree` and reads the selector besides
+    /// `variables`.
+    #[allow(clippy::type_complexity)]
+    pub fn add_transition_constraint_with_degree(
+        &mut self,
+        name: String,
+        mut variables: Vec<ProgramVariable>,
+        degree: usize,
+        evaluate: TransitionEvaluator<F>,
+    ) -> &mut Self {
+        let selector = self.selector.clone();
+        if !variables.contains(&selector) {
+            variables.push(selector.clone());
         }
+        self.system.add_transition_constraint_with_degree(
+            name,
+            variables,
+            degree + 1,
+            Box::new(move |current, next| current[&selector] * evaluate(current, next)),
+        );
+        self
+    }
+
+    /// Adds transition constraint given as an expression in `variables` to the group.
+    ///
+    /// The expression is multiplied by the selector, which becomes column
+    /// `variables.len()` of the registered expression unless `variables` holds it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InvalidExpression` if the expression reads a column beyond
+    /// `variables` or a row beyond the next
+    pub fn add_transition_expr(
+        &mut self,
+        name: String,
+        mut variables: Vec<ProgramVariable>,
+        expression: Expr<F>,
+    ) -> Result<&mut Self, ToyniError> {
+        check_expression(&expression, &variables, 1)?;
+        let index = match variables
+            .iter()
+            .position(|variable| *variable == self.selector)
+        {
+            Some(index) => index,
+            None => {
+                variables.push(self.selector.clone());
+                variables.len() - 1
+            }
+        };
+        self.system
+            .add_transition_expr(name, variables, Expr::current(index) * expression)?;
+        Ok(self)
+    }
+}
+
+/// Evaluation domain of a complete trace whose height is a power of two.
+fn trace_domain<F: FftField>(
+    trace: &ExecutionTrace<F::BasePrimeField>,
+) -> Result<GeneralEvaluationDomain<F>, ToyniError> {
+    trace.validate()?;
+    let height = trace.height as usize;
+    if !height.is_power_of_two() {
+        return Err(ToyniError::NonPowerOfTwoDomain(height));
+    }
+    GeneralEvaluationDomain::<F>::new(height).ok_or(ToyniError::NonPowerOfTwoDomain(height))
+}
 
-        polys
+/// Fails if `expression` reads a column beyond `variables` or a row beyond `max_offset`,
+/// nests deeper than `MAX_DEPTH` or has a degree beyond the largest evaluation domain.
+fn check_expression<F: FftField>(
+    expression: &Expr<F>,
+    variables: &[ProgramVariable],
+    max_offset: usize,
+) -> Result<(), ToyniError> {
+    if expression.width() > variables.len() {
+        return Err(ToyniError::InvalidExpression(format!(
+            "column {} is read but only {} variables are given",
+            expression.width() - 1,
+            variables.len()
+        )));
     }
+    if expression.max_offset() > max_offset {
+        return Err(ToyniError::InvalidExpression(format!(
+            "row offset {} is read but at most {} is allowed",
+            expression.max_offset(),
+            max_offset
+        )));
+    }
+    if expression.depth() > MAX_DEPTH {
+        return Err(ToyniError::InvalidExpression(format!(
+            "expression nests {} deep but at most {} is allowed",
+            expression.depth(),
+            MAX_DEPTH
+        )));
+    }
+    match expression.checked_degree() {
+        Some(degree) if degree <= max_domain_size::<F>() => Ok(()),
+        _ => Err(ToyniError::InvalidExpression(format!(
+            "degree exceeds {}, the size of the largest evaluation domain",
+            max_domain_size::<F>()
+        ))),
+    }
+}
+
+/// Size of the largest evaluation domain of `F`, which bounds the trace length and the
+/// degree of any constraint the trace can be extended for.
+fn max_domain_size<F: FftField>() -> usize {
+    1usize.checked_shl(F::TWO_ADICITY).unwrap_or(usize::MAX)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::vm::dsl::col;
     use crate::vm::trace::ExecutionTrace;
+    use ark_ff::{Field, Zero};
 
     fn create_test_trace() -> ExecutionTrace {
-        let mut trace = ExecutionTrace::new(3, 2);
-        for i in 0..3 {
-            let mut column = HashMap::new();
-            column.insert("x".to_string(), i);
-            column.insert("y".to_string(), i * 2);
-            trace.insert_column(column);
-        }
-        trace
+        ExecutionTrace::from_fn(["x", "y"], 3, |i| [i, i * 2]).unwrap()
     }
 
     #[test]
@@ -190,14 +2403,14 @@ mod tests {
             "y_equals_2x".to_string(),
             vec!["x".to_string(), "y".to_string()],
             Box::new(|current, _| {
-                let x = Fr::from(*current.get("x").unwrap());
-                let y = Fr::from(*current.get("y").unwrap());
+                let x = *current.get("x").unwrap();
+                let y = *current.get("y").unwrap();
                 y - Fr::from(2u64) * x
             }),
         );
 
         let trace = create_test_trace();
-        assert!(system.is_satisfied(&trace));
+        assert!(system.is_satisfied(&trace).unwrap());
     }
 
     #[test]
@@ -209,11 +2422,11 @@ mod tests {
             "x_starts_at_zero".to_string(),
             0,
             vec!["x".to_string()],
-            Box::new(|row| Fr::from(*row.get("x").unwrap())),
+            Box::new(|row| *row.get("x").unwrap()),
         );
 
         let trace = create_test_trace();
-        assert!(system.is_satisfied(&trace));
+        assert!(system.is_satisfied(&trace).unwrap());
     }
 
     #[test]
@@ -225,21 +2438,17 @@ mod tests {
             "x_increments".to_string(),
             vec!["x".to_string()],
             Box::new(|current, next| {
-                let x_current = Fr::from(*current.get("x").unwrap());
-                let x_next = Fr::from(*next.get("x").unwrap());
+                let x_current = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
                 x_next - (x_current + Fr::from(1u64))
             }),
         );
 
         // Create a trace that doesn't satisfy the constraint
-        let mut trace = ExecutionTrace::new(3, 1);
-        for i in 0..3 {
-            let mut column = HashMap::new();
-            column.insert("x".to_string(), i * 2); // x[n] = 2n instead of n
-            trace.insert_column(column);
-        }
+        // x[n] = 2n instead of n
+        let trace = ExecutionTrace::from_fn(["x"], 3, |i| [i * 2]).unwrap();
 
-        assert!(!system.is_satisfied(&trace));
+        assert!(!system.is_satisfied(&trace).unwrap());
     }
 
     #[test]
@@ -251,8 +2460,8 @@ mod tests {
             "increment".to_string(),
             vec!["x".to_string()],
             Box::new(|current, next| {
-                let x_current = Fr::from(*current.get("x").unwrap());
-                let x_next = Fr::from(*next.get("x").unwrap());
+                let x_current = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
                 x_next - (x_current + Fr::from(1u64))
             }),
         );
@@ -262,24 +2471,19 @@ mod tests {
             "start".to_string(),
             0,
             vec!["x".to_string()],
-            Box::new(|row| Fr::from(*row.get("x").unwrap())),
+            Box::new(|row| *row.get("x").unwrap()),
         );
 
         // Create a trace that satisfies the constraints
-        let mut trace = ExecutionTrace::new(4, 1);
-        for i in 0..4 {
-            let mut column = HashMap::new();
-            column.insert("x".to_string(), i);
-            trace.insert_column(column);
-        }
+        let trace = ExecutionTrace::from_fn(["x"], 4, |i| [i]).unwrap();
 
         // Interpolate all constraints
-        let polynomials = system.interpolate_all_constraints(&trace);
+        let polynomials = system.interpolate_all_constraints(&trace).unwrap();
 
         // Verify that the interpolated polynomials evaluate to zero at the trace points
+        let points: Vec<Fr> = (0..trace.height).map(Fr::from).collect();
         for (i, poly) in polynomials.iter().enumerate() {
-            for j in 0..trace.height {
-                let eval = poly.evaluate(Fr::from(j));
+            for (j, eval) in poly.evaluate_batch(&points).into_iter().enumerate() {
                 assert!(
                     eval.is_zero(),
                     "Polynomial {} should evaluate to zero at x={}",
@@ -289,4 +2493,761 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_selective_zerofiers() {
+        let mut system = ConstraintSystem::default();
+        system.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_current = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - (x_current + Fr::from(1u64))
+            }),
+        );
+        system.add_boundary_constraint(
+            "start".to_string(),
+            2,
+            vec!["x".to_string()],
+            Box::new(|row| *row.get("x").unwrap()),
+        );
+
+        let domain = GeneralEvaluationDomain::<Fr>::new(4).unwrap();
+        // The transition zerofier vanishes on every row but the last
+        let x = Fr::from(7u64);
+        let zerofiers = system.evaluate_zerofiers(&domain, 4, x);
+        let expected: Fr = (0..3).map(|i| x - domain.element(i)).product();
+        assert_eq!(zerofiers[0], expected);
+        assert_eq!(zerofiers[1], x - domain.element(2));
+
+        // With two blinding rows the transitions only hold from row 0 to row 1
+        let zerofiers = system.evaluate_zerofiers(&domain, 2, x);
+        assert_eq!(zerofiers[0], x - domain.element(0));
+        assert_eq!(system.quotient_degrees(4, 2), vec![2, 2]);
+
+        // The inverses agree with the zerofiers, whichever way the roots are looked up
+        let points: Vec<Fr> = domain.elements().collect();
+        let vanishing = domain.evaluate_vanishing_polynomial(x);
+        for trace_len in [2, 4] {
+            let inverses = system.zerofier_inverses(|i| points[i], 4, trace_len, x, vanishing);
+            let zerofiers = system.evaluate_zerofiers(&domain, trace_len, x);
+            for (inverse, zerofier) in inverses.iter().zip(&zerofiers) {
+                assert_eq!(*inverse * zerofier, Fr::ONE);
+            }
+        }
+    }
+
+    #[test]
+    fn test_degree_adjusted_composition() {
+        let mut system = ConstraintSystem::default();
+        system.add_transition_constraint_with_degree(
+            "square".to_string(),
+            vec!["x".to_string()],
+            2,
+            Box::new(|_, _| Fr::zero()),
+        );
+        system.add_boundary_constraint(
+            "start".to_string(),
+            1,
+            vec!["x".to_string()],
+            Box::new(|_| Fr::zero()),
+        );
+        assert_eq!(system.degree_adjustments(8, 8), vec![0, 1]);
+
+        // Constraints with maximal quotients: Z_T(x) * p(x), deg p = 7, and
+        // (x - g) * q(x), deg q = 6
+        let domain = GeneralEvaluationDomain::<Fr>::new(8).unwrap();
+        let transition_quotient = ToyniPolynomial::new((1..=8).map(Fr::from).collect::<Vec<_>>());
+        let transition = (0..7).fold(transition_quotient, |acc, i| {
+            acc * ToyniPolynomial::new(vec![-domain.element(i), Fr::ONE])
+        });
+        let boundary_quotient = ToyniPolynomial::new((1..=7).map(Fr::from).collect::<Vec<_>>());
+        let boundary = boundary_quotient * ToyniPolynomial::new(vec![-domain.element(1), Fr::ONE]);
+
+        // Both adjusted quotients reach the composition degree of 7
+        let coset = GeneralEvaluationDomain::<Fr>::new(16)
+            .unwrap()
+            .get_coset(Fr::from(7u64))
+            .unwrap();
+        let transition_evals = transition.evaluate_over_domain(&coset);
+        let boundary_evals = boundary.evaluate_over_domain(&coset);
+        let evals: Vec<Fr> = coset
+            .elements()
+            .enumerate()
+            .map(|(i, x)| {
+                system.evaluate_composition(
+                    &[transition_evals[i], boundary_evals[i]],
+                    &[Fr::from(2u64), Fr::from(3u64)],
+                    &[Fr::from(4u64), Fr::from(5u64)],
+                    &domain,
+                    8,
+                    x,
+                )
+            })
+            .collect();
+        let composition = ToyniPolynomial::new(coset.ifft(&evals));
+        assert_eq!(composition.degree(), system.composition_degree(8, 8));
+    }
+
+    #[test]
+    fn test_random_combination() {
+        let mut system = ConstraintSystem::default();
+        for name in ["a", "b"] {
+            system.add_transition_constraint(
+                name.to_string(),
+                vec!["x".to_string()],
+                Box::new(|_, _| Fr::zero()),
+            );
+        }
+        let domain = GeneralEvaluationDomain::<Fr>::new(4).unwrap();
+        let x = Fr::from(7u64);
+        let values = [Fr::from(3u64), -Fr::from(3u64)];
+        let betas = [Fr::zero(); 2];
+
+        // A plain sum cancels the two violations, a random combination keeps them
+        let sum = system.evaluate_composition(&values, &[Fr::ONE; 2], &betas, &domain, 4, x);
+        assert!(sum.is_zero());
+        let alphas = [Fr::from(3u64), Fr::from(5u64)];
+        let combined = system.evaluate_composition(&values, &alphas, &betas, &domain, 4, x);
+        assert!(!combined.is_zero());
+    }
+
+    #[test]
+    fn test_boundary_assertion_quotient() {
+        let mut system = ConstraintSystem::default();
+        system.add_boundary_assertion("start".to_string(), "x".to_string(), 0, Fr::from(5u64));
+        system.add_boundary_assertion("end".to_string(), "x".to_string(), 3, Fr::from(9u64));
+
+        let trace = ExecutionTrace::from_fn(["x"], 4, |i| [5 + i]).unwrap();
+        assert!(!system.is_satisfied(&trace).unwrap());
+
+        // x = 5 + i holds at row 0, so (T(x) - 5) / (x - 1) is a polynomial
+        let start = &system.boundary_constraints[0];
+        let quotient = system.interpolate_boundary_quotient(&trace, start).unwrap();
+        let domain = GeneralEvaluationDomain::<Fr>::new(4).unwrap();
+        let x = Fr::from(11u64);
+        let column = ToyniPolynomial::from_dense_poly(
+            Evaluations::from_vec_and_domain((5..9u64).map(Fr::from).collect::<Vec<_>>(), domain)
+                .interpolate(),
+        );
+        assert_eq!(
+            quotient.evaluate(x) * (x - Fr::ONE),
+            column.evaluate(x) - Fr::from(5u64)
+        );
+
+        // x[3] = 8, so the assertion x[3] = 9 leaves a remainder
+        let end = &system.boundary_constraints[1];
+        assert_eq!(
+            system.interpolate_boundary_quotient(&trace, end).err(),
+            Some(ToyniError::UnsatisfiedConstraint("end".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_assertions() {
+        let mut system = ConstraintSystem::default();
+        system.assert_first("x", Fr::from(5u64));
+        system.assert_eq(2, "x", Fr::from(7u64));
+        system.assert_last("x", Fr::from(8u64));
+        let rows: Vec<(&str, u64)> = system
+            .boundary_constraints
+            .iter()
+            .map(|constraint| (constraint.name.as_str(), constraint.row(4)))
+            .collect();
+        assert_eq!(rows, [("x[first]", 0), ("x[2]", 2), ("x[last]", 3)]);
+
+        let trace = ExecutionTrace::from_fn(["x"], 4, |i| [5 + i]).unwrap();
+        assert!(system.is_satisfied(&trace).unwrap());
+        let quotient =
+            system.interpolate_boundary_quotient(&trace, &system.boundary_constraints[2]);
+        assert!(quotient.is_ok());
+
+        // The asserted value is part of the digest
+        let mut other = ConstraintSystem::default();
+        other.assert_first("x", Fr::from(5u64));
+        other.assert_eq(2, "x", Fr::from(7u64));
+        other.assert_last("x", Fr::from(9u64));
+        assert!(!other.is_satisfied(&trace).unwrap());
+        let columns = trace.variables();
+        assert_ne!(other.digest(&columns), system.digest(&columns));
+    }
+
+    #[test]
+    fn test_empty_trace() {
+        let mut system = ConstraintSystem::default();
+        let x = crate::vm::dsl::col("x");
+        system.transition(x.next() - x.cur() - 1);
+        let trace = ExecutionTrace::<Fr>::new(0, 1);
+        assert_eq!(system.evaluate(&trace), Ok(vec![]));
+        assert!(system.is_satisfied(&trace).unwrap());
+        assert_eq!(
+            system
+                .interpolate_transition_constraint(&trace, &system.transition_constraints[0])
+                .err(),
+            Some(ToyniError::NonPowerOfTwoDomain(0))
+        );
+
+        // An empty trace has no row for a boundary constraint
+        system.assert_first("x", Fr::from(0u64));
+        assert!(matches!(
+            system.evaluate(&trace),
+            Err(ToyniError::InvalidOptions(_))
+        ));
+        assert!(matches!(
+            system.check(&trace),
+            Err(ToyniError::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn test_constraint_system_is_thread_safe() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ConstraintSystem>();
+        assert_send_sync::<ConstraintSystem<crate::math::fr_ext2::FrExt2>>();
+
+        // Closures capturing shared state stay shareable
+        let offset = std::sync::Arc::new(Fr::ONE);
+        let mut system = ConstraintSystem::default();
+        system.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(move |current, next| next["x"] - current["x"] - *offset),
+        );
+        let trace = ExecutionTrace::from_fn(["x"], 4, |i| [i]).unwrap();
+        let satisfied = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..2)
+                .map(|_| scope.spawn(|| system.is_satisfied(&trace).unwrap()))
+                .collect();
+            handles.into_iter().all(|handle| handle.join().unwrap())
+        });
+        assert!(satisfied);
+    }
+
+    #[test]
+    fn test_dsl_constraints() {
+        let (x, y) = (col("x"), col("y"));
+        let mut system = ConstraintSystem::<Fr>::default();
+        system
+            .transition(x.next() - x.cur() - 1)
+            .transition(y.at(2) - y.next() - y.cur());
+        system
+            .boundary(BoundaryPosition::First, x.cur() - 1)
+            .unwrap();
+        assert!(system.boundary(BoundaryPosition::Last, x.next()).is_err());
+        assert!(
+            system
+                .boundary(BoundaryPosition::Last, x.cur() * y.cur())
+                .is_err()
+        );
+        assert_eq!(system.transition_constraints.len(), 1);
+        assert_eq!(system.window_constraints[0].rows, 3);
+        assert_eq!(system.window_constraints[0].name, "transition/1");
+        assert_eq!(system.boundary_constraints[0].name, "boundary/0");
+
+        // x counts up from 1, y is Fibonacci
+        let mut y_values = vec![1u64, 1];
+        while y_values.len() < 8 {
+            y_values.push(y_values[y_values.len() - 1] + y_values[y_values.len() - 2]);
+        }
+        let trace =
+            ExecutionTrace::from_fn(["x", "y"], 8, |i| [i + 1, y_values[i as usize]]).unwrap();
+        assert!(system.is_satisfied(&trace).unwrap());
+        assert!(system.to_bytes().is_ok());
+    }
+
+    #[test]
+    fn test_boundary_positions() {
+        let positions = [
+            BoundaryPosition::First,
+            BoundaryPosition::Last,
+            BoundaryPosition::Absolute(2),
+            BoundaryPosition::FromEnd(1),
+        ];
+        let rows: Vec<u64> = positions.iter().map(|position| position.row(8)).collect();
+        assert_eq!(rows, [0, 7, 2, 6]);
+        assert_eq!(BoundaryPosition::from(3), BoundaryPosition::Absolute(3));
+        assert_eq!(BoundaryPosition::FromEnd(2).to_string(), "last-2");
+        for position in positions {
+            let (tag, row) = position.encode();
+            assert_eq!(BoundaryPosition::decode(tag, row), Some(position));
+        }
+
+        let mut system = ConstraintSystem::default();
+        system.assert_at(BoundaryPosition::FromEnd(1), "x", Fr::from(6u64));
+        system.assert_last("x", Fr::from(7u64));
+        // Resolved against the trace length, so the same system holds for 4 and 8 rows
+        for len in [4, 8] {
+            let trace = ExecutionTrace::from_fn(["x"], len, |i| [i + 8 - len]).unwrap();
+            assert!(system.is_satisfied(&trace).unwrap());
+        }
+        assert!(system.validate_boundaries(2).is_ok());
+        assert!(system.validate_boundaries(1).is_err());
+
+        // Relative and absolute positions of the same row are different statements
+        let mut absolute = ConstraintSystem::default();
+        absolute.assert_eq(2, "x", Fr::from(6u64));
+        absolute.assert_eq(3, "x", Fr::from(7u64));
+        let columns = vec!["x".to_string()];
+        assert_ne!(absolute.digest(&columns), system.digest(&columns));
+    }
+
+    #[test]
+    fn test_constraint_digest() {
+        let build = |name: &str, start: u64, degree: usize| {
+            let mut system = ConstraintSystem::default();
+            system.add_transition_constraint_with_degree(
+                name.to_string(),
+                vec!["x".to_string()],
+                degree,
+                Box::new(|current, next| next["x"] - current["x"] - Fr::ONE),
+            );
+            system.add_boundary_assertion("start".to_string(), "x".to_string(), 0, Fr::from(start));
+            system
+        };
+        let columns = vec!["x".to_string()];
+        let digest = build("increment", 0, 1).digest(&columns);
+        assert_eq!(build("increment", 0, 1).digest(&columns), digest);
+
+        // Names, degrees and the values captured by evaluators all change the digest
+        assert_ne!(build("step", 0, 1).digest(&columns), digest);
+        assert_ne!(build("increment", 0, 2).digest(&columns), digest);
+        assert_ne!(build("increment", 1, 1).digest(&columns), digest);
+
+        // Columns no constraint reads do not
+        let wider = vec!["x".to_string(), "y".to_string()];
+        assert_eq!(build("increment", 0, 1).digest(&wider), digest);
+    }
+
+    #[test]
+    fn test_expression_constraints() {
+        // y' = x² + y and x starts at 2, over the columns [x, y]
+        let variables = vec!["x".to_string(), "y".to_string()];
+        let mut system = ConstraintSystem::default();
+        let step = Expr::next(1) - (Expr::current(0).pow(2) + Expr::current(1));
+        system
+            .add_transition_expr("step".to_string(), variables.clone(), step.clone())
+            .unwrap();
+        let start = Expr::current(0) - Expr::Const(Fr::from(2u64));
+        system
+            .add_boundary_expr("start".to_string(), 0, variables.clone(), start)
+            .unwrap();
+        assert_eq!(system.transition_constraints[0].degree, 2);
+        assert_eq!(system.transition_constraints[0].expression, Some(step));
+        assert_eq!(system.max_degree(), 2);
+
+        let trace =
+            ExecutionTrace::from_columns([("x", vec![2u64, 2, 2, 2]), ("y", vec![0u64, 4, 8, 12])])
+                .unwrap();
+        assert!(system.is_satisfied(&trace).unwrap());
+        let wrong =
+            ExecutionTrace::from_columns([("x", vec![2u64, 2, 2, 2]), ("y", vec![0u64, 4, 9, 12])])
+                .unwrap();
+        assert!(!system.is_satisfied(&wrong).unwrap());
+
+        // Expressions reading columns or rows they are not given are rejected
+        assert!(matches!(
+            system.add_transition_expr("wide".to_string(), variables.clone(), Expr::current(2)),
+            Err(ToyniError::InvalidExpression(_))
+        ));
+        assert!(matches!(
+            system.add_transition_expr("far".to_string(), variables.clone(), Expr::Column(0, 2)),
+            Err(ToyniError::InvalidExpression(_))
+        ));
+        assert!(
+            system
+                .add_boundary_expr("next".to_string(), 0, variables.clone(), Expr::next(0))
+                .is_err()
+        );
+        assert!(
+            system
+                .add_boundary_expr(
+                    "square".to_string(),
+                    0,
+                    variables.clone(),
+                    Expr::current(0).pow(2)
+                )
+                .is_err()
+        );
+
+        // So are expressions whose degree overflows or exceeds any domain of the field
+        let square = Expr::current(0) * Expr::current(0);
+        for exponent in [u64::MAX, 1 << 32] {
+            assert!(matches!(
+                system.add_transition_expr(
+                    "overflow".to_string(),
+                    variables.clone(),
+                    square.clone().pow(exponent)
+                ),
+                Err(ToyniError::InvalidExpression(_))
+            ));
+        }
+        assert_eq!(system.num_constraints(), 2);
+    }
+
+    #[test]
+    fn test_window_constraints() {
+        // x'' = x' + x over windows of three rows
+        let mut system = ConstraintSystem::default();
+        system.add_window_constraint(
+            "fibonacci".to_string(),
+            vec!["x".to_string()],
+            3,
+            1,
+            Box::new(|rows| rows[2]["x"] - rows[1]["x"] - rows[0]["x"]),
+        );
+        assert_eq!(system.window(), 3);
+        assert_eq!(system.num_constraints(), 1);
+
+        let trace = ExecutionTrace::from_columns([("x", vec![1u64, 1, 2, 3])]).unwrap();
+        assert!(system.is_satisfied(&trace).unwrap());
+        // The windows starting at the last two rows would wrap around and are not checked
+        assert_eq!(system.evaluate(&trace).unwrap().len(), 2);
+        let wrong = ExecutionTrace::from_columns([("x", vec![1u64, 1, 2, 4])]).unwrap();
+        let violations = system.check(&wrong).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!((violations[0].row, violations[0].value), (1, Fr::ONE));
+
+        // The zerofier leaves out the rows no whole window starts at
+        let domain = GeneralEvaluationDomain::<Fr>::new(4).unwrap();
+        let x = Fr::from(7u64);
+        let zerofiers = system.evaluate_zerofiers(&domain, 4, x);
+        assert_eq!(
+            zerofiers[0],
+            (x - domain.element(0)) * (x - domain.element(1))
+        );
+        assert_eq!(system.quotient_degrees(4, 4), vec![1]);
+        let points: Vec<Fr> = domain.elements().collect();
+        let vanishing = domain.evaluate_vanishing_polynomial(x);
+        for trace_len in [3, 4] {
+            let inverses = system.zerofier_inverses(|i| points[i], 4, trace_len, x, vanishing);
+            let zerofiers = system.evaluate_zerofiers(&domain, trace_len, x);
+            assert_eq!(inverses[0] * zerofiers[0], Fr::ONE);
+        }
+
+        // Expressions span one row more than their largest offset
+        system
+            .add_window_expr(
+                "skip".to_string(),
+                vec!["x".to_string()],
+                Expr::Column(0, 3) - Expr::Column(0, 2) - Expr::Column(0, 1),
+            )
+            .unwrap();
+        assert_eq!(system.window(), 4);
+        assert!(system.is_satisfied(&trace).unwrap());
+        assert!(system.validate_windows(4).is_ok());
+        assert!(system.validate_windows(2).is_err());
+
+        // A window longer than the largest domain of the field is rejected
+        for offset in [usize::MAX, 1 << 32] {
+            assert!(matches!(
+                system.add_window_expr(
+                    "far".to_string(),
+                    vec!["x".to_string()],
+                    Expr::Column(0, offset)
+                ),
+                Err(ToyniError::InvalidExpression(_))
+            ));
+        }
+        assert_eq!(system.window(), 4);
+    }
+
+    #[test]
+    fn test_periodic_constraints() {
+        // x counts up and resets to zero after every fourth row
+        let mut system = ConstraintSystem::default();
+        system
+            .add_periodic_constraint(
+                "reset".to_string(),
+                vec!["x".to_string()],
+                4,
+                3,
+                1,
+                Box::new(|_, next| next["x"]),
+            )
+            .unwrap();
+        assert_eq!(system.transition_constraints[0].degree, 2);
+        assert_eq!(system.periodic_columns[0].name, "selector/reset");
+        assert_eq!(
+            (0..8)
+                .map(|row| system.periodic_columns[0].value(row))
+                .collect::<Vec<_>>(),
+            [0u64, 0, 0, 1, 0, 0, 0, 1].map(Fr::from)
+        );
+
+        let trace = ExecutionTrace::from_columns([("x", vec![0u64, 1, 2, 3, 0, 1, 2, 3])]).unwrap();
+        assert!(system.is_satisfied(&trace).unwrap());
+        // Rows off the period may hold anything, a selected one may not
+        let wrong = ExecutionTrace::from_columns([("x", vec![0u64, 5, 2, 3, 1, 1, 2, 3])]).unwrap();
+        let violations = system.check(&wrong).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].row, 3);
+
+        assert!(matches!(
+            system.add_periodic_constraint(
+                "odd".to_string(),
+                vec!["x".to_string()],
+                3,
+                0,
+                1,
+                Box::new(|_, next| next["x"]),
+            ),
+            Err(ToyniError::NonPowerOfTwoDomain(3))
+        ));
+    }
+
+    #[test]
+    fn test_selector_groups() {
+        // Rows with `inc` set increment x, rows with `dbl` set double it
+        let mut system = ConstraintSystem::default();
+        system
+            .add_selector_group("inc".to_string())
+            .add_transition_constraint(
+                "increment".to_string(),
+                vec!["x".to_string()],
+                Box::new(|current, next| next["x"] - current["x"] - Fr::ONE),
+            );
+        system
+            .add_selector_group("dbl".to_string())
+            .add_transition_constraint(
+                "double".to_string(),
+                vec!["x".to_string()],
+                Box::new(|current, next| next["x"] - current["x"] - current["x"]),
+            );
+        // A second group of the same selector shares its booleanity constraint
+        system.add_selector_group("inc".to_string());
+        assert_eq!(system.num_constraints(), 4);
+        assert_eq!(system.max_degree(), 2);
+        assert_eq!(system.transition_constraints[0].variables, ["x", "inc"]);
+
+        let trace = |x: Vec<u64>, inc: Vec<u64>| {
+            let dbl = inc.iter().map(|inc| 1 - inc.min(&1)).collect();
+            ExecutionTrace::from_columns([("x", x), ("inc", inc), ("dbl", dbl)]).unwrap()
+        };
+        assert!(
+            system
+                .is_satisfied(&trace(vec![1, 2, 4, 5], vec![1, 0, 1, 0]))
+                .unwrap()
+        );
+        assert!(
+            !system
+                .is_satisfied(&trace(vec![1, 2, 3, 5], vec![1, 0, 1, 0]))
+                .unwrap()
+        );
+
+        // The booleanity constraint holds on every row, including the last
+        let violations = system
+            .check(&trace(vec![1, 2, 4, 5], vec![1, 0, 1, 2]))
+            .unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            (violations[0].constraint.as_str(), violations[0].row),
+            ("boolean/inc", 3)
+        );
+
+        let domain = GeneralEvaluationDomain::<Fr>::new(8).unwrap();
+        let points: Vec<Fr> = domain.elements().collect();
+        let x = Fr::from(7u64);
+        let vanishing = domain.evaluate_vanishing_polynomial(x);
+        let inverses = system.zerofier_inverses(|i| points[i], 8, 6, x, vanishing);
+        let zerofiers = system.evaluate_zerofiers(&domain, 6, x);
+        for (inverse, zerofier) in inverses.iter().zip(&zerofiers) {
+            assert_eq!(*inverse * zerofier, Fr::ONE);
+        }
+
+        // Expressions in a group read the selector as one more column
+        let mut symbolic = ConstraintSystem::default();
+        symbolic
+            .add_selector_group("inc".to_string())
+            .add_transition_expr(
+                "increment".to_string(),
+                vec!["x".to_string()],
+                Expr::next(0) - Expr::current(0) - Expr::Const(Fr::ONE),
+            )
+            .unwrap();
+        let constraint = &symbolic.transition_constraints[0];
+        assert_eq!(constraint.variables, ["x", "inc"]);
+        assert_eq!(constraint.degree, 2);
+        assert!(symbolic.window_constraints[0].expression.is_some());
+        assert!(
+            symbolic
+                .is_satisfied(&trace(vec![1, 2, 4, 5], vec![1, 0, 1, 0]))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_blowup_validation() {
+        let mut system = ConstraintSystem::default();
+        system.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|_, _| Fr::zero()),
+        );
+        assert_eq!(system.max_degree(), 1);
+        assert_eq!(system.degree_adjustments(8, 8), vec![7]);
+
+        // A cubic constraint does not fit a blowup of 2
+        system.add_transition_constraint_with_degree(
+            "cube".to_string(),
+            vec!["x".to_string(), "y".to_string()],
+            3,
+            Box::new(|current, _| current["y"] - current["x"].pow([3])),
+        );
+        assert_eq!(system.max_degree(), 3);
+        assert!(system.validate_blowup(2).is_err());
+        assert!(system.validate_blowup(4).is_ok());
+        assert_eq!(system.required_blowup(), 4);
+        assert!(system.validate_composition_degree(8, 8, 4).is_ok());
+        assert_eq!(
+            system.validate_composition_degree(8, 8, 1),
+            Err(ToyniError::CompositionDegreeTooHigh {
+                degree: 14,
+                extended_size: 8
+            })
+        );
+    }
+
+    #[test]
+    fn test_private_boundary_constraint() {
+        let mut system = ConstraintSystem::default();
+        system.add_private_boundary_constraint(
+            "halts_at_output".to_string(),
+            "x".to_string(),
+            2,
+            "output".to_string(),
+        );
+        assert_eq!(system.num_constraints(), 2);
+        assert!(system.validate_private_columns(0).is_err());
+        assert!(system.validate_private_columns(4).is_ok());
+
+        let mut trace = create_test_trace();
+        trace.add_constant_column("output".to_string(), 2).unwrap();
+        assert!(system.is_satisfied(&trace).unwrap());
+
+        let mut trace = create_test_trace();
+        trace.add_constant_column("output".to_string(), 3).unwrap();
+        assert!(!system.is_satisfied(&trace).unwrap());
+    }
+
+    #[test]
+    fn test_periodic_column() {
+        let values: Vec<Fr> = [5u64, 7, 11, 13].map(Fr::from).to_vec();
+        let column = PeriodicColumn::new("k".to_string(), values.clone()).unwrap();
+        let domain = GeneralEvaluationDomain::<Fr>::new(16).unwrap();
+        for i in 0..16 {
+            assert_eq!(column.evaluate_at(domain.element(i), 16), values[i % 4]);
+        }
+        let extended = GeneralEvaluationDomain::<Fr>::new(64)
+            .unwrap()
+            .get_coset(Fr::GENERATOR)
+            .unwrap();
+        let cycle = column.evaluate_over(&extended, 16);
+        assert_eq!(cycle.len(), 16);
+        for i in 0..64 {
+            assert_eq!(cycle[i % 16], column.evaluate_at(extended.element(i), 16));
+        }
+
+        let mut system = ConstraintSystem::default();
+        system.add_periodic_column("k".to_string(), values).unwrap();
+        system.add_transition_constraint(
+            "x_is_k".to_string(),
+            vec!["x".to_string(), "k".to_string()],
+            Box::new(|current, _| current["x"] - current["k"]),
+        );
+        assert!(system.validate_periodic_columns(2).is_err());
+        assert!(system.validate_periodic_columns(4).is_ok());
+        let mut trace = ExecutionTrace::new(4, 1);
+        for i in [5u64, 7, 11, 13] {
+            trace
+                .insert_column(HashMap::from([("x".to_string(), i)]))
+                .unwrap();
+        }
+        assert!(system.is_satisfied(&trace).unwrap());
+
+        assert_eq!(
+            system.add_periodic_column("k".to_string(), vec![Fr::ONE; 3]),
+            Err(ToyniError::NonPowerOfTwoDomain(3))
+        );
+    }
+
+    #[test]
+    fn test_check_reports_violations() {
+        let mut system = ConstraintSystem::default();
+        system.add_transition_constraint(
+            "x_increments".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| next["x"] - current["x"] - Fr::ONE),
+        );
+        system.add_boundary_assertion("y_starts_at_one".to_string(), "y".to_string(), 0, Fr::ONE);
+
+        let mut trace = ExecutionTrace::new(3, 2);
+        for (x, y) in [(0u64, 0u64), (1, 2), (3, 4)] {
+            let row = HashMap::from([("x".to_string(), x), ("y".to_string(), y)]);
+            trace.insert_column(row).unwrap();
+        }
+        let violations = system.check(&trace).unwrap();
+        assert_eq!(
+            violations,
+            vec![
+                Violation {
+                    constraint: "x_increments".to_string(),
+                    row: 1,
+                    current: vec![("x".to_string(), Fr::ONE)],
+                    next: vec![("x".to_string(), Fr::from(3u64))],
+                    value: Fr::ONE,
+                },
+                Violation {
+                    constraint: "y_starts_at_one".to_string(),
+                    row: 0,
+                    current: vec![("y".to_string(), Fr::zero())],
+                    next: Vec::new(),
+                    value: -Fr::ONE,
+                },
+            ]
+        );
+        assert_eq!(
+            violations[0].to_string(),
+            "constraint x_increments violated at row 1: evaluates to 1 with x = 1, next x = 3"
+        );
+        assert!(!system.is_satisfied(&trace).unwrap());
+    }
+
+    #[test]
+    fn test_public_cells() {
+        let mut system = ConstraintSystem::default();
+        system.add_public_cell("start".to_string(), "x".to_string(), 0);
+        system.add_public_column("y".to_string(), 2);
+        let names: Vec<&str> = system
+            .public_cells
+            .iter()
+            .map(|cell| cell.name.as_str())
+            .collect();
+        assert_eq!(names, ["start", "y[0]", "y[1]"]);
+        assert_eq!(system.boundary_constraints.len(), 3);
+
+        // Public cells hold on any trace, whose own values are published
+        let trace = create_test_trace();
+        assert!(system.is_satisfied(&trace).unwrap());
+        let columns = vec![
+            vec![Fr::from(4u64), Fr::ONE],
+            vec![Fr::from(6u64), Fr::zero()],
+        ];
+        let values = system
+            .public_values(&["x".to_string(), "y".to_string()], &columns)
+            .unwrap();
+        assert_eq!(values[0], ("start".to_string(), Fr::from(4u64)));
+        assert_eq!(values[2], ("y[1]".to_string(), Fr::zero()));
+        assert_eq!(
+            system.public_values(&["x".to_string()], &columns[..1]),
+            Err(ToyniError::UnknownVariable("y".to_string()))
+        );
+
+        // The declared cells are part of the digest
+        let columns = vec!["x".to_string(), "y".to_string()];
+        let mut moved = ConstraintSystem::default();
+        moved.add_public_cell("start".to_string(), "x".to_string(), 1);
+        moved.add_public_column("y".to_string(), 2);
+        assert_ne!(moved.digest(&columns), system.digest(&columns));
+    }
 }
@@ -0,0 +1,236 @@
+//! Constraint-building frontend over named columns.
+//!
+//! `col("x")` names a trace column, and `cur`, `next` and `at` read it on a row of the
+//! constraint, while `challenge("gamma")` reads a challenge of the auxiliary segment.
+//! The `Term`s they return combine with `+`, `-`, `*`, `pow` and integer literals into
+//! an `Expr` together with the variables it reads, so a constraint is written once
+//! instead of as an expression over indices plus its variable list:
+//!
+//! ```
+//! use toyni::vm::{constraints::ConstraintSystem, dsl::col};
+//!
+//! let x = col("x");
+//! let mut constraints = ConstraintSystem::default();
+//! constraints.transition(x.next() - x.cur() - 1);
+//! ```
+
+use ark_bls12_381::Fr;
+use ark_ff::Field;
+use std::{fmt, ops};
+
+use crate::vm::{expr::Expr, trace::ProgramVariable};
+
+/// The challenge named `name`, see `ConstraintSystem::set_auxiliary_segment`.
+///
+/// A challenge is the same on every row, so it does not raise the degree of a term.
+pub fn challenge<F: Field>(name: impl Into<ProgramVariable>) -> Term<F> {
+    Term {
+        variables: vec![name.into()],
+        expression: Expr::Challenge(0),
+    }
+}
+
+/// A trace column by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Col {
+    name: ProgramVariable,
+}
+
+/// The trace column named `name`.
+pub fn col(name: impl Into<ProgramVariable>) -> Col {
+    Col { name: name.into() }
+}
+
+impl Col {
+    /// Name of the column.
+    pub fn name(&self) -> &ProgramVariable {
+        &self.name
+    }
+
+    /// The column on the current row.
+    pub fn cur<F: Field>(&self) -> Term<F> {
+        self.at(0)
+    }
+
+    /// The column on the next row.
+    pub fn next<F: Field>(&self) -> Term<F> {
+        self.at(1)
+    }
+
+    /// The column `offset` rows after the current one, which makes a constraint reading
+    /// it a window constraint if `offset` exceeds one.
+    pub fn at<F: Field>(&self, offset: usize) -> Term<F> {
+        Term {
+            variables: vec![self.name.clone()],
+            expression: Expr::Column(0, offset),
+        }
+    }
+}
+
+/// An expression in named columns.
+///
+/// `expression` reads `variables[i]` as its column `i`, the form constraints are
+/// registered in. Variables are listed in the order the term first reads them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term<F: Field = Fr> {
+    variables: Vec<ProgramVariable>,
+    expression: Expr<F>,
+}
+
+impl<F: Field> Term<F> {
+    /// A constant term.
+    pub fn constant(value: F) -> Self {
+        Term {
+            variables: Vec::new(),
+            expression: Expr::Const(value),
+        }
+    }
+
+    /// The term raised to the power `exponent`.
+    pub fn pow(self, exponent: u64) -> Self {
+        Term {
+            variables: self.variables,
+            expression: self.expression.pow(exponent),
+        }
+    }
+
+    /// Variables the term reads.
+    pub fn variables(&self) -> &[ProgramVariable] {
+        &self.variables
+    }
+
+    /// Expression of the term over its variables.
+    pub fn expression(&self) -> &Expr<F> {
+        &self.expression
+    }
+
+    /// Splits the term into its variables and its expression over them, the arguments
+    /// of `ConstraintSystem::add_transition_expr` and its siblings.
+    pub fn into_parts(self) -> (Vec<ProgramVariable>, Expr<F>) {
+        (self.variables, self.expression)
+    }
+
+    /// Combines the term with `other`, reading the variables of both.
+    fn combine(self, other: Self, operation: impl Fn(Expr<F>, Expr<F>) -> Expr<F>) -> Self {
+        let mut variables = self.variables;
+        let indices: Vec<usize> = other
+            .variables
+            .into_iter()
+            .map(
+                |variable| match variables.iter().position(|v| *v == variable) {
+                    Some(index) => index,
+                    None => {
+                        variables.push(variable);
+                        variables.len() - 1
+                    }
+                },
+            )
+            .collect();
+        let right = other.expression.map_columns(&|i| indices[i]);
+        Term {
+            variables,
+            expression: operation(self.expression, right),
+        }
+    }
+}
+
+impl<F: Field> fmt::Display for Term<F> {
+    /// Writes columns by name, with a prime per row after the current one.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.expression.write_with(f, &|f, i, offset| {
+            write!(f, "{}{}", self.variables[i], "'".repeat(offset))
+        })
+    }
+}
+
+impl<F: Field> From<F> for Term<F> {
+    fn from(value: F) -> Self {
+        Term::constant(value)
+    }
+}
+
+impl<F: Field> From<&Col> for Term<F> {
+    /// The column on the current row.
+    fn from(column: &Col) -> Self {
+        column.cur()
+    }
+}
+
+impl<F: Field> ops::Neg for Term<F> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Term {
+            variables: self.variables,
+            expression: -self.expression,
+        }
+    }
+}
+
+/// Implements an operator between terms, and between a term and an integer literal on
+/// either side, which becomes a constant of the field.
+macro_rules! term_operator {
+    ($trait:ident, $method:ident) => {
+        impl<F: Field> ops::$trait for Term<F> {
+            type Output = Self;
+
+            fn $method(self, other: Self) -> Self {
+                self.combine(other, |left, right| ops::$trait::$method(left, right))
+            }
+        }
+
+        impl<F: Field> ops::$trait<u64> for Term<F> {
+            type Output = Self;
+
+            fn $method(self, other: u64) -> Self {
+                ops::$trait::$method(self, Term::constant(F::from(other)))
+            }
+        }
+
+        impl<F: Field> ops::$trait<Term<F>> for u64 {
+            type Output = Term<F>;
+
+            fn $method(self, other: Term<F>) -> Term<F> {
+                ops::$trait::$method(Term::constant(F::from(self)), other)
+            }
+        }
+    };
+}
+
+term_operator!(Add, add);
+term_operator!(Sub, sub);
+term_operator!(Mul, mul);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::Field;
+
+    #[test]
+    fn test_terms() {
+        let (x, y) = (col("x"), col("y"));
+        // y' = x² + y - 2x + 1, reading y before x
+        let term: Term = y.next() - (x.cur().pow(2) + y.cur()) + 2 * x.cur() - 1;
+        assert_eq!(term.variables(), ["y", "x"]);
+        assert_eq!(term.expression().degree(), 2);
+        assert_eq!(term.expression().max_offset(), 1);
+
+        // x = 3, y = 5, y' = 9 + 5 - 6 + 1
+        let current = [5u64, 3].map(Fr::from);
+        let next = [9u64, 0].map(Fr::from);
+        assert_eq!(term.expression().evaluate(&current, &next), Fr::from(0u64));
+
+        assert_eq!((x.next::<Fr>() * y.cur()).to_string(), "x' * y");
+        assert_eq!(x.at::<Fr>(2).expression().max_offset(), 2);
+        assert_eq!(Term::from(&x), x.cur::<Fr>());
+
+        // A challenge is constant over the rows
+        let product: Term = challenge("gamma") * x.cur() - y.next();
+        assert_eq!(product.expression().degree(), 1);
+        assert_eq!(product.expression().challenges(), [0]);
+        assert_eq!(
+            product.to_string(),
+            format!("([gamma] * x + {} * y')", -Fr::ONE)
+        );
+    }
+}
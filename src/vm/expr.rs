@@ -0,0 +1,473 @@
+//! Symbolic constraint expressions.
+//!
+//! An `Expr` is a polynomial in the columns of two consecutive rows, written as a tree
+//! of sums, products and powers. Unlike an evaluator closure it can be inspected: its
+//! degree is computed from the tree, it has a canonical encoding, and it evaluates over
+//! any field holding the row values, such as an extension field or the values of the
+//! columns at the out-of-domain point.
+//!
+//! Columns are referred to by their index into the variables the constraint is
+//! registered with, see `ConstraintSystem::add_transition_expr`, and by the row they are
+//! read from: offset 0 for the current row and 1 for the next. Window constraints, see
+//! `ConstraintSystem::add_window_expr`, read further offsets into their window.
+//!
+//! A variable may also name a challenge of the auxiliary segment, see
+//! `ConstraintSystem::set_auxiliary_segment`, read with `Expr::Challenge`. Challenges
+//! are drawn from the transcript after the main trace is committed and are the same on
+//! every row, so unlike a column they do not count towards the degree: the product
+//! `α·x` of a challenge and a column is linear.
+
+use ark_bls12_381::Fr;
+use ark_ff::{Field, PrimeField};
+use std::{fmt, ops};
+
+use crate::error::ToyniError;
+use crate::math::serde_fr::{decode, encoded_len, to_bytes};
+
+/// Deepest nesting of an expression tree, counting the root, that is accepted.
+///
+/// Evaluating, encoding and decoding an expression recurse into its tree, so bounding
+/// the depth keeps an untrusted encoding from exhausting the stack.
+pub const MAX_DEPTH: usize = 256;
+
+/// Polynomial expression in the columns of two consecutive rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr<F: Field = Fr> {
+    /// Column `i` of the constraint's variables on the row at the offset, 0 for the
+    /// current row and 1 for the next
+    Column(usize, usize),
+    /// A constant
+    Const(F),
+    /// Challenge `i` of the constraint's variables, constant over the rows
+    Challenge(usize),
+    /// Sum of two expressions
+    Add(Box<Expr<F>>, Box<Expr<F>>),
+    /// Product of two expressions
+    Mul(Box<Expr<F>>, Box<Expr<F>>),
+    /// An expression raised to a constant power
+    Pow(Box<Expr<F>>, u64),
+}
+
+impl<F: Field> Expr<F> {
+    /// Column `i` on the current row.
+    pub fn current(i: usize) -> Self {
+        Expr::Column(i, 0)
+    }
+
+    /// Column `i` on the next row.
+    pub fn next(i: usize) -> Self {
+        Expr::Column(i, 1)
+    }
+
+    /// The expression raised to the power `exponent`.
+    pub fn pow(self, exponent: u64) -> Self {
+        Expr::Pow(Box::new(self), exponent)
+    }
+
+    /// Degree of the expression in the trace columns.
+    ///
+    /// This is the degree of the tree as written, an upper bound that terms cancelling
+    /// each other do not lower.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the degree overflows a `usize`, see `checked_degree`.
+    pub fn degree(&self) -> usize {
+        self.checked_degree()
+            .expect("The degree of the expression overflows")
+    }
+
+    /// Degree of the expression in the trace columns, or `None` if it overflows a
+    /// `usize`.
+    pub fn checked_degree(&self) -> Option<usize> {
+        match self {
+            Expr::Column(..) => Some(1),
+            Expr::Const(_) | Expr::Challenge(_) => Some(0),
+            Expr::Add(left, right) => Some(left.checked_degree()?.max(right.checked_degree()?)),
+            Expr::Mul(left, right) => left.checked_degree()?.checked_add(right.checked_degree()?),
+            Expr::Pow(base, exponent) => base
+                .checked_degree()?
+                .checked_mul(usize::try_from(*exponent).ok()?),
+        }
+    }
+
+    /// Number of nested nodes on the longest path from the root to a leaf.
+    pub fn depth(&self) -> usize {
+        match self {
+            Expr::Column(..) | Expr::Const(_) | Expr::Challenge(_) => 1,
+            Expr::Add(left, right) | Expr::Mul(left, right) => 1 + left.depth().max(right.depth()),
+            Expr::Pow(base, _) => 1 + base.depth(),
+        }
+    }
+
+    /// Number of columns the expression reads, one more than the largest index.
+    pub fn width(&self) -> usize {
+        match self {
+            Expr::Column(i, _) | Expr::Challenge(i) => i + 1,
+            Expr::Const(_) => 0,
+            Expr::Add(left, right) | Expr::Mul(left, right) => left.width().max(right.width()),
+            Expr::Pow(base, _) => base.width(),
+        }
+    }
+
+    /// Largest row offset the expression reads, 0 if it only reads the current row.
+    pub fn max_offset(&self) -> usize {
+        match self {
+            Expr::Column(_, offset) => *offset,
+            Expr::Const(_) | Expr::Challenge(_) => 0,
+            Expr::Add(left, right) | Expr::Mul(left, right) => {
+                left.max_offset().max(right.max_offset())
+            }
+            Expr::Pow(base, _) => base.max_offset(),
+        }
+    }
+
+    /// Indices of the variables the expression reads as challenges, in the order it
+    /// reads them.
+    pub fn challenges(&self) -> Vec<usize> {
+        match self {
+            Expr::Challenge(i) => vec![*i],
+            Expr::Column(..) | Expr::Const(_) => Vec::new(),
+            Expr::Add(left, right) | Expr::Mul(left, right) => {
+                let mut challenges = left.challenges();
+                challenges.extend(right.challenges());
+                challenges
+            }
+            Expr::Pow(base, _) => base.challenges(),
+        }
+    }
+
+    /// The same expression reading column `index(i)` wherever it reads column `i`.
+    pub fn map_columns(&self, index: &impl Fn(usize) -> usize) -> Self {
+        match self {
+            Expr::Column(i, offset) => Expr::Column(index(*i), *offset),
+            Expr::Const(value) => Expr::Const(*value),
+            Expr::Challenge(i) => Expr::Challenge(index(*i)),
+            Expr::Add(left, right) => Expr::Add(
+                Box::new(left.map_columns(index)),
+                Box::new(right.map_columns(index)),
+            ),
+            Expr::Mul(left, right) => Expr::Mul(
+                Box::new(left.map_columns(index)),
+                Box::new(right.map_columns(index)),
+            ),
+            Expr::Pow(base, exponent) => Expr::Pow(Box::new(base.map_columns(index)), *exponent),
+        }
+    }
+
+    /// Writes the expression as `Display` does, with `column(f, i, offset)` writing
+    /// each column.
+    pub(crate) fn write_with(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        column: &impl Fn(&mut fmt::Formatter<'_>, usize, usize) -> fmt::Result,
+    ) -> fmt::Result {
+        match self {
+            Expr::Column(i, offset) => column(f, *i, *offset),
+            Expr::Const(value) => write!(f, "{}", value),
+            Expr::Challenge(i) => {
+                write!(f, "[")?;
+                column(f, *i, 0)?;
+                write!(f, "]")
+            }
+            Expr::Add(left, right) => {
+                write!(f, "(")?;
+                left.write_with(f, column)?;
+                write!(f, " + ")?;
+                right.write_with(f, column)?;
+                write!(f, ")")
+            }
+            Expr::Mul(left, right) => {
+                left.write_with(f, column)?;
+                write!(f, " * ")?;
+                right.write_with(f, column)
+            }
+            Expr::Pow(base, exponent) => match **base {
+                Expr::Mul(..) | Expr::Pow(..) => {
+                    write!(f, "(")?;
+                    base.write_with(f, column)?;
+                    write!(f, ")^{}", exponent)
+                }
+                _ => {
+                    base.write_with(f, column)?;
+                    write!(f, "^{}", exponent)
+                }
+            },
+        }
+    }
+
+    /// Evaluates the expression on a row pair, given as one value per column.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a row holds fewer values than `width`.
+    pub fn evaluate(&self, current: &[F], next: &[F]) -> F {
+        self.evaluate_with(&|i, offset| match offset {
+            0 => current[i],
+            _ => next[i],
+        })
+    }
+
+    /// Evaluates the expression with `column(i, offset)` giving the value of each
+    /// column, and `column(i, 0)` that of each challenge.
+    pub fn evaluate_with(&self, column: &impl Fn(usize, usize) -> F) -> F {
+        match self {
+            Expr::Column(i, offset) => column(*i, *offset),
+            Expr::Const(value) => *value,
+            Expr::Challenge(i) => column(*i, 0),
+            Expr::Add(left, right) => left.evaluate_with(column) + right.evaluate_with(column),
+            Expr::Mul(left, right) => left.evaluate_with(column) * right.evaluate_with(column),
+            Expr::Pow(base, exponent) => base.evaluate_with(column).pow([*exponent]),
+        }
+    }
+
+    /// Canonical encoding of the expression, in prefix order.
+    ///
+    /// Every node is a tag byte followed by its operands: the index and offset of a
+    /// column as little-endian `u64`s, the canonical bytes of a constant, the two
+    /// operands of a sum or product, the base and little-endian `u64` exponent of a
+    /// power, and the index of a challenge as a little-endian `u64`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.encode(&mut bytes);
+        bytes
+    }
+
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Expr::Column(i, offset) => {
+                bytes.push(0);
+                bytes.extend_from_slice(&(*i as u64).to_le_bytes());
+                bytes.extend_from_slice(&(*offset as u64).to_le_bytes());
+            }
+            Expr::Const(value) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&to_bytes(value));
+            }
+            Expr::Add(left, right) | Expr::Mul(left, right) => {
+                bytes.push(if matches!(self, Expr::Add(..)) { 2 } else { 3 });
+                left.encode(bytes);
+                right.encode(bytes);
+            }
+            Expr::Pow(base, exponent) => {
+                bytes.push(4);
+                base.encode(bytes);
+                bytes.extend_from_slice(&exponent.to_le_bytes());
+            }
+            Expr::Challenge(i) => {
+                bytes.push(5);
+                bytes.extend_from_slice(&(*i as u64).to_le_bytes());
+            }
+        }
+    }
+
+    /// Decodes an expression encoded by `to_bytes`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InvalidExpression` if the bytes are not exactly one encoded
+    /// expression or nest deeper than `MAX_DEPTH`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ToyniError> {
+        let mut reader = bytes;
+        let expr = Self::decode(&mut reader, 1)?;
+        if !reader.is_empty() {
+            return Err(invalid("trailing bytes after the expression"));
+        }
+        Ok(expr)
+    }
+
+    fn decode(bytes: &mut &[u8], depth: usize) -> Result<Self, ToyniError> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("expression nested too deeply"));
+        }
+        let (&tag, rest) = bytes.split_first().ok_or(invalid("truncated expression"))?;
+        *bytes = rest;
+        Ok(match tag {
+            0 => Expr::Column(read_u64(bytes)? as usize, read_u64(bytes)? as usize),
+            1 => Expr::Const(decode(take(bytes, encoded_len::<F>())?).map_err(invalid)?),
+            2 => Expr::Add(
+                Box::new(Self::decode(bytes, depth + 1)?),
+                Box::new(Self::decode(bytes, depth + 1)?),
+            ),
+            3 => Expr::Mul(
+                Box::new(Self::decode(bytes, depth + 1)?),
+                Box::new(Self::decode(bytes, depth + 1)?),
+            ),
+            4 => Expr::Pow(Box::new(Self::decode(bytes, depth + 1)?), read_u64(bytes)?),
+            5 => Expr::Challenge(read_u64(bytes)? as usize),
+            _ => return Err(invalid("unknown expression tag")),
+        })
+    }
+}
+
+impl<F: PrimeField> Expr<F> {
+    /// The same expression over an extension `E` of `F`, to evaluate it on extension
+    /// field values.
+    pub fn lift<E: Field<BasePrimeField = F>>(&self) -> Expr<E> {
+        match self {
+            Expr::Column(i, offset) => Expr::Column(*i, *offset),
+            Expr::Const(value) => Expr::Const(E::from_base_prime_field(*value)),
+            Expr::Challenge(i) => Expr::Challenge(*i),
+            Expr::Add(left, right) => Expr::Add(Box::new(left.lift()), Box::new(right.lift())),
+            Expr::Mul(left, right) => Expr::Mul(Box::new(left.lift()), Box::new(right.lift())),
+            Expr::Pow(base, exponent) => Expr::Pow(Box::new(base.lift()), *exponent),
+        }
+    }
+}
+
+fn invalid(reason: &str) -> ToyniError {
+    ToyniError::InvalidExpression(reason.to_string())
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], ToyniError> {
+    if bytes.len() < len {
+        return Err(invalid("truncated expression"));
+    }
+    let (head, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(head)
+}
+
+fn read_u64(bytes: &mut &[u8]) -> Result<u64, ToyniError> {
+    Ok(u64::from_le_bytes(take(bytes, 8)?.try_into().unwrap()))
+}
+
+impl<F: Field> fmt::Display for Expr<F> {
+    /// Writes column `i` as `x{i}` on the current row and `x{i}'` on the next.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_with(f, &|f, i, offset| write!(f, "x{}{}", i, "'".repeat(offset)))
+    }
+}
+
+impl<F: Field> From<F> for Expr<F> {
+    fn from(value: F) -> Self {
+        Expr::Const(value)
+    }
+}
+
+impl<F: Field> ops::Add for Expr<F> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Expr::Add(Box::new(self), Box::new(other))
+    }
+}
+
+impl<F: Field> ops::Mul for Expr<F> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Expr::Mul(Box::new(self), Box::new(other))
+    }
+}
+
+impl<F: Field> ops::Neg for Expr<F> {
+    type Output = Self;
+
+    /// Multiplies by the constant `-1`, as the tree has no negation node.
+    fn neg(self) -> Self {
+        Expr::Const(-F::ONE) * self
+    }
+}
+
+impl<F: Field> ops::Sub for Expr<F> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::fr_ext2::FrExt2;
+
+    #[test]
+    fn test_expr_degree_and_evaluation() {
+        // next x = x² + y
+        let x = Expr::<Fr>::current(0);
+        let expr = Expr::next(0) - (x.pow(2) + Expr::current(1));
+        assert_eq!(expr.degree(), 2);
+        assert_eq!(expr.width(), 2);
+        assert_eq!(expr.max_offset(), 1);
+
+        let current = [3u64, 5].map(Fr::from);
+        assert_eq!(
+            expr.evaluate(&current, &[Fr::from(14u64), Fr::from(0u64)]),
+            Fr::from(0u64)
+        );
+        assert_eq!(
+            expr.evaluate(&current, &[Fr::from(15u64), Fr::from(0u64)]),
+            Fr::from(1u64)
+        );
+        assert_eq!((Expr::<Fr>::current(0) * Expr::next(1)).pow(3).degree(), 6);
+        assert_eq!(Expr::Const(Fr::from(7u64)).degree(), 0);
+    }
+
+    #[test]
+    fn test_expr_lifts_to_extension() {
+        let expr = Expr::<Fr>::next(0) - Expr::current(0) * Expr::Const(Fr::from(2u64));
+        let lifted = expr.lift::<FrExt2>();
+        let x = FrExt2::new(Fr::from(3u64), Fr::from(4u64));
+        assert_eq!(lifted.evaluate(&[x], &[x + x]), FrExt2::from(0u64));
+        assert_eq!(lifted.degree(), expr.degree());
+    }
+
+    #[test]
+    fn test_expr_encoding() {
+        let expr = (Expr::<Fr>::next(1) + Expr::Const(Fr::from(9u64))).pow(5) * Expr::current(0);
+        let bytes = expr.to_bytes();
+        assert_eq!(Expr::from_bytes(&bytes).unwrap(), expr);
+        assert_ne!(
+            Expr::<Fr>::next(0).to_bytes(),
+            Expr::<Fr>::current(0).to_bytes()
+        );
+
+        assert!(matches!(
+            Expr::<Fr>::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(ToyniError::InvalidExpression(_))
+        ));
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(Expr::<Fr>::from_bytes(&trailing).is_err());
+        assert!(Expr::<Fr>::from_bytes(&[5]).is_err());
+        assert_eq!(expr.to_string(), "(x1' + 9)^5 * x0");
+
+        let randomized = Expr::<Fr>::Challenge(1) * Expr::current(0);
+        assert_eq!(
+            Expr::from_bytes(&randomized.to_bytes()).unwrap(),
+            randomized
+        );
+        assert_eq!(randomized.degree(), 1);
+        assert_eq!(randomized.to_string(), "[x1] * x0");
+    }
+
+    #[test]
+    fn test_expr_degree_overflow() {
+        let x = Expr::<Fr>::current(0);
+        assert_eq!(
+            (x.clone() * x.clone()).pow(1 << 20).checked_degree(),
+            Some(1 << 21)
+        );
+        assert_eq!((x.clone() * x).pow(u64::MAX).checked_degree(), None);
+    }
+
+    #[test]
+    fn test_expr_decoding_depth() {
+        let nested = |depth: usize| {
+            let mut bytes = vec![4; depth - 1];
+            bytes.extend(Expr::<Fr>::current(0).to_bytes());
+            for _ in 1..depth {
+                bytes.extend(1u64.to_le_bytes());
+            }
+            bytes
+        };
+        let expr = Expr::<Fr>::from_bytes(&nested(MAX_DEPTH)).unwrap();
+        assert_eq!(expr.depth(), MAX_DEPTH);
+        assert!(matches!(
+            Expr::<Fr>::from_bytes(&nested(MAX_DEPTH + 1)),
+            Err(ToyniError::InvalidExpression(_))
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+//! Lookup arguments into preprocessed tables, in the LogUp style.
+//!
+//! Every value `vᵢ` of a column appears in a table `t` exactly if there are
+//! multiplicities `mⱼ` with `Σᵢ 1 / (γ - vᵢ) = Σⱼ mⱼ / (γ - tⱼ)` as rational functions
+//! of `γ`. The table is a periodic column, which both prover and verifier evaluate, and
+//! the multiplicities are a main trace column, counting at the first row of each table
+//! value how often the column holds it. Drawing `γ` after the main trace is committed
+//! makes the sums of a value outside the table agree with negligible probability.
+//!
+//! Two auxiliary columns carry the argument: `h` holds the term
+//! `1 / (γ - vᵢ) - mᵢ / (γ - tᵢ)` of every row, checked by a single-row constraint
+//! without any division, and `s` sums the terms, starting from zero on the first row
+//! and reaching zero again with the term of the last row.
+
+use ark_bls12_381::Fr;
+use ark_ff::FftField;
+use std::collections::HashMap;
+
+use crate::error::ToyniError;
+use crate::math::inversion::batch_inverse;
+use crate::vm::{
+    auxiliary::{AuxiliaryTrace, MainColumns},
+    trace::ProgramVariable,
+};
+
+/// Argument that every value of `column` appears in `table`, registered with
+/// `ConstraintSystem::add_lookup_argument`.
+///
+/// Like a `PermutationArgument`, the argument builds its own auxiliary columns and is
+/// passed to the prover as the `AuxiliaryTrace`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupArgument<F: FftField = Fr> {
+    /// Name the columns and the challenge of the argument are prefixed with
+    pub name: String,
+    /// Column whose values are looked up
+    pub column: ProgramVariable,
+    /// Values of the table, padded to a power of two rows by repeating the last
+    pub table: Vec<F>,
+}
+
+impl<F: FftField> LookupArgument<F> {
+    /// An argument that every value of `column` appears in `table`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InvalidOptions` if the table is empty
+    pub fn new(
+        name: String,
+        column: ProgramVariable,
+        mut table: Vec<F>,
+    ) -> Result<Self, ToyniError> {
+        let Some(last) = table.last().copied() else {
+            return Err(ToyniError::InvalidOptions(format!(
+                "lookup argument {} has an empty table",
+                name
+            )));
+        };
+        table.resize(table.len().next_power_of_two(), last);
+        Ok(Self {
+            name,
+            column,
+            table,
+        })
+    }
+
+    /// The periodic column holding the table, `name/table`.
+    pub fn table_column(&self) -> ProgramVariable {
+        format!("{}/table", self.name)
+    }
+
+    /// The main trace column holding the multiplicities, `name/multiplicity`.
+    pub fn multiplicity_column(&self) -> ProgramVariable {
+        format!("{}/multiplicity", self.name)
+    }
+
+    /// The auxiliary columns of the argument, the terms `name/h` and their running sum
+    /// `name/s`.
+    pub fn columns(&self) -> Vec<ProgramVariable> {
+        vec![format!("{}/h", self.name), format!("{}/s", self.name)]
+    }
+
+    /// The challenge of the argument, `name/gamma`.
+    pub fn challenge(&self) -> ProgramVariable {
+        format!("{}/gamma", self.name)
+    }
+
+    /// The multiplicity column of a trace whose looked-up column holds `values`.
+    ///
+    /// Each table value is counted at the first row the table holds it, every other row
+    /// is zero. Padding repeats the last row, multiplicity included, so the trace is
+    /// best built at a power-of-two height.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::UnsatisfiedConstraint` naming the argument if a value is
+    /// missing from the table, and `ToyniError::InvalidOptions` if the table has more
+    /// rows than `values`
+    pub fn multiplicities(&self, values: &[F::BasePrimeField]) -> Result<Vec<u64>, ToyniError> {
+        if self.table.len() > values.len() {
+            return Err(ToyniError::InvalidOptions(format!(
+                "lookup table {} has {} rows, more than the trace length {}",
+                self.name,
+                self.table.len(),
+                values.len()
+            )));
+        }
+        let mut rows: HashMap<F, usize> = HashMap::new();
+        for (row, value) in self.table.iter().enumerate().rev() {
+            rows.insert(*value, row);
+        }
+        let mut multiplicities = vec![0; values.len()];
+        for value in values {
+            let row = rows
+                .get(&F::from_base_prime_field(*value))
+                .ok_or_else(|| ToyniError::UnsatisfiedConstraint(self.name.clone()))?;
+            multiplicities[*row] += 1;
+        }
+        Ok(multiplicities)
+    }
+}
+
+impl<F: FftField> AuxiliaryTrace<F> for LookupArgument<F> {
+    /// Builds `hᵢ = 1 / (γ - vᵢ) - mᵢ / (γ - tᵢ)` and `s` with `s₀ = 0` and
+    /// `sᵢ₊₁ = sᵢ + hᵢ`.
+    fn build(
+        &self,
+        main: &MainColumns<'_, F::BasePrimeField>,
+        challenges: &HashMap<ProgramVariable, F>,
+    ) -> Result<HashMap<ProgramVariable, Vec<F>>, ToyniError> {
+        let challenge = self.challenge();
+        let gamma = *challenges
+            .get(&challenge)
+            .ok_or(ToyniError::UnknownVariable(challenge))?;
+        let values = main.column(&self.column)?;
+        let multiplicities = main.column(&self.multiplicity_column())?;
+        let rows = main.num_rows();
+        let [h, s] = <[ProgramVariable; 2]>::try_from(self.columns()).unwrap();
+
+        let denominators: Vec<F> = (0..rows)
+            .flat_map(|i| {
+                [
+                    gamma - F::from_base_prime_field(values[i]),
+                    gamma - self.table[i % self.table.len()],
+                ]
+            })
+            .collect();
+        let inverses = batch_inverse(&denominators)
+            .ok_or_else(|| ToyniError::InvalidAuxiliaryColumn(h.clone()))?;
+        let terms: Vec<F> = (0..rows)
+            .map(|i| {
+                inverses[2 * i] - F::from_base_prime_field(multiplicities[i]) * inverses[2 * i + 1]
+            })
+            .collect();
+        let sums = terms
+            .iter()
+            .scan(F::ZERO, |sum, term| {
+                let current = *sum;
+                *sum += term;
+                Some(current)
+            })
+            .collect();
+        Ok(HashMap::from([(h, terms), (s, sums)]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::{AdditiveGroup, Field};
+
+    #[test]
+    fn test_lookup_columns() {
+        let table = [1u64, 2, 3].map(Fr::from).to_vec();
+        let argument = LookupArgument::new("range".to_string(), "x".to_string(), table).unwrap();
+        // The table is padded by repeating its last value
+        assert_eq!(argument.table.len(), 4);
+        assert_eq!(argument.table[3], Fr::from(3u64));
+
+        let values = [3u64, 1, 3, 3].map(Fr::from);
+        let multiplicities = argument.multiplicities(&values).unwrap();
+        assert_eq!(multiplicities, [1, 0, 3, 0]);
+        assert_eq!(
+            argument.multiplicities(&[4u64, 1, 1, 1].map(Fr::from)),
+            Err(ToyniError::UnsatisfiedConstraint("range".to_string()))
+        );
+        assert!(argument.multiplicities(&values[..2]).is_err());
+
+        let variables = vec!["x".to_string(), argument.multiplicity_column()];
+        let columns = vec![
+            values.to_vec(),
+            multiplicities.into_iter().map(Fr::from).collect(),
+        ];
+        let main = MainColumns::new(&variables, &columns);
+        let challenges = HashMap::from([(argument.challenge(), Fr::from(10u64))]);
+        let built = argument.build(&main, &challenges).unwrap();
+        let (h, s) = (&built["range/h"], &built["range/s"]);
+        assert_eq!(s[0], Fr::ZERO);
+        assert_eq!(s[3] + h[3], Fr::ZERO);
+        // 1 / (10 - 3) - 1 / (10 - 1) on the first row
+        assert_eq!(
+            h[0],
+            Fr::from(7u64).inverse().unwrap() - Fr::from(9u64).inverse().unwrap()
+        );
+        assert!(LookupArgument::<Fr>::new("empty".to_string(), "x".to_string(), vec![]).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+//! Execution traces backed by a memory-mapped file.
+//!
+//! `MappedTrace` maps a trace file in the binary layout of `ExecutionTrace::to_writer`
+//! and implements `TraceTable` by decoding each fragment straight from the mapping, so
+//! traces of long VM runs never have to be held as rows of maps. The operating system
+//! pages rows in as the prover reads them and may evict them again afterwards; only the
+//! columns the prover fills and their extension stay resident, and with
+//! `ProvingStrategy::Streaming` the extension is recomputed one segment at a time.
+//!
+//! `MappedTrace::create` writes the file row by row from any iterator, without knowing
+//! the number of rows up front, and maps the result.
+//!
+//! Requires the `mmap` feature.
+
+use ark_bls12_381::Fr;
+use ark_ff::PrimeField;
+use memmap2::Mmap;
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::Path,
+};
+
+use crate::error::ToyniError;
+use crate::math::serde_fr::{decode, encoded_len, to_bytes};
+use crate::vm::table::{TraceFragment, TraceTable};
+use crate::vm::trace::{ProgramVariable, distinct_columns};
+use crate::vm::trace_io::{TRACE_MAGIC, TraceHeader, io_error, malformed};
+
+/// A trace read from a memory-mapped file in the binary trace layout.
+#[derive(Debug)]
+pub struct MappedTrace<F: PrimeField = Fr> {
+    /// Dimensions and column names read from the file
+    header: TraceHeader,
+    /// Offset of the first row in the mapping
+    rows_offset: usize,
+    /// The whole file
+    map: Mmap,
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField> MappedTrace<F> {
+    /// Maps a trace file written by `ExecutionTrace::to_writer` or `create`.
+    ///
+    /// Only the header is read; values are decoded when the prover fills them. The file
+    /// must not be modified while it is mapped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::MalformedTrace` if the header does not follow the layout of
+    /// `TRACE_VERSION` or the file length does not match its dimensions, and
+    /// `ToyniError::Io` if the file cannot be opened or mapped
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ToyniError> {
+        let file = File::open(path).map_err(io_error)?;
+        // SAFETY: the mapping is only read, and callers must not modify the file while
+        // it is mapped
+        let map = unsafe { Mmap::map(&file) }.map_err(io_error)?;
+        let mut reader = &map[..];
+        let header = TraceHeader::read(&mut reader)?;
+        let rows_offset = map.len() - reader.len();
+        let rows_len = usize::try_from(header.height)
+            .ok()
+            .and_then(|height| height.checked_mul(header.variables.len()))
+            .and_then(|values| values.checked_mul(encoded_len::<F>()));
+        if rows_len != Some(reader.len()) {
+            return Err(malformed("file length does not match the trace dimensions"));
+        }
+        Ok(Self {
+            header,
+            rows_offset,
+            map,
+            _field: PhantomData,
+        })
+    }
+
+    /// Writes `rows` to a new trace file at `path` and maps it.
+    ///
+    /// Each row holds one value per column in the order of `variables`, which need not
+    /// be sorted. Rows are written as they are produced, so the iterator can generate a
+    /// trace larger than memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::DuplicateColumn` if a column name repeats,
+    /// `ToyniError::WidthMismatch` if a row does not hold one value per column, and
+    /// `ToyniError::Io` if writing fails
+    pub fn create<S: Into<ProgramVariable>>(
+        path: impl AsRef<Path>,
+        variables: impl IntoIterator<Item = S>,
+        rows: impl IntoIterator<Item = Vec<F>>,
+    ) -> Result<Self, ToyniError> {
+        let variables: Vec<ProgramVariable> =
+            distinct_columns(variables.into_iter().map(Into::into).collect::<Vec<_>>())?;
+        let mut order: Vec<usize> = (0..variables.len()).collect();
+        order.sort_by_key(|&column| &variables[column]);
+        let mut header = TraceHeader {
+            height: 0,
+            padding: 0,
+            variables: order
+                .iter()
+                .map(|&column| variables[column].clone())
+                .collect(),
+            randomizers: Vec::new(),
+        };
+
+        // The height is patched in once every row has been written
+        let mut writer = BufWriter::new(File::create(&path).map_err(io_error)?);
+        writer.write_all(&header.to_bytes()).map_err(io_error)?;
+        let mut bytes = Vec::with_capacity(variables.len() * encoded_len::<F>());
+        for row in rows {
+            if row.len() != variables.len() {
+                return Err(ToyniError::WidthMismatch {
+                    expected: variables.len() as u64,
+                    actual: row.len(),
+                });
+            }
+            bytes.clear();
+            for &column in &order {
+                bytes.extend_from_slice(&to_bytes(&row[column]));
+            }
+            writer.write_all(&bytes).map_err(io_error)?;
+            header.height += 1;
+        }
+        let height_offset = TRACE_MAGIC.len() as u64 + 2;
+        writer
+            .seek(SeekFrom::Start(height_offset))
+            .and_then(|_| writer.write_all(&header.height.to_le_bytes()))
+            .and_then(|_| writer.flush())
+            .map_err(io_error)?;
+        drop(writer);
+        Self::open(path)
+    }
+
+    /// Number of rows, including padding.
+    pub fn height(&self) -> u64 {
+        self.header.height
+    }
+
+    /// Index of the last row before padding, if the trace has one.
+    pub fn last_row(&self) -> Option<u64> {
+        (self.header.height - self.header.padding).checked_sub(1)
+    }
+
+    /// Values of row `index`, in the order of `TraceTable::variables`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::RowOutOfBounds` if the row is not in the trace and
+    /// `ToyniError::MalformedTrace` if a value is not canonically encoded
+    pub fn row(&self, index: u64) -> Result<Vec<F>, ToyniError> {
+        if index >= self.header.height {
+            return Err(ToyniError::RowOutOfBounds {
+                index,
+                rows: self.header.height as usize,
+            });
+        }
+        self.row_bytes(index as usize)
+            .chunks(encoded_len::<F>())
+            .map(|bytes| decode(bytes).map_err(malformed))
+            .collect()
+    }
+
+    /// Encoded values of row `index`.
+    fn row_bytes(&self, index: usize) -> &[u8] {
+        let row_len = self.header.variables.len() * encoded_len::<F>();
+        let start = self.rows_offset + index * row_len;
+        &self.map[start..start + row_len]
+    }
+}
+
+impl<F: PrimeField> TraceTable<F> for MappedTrace<F> {
+    fn variables(&self) -> Vec<ProgramVariable> {
+        self.header.variables.clone()
+    }
+
+    fn length(&self) -> usize {
+        self.header.height as usize
+    }
+
+    fn randomizers(&self) -> Vec<ProgramVariable> {
+        self.header.randomizers.clone()
+    }
+
+    fn fill(&self, fragment: &mut TraceFragment<'_, F>) -> Result<(), ToyniError> {
+        let value_len = encoded_len::<F>();
+        for row in 0..fragment.num_rows() {
+            let bytes = self.row_bytes(fragment.offset() + row);
+            for (column, value) in bytes.chunks(value_len).enumerate() {
+                fragment.set(row, column, decode::<F>(value).map_err(malformed)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::{ProvingStrategy, StarkProver};
+    use crate::verifier::StarkVerifier;
+    use crate::vm::constraints::ConstraintSystem;
+    use crate::vm::table::fill_columns;
+    use crate::vm::trace::{ExecutionTrace, PaddingStrategy};
+    use ark_ff::Field;
+    use std::path::PathBuf;
+
+    /// A path in the temporary directory unique to this process and test.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("toyni-{}-{}.trace", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_mapped_trace_matches_execution_trace() {
+        let mut trace = ExecutionTrace::<Fr>::from_fn(["y", "x"], 3, |i| [i * i, i]).unwrap();
+        trace.add_randomizer_column("r".to_string()).unwrap();
+        trace.pad(&PaddingStrategy::RepeatLastRow).unwrap();
+        let path = temp_path("written");
+        trace.to_writer(File::create(&path).unwrap()).unwrap();
+
+        let mapped = MappedTrace::<Fr>::open(&path).unwrap();
+        assert_eq!(TraceTable::variables(&mapped), trace.variables());
+        assert_eq!(TraceTable::randomizers(&mapped), trace.randomizers);
+        assert_eq!((mapped.height(), mapped.last_row()), (4, trace.last_row()));
+        assert_eq!(mapped.row(2).unwrap(), [2u64, 4].map(Fr::from));
+        assert_eq!(
+            fill_columns(&mapped, &PaddingStrategy::RepeatLastRow).unwrap(),
+            fill_columns(&trace, &PaddingStrategy::RepeatLastRow).unwrap()
+        );
+        assert_eq!(
+            mapped.row(4).err(),
+            Some(ToyniError::RowOutOfBounds { index: 4, rows: 4 })
+        );
+
+        // A truncated file is rejected when mapped
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(matches!(
+            MappedTrace::<Fr>::open(&path),
+            Err(ToyniError::MalformedTrace(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_create_mapped_trace() {
+        let path = temp_path("created");
+        let rows = (0..5u64).map(|i| vec![Fr::from(i * i), Fr::from(i)]);
+        let mapped = MappedTrace::create(&path, ["y", "x"], rows).unwrap();
+        assert_eq!(TraceTable::variables(&mapped), ["x", "y"]);
+        assert_eq!(mapped.height(), 5);
+        assert_eq!(mapped.row(3).unwrap(), [3u64, 9].map(Fr::from));
+
+        // The file is a regular trace file
+        let trace = ExecutionTrace::<Fr>::from_reader(File::open(&path).unwrap()).unwrap();
+        assert_eq!(trace.get(4, "y").unwrap(), Fr::from(16u64));
+
+        assert_eq!(
+            MappedTrace::create(&path, ["x", "y"], [vec![Fr::from(1u64)]]).err(),
+            Some(ToyniError::WidthMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            MappedTrace::<Fr>::create(&path, ["x", "x"], []).err(),
+            Some(ToyniError::DuplicateColumn("x".to_string()))
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_prove_mapped_trace() {
+        let path = temp_path("proved");
+        let rows = (0..64u64).map(|i| vec![Fr::from(i)]);
+        let mapped = MappedTrace::create(&path, ["x"], rows).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| next["x"] - current["x"] - Fr::ONE),
+        );
+        let proof = StarkProver::new(&mapped, &constraints)
+            .with_strategy(ProvingStrategy::Streaming)
+            .generate_proof()
+            .unwrap();
+        assert!(StarkVerifier::new(&constraints, 64).verify(&proof));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -3,5 +3,18 @@
 //! Provides execution trace recording, constraint system, and integration with math components.
 //! Designed to be deterministic, simple, traceable, and verifiable.
 
+pub mod air;
+pub mod auxiliary;
+pub mod chiplet;
+pub mod commitment;
 pub mod constraints;
+pub mod dsl;
+pub mod expr;
+pub mod lookup;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod permutation;
+pub mod range;
+pub mod table;
 pub mod trace;
+pub mod trace_io;
@@ -0,0 +1,179 @@
+//! Permutation arguments over the auxiliary trace segment.
+//!
+//! Two sets of `k` columns hold the same rows in some order exactly if the products
+//! `Π (γ - fₗ(i))` and `Π (γ - fᵣ(i))` over the rows agree as polynomials in `γ`,
+//! where `f(i) = c₀(i) + α₁·c₁(i) + ... + αₖ₋₁·cₖ₋₁(i)` compresses a row of a set with
+//! random coefficients. Drawing `γ` and the `αⱼ` after the main trace is committed
+//! makes two unequal products agree with negligible probability.
+//!
+//! The auxiliary column `z` accumulates the ratio of the two products row by row,
+//! starting from one, and the argument asserts that it returns to one on the last row.
+//! Transition constraints do not reach past the last row, so the last rows of both
+//! sets are constrained to agree instead and left out of the product. This costs
+//! nothing for padded traces, whose padding repeats the last row on both sides.
+
+use ark_ff::FftField;
+use std::collections::HashMap;
+
+use crate::error::ToyniError;
+use crate::math::inversion::batch_inverse;
+use crate::vm::{
+    auxiliary::{AuxiliaryTrace, MainColumns},
+    dsl::{Term, challenge, col},
+    trace::ProgramVariable,
+};
+
+/// Argument that the rows of `right` permute the rows of `left`, registered with
+/// `ConstraintSystem::add_permutation_argument`.
+///
+/// The argument builds its own auxiliary column, so it is passed to the prover as the
+/// `AuxiliaryTrace`, alone or in a `Vec` with the builders of other columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermutationArgument {
+    /// Name the column and challenges of the argument are prefixed with
+    pub name: String,
+    /// Columns of the first set
+    pub left: Vec<ProgramVariable>,
+    /// Columns of the second set, compared with `left` in order
+    pub right: Vec<ProgramVariable>,
+}
+
+impl PermutationArgument {
+    /// An argument that the rows of `right` permute those of `left`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::InvalidExpression` if the sets are empty or of different
+    /// widths
+    pub fn new(
+        name: String,
+        left: Vec<ProgramVariable>,
+        right: Vec<ProgramVariable>,
+    ) -> Result<Self, ToyniError> {
+        if left.is_empty() || left.len() != right.len() {
+            return Err(ToyniError::InvalidExpression(format!(
+                "permutation argument {} compares {} columns with {}",
+                name,
+                left.len(),
+                right.len()
+            )));
+        }
+        Ok(Self { name, left, right })
+    }
+
+    /// The running product column, `name/z`.
+    pub fn column(&self) -> ProgramVariable {
+        format!("{}/z", self.name)
+    }
+
+    /// The challenges of the argument: `name/gamma`, then `name/alpha/j` for every
+    /// column `j > 0` of a set.
+    pub fn challenges(&self) -> Vec<ProgramVariable> {
+        std::iter::once(format!("{}/gamma", self.name))
+            .chain((1..self.left.len()).map(|j| format!("{}/alpha/{}", self.name, j)))
+            .collect()
+    }
+
+    /// The linear term `γ - f(i)` of a row of `columns`, one of `left` and `right`.
+    pub(crate) fn fingerprint<F: FftField>(&self, columns: &[ProgramVariable]) -> Term<F> {
+        let challenges = self.challenges();
+        let compressed = columns
+            .iter()
+            .zip(&challenges)
+            .skip(1)
+            .fold(col(&columns[0]).cur(), |sum, (column, alpha)| {
+                sum + challenge(alpha) * col(column).cur()
+            });
+        challenge(&challenges[0]) - compressed
+    }
+
+    /// Values `γ - f(i)` of every row of `columns`.
+    fn fingerprints<F: FftField>(
+        &self,
+        main: &MainColumns<'_, F::BasePrimeField>,
+        challenges: &HashMap<ProgramVariable, F>,
+        columns: &[ProgramVariable],
+    ) -> Result<Vec<F>, ToyniError> {
+        let coefficients = self
+            .challenges()
+            .iter()
+            .map(|challenge| {
+                challenges
+                    .get(challenge)
+                    .copied()
+                    .ok_or_else(|| ToyniError::UnknownVariable(challenge.clone()))
+            })
+            .collect::<Result<Vec<F>, _>>()?;
+        let values = columns
+            .iter()
+            .map(|column| main.column(column))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((0..main.num_rows())
+            .map(|i| {
+                let compressed = values[1..].iter().zip(&coefficients[1..]).fold(
+                    F::from_base_prime_field(values[0][i]),
+                    |sum, (column, alpha)| sum + *alpha * F::from_base_prime_field(column[i]),
+                );
+                coefficients[0] - compressed
+            })
+            .collect())
+    }
+}
+
+impl<F: FftField> AuxiliaryTrace<F> for PermutationArgument {
+    /// Builds `z` with `z₀ = 1` and `zᵢ₊₁ = zᵢ·(γ - fₗ(i)) / (γ - fᵣ(i))`.
+    fn build(
+        &self,
+        main: &MainColumns<'_, F::BasePrimeField>,
+        challenges: &HashMap<ProgramVariable, F>,
+    ) -> Result<HashMap<ProgramVariable, Vec<F>>, ToyniError> {
+        let numerators = self.fingerprints(main, challenges, &self.left)?;
+        let denominators = batch_inverse(&self.fingerprints(main, challenges, &self.right)?)
+            .ok_or_else(|| ToyniError::InvalidAuxiliaryColumn(self.column()))?;
+        let mut z = Vec::with_capacity(main.num_rows());
+        let mut product = F::ONE;
+        for (numerator, inverse) in numerators.iter().zip(&denominators) {
+            z.push(product);
+            product *= *numerator * inverse;
+        }
+        Ok(HashMap::from([(self.column(), z)]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::Fr;
+
+    #[test]
+    fn test_permutation_column() {
+        let argument = PermutationArgument::new(
+            "perm".to_string(),
+            vec!["a".to_string(), "b".to_string()],
+            vec!["c".to_string(), "d".to_string()],
+        )
+        .unwrap();
+        assert_eq!(argument.challenges(), ["perm/gamma", "perm/alpha/1"]);
+
+        // (a, b) = (1, 2), (3, 4), (5, 6) and (c, d) = (3, 4), (1, 2), (5, 6)
+        let variables = ["a", "b", "c", "d"].map(String::from);
+        let columns = [[1u64, 3, 5], [2, 4, 6], [3, 1, 5], [4, 2, 6]]
+            .map(|column| column.map(Fr::from).to_vec())
+            .to_vec();
+        let main = MainColumns::new(&variables, &columns);
+        let challenges = HashMap::from([
+            ("perm/gamma".to_string(), Fr::from(100u64)),
+            ("perm/alpha/1".to_string(), Fr::from(10u64)),
+        ]);
+        let z = &AuxiliaryTrace::<Fr>::build(&argument, &main, &challenges).unwrap()["perm/z"];
+        // γ - f is 79, 57, 35 on the left and 57, 79, 35 on the right
+        let ratio = Fr::from(79u64) / Fr::from(57u64);
+        assert_eq!(*z, vec![Fr::from(1u64), ratio, Fr::from(1u64)]);
+
+        assert!(PermutationArgument::new("perm".to_string(), variables.to_vec(), vec![]).is_err());
+        assert_eq!(
+            AuxiliaryTrace::<Fr>::build(&argument, &main, &HashMap::new()).err(),
+            Some(ToyniError::UnknownVariable("perm/gamma".to_string()))
+        );
+    }
+}
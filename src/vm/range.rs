@@ -0,0 +1,85 @@
+//! Range checks by bit decomposition.
+//!
+//! A column holds values in `[0, 2ᵏ)` exactly if every value is `Σ 2ʲ·bⱼ` for bits
+//! `bⱼ` with `bⱼ·(bⱼ - 1) = 0`, as long as `2ᵏ` stays below the field modulus so the
+//! sum cannot wrap around. The bits are main trace columns next to the checked one,
+//! so the check costs `k` columns but no auxiliary segment and works at any trace
+//! length, where a lookup into a table of `2ᵏ` rows needs as many trace rows.
+
+use std::collections::HashMap;
+
+use crate::error::ToyniError;
+use crate::vm::trace::ProgramVariable;
+
+/// Range check that `column` holds values below `2^bits`, registered with
+/// `ConstraintSystem::range_check`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeCheck {
+    /// Column whose values are checked
+    pub column: ProgramVariable,
+    /// Number of bits the values fit in
+    pub bits: usize,
+}
+
+impl RangeCheck {
+    /// The bit columns of the check, `column/bit/j` for the bit of weight `2^j`.
+    pub fn bit_columns(&self) -> Vec<ProgramVariable> {
+        (0..self.bits)
+            .map(|j| format!("{}/bit/{}", self.column, j))
+            .collect()
+    }
+
+    /// Values of the bit columns on a row where the checked column holds `value`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::UnsatisfiedConstraint` naming the check's recomposition
+    /// constraint if `value` does not fit in `bits` bits
+    pub fn decompose(&self, value: u64) -> Result<HashMap<ProgramVariable, u64>, ToyniError> {
+        if self.bits < u64::BITS as usize && value >> self.bits != 0 {
+            return Err(ToyniError::UnsatisfiedConstraint(format!(
+                "{}/range",
+                self.column
+            )));
+        }
+        Ok(self
+            .bit_columns()
+            .into_iter()
+            .enumerate()
+            .map(|(j, column)| (column, value.checked_shr(j as u32).unwrap_or(0) & 1))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decompose() {
+        let check = RangeCheck {
+            column: "x".to_string(),
+            bits: 4,
+        };
+        assert_eq!(check.bit_columns()[3], "x/bit/3");
+        let bits = check.decompose(13).unwrap();
+        assert_eq!(
+            check
+                .bit_columns()
+                .iter()
+                .map(|column| bits[column])
+                .collect::<Vec<_>>(),
+            [1, 0, 1, 1]
+        );
+        assert_eq!(
+            check.decompose(16),
+            Err(ToyniError::UnsatisfiedConstraint("x/range".to_string()))
+        );
+
+        let wide = RangeCheck {
+            column: "x".to_string(),
+            bits: 70,
+        };
+        assert_eq!(wide.decompose(u64::MAX).unwrap()["x/bit/69"], 0);
+    }
+}
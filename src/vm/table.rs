@@ -0,0 +1,271 @@
+//! Trace generation in fragments.
+//!
+//! The prover reads its trace through the `TraceTable` trait instead of a materialized
+//! `ExecutionTrace`. It allocates the trace columns itself and hands out disjoint
+//! fragments of consecutive rows for the table to fill, in parallel with the `parallel`
+//! feature, so an application can generate each fragment directly from its own state
+//! without first recording every row as a map of variable names to values.
+
+use ark_bls12_381::Fr;
+use ark_ff::PrimeField;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use std::sync::Arc;
+
+use crate::error::ToyniError;
+use crate::math::lde::TraceLde;
+use crate::parallel::into_iter;
+use crate::vm::trace::{ExecutionTrace, PaddingStrategy, ProgramVariable};
+
+/// Number of rows in every fragment but the last.
+pub const FRAGMENT_LEN: usize = 1 << 10;
+
+/// A trace the prover can fill fragment by fragment.
+///
+/// Tables must be `Sync`, as fragments may be filled from several threads at once.
+pub trait TraceTable<F: PrimeField = Fr>: Sync {
+    /// Names of the trace columns, in the order `fill` writes them.
+    ///
+    /// The prover commits the columns in this order, so it must be the sorted order
+    /// that `ExecutionTrace::variables` returns as well.
+    fn variables(&self) -> Vec<ProgramVariable>;
+
+    /// Number of trace columns.
+    fn width(&self) -> usize {
+        self.variables().len()
+    }
+
+    /// Number of rows, before padding to a power of two.
+    fn length(&self) -> usize;
+
+    /// Columns the prover fills with fresh randomness, see
+    /// `ExecutionTrace::add_randomizer_column`.
+    fn randomizers(&self) -> Vec<ProgramVariable> {
+        Vec::new()
+    }
+
+    /// Writes every value of the rows covered by `fragment`.
+    ///
+    /// Fragments are filled independently and in no particular order.
+    fn fill(&self, fragment: &mut TraceFragment<'_, F>) -> Result<(), ToyniError>;
+
+    /// Extension of the columns over the coset of `coset` with `blowup` times as many
+    /// points, if the table caches one, see `ExecutionTrace::lde`.
+    ///
+    /// The prover asks for it when the table needs no padding and the trace domain has
+    /// no blinding rows, and builds on it instead of interpolating the columns again.
+    fn lde(&self, _blowup: usize, _coset: F) -> Option<Result<Arc<TraceLde<F>>, ToyniError>> {
+        None
+    }
+}
+
+/// Consecutive rows of every trace column, handed to `TraceTable::fill`.
+pub struct TraceFragment<'a, F: PrimeField = Fr> {
+    /// Index of the first row of the fragment within the trace
+    offset: usize,
+    /// Number of rows in the fragment
+    num_rows: usize,
+    /// The rows of every column, in the order of `TraceTable::variables`
+    columns: Vec<&'a mut [F]>,
+}
+
+impl<F: PrimeField> TraceFragment<'_, F> {
+    /// Index of the first row of the fragment within the trace.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Number of rows in the fragment.
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    /// Number of columns in the fragment.
+    pub fn width(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// The rows of column `column` in the fragment.
+    pub fn column_mut(&mut self, column: usize) -> &mut [F] {
+        self.columns[column]
+    }
+
+    /// Sets the value of `column` at row `row` of the fragment, counted from its offset.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the row or column lies outside the fragment
+    pub fn set(&mut self, row: usize, column: usize, value: impl Into<F>) {
+        self.columns[column][row] = value.into();
+    }
+}
+
+/// Fills the columns of `table` fragment by fragment and pads them to the next power of
+/// two rows.
+///
+/// # Returns
+///
+/// The columns in the order of `TraceTable::variables`
+///
+/// # Errors
+///
+/// Returns any error of `TraceTable::fill`, `ToyniError::TraceTooShort` if there is no
+/// row to repeat, and `ToyniError::UnknownVariable` or `ToyniError::WidthMismatch` if a
+/// dummy row does not hold one value per column
+pub fn fill_columns<F: PrimeField>(
+    table: &dyn TraceTable<F>,
+    padding: &PaddingStrategy<F>,
+) -> Result<Vec<Vec<F>>, ToyniError> {
+    let length = table.length();
+    let mut columns = vec![vec![F::ZERO; length]; table.width()];
+    let mut fragments: Vec<TraceFragment<'_, F>> = (0..length)
+        .step_by(FRAGMENT_LEN)
+        .map(|offset| TraceFragment {
+            offset,
+            num_rows: FRAGMENT_LEN.min(length - offset),
+            columns: Vec::with_capacity(columns.len()),
+        })
+        .collect();
+    for column in &mut columns {
+        for (fragment, rows) in fragments.iter_mut().zip(column.chunks_mut(FRAGMENT_LEN)) {
+            fragment.columns.push(rows);
+        }
+    }
+    into_iter!(fragments).try_for_each(|mut fragment| table.fill(&mut fragment))?;
+
+    if length.is_power_of_two() {
+        return Ok(columns);
+    }
+    let row = match padding {
+        PaddingStrategy::RepeatLastRow => {
+            if length == 0 {
+                return Err(ToyniError::TraceTooShort { rows: 0, height: 1 });
+            }
+            columns.iter().map(|column| column[length - 1]).collect()
+        }
+        PaddingStrategy::DummyRow(row) => {
+            let variables = table.variables();
+            if let Some(variable) = row.keys().find(|variable| !variables.contains(variable)) {
+                return Err(ToyniError::UnknownVariable(variable.clone()));
+            }
+            if row.len() != variables.len() {
+                return Err(ToyniError::WidthMismatch {
+                    expected: variables.len() as u64,
+                    actual: row.len(),
+                });
+            }
+            variables
+                .iter()
+                .map(|variable| row[variable])
+                .collect::<Vec<F>>()
+        }
+    };
+    for (column, value) in columns.iter_mut().zip(row) {
+        column.resize(length.next_power_of_two(), value);
+    }
+    Ok(columns)
+}
+
+impl<F: PrimeField> TraceTable<F> for ExecutionTrace<F> {
+    fn variables(&self) -> Vec<ProgramVariable> {
+        ExecutionTrace::variables(self)
+    }
+
+    fn length(&self) -> usize {
+        self.height as usize
+    }
+
+    fn randomizers(&self) -> Vec<ProgramVariable> {
+        self.randomizers.clone()
+    }
+
+    fn lde(&self, blowup: usize, coset: F) -> Option<Result<Arc<TraceLde<F>>, ToyniError>> {
+        self.height
+            .is_power_of_two()
+            .then(|| ExecutionTrace::lde(self, blowup, coset))
+    }
+
+    fn fill(&self, fragment: &mut TraceFragment<'_, F>) -> Result<(), ToyniError> {
+        self.validate()?;
+        let variables = ExecutionTrace::variables(self);
+        for row in 0..fragment.num_rows() {
+            let values = self.get_column((fragment.offset() + row) as u64)?;
+            for (column, variable) in variables.iter().enumerate() {
+                let value = values
+                    .get(variable)
+                    .ok_or_else(|| ToyniError::UnknownVariable(variable.clone()))?;
+                fragment.set(row, column, *value);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Squares of the row index, generated without recording any row.
+    struct Squares {
+        length: usize,
+    }
+
+    impl TraceTable for Squares {
+        fn variables(&self) -> Vec<ProgramVariable> {
+            vec!["i".to_string(), "square".to_string()]
+        }
+
+        fn length(&self) -> usize {
+            self.length
+        }
+
+        fn fill(&self, fragment: &mut TraceFragment<'_, Fr>) -> Result<(), ToyniError> {
+            for row in 0..fragment.num_rows() {
+                let i = (fragment.offset() + row) as u64;
+                fragment.set(row, 0, i);
+                fragment.set(row, 1, i * i);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_fill_columns_across_fragments() {
+        let length = 2 * FRAGMENT_LEN + 5;
+        let columns = fill_columns(&Squares { length }, &PaddingStrategy::RepeatLastRow).unwrap();
+        assert_eq!(columns.len(), 2);
+        for (i, (index, square)) in columns[0].iter().zip(&columns[1]).enumerate() {
+            // Rows past the table are copies of its last row
+            let i = i.min(length - 1) as u64;
+            assert_eq!((*index, *square), (Fr::from(i), Fr::from(i * i)));
+        }
+        assert_eq!(columns[0].len(), 4 * FRAGMENT_LEN);
+    }
+
+    #[test]
+    fn test_execution_trace_table() {
+        let mut trace = ExecutionTrace::<Fr>::new(3, 2);
+        for i in 0..3u64 {
+            let row = HashMap::from([("y".to_string(), 2 * i), ("x".to_string(), i)]);
+            trace.insert_column(row).unwrap();
+        }
+        let dummy = HashMap::from([
+            ("x".to_string(), Fr::from(9u64)),
+            ("y".to_string(), Fr::from(0u64)),
+        ]);
+        let columns = fill_columns(&trace, &PaddingStrategy::DummyRow(dummy)).unwrap();
+        assert_eq!(columns[0], [0u64, 1, 2, 9].map(Fr::from));
+        assert_eq!(columns[1], [0u64, 2, 4, 0].map(Fr::from));
+
+        let dummy = HashMap::from([("x".to_string(), Fr::from(9u64))]);
+        assert_eq!(
+            fill_columns(&trace, &PaddingStrategy::DummyRow(dummy)),
+            Err(ToyniError::WidthMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+}
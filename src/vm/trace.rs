@@ -1,85 +1,686 @@
 //! Execution trace recording for virtual machine.
 //!
 //! Records program execution as a matrix where columns are variables and rows are execution steps.
+//!
+//! Cells are field elements, so a trace can hold negative values, values beyond 64 bits
+//! and hash outputs. The prover commits the trace over the base prime field of the field
+//! it proves over.
+
+use ark_bls12_381::Fr;
+use ark_ff::{Field, PrimeField};
+use ark_poly::{
+    DenseUVPolynomial, EvaluationDomain, GeneralEvaluationDomain, univariate::DensePolynomial,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex};
 
-use std::collections::HashMap;
+use crate::error::ToyniError;
+use crate::math::domain::barycentric_evaluate;
+use crate::math::lde::TraceLde;
 
 /// Program variable name type.
 pub type ProgramVariable = String;
 
+/// One recorded execution step, with its values ordered by variable name.
+pub type TraceRow<F = Fr> = BTreeMap<ProgramVariable, F>;
+
+/// How `ExecutionTrace::pad` fills the rows up to the next power of two.
+///
+/// Padding rows are trace rows like any other, so the transition constraints must hold
+/// across them as well.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum PaddingStrategy<F: PrimeField = Fr> {
+    /// Every padding row is a copy of the last row
+    #[default]
+    RepeatLastRow,
+    /// Every padding row holds the given values, which the constraints accept as a dummy
+    /// row, e.g. through a selector column that is zero on it
+    DummyRow(HashMap<ProgramVariable, F>),
+}
+
 /// Execution trace storing program state changes.
-pub struct ExecutionTrace {
+#[derive(Clone)]
+pub struct ExecutionTrace<F: PrimeField = Fr> {
     /// Number of execution steps
     pub height: u64,
     /// Number of program variables
     pub width: u64,
     /// Trace data as vector of variable-value maps
-    pub trace: Vec<HashMap<ProgramVariable, u64>>,
+    pub trace: Vec<TraceRow<F>>,
+    /// Columns the prover fills with fresh randomness, hidden from the constraints
+    pub randomizers: Vec<ProgramVariable>,
+    /// Number of rows at the end of the trace appended by `pad`
+    pub padding: u64,
+    /// Extension computed by `lde`, dropped when a row or column is added
+    lde_cache: LdeCache<F>,
+}
+
+/// An extension computed by `ExecutionTrace::lde`, with its blowup, coset and the
+/// fingerprint of the rows it extends.
+type CachedLde<F> = (usize, F, u64, Arc<TraceLde<F>>);
+
+/// The last extension computed by `ExecutionTrace::lde`.
+#[derive(Default)]
+struct LdeCache<F: PrimeField>(Mutex<Option<CachedLde<F>>>);
+
+impl<F: PrimeField> Clone for LdeCache<F> {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.0.lock().unwrap().clone()))
+    }
 }
 
-impl ExecutionTrace {
+impl<F: PrimeField> LdeCache<F> {
+    fn clear(&mut self) {
+        *self.0.get_mut().unwrap() = None;
+    }
+}
+
+impl<F: PrimeField> ExecutionTrace<F> {
     /// Creates empty trace with given dimensions.
     pub fn new(height: u64, width: u64) -> Self {
         Self {
             height,
             width,
             trace: Vec::new(),
+            randomizers: Vec::new(),
+            padding: 0,
+            lde_cache: LdeCache::default(),
         }
     }
 
+    /// Creates a trace of `height` rows over `columns`, where row `i` holds the values
+    /// `f(i)` in the order of `columns`.
+    ///
+    /// The trace is not padded; the prover pads it if its height is not a power of two.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::DuplicateColumn` if a column name repeats
+    pub fn from_fn<const W: usize, S: Into<ProgramVariable>, V: Into<F>>(
+        columns: [S; W],
+        height: u64,
+        mut f: impl FnMut(u64) -> [V; W],
+    ) -> Result<Self, ToyniError> {
+        let columns = distinct_columns(columns.map(Into::into))?;
+        let mut trace = ExecutionTrace::new(height, W as u64);
+        for row in 0..height {
+            trace.insert_column(columns.iter().cloned().zip(f(row)))?;
+        }
+        Ok(trace)
+    }
+
+    /// Creates a trace from named columns of equal length, one row per value.
+    ///
+    /// The trace is not padded; the prover pads it if its height is not a power of two.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::DuplicateColumn` if a column name repeats and
+    /// `ToyniError::ColumnLengthMismatch` if a column is not as long as the first
+    pub fn from_columns<S: Into<ProgramVariable>, V: Into<F>>(
+        columns: impl IntoIterator<Item = (S, Vec<V>)>,
+    ) -> Result<Self, ToyniError> {
+        let (names, columns): (Vec<ProgramVariable>, Vec<Vec<V>>) = columns
+            .into_iter()
+            .map(|(name, values)| (name.into(), values))
+            .unzip();
+        let names = distinct_columns(names)?;
+        let height = columns.first().map_or(0, Vec::len);
+        let mismatch = names
+            .iter()
+            .zip(&columns)
+            .find(|(_, column)| column.len() != height);
+        if let Some((name, column)) = mismatch {
+            return Err(ToyniError::ColumnLengthMismatch {
+                column: name.clone(),
+                expected: height,
+                actual: column.len(),
+            });
+        }
+
+        let mut trace = ExecutionTrace::new(height as u64, names.len() as u64);
+        let mut columns: Vec<_> = columns.into_iter().map(Vec::into_iter).collect();
+        for _ in 0..height {
+            let row = names
+                .iter()
+                .cloned()
+                .zip(columns.iter_mut().map(|column| column.next().unwrap()));
+            trace.insert_column(row)?;
+        }
+        Ok(trace)
+    }
+
+    /// Designates a randomizer column.
+    ///
+    /// Randomizer columns hold no witness data: the prover fills them with fresh field
+    /// elements, commits them alongside the trace and adds them to the composition
+    /// polynomial to hide it. Constraints cannot read them.
+    pub fn add_randomizer_column(&mut self, name: ProgramVariable) -> Result<(), ToyniError> {
+        self.check_column_name(&name)?;
+        self.randomizers.push(name);
+        Ok(())
+    }
+
     /// Adds new execution step to trace.
-    pub fn insert_column(&mut self, column: HashMap<ProgramVariable, u64>) {
-        assert!(column.len() == self.width as usize);
-        assert!(self.trace.len() < self.height as usize);
+    ///
+    /// The row can be given as any map or list of variable-value pairs. Values are
+    /// converted into the field, so integer columns can be inserted as they are, and
+    /// stored in variable order. The first row fixes the columns of the trace; every
+    /// later row must hold exactly the same variables.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::ColumnSchemaMismatch` if the row's variables differ from
+    /// those of the rows recorded so far, `ToyniError::WidthMismatch` if the first row
+    /// does not hold `width` values and `ToyniError::TraceFull` if all `height` rows
+    /// are recorded
+    pub fn insert_column<V: Into<F>>(
+        &mut self,
+        column: impl IntoIterator<Item = (ProgramVariable, V)>,
+    ) -> Result<(), ToyniError> {
+        let column: TraceRow<F> = column
+            .into_iter()
+            .map(|(variable, value)| (variable, value.into()))
+            .collect();
+        if let Some(schema) = self.trace.first()
+            && (column.len() != schema.len()
+                || column.keys().any(|variable| !schema.contains_key(variable)))
+        {
+            let missing = schema
+                .keys()
+                .filter(|variable| !column.contains_key(*variable))
+                .cloned()
+                .collect();
+            let extra = column
+                .keys()
+                .filter(|variable| !schema.contains_key(*variable))
+                .cloned()
+                .collect();
+            return Err(ToyniError::ColumnSchemaMismatch { missing, extra });
+        }
+        if column.len() != self.width as usize {
+            return Err(ToyniError::WidthMismatch {
+                expected: self.width,
+                actual: column.len(),
+            });
+        }
+        if self.trace.len() >= self.height as usize {
+            return Err(ToyniError::TraceFull {
+                height: self.height,
+            });
+        }
+        self.lde_cache.clear();
         self.trace.push(column);
+        Ok(())
+    }
+
+    /// Adds a column holding `value` on every execution step recorded so far.
+    pub fn add_constant_column(
+        &mut self,
+        name: ProgramVariable,
+        value: impl Into<F>,
+    ) -> Result<(), ToyniError> {
+        self.check_column_name(&name)?;
+        let value = value.into();
+        self.lde_cache.clear();
+        for column in &mut self.trace {
+            column.insert(name.clone(), value);
+        }
+        self.width += 1;
+        Ok(())
+    }
+
+    /// Checks that every one of the `height` execution steps has been recorded.
+    pub fn validate(&self) -> Result<(), ToyniError> {
+        if self.trace.len() < self.height as usize {
+            return Err(ToyniError::TraceTooShort {
+                rows: self.trace.len(),
+                height: self.height,
+            });
+        }
+        Ok(())
+    }
+
+    /// Pads the trace to the next power of two rows, as the prover requires, recording
+    /// how many rows were appended in `padding`.
+    ///
+    /// Rows keep their indices, so boundary constraints on the last recorded row, see
+    /// `last_row`, apply unchanged. Traces whose height is a power of two are left as
+    /// they are.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::TraceTooShort` if rows are missing or there is no row to
+    /// repeat, and `ToyniError::UnknownVariable` or `ToyniError::WidthMismatch` if a
+    /// dummy row does not hold one value per trace column
+    pub fn pad(&mut self, strategy: &PaddingStrategy<F>) -> Result<(), ToyniError> {
+        self.validate()?;
+        if self.height.is_power_of_two() {
+            return Ok(());
+        }
+        let row = match strategy {
+            PaddingStrategy::RepeatLastRow => self
+                .trace
+                .last()
+                .cloned()
+                .ok_or(ToyniError::TraceTooShort { rows: 0, height: 1 })?,
+            PaddingStrategy::DummyRow(row) => {
+                // An empty trace takes its columns from the dummy row
+                let variables = self.variables();
+                let unknown = row
+                    .keys()
+                    .find(|variable| !variables.is_empty() && !variables.contains(variable));
+                if let Some(variable) = unknown {
+                    return Err(ToyniError::UnknownVariable(variable.clone()));
+                }
+                row.clone().into_iter().collect()
+            }
+        };
+
+        let height = self.height.next_power_of_two();
+        let padding = height - self.height;
+        self.height = height;
+        for _ in 0..padding {
+            self.insert_column(row.clone())?;
+        }
+        self.padding += padding;
+        Ok(())
+    }
+
+    /// Height of the trace once padded, the trace length to verify its proofs against.
+    pub fn padded_height(&self) -> u64 {
+        self.height.next_power_of_two()
+    }
+
+    /// Index of the last row recorded before padding, or `None` for an empty trace.
+    pub fn last_row(&self) -> Option<u64> {
+        (self.height - self.padding).checked_sub(1)
+    }
+
+    /// Value of `column` at row `row`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::RowOutOfBounds` if the row has not been recorded and
+    /// `ToyniError::UnknownVariable` if the trace has no such column
+    pub fn get(&self, row: u64, column: &str) -> Result<F, ToyniError> {
+        self.get_column(row)?
+            .get(column)
+            .copied()
+            .ok_or_else(|| ToyniError::UnknownVariable(column.to_string()))
+    }
+
+    /// Values of `column` on every recorded row, in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::UnknownVariable` if a row has no such column
+    pub fn column_iter<'a>(
+        &'a self,
+        column: &'a str,
+    ) -> Result<impl ExactSizeIterator<Item = F> + 'a, ToyniError> {
+        if self.trace.iter().any(|row| !row.contains_key(column)) {
+            return Err(ToyniError::UnknownVariable(column.to_string()));
+        }
+        Ok(self.trace.iter().map(move |row| row[column]))
+    }
+
+    /// Every recorded row, in order.
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &TraceRow<F>> {
+        self.trace.iter()
+    }
+
+    /// Every recorded row but the last with its successor, indexed by the row, i.e.
+    /// the row pairs a transition constraint is evaluated on.
+    pub fn transitions(&self) -> impl Iterator<Item = (u64, &TraceRow<F>, &TraceRow<F>)> {
+        self.trace
+            .windows(2)
+            .enumerate()
+            .map(|(row, pair)| (row as u64, &pair[0], &pair[1]))
+    }
+
+    /// Low-degree extension of the columns, in the order of `variables`, over the coset
+    /// `coset·⟨w⟩` of `blowup` times the trace height.
+    ///
+    /// The extension is computed once and shared by every later call with the same
+    /// blowup and coset, as long as the trace is unchanged. The cache is keyed on a hash
+    /// of the height and rows, so edits through the public fields are noticed as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::TraceTooShort` if rows are missing and
+    /// `ToyniError::NonPowerOfTwoDomain` if the height or the extended size is not a
+    /// power of two, or the coset is not a valid offset
+    pub fn lde(&self, blowup: usize, coset: F) -> Result<Arc<TraceLde<F>>, ToyniError> {
+        let fingerprint = self.fingerprint();
+        let mut cache = self.lde_cache.0.lock().unwrap();
+        if let Some((cached_blowup, cached_coset, cached_fingerprint, lde)) = cache.as_ref()
+            && (*cached_blowup, *cached_coset, *cached_fingerprint) == (blowup, coset, fingerprint)
+        {
+            return Ok(lde.clone());
+        }
+        let domain = self.trace_domain()?;
+        let height = domain.size();
+        let extended_len = height * blowup;
+        let extended_domain = GeneralEvaluationDomain::<F>::new(extended_len)
+            .filter(|domain| domain.size() == extended_len)
+            .and_then(|domain| domain.get_coset(coset))
+            .ok_or(ToyniError::NonPowerOfTwoDomain(extended_len))?;
+        let polynomials = self
+            .variables()
+            .iter()
+            .map(|variable| {
+                let values = self.column_values(variable)?;
+                Ok(DensePolynomial::from_coefficients_vec(domain.ifft(&values)))
+            })
+            .collect::<Result<Vec<_>, ToyniError>>()?;
+        let lde = Arc::new(TraceLde::new(polynomials, height, extended_domain, false));
+        *cache = Some((blowup, coset, fingerprint, lde.clone()));
+        Ok(lde)
+    }
+
+    /// Hash of the height and every row, identifying the contents an extension was
+    /// computed from.
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.height.hash(&mut hasher);
+        self.trace.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Interpolates the column `variable` over the trace domain, i.e. the polynomial of
+    /// degree below the height taking the value of row `i` at the `i`-th domain point.
+    ///
+    /// This is the column polynomial the prover commits to, before blinding.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::TraceTooShort` if rows are missing,
+    /// `ToyniError::UnknownVariable` if `variable` is not a column and
+    /// `ToyniError::NonPowerOfTwoDomain` if the height is not a power of two
+    pub fn column_poly(&self, variable: &str) -> Result<DensePolynomial<F>, ToyniError> {
+        let domain = self.trace_domain()?;
+        let values = self.column_values(variable)?;
+        Ok(DensePolynomial::from_coefficients_vec(domain.ifft(&values)))
+    }
+
+    /// Evaluates the polynomial of the column `variable` at `x`, see `column_poly`.
+    ///
+    /// Uses the barycentric formula over the column values, so the polynomial is never
+    /// interpolated.
+    ///
+    /// # Errors
+    ///
+    /// See [`ExecutionTrace::column_poly`].
+    pub fn eval_column_at(&self, variable: &str, x: F) -> Result<F, ToyniError> {
+        let domain = self.trace_domain()?;
+        let values = self.column_values(variable)?;
+        Ok(barycentric_evaluate(&values, &domain, x))
+    }
+
+    /// The subgroup of the trace height, after checking that every row is recorded.
+    fn trace_domain(&self) -> Result<GeneralEvaluationDomain<F>, ToyniError> {
+        self.validate()?;
+        let height = self.height as usize;
+        GeneralEvaluationDomain::<F>::new(height)
+            .filter(|domain| domain.size() == height)
+            .ok_or(ToyniError::NonPowerOfTwoDomain(height))
+    }
+
+    /// Every value of the column `variable`, in row order.
+    fn column_values(&self, variable: &str) -> Result<Vec<F>, ToyniError> {
+        self.trace
+            .iter()
+            .map(|row| {
+                row.get(variable)
+                    .copied()
+                    .ok_or_else(|| ToyniError::UnknownVariable(variable.to_string()))
+            })
+            .collect()
     }
 
     /// Gets execution step by index.
-    pub fn get_column(&self, index: u64) -> &HashMap<ProgramVariable, u64> {
-        &self.trace[index as usize]
+    pub fn get_column(&self, index: u64) -> Result<&TraceRow<F>, ToyniError> {
+        self.trace
+            .get(index as usize)
+            .ok_or(ToyniError::RowOutOfBounds {
+                index,
+                rows: self.trace.len(),
+            })
+    }
+
+    /// Gets execution step by index with values lifted into a field `E` over the trace
+    /// field, e.g. the extension the constraints are evaluated over.
+    pub fn get_field_column<E: Field<BasePrimeField = F>>(
+        &self,
+        index: u64,
+    ) -> Result<HashMap<ProgramVariable, E>, ToyniError> {
+        Ok(self
+            .get_column(index)?
+            .iter()
+            .map(|(variable, value)| (variable.clone(), E::from_base_prime_field(*value)))
+            .collect())
+    }
+
+    /// Fails if `name` is already a trace or randomizer column.
+    fn check_column_name(&self, name: &ProgramVariable) -> Result<(), ToyniError> {
+        if self.randomizers.contains(name) || self.variables().contains(name) {
+            return Err(ToyniError::DuplicateColumn(name.clone()));
+        }
+        Ok(())
+    }
+
+    /// Returns the variable names in sorted order.
+    ///
+    /// Gives every consumer that needs a fixed column order (LDE, commitments) the same one,
+    /// which is also the order the values of every row are stored in.
+    pub fn variables(&self) -> Vec<ProgramVariable> {
+        self.trace
+            .first()
+            .map(|column| column.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Compares the trace cell by cell with `other`.
+    ///
+    /// Rows are matched by index. A cell present in only one trace, because of a
+    /// missing row or column, is reported with `None` on the other side.
+    pub fn diff(&self, other: &ExecutionTrace<F>) -> TraceDiff<F> {
+        let rows = self.trace.len().max(other.trace.len());
+        let mut cells = Vec::new();
+        for row in 0..rows {
+            let left = self.trace.get(row);
+            let right = other.trace.get(row);
+            let columns: BTreeSet<&ProgramVariable> = left
+                .iter()
+                .chain(right.iter())
+                .flat_map(|values| values.keys())
+                .collect();
+            for column in columns {
+                let left = left.and_then(|values| values.get(column)).copied();
+                let right = right.and_then(|values| values.get(column)).copied();
+                if left != right {
+                    cells.push(CellDiff {
+                        row: row as u64,
+                        column: column.clone(),
+                        left,
+                        right,
+                    });
+                }
+            }
+        }
+        TraceDiff { cells }
     }
 
     /// Prints trace in tabular format.
     pub fn print_trace(&self, variables: Vec<ProgramVariable>) {
-        for i in 0..self.height {
-            let column = self.get_column(i);
+        for column in &self.trace {
             for var in &variables {
-                print!("{} |", column.get(var).unwrap_or(&0));
+                print!("{} |", column.get(var).copied().unwrap_or(F::ZERO));
             }
             println!();
         }
     }
 
     /// Interpolates variable value between two steps.
-    pub fn interpolate(&self, variable: &ProgramVariable, step1: u64, step2: u64, t: u8) -> u64 {
-        assert!(
-            step1 < self.height && step2 < self.height,
-            "Step indices out of bounds"
-        );
-        assert!(
-            t <= 100,
-            "Interpolation parameter must be between 0 and 100"
-        );
+    ///
+    /// Computes `v1 + (v2 - v1) * t / 100` exactly in the field. This linear blend of two
+    /// rows is unrelated to the column polynomials the proof is built from; see
+    /// `column_poly` and `eval_column_at` for those.
+    pub fn interpolate(
+        &self,
+        variable: &ProgramVariable,
+        step1: u64,
+        step2: u64,
+        t: u8,
+    ) -> Result<F, ToyniError> {
+        if t > 100 {
+            return Err(ToyniError::InvalidInterpolationParameter(t));
+        }
 
-        let col1 = self.get_column(step1);
-        let col2 = self.get_column(step2);
+        let val1 = self.get(step1, variable)?;
+        let val2 = self.get(step2, variable)?;
 
-        let val1 = *col1
-            .get(variable)
-            .expect("Variable not found in first step");
-        let val2 = *col2
-            .get(variable)
-            .expect("Variable not found in second step");
+        let scale = F::from(t as u64) * F::from(100u64).inverse().unwrap();
+        Ok(val1 + (val2 - val1) * scale)
+    }
+}
+
+/// A cell that differs between two traces, see `ExecutionTrace::diff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellDiff<F: PrimeField = Fr> {
+    /// Row of the cell
+    pub row: u64,
+    /// Column of the cell
+    pub column: ProgramVariable,
+    /// Value in the trace `diff` was called on, `None` if it has no such cell
+    pub left: Option<F>,
+    /// Value in the other trace, `None` if it has no such cell
+    pub right: Option<F>,
+}
 
-        let diff = val2 - val1;
-        let scaled_diff = (diff * (t as u64)) / 100;
-        val1 + scaled_diff
+/// Every differing cell of two traces, ordered by row and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceDiff<F: PrimeField = Fr> {
+    /// The differing cells
+    pub cells: Vec<CellDiff<F>>,
+}
+
+impl<F: PrimeField> TraceDiff<F> {
+    /// Whether both traces hold the same cells.
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
     }
+
+    /// Rows holding at least one differing cell, in order.
+    pub fn rows(&self) -> Vec<u64> {
+        let mut rows: Vec<u64> = self.cells.iter().map(|cell| cell.row).collect();
+        rows.dedup();
+        rows
+    }
+}
+
+impl<F: PrimeField> fmt::Display for TraceDiff<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<F>| match value {
+            Some(value) => value.to_string(),
+            None => "missing".to_string(),
+        };
+        for cell in &self.cells {
+            writeln!(
+                f,
+                "row {}, column {}: {} | {}",
+                cell.row,
+                cell.column,
+                show(&cell.left),
+                show(&cell.right)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Builder for an `ExecutionTrace` over a fixed set of `W` columns, started with
+/// `TraceBuilder::new`.
+///
+/// Rows are arrays with one value per column in the order the columns were registered,
+/// so a row of the wrong width fails to compile instead of at proving time. `build`
+/// pads the rows to a power of two by repeating the last one.
+pub struct TraceBuilder<const W: usize, F: PrimeField = Fr> {
+    columns: [ProgramVariable; W],
+    rows: Vec<[F; W]>,
+    randomizers: Vec<ProgramVariable>,
+}
+
+impl<const W: usize, F: PrimeField> TraceBuilder<W, F> {
+    /// Registers the trace columns.
+    pub fn new<S: Into<ProgramVariable>>(columns: [S; W]) -> Self {
+        Self {
+            columns: columns.map(Into::into),
+            rows: Vec::new(),
+            randomizers: Vec::new(),
+        }
+    }
+
+    /// Appends an execution step, with values in the order of the columns.
+    pub fn row<V: Into<F>>(mut self, values: [V; W]) -> Self {
+        self.rows.push(values.map(Into::into));
+        self
+    }
+
+    /// Appends every execution step of `rows`, see `row`.
+    pub fn rows<V: Into<F>>(mut self, rows: impl IntoIterator<Item = [V; W]>) -> Self {
+        self.rows
+            .extend(rows.into_iter().map(|values| values.map(Into::into)));
+        self
+    }
+
+    /// Designates a randomizer column, see `ExecutionTrace::add_randomizer_column`.
+    pub fn randomizer<S: Into<ProgramVariable>>(mut self, name: S) -> Self {
+        self.randomizers.push(name.into());
+        self
+    }
+
+    /// Builds the trace, padded to the next power of two rows with copies of the last
+    /// row.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::IncompleteBuilder` if no row was added and
+    /// `ToyniError::DuplicateColumn` if a column name is registered twice
+    pub fn build(self) -> Result<ExecutionTrace<F>, ToyniError> {
+        if self.rows.is_empty() {
+            return Err(ToyniError::IncompleteBuilder("rows"));
+        }
+        let mut rows = self.rows.into_iter();
+        let mut trace =
+            ExecutionTrace::from_fn(self.columns, rows.len() as u64, |_| rows.next().unwrap())?;
+        trace.pad(&PaddingStrategy::RepeatLastRow)?;
+        for name in self.randomizers {
+            trace.add_randomizer_column(name)?;
+        }
+        Ok(trace)
+    }
+}
+
+/// Fails on the first column name that repeats.
+pub(crate) fn distinct_columns<C: AsRef<[ProgramVariable]>>(columns: C) -> Result<C, ToyniError> {
+    let names = columns.as_ref();
+    for (i, column) in names.iter().enumerate() {
+        if names[..i].contains(column) {
+            return Err(ToyniError::DuplicateColumn(column.clone()));
+        }
+    }
+    Ok(columns)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ark_ff::{AdditiveGroup, FftField};
+    use ark_poly::Polynomial;
 
     fn generate_test_trace() -> ExecutionTrace {
         let mut execution_trace = ExecutionTrace::new(5, 5);
@@ -90,35 +691,401 @@ mod tests {
             column.insert("c".to_string(), i + 2);
             column.insert("d".to_string(), i + 3);
             column.insert("e".to_string(), i + 4);
-            execution_trace.insert_column(column);
+            execution_trace.insert_column(column).unwrap();
         }
         execution_trace
     }
-    
+
     #[test]
     fn test_interpolation() {
         let execution_trace = generate_test_trace();
 
         // Test variable "a" interpolation between steps 0 and 1
         // step 0: 0, step 1: 1
-        let interpolated = execution_trace.interpolate(&"a".to_string(), 0, 1, 50);
-        assert_eq!(interpolated, 0); // At 50% between 0 and 1
+        let interpolated = execution_trace
+            .interpolate(&"a".to_string(), 0, 1, 50)
+            .unwrap();
+        assert_eq!(interpolated, Fr::from(2u64).inverse().unwrap()); // At 50% between 0 and 1
 
         // Test variable "b" interpolation between steps 0 and 1
         // step 0: 1, step 1: 2
-        let interpolated = execution_trace.interpolate(&"b".to_string(), 0, 1, 0);
-        assert_eq!(interpolated, 1);
+        let interpolated = execution_trace
+            .interpolate(&"b".to_string(), 0, 1, 0)
+            .unwrap();
+        assert_eq!(interpolated, Fr::from(1u64));
 
         // Test variable "c" interpolation between steps 0 and 1
         // step 0: 2, step 1: 3
-        let interpolated = execution_trace.interpolate(&"c".to_string(), 0, 1, 100);
-        assert_eq!(interpolated, 3);
+        let interpolated = execution_trace
+            .interpolate(&"c".to_string(), 0, 1, 100)
+            .unwrap();
+        assert_eq!(interpolated, Fr::from(3u64));
 
         // Additional test cases
-        let interpolated = execution_trace.interpolate(&"a".to_string(), 0, 1, 100);
-        assert_eq!(interpolated, 1);
+        let interpolated = execution_trace
+            .interpolate(&"a".to_string(), 0, 1, 100)
+            .unwrap();
+        assert_eq!(interpolated, Fr::from(1u64));
+
+        let interpolated = execution_trace
+            .interpolate(&"b".to_string(), 0, 1, 100)
+            .unwrap();
+        assert_eq!(interpolated, Fr::from(2u64));
+
+        // Decreasing values interpolate below the earlier one without wrapping
+        let mut decreasing = ExecutionTrace::<Fr>::new(2, 1);
+        for value in [Fr::from(3u64), -Fr::from(1u64)] {
+            decreasing
+                .insert_column(HashMap::from([("a".to_string(), value)]))
+                .unwrap();
+        }
+        let interpolated = decreasing.interpolate(&"a".to_string(), 0, 1, 25).unwrap();
+        assert_eq!(interpolated, Fr::from(2u64));
+    }
+
+    #[test]
+    fn test_column_poly() {
+        let trace = ExecutionTrace::<Fr>::from_fn(["x", "y"], 8, |i| [i, i * i + 1]).unwrap();
+        let poly = trace.column_poly("y").unwrap();
+        assert!(poly.degree() < 8);
+        let domain = GeneralEvaluationDomain::<Fr>::new(8).unwrap();
+        for (i, x) in domain.elements().enumerate() {
+            assert_eq!(poly.evaluate(&x), trace.get(i as u64, "y").unwrap());
+            assert_eq!(trace.eval_column_at("y", x).unwrap(), poly.evaluate(&x));
+        }
+        let x = Fr::from(12345u64);
+        assert_eq!(trace.eval_column_at("y", x).unwrap(), poly.evaluate(&x));
+
+        assert_eq!(
+            trace.column_poly("z").err(),
+            Some(ToyniError::UnknownVariable("z".to_string()))
+        );
+        let short = ExecutionTrace::<Fr>::from_fn(["x"], 5, |i| [i]).unwrap();
+        assert_eq!(
+            short.eval_column_at("x", x).err(),
+            Some(ToyniError::NonPowerOfTwoDomain(5))
+        );
+    }
+
+    #[test]
+    fn test_variables_are_sorted() {
+        let execution_trace = generate_test_trace();
+        assert_eq!(execution_trace.variables(), vec!["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn test_rows_are_ordered() {
+        // Rows given in any order are stored, iterated and written in variable order
+        let mut forward = ExecutionTrace::<Fr>::new(1, 3);
+        forward
+            .insert_column([("x", 1u64), ("y", 2), ("z", 3)].map(|(v, x)| (v.to_string(), x)))
+            .unwrap();
+        let mut backward = ExecutionTrace::<Fr>::new(1, 3);
+        backward
+            .insert_column([("z", 3u64), ("y", 2), ("x", 1)].map(|(v, x)| (v.to_string(), x)))
+            .unwrap();
+        let values: Vec<Fr> = backward.get_column(0).unwrap().values().copied().collect();
+        assert_eq!(values, [1u64, 2, 3].map(Fr::from));
+
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+        forward.to_writer(&mut left).unwrap();
+        backward.to_writer(&mut right).unwrap();
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn test_randomizer_columns() {
+        let mut execution_trace = generate_test_trace();
+        execution_trace
+            .add_randomizer_column("r".to_string())
+            .unwrap();
+        assert_eq!(execution_trace.randomizers, vec!["r"]);
+        // Randomizers are not program variables
+        assert_eq!(execution_trace.variables(), vec!["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn test_constant_column() {
+        let mut execution_trace = generate_test_trace();
+        execution_trace
+            .add_constant_column("k".to_string(), 7)
+            .unwrap();
+        assert_eq!(execution_trace.width, 6);
+        assert!(
+            execution_trace
+                .column_iter("k")
+                .unwrap()
+                .all(|k| k == Fr::from(7u64))
+        );
+    }
+
+    #[test]
+    fn test_randomizer_name_collision() {
+        let mut execution_trace = generate_test_trace();
+        assert_eq!(
+            execution_trace.add_randomizer_column("a".to_string()),
+            Err(ToyniError::DuplicateColumn("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_trace_errors() {
+        let mut execution_trace = ExecutionTrace::<Fr>::new(2, 1);
+        assert_eq!(
+            execution_trace.insert_column(HashMap::<ProgramVariable, u64>::new()),
+            Err(ToyniError::WidthMismatch {
+                expected: 1,
+                actual: 0
+            })
+        );
+        let row: HashMap<ProgramVariable, u64> = HashMap::from([("a".to_string(), 1)]);
+        execution_trace.insert_column(row.clone()).unwrap();
+        assert_eq!(
+            execution_trace.validate(),
+            Err(ToyniError::TraceTooShort { rows: 1, height: 2 })
+        );
+        assert_eq!(
+            execution_trace.get_column(1),
+            Err(ToyniError::RowOutOfBounds { index: 1, rows: 1 })
+        );
+        let misspelled: HashMap<ProgramVariable, u64> = HashMap::from([("b".to_string(), 1)]);
+        assert_eq!(
+            execution_trace.insert_column(misspelled),
+            Err(ToyniError::ColumnSchemaMismatch {
+                missing: vec!["a".to_string()],
+                extra: vec!["b".to_string()]
+            })
+        );
+        execution_trace.insert_column(row.clone()).unwrap();
+        assert!(execution_trace.validate().is_ok());
+        assert_eq!(
+            execution_trace.insert_column(row),
+            Err(ToyniError::TraceFull { height: 2 })
+        );
+        assert_eq!(
+            execution_trace.interpolate(&"b".to_string(), 0, 1, 50),
+            Err(ToyniError::UnknownVariable("b".to_string()))
+        );
+        assert_eq!(
+            execution_trace.interpolate(&"a".to_string(), 0, 1, 101),
+            Err(ToyniError::InvalidInterpolationParameter(101))
+        );
+    }
+
+    #[test]
+    fn test_trace_builder() {
+        let trace = TraceBuilder::<2>::new(["x", "y"])
+            .row([0u64, 1])
+            .rows((1..5u64).map(|i| [i, i + 1]))
+            .randomizer("r")
+            .build()
+            .unwrap();
+        assert!(trace.validate().is_ok());
+        assert_eq!(trace.variables(), vec!["x", "y"]);
+        assert_eq!(trace.randomizers, vec!["r"]);
 
-        let interpolated = execution_trace.interpolate(&"b".to_string(), 0, 1, 100);
-        assert_eq!(interpolated, 2);
+        // Five rows are padded to eight by repeating the last one
+        assert_eq!((trace.height, trace.width, trace.padding), (8, 2, 3));
+        assert_eq!(trace.last_row(), Some(4));
+        for i in 0..8 {
+            assert_eq!(trace.get(i, "x").unwrap(), Fr::from(i.min(4)));
+            assert_eq!(trace.get(i, "y").unwrap(), Fr::from(i.min(4) + 1));
+        }
+    }
+
+    #[test]
+    fn test_padding() {
+        let mut execution_trace = generate_test_trace();
+        assert_eq!(execution_trace.padded_height(), 8);
+        let dummy: HashMap<ProgramVariable, Fr> = ["a", "b", "c", "d", "e"]
+            .map(|v| (v.to_string(), Fr::ZERO))
+            .into();
+        execution_trace
+            .pad(&PaddingStrategy::DummyRow(dummy))
+            .unwrap();
+        assert_eq!((execution_trace.height, execution_trace.padding), (8, 3));
+        assert_eq!(execution_trace.last_row(), Some(4));
+        assert!(execution_trace.validate().is_ok());
+        assert_eq!(execution_trace.get(4, "a").unwrap(), Fr::from(4u64));
+        assert!(
+            execution_trace
+                .column_iter("e")
+                .unwrap()
+                .skip(5)
+                .all(|e| e == Fr::ZERO)
+        );
+
+        // A power of two height is left unpadded
+        execution_trace
+            .pad(&PaddingStrategy::RepeatLastRow)
+            .unwrap();
+        assert_eq!(execution_trace.height, 8);
+
+        let mut execution_trace = generate_test_trace();
+        let dummy = HashMap::from([("z".to_string(), Fr::ZERO)]);
+        assert_eq!(
+            execution_trace.pad(&PaddingStrategy::DummyRow(dummy)),
+            Err(ToyniError::UnknownVariable("z".to_string()))
+        );
+        assert_eq!(
+            ExecutionTrace::<Fr>::new(0, 1).pad(&PaddingStrategy::RepeatLastRow),
+            Err(ToyniError::TraceTooShort { rows: 0, height: 1 })
+        );
+    }
+
+    #[test]
+    fn test_trace_builder_errors() {
+        assert_eq!(
+            TraceBuilder::<1>::new(["x"]).build().err(),
+            Some(ToyniError::IncompleteBuilder("rows"))
+        );
+        assert_eq!(
+            TraceBuilder::<2>::new(["x", "x"])
+                .row([1u64, 2])
+                .build()
+                .err(),
+            Some(ToyniError::DuplicateColumn("x".to_string()))
+        );
+        assert_eq!(
+            TraceBuilder::<1>::new(["x"])
+                .row([1u64])
+                .randomizer("x")
+                .build()
+                .err(),
+            Some(ToyniError::DuplicateColumn("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_cached_lde() {
+        let mut trace = ExecutionTrace::<Fr>::new(4, 1);
+        for i in 0..4u64 {
+            trace
+                .insert_column(HashMap::from([("x".to_string(), i)]))
+                .unwrap();
+        }
+        let lde = trace.lde(4, Fr::GENERATOR).unwrap();
+        assert_eq!(lde.width(), 1);
+        assert_eq!(lde.extended_domain().size(), 16);
+        let domain = GeneralEvaluationDomain::<Fr>::new(4).unwrap();
+        for i in 0..4 {
+            let value: Fr = lde.evaluate_at(domain.element(i))[0];
+            assert_eq!(value, Fr::from(i as u64));
+        }
+
+        // Later calls share the extension until the trace changes
+        assert!(Arc::ptr_eq(&lde, &trace.lde(4, Fr::GENERATOR).unwrap()));
+        assert!(!Arc::ptr_eq(&lde, &trace.lde(2, Fr::GENERATOR).unwrap()));
+        let lde = trace.lde(2, Fr::GENERATOR).unwrap();
+        trace.add_constant_column("c".to_string(), 7u64).unwrap();
+        let extended = trace.lde(2, Fr::GENERATOR).unwrap();
+        assert!(!Arc::ptr_eq(&lde, &extended));
+        assert_eq!(extended.width(), 2);
+
+        // Edits through the public fields are noticed as well
+        trace.trace[0].insert("x".to_string(), Fr::from(9u64));
+        let edited = trace.lde(2, Fr::GENERATOR).unwrap();
+        assert!(!Arc::ptr_eq(&extended, &edited));
+        assert_eq!(edited.evaluate_at(Fr::ONE)[1], Fr::from(9u64));
+
+        let mut trace = ExecutionTrace::<Fr>::new(3, 1);
+        for i in 0..3u64 {
+            trace
+                .insert_column(HashMap::from([("x".to_string(), i)]))
+                .unwrap();
+        }
+        assert_eq!(
+            trace.lde(4, Fr::GENERATOR).err(),
+            Some(ToyniError::NonPowerOfTwoDomain(3))
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let trace = generate_test_trace();
+        assert!(trace.diff(&trace.clone()).is_empty());
+
+        let mut other = ExecutionTrace::new(4, 5);
+        for (i, row) in trace.trace.iter().take(4).enumerate() {
+            let mut row = row.clone();
+            if i == 2 {
+                row.insert("c".to_string(), Fr::from(9u64));
+            }
+            other.insert_column(row).unwrap();
+        }
+        let diff = trace.diff(&other);
+        assert_eq!(diff.rows(), vec![2, 4]);
+        assert_eq!(
+            diff.cells[0],
+            CellDiff {
+                row: 2,
+                column: "c".to_string(),
+                left: Some(Fr::from(4u64)),
+                right: Some(Fr::from(9u64)),
+            }
+        );
+        // The missing last row differs in every column
+        assert_eq!(diff.cells.len(), 6);
+        assert_eq!(diff.cells[1].right, None);
+        assert!(
+            diff.to_string()
+                .starts_with("row 2, column c: 4 | 9\nrow 4, column a: 4 | missing\n")
+        );
+    }
+
+    #[test]
+    fn test_accessors() {
+        let trace = generate_test_trace();
+        assert_eq!(trace.get(2, "c").unwrap(), Fr::from(4u64));
+        assert_eq!(
+            trace.get(5, "c"),
+            Err(ToyniError::RowOutOfBounds { index: 5, rows: 5 })
+        );
+        assert_eq!(
+            trace.get(0, "z"),
+            Err(ToyniError::UnknownVariable("z".to_string()))
+        );
+
+        let b: Vec<Fr> = trace.column_iter("b").unwrap().collect();
+        assert_eq!(b, (1..6u64).map(Fr::from).collect::<Vec<_>>());
+        assert!(trace.column_iter("z").is_err());
+        assert_eq!(trace.rows().len(), 5);
+
+        // Four transitions between five rows
+        let transitions: Vec<u64> = trace
+            .transitions()
+            .map(|(row, current, next)| {
+                assert_eq!(next["a"] - current["a"], Fr::ONE);
+                row
+            })
+            .collect();
+        assert_eq!(transitions, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_constructors() {
+        let trace = ExecutionTrace::<Fr>::from_fn(["x", "square"], 5, |i| [i, i * i]).unwrap();
+        assert_eq!((trace.height, trace.width), (5, 2));
+        assert_eq!(trace.get(4, "square").unwrap(), Fr::from(16u64));
+
+        let columns = ExecutionTrace::<Fr>::from_columns([
+            ("x", (0..5u64).collect()),
+            ("square", (0..5u64).map(|i| i * i).collect()),
+        ])
+        .unwrap();
+        assert!(trace.diff(&columns).is_empty());
+
+        assert_eq!(
+            ExecutionTrace::<Fr>::from_fn(["x", "x"], 1, |i| [i, i]).err(),
+            Some(ToyniError::DuplicateColumn("x".to_string()))
+        );
+        assert_eq!(
+            ExecutionTrace::<Fr>::from_columns([("x", vec![1u64, 2]), ("y", vec![3])]).err(),
+            Some(ToyniError::ColumnLengthMismatch {
+                column: "y".to_string(),
+                expected: 2,
+                actual: 1
+            })
+        );
     }
 }
@@ -0,0 +1,377 @@
+//! Export and import of execution traces.
+//!
+//! Traces of long runs can be captured once with `ExecutionTrace::to_writer` and proved
+//! again or inspected offline. The binary layout opens with the magic bytes `TOYT` and a
+//! little-endian `u16` format version, followed by the height, the padding, the column
+//! names in sorted order and the randomizer names, then every row as the canonical
+//! big-endian bytes of one value per column. Integers are little-endian `u64`s and
+//! names are prefixed by their length.
+//!
+//! `ExecutionTrace::to_csv` writes a header of column names and one line of decimal
+//! values per row, for reading with any spreadsheet or script. CSV holds the values only:
+//! randomizers and the padding count are not part of it.
+
+use ark_ff::PrimeField;
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+};
+
+use crate::error::ToyniError;
+use crate::math::serde_fr::{decode, encoded_len, to_bytes};
+use crate::vm::trace::{ExecutionTrace, ProgramVariable};
+
+/// Magic bytes opening every exported trace
+pub const TRACE_MAGIC: [u8; 4] = *b"TOYT";
+
+/// Version of the layout written by `ExecutionTrace::to_writer`
+pub const TRACE_VERSION: u16 = 1;
+
+impl<F: PrimeField> ExecutionTrace<F> {
+    /// Writes the trace in the binary layout of `TRACE_VERSION`.
+    ///
+    /// Rows are written one at a time, so wrap unbuffered writers in a `BufWriter`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::TraceTooShort` if rows are missing,
+    /// `ToyniError::UnknownVariable` if a row lacks a column of the first one, and
+    /// `ToyniError::Io` if writing fails
+    pub fn to_writer(&self, mut writer: impl Write) -> Result<(), ToyniError> {
+        self.validate()?;
+        let variables = self.variables();
+        let header = TraceHeader {
+            height: self.height,
+            padding: self.padding,
+            variables: variables.clone(),
+            randomizers: self.randomizers.clone(),
+        };
+        writer.write_all(&header.to_bytes()).map_err(io_error)?;
+
+        let mut bytes = Vec::with_capacity(variables.len() * encoded_len::<F>());
+        for row in &self.trace {
+            bytes.clear();
+            for variable in &variables {
+                let value = row
+                    .get(variable)
+                    .ok_or_else(|| ToyniError::UnknownVariable(variable.clone()))?;
+                bytes.extend_from_slice(&to_bytes(value));
+            }
+            writer.write_all(&bytes).map_err(io_error)?;
+        }
+        writer.flush().map_err(io_error)
+    }
+
+    /// Reads a trace written by `to_writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::MalformedTrace` if the input does not follow the layout of
+    /// `TRACE_VERSION`, including non-canonical values and trailing bytes, and
+    /// `ToyniError::Io` if reading fails
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, ToyniError> {
+        let TraceHeader {
+            height,
+            padding,
+            variables,
+            randomizers,
+        } = TraceHeader::read(&mut reader)?;
+
+        let mut trace = ExecutionTrace::new(height, variables.len() as u64);
+        let value_len = encoded_len::<F>();
+        for _ in 0..height {
+            let bytes = read_bytes(&mut reader, variables.len() * value_len)?;
+            let row = variables
+                .iter()
+                .cloned()
+                .zip(bytes.chunks(value_len).map(decode::<F>))
+                .map(|(variable, value)| value.map(|value| (variable, value)))
+                .collect::<Result<HashMap<ProgramVariable, F>, _>>()
+                .map_err(malformed)?;
+            trace.insert_column(row)?;
+        }
+        let mut rest = [0u8];
+        if reader.read(&mut rest).map_err(io_error)? != 0 {
+            return Err(malformed("trailing bytes after the trace"));
+        }
+        for name in randomizers {
+            trace
+                .add_randomizer_column(name)
+                .map_err(|_| malformed("duplicate column name"))?;
+        }
+        trace.padding = padding;
+        Ok(trace)
+    }
+
+    /// Writes the trace as CSV: a header of the sorted column names, then one line of
+    /// decimal values per row.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::TraceTooShort` if rows are missing,
+    /// `ToyniError::UnknownVariable` if a row lacks a column of the first one,
+    /// `ToyniError::MalformedTrace` if a column name contains a comma, quote or line
+    /// break, and `ToyniError::Io` if writing fails
+    pub fn to_csv(&self, mut writer: impl Write) -> Result<(), ToyniError> {
+        self.validate()?;
+        let variables = self.variables();
+        if let Some(name) = variables
+            .iter()
+            .find(|name| name.contains([',', '"', '\n', '\r']))
+        {
+            return Err(malformed(&format!(
+                "column name {:?} cannot be written as CSV",
+                name
+            )));
+        }
+        writeln!(writer, "{}", variables.join(",")).map_err(io_error)?;
+        for row in &self.trace {
+            let values = variables
+                .iter()
+                .map(|variable| {
+                    row.get(variable)
+                        .map(|value| value.into_bigint().to_string())
+                        .ok_or_else(|| ToyniError::UnknownVariable(variable.clone()))
+                })
+                .collect::<Result<Vec<String>, ToyniError>>()?;
+            writeln!(writer, "{}", values.join(",")).map_err(io_error)?;
+        }
+        writer.flush().map_err(io_error)
+    }
+
+    /// Reads a trace written by `to_csv`, with one row per line after the header.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::MalformedTrace` if the header is missing or a value is not a
+    /// decimal integer below the field modulus, any error of `insert_column` if a line
+    /// does not hold one value per column, and `ToyniError::Io` if reading fails
+    pub fn from_csv(reader: impl Read) -> Result<Self, ToyniError> {
+        let mut lines = BufReader::new(reader).lines();
+        let header = lines
+            .next()
+            .ok_or_else(|| malformed("missing header"))?
+            .map_err(io_error)?;
+        let variables: Vec<ProgramVariable> = header.split(',').map(str::to_string).collect();
+
+        let mut rows = Vec::new();
+        for line in lines {
+            let line = line.map_err(io_error)?;
+            if line.is_empty() {
+                continue;
+            }
+            let row = line
+                .split(',')
+                .map(|value| {
+                    value
+                        .parse::<F::BigInt>()
+                        .ok()
+                        .and_then(F::from_bigint)
+                        .ok_or_else(|| malformed(&format!("invalid value {:?}", value)))
+                })
+                .collect::<Result<Vec<F>, ToyniError>>()?;
+            if row.len() != variables.len() {
+                return Err(ToyniError::WidthMismatch {
+                    expected: variables.len() as u64,
+                    actual: row.len(),
+                });
+            }
+            rows.push(
+                variables
+                    .iter()
+                    .cloned()
+                    .zip(row)
+                    .collect::<HashMap<_, _>>(),
+            );
+        }
+
+        let mut trace = ExecutionTrace::new(rows.len() as u64, variables.len() as u64);
+        for row in rows {
+            trace.insert_column(row)?;
+        }
+        Ok(trace)
+    }
+}
+
+/// Dimensions and column names opening a trace in the binary layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TraceHeader {
+    /// Number of rows, including padding
+    pub height: u64,
+    /// Number of rows at the end appended by `pad`
+    pub padding: u64,
+    /// Column names in sorted order
+    pub variables: Vec<ProgramVariable>,
+    /// Randomizer column names
+    pub randomizers: Vec<ProgramVariable>,
+}
+
+impl TraceHeader {
+    /// The magic bytes, the format version and the header fields.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = TRACE_MAGIC.to_vec();
+        bytes.extend_from_slice(&TRACE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.height.to_le_bytes());
+        bytes.extend_from_slice(&self.padding.to_le_bytes());
+        push_names(&mut bytes, &self.variables);
+        push_names(&mut bytes, &self.randomizers);
+        bytes
+    }
+
+    /// Reads the magic bytes, the format version and the header fields.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ToyniError::MalformedTrace` if the header does not follow the layout of
+    /// `TRACE_VERSION` and `ToyniError::Io` if reading fails
+    pub fn read(reader: &mut impl Read) -> Result<Self, ToyniError> {
+        if read_bytes(reader, TRACE_MAGIC.len())? != TRACE_MAGIC {
+            return Err(malformed("missing magic bytes"));
+        }
+        let version = u16::from_le_bytes(read_bytes(reader, 2)?.try_into().unwrap());
+        if version != TRACE_VERSION {
+            return Err(malformed(&format!(
+                "unsupported format version {}",
+                version
+            )));
+        }
+        let height = read_u64(reader)?;
+        let padding = read_u64(reader)?;
+        let variables = read_names(reader)?;
+        let randomizers = read_names(reader)?;
+        if padding > height || (height > 0 && variables.is_empty()) {
+            return Err(malformed("inconsistent trace dimensions"));
+        }
+        Ok(Self {
+            height,
+            padding,
+            variables,
+            randomizers,
+        })
+    }
+}
+
+pub(crate) fn malformed(reason: &str) -> ToyniError {
+    ToyniError::MalformedTrace(reason.to_string())
+}
+
+pub(crate) fn io_error(error: io::Error) -> ToyniError {
+    match error.kind() {
+        io::ErrorKind::UnexpectedEof => malformed("unexpected end of input"),
+        _ => ToyniError::Io(error.to_string()),
+    }
+}
+
+fn push_names(bytes: &mut Vec<u8>, names: &[ProgramVariable]) {
+    bytes.extend_from_slice(&(names.len() as u64).to_le_bytes());
+    for name in names {
+        bytes.extend_from_slice(&(name.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(name.as_bytes());
+    }
+}
+
+/// Reads exactly `len` bytes. Only the bytes actually present are allocated, so a
+/// corrupted length fails on the missing input instead of allocating for it.
+fn read_bytes(reader: &mut impl Read, len: usize) -> Result<Vec<u8>, ToyniError> {
+    let mut bytes = Vec::new();
+    reader
+        .take(len as u64)
+        .read_to_end(&mut bytes)
+        .map_err(io_error)?;
+    if bytes.len() != len {
+        return Err(malformed("unexpected end of input"));
+    }
+    Ok(bytes)
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, ToyniError> {
+    Ok(u64::from_le_bytes(
+        read_bytes(reader, 8)?.try_into().unwrap(),
+    ))
+}
+
+fn read_names(reader: &mut impl Read) -> Result<Vec<ProgramVariable>, ToyniError> {
+    let count = read_u64(reader)?;
+    let mut names = Vec::new();
+    for _ in 0..count {
+        let len = usize::try_from(read_u64(reader)?)
+            .map_err(|_| malformed("name length out of range"))?;
+        let name = String::from_utf8(read_bytes(reader, len)?)
+            .map_err(|_| malformed("invalid UTF-8 name"))?;
+        names.push(name);
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::Fr;
+    use ark_ff::Field;
+
+    fn create_test_trace() -> ExecutionTrace {
+        let mut trace = ExecutionTrace::new(3, 2);
+        for i in 0..3u64 {
+            let row = HashMap::from([
+                ("x".to_string(), Fr::from(i)),
+                ("y".to_string(), -Fr::from(i).square()),
+            ]);
+            trace.insert_column(row).unwrap();
+        }
+        trace.add_randomizer_column("r".to_string()).unwrap();
+        trace.pad(&Default::default()).unwrap();
+        trace
+    }
+
+    fn assert_same_rows(left: &ExecutionTrace, right: &ExecutionTrace) {
+        assert_eq!(left.height, right.height);
+        assert_eq!(left.width, right.width);
+        assert_eq!(left.trace, right.trace);
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let trace = create_test_trace();
+        let mut bytes = Vec::new();
+        trace.to_writer(&mut bytes).unwrap();
+        assert_eq!(bytes[..4], TRACE_MAGIC);
+
+        let decoded = ExecutionTrace::<Fr>::from_reader(bytes.as_slice()).unwrap();
+        assert_same_rows(&decoded, &trace);
+        assert_eq!(decoded.randomizers, trace.randomizers);
+        assert_eq!(decoded.padding, 1);
+        assert_eq!(decoded.last_row(), Some(2));
+
+        // Truncated or extended inputs are rejected
+        assert!(matches!(
+            ExecutionTrace::<Fr>::from_reader(&bytes[..bytes.len() - 1]),
+            Err(ToyniError::MalformedTrace(_))
+        ));
+        bytes.push(0);
+        assert!(matches!(
+            ExecutionTrace::<Fr>::from_reader(bytes.as_slice()),
+            Err(ToyniError::MalformedTrace(_))
+        ));
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let trace = create_test_trace();
+        let mut csv = Vec::new();
+        trace.to_csv(&mut csv).unwrap();
+        let text = String::from_utf8(csv.clone()).unwrap();
+        assert!(text.starts_with("x,y\n0,0\n1,"));
+
+        let decoded = ExecutionTrace::<Fr>::from_csv(csv.as_slice()).unwrap();
+        assert_same_rows(&decoded, &trace);
+
+        assert!(matches!(
+            ExecutionTrace::<Fr>::from_csv("x\n-1\n".as_bytes()),
+            Err(ToyniError::MalformedTrace(_))
+        ));
+        assert!(matches!(
+            ExecutionTrace::<Fr>::from_csv("x,y\n1\n".as_bytes()),
+            Err(ToyniError::WidthMismatch { .. })
+        ));
+    }
+}
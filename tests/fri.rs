@@ -3,13 +3,22 @@ mod tests {
     use ark_bls12_381::Fr;
     use ark_ff::{AdditiveGroup, Field, UniformRand};
     use ark_poly::{
-        DenseUVPolynomial, EvaluationDomain, GeneralEvaluationDomain, univariate::DensePolynomial,
+        DenseUVPolynomial, EvaluationDomain, GeneralEvaluationDomain, Polynomial as _,
+        univariate::DensePolynomial,
     };
     use ark_std::test_rng;
+    use toyni::error::ToyniError;
     use toyni::math::{
-        fri::{fri_fold, interpolate_poly},
+        fri::{
+            FriFailure, FriProof, FriProver, FriVerifier, MAX_GRINDING_BITS, fri_fold,
+            fri_fold_by_factor, fri_fold_with_domain, fri_folding_factors, fri_prove,
+            fri_prove_observed, fri_verify, fri_verify_with_report, interpolate_poly,
+        },
         polynomial::Polynomial,
     };
+    use toyni::merkle::MerkleTree;
+    use toyni::options::ProofOptions;
+    use toyni::transcript::{MockOracle, RandomOracle, Transcript};
 
     #[test]
     fn test_general_evaluation_domain() {
@@ -65,7 +74,10 @@ mod tests {
     fn test_polynomial_division_zero() {
         let dividend = Polynomial::new(vec![Fr::from(1u64), Fr::from(2u64), Fr::from(1u64)]);
         let divisor = Polynomial::new(vec![Fr::ZERO]);
-        assert!(dividend.divide(&divisor).is_none());
+        assert!(matches!(
+            dividend.divide(&divisor),
+            Err(ToyniError::DivisionByZeroPoly)
+        ));
     }
 
     #[test]
@@ -135,4 +147,310 @@ mod tests {
             assert_eq!(eval, y);
         }
     }
+
+    #[test]
+    fn test_fri_fold_with_scripted_zero_challenge() {
+        // f(x) = 1 + 2x + 3x^2 + 4x^3, so f_even(y) = 1 + 3y and f_odd(y) = 2 + 4y
+        let poly = DensePolynomial::<Fr>::from_coefficients_vec(vec![
+            Fr::from(1u64),
+            Fr::from(2u64),
+            Fr::from(3u64),
+            Fr::from(4u64),
+        ]);
+        let domain = GeneralEvaluationDomain::<Fr>::new(8).unwrap();
+        let evals = domain.fft(&poly.coeffs);
+
+        // With beta = 0 the fold keeps only the even part of the polynomial
+        let mut oracle = MockOracle::new(vec![Fr::ZERO], vec![]);
+        let folded = fri_fold(&evals, oracle.draw_field_element());
+        for (i, value) in folded.iter().enumerate() {
+            let y = domain.element(i) * domain.element(i);
+            assert_eq!(*value, Fr::from(1u64) + Fr::from(3u64) * y);
+        }
+    }
+
+    #[test]
+    fn test_fri_query_phase() {
+        let mut rng = test_rng();
+        let poly = DensePolynomial::<Fr>::rand(15, &mut rng);
+        let domain = GeneralEvaluationDomain::<Fr>::new(64).unwrap();
+        let evals = domain.fft(&poly.coeffs);
+
+        let base_tree = MerkleTree::from_field_elements(&evals);
+        let base_cap = base_tree.cap(0);
+        let mut prover_transcript = Transcript::default();
+        prover_transcript.absorb(&base_cap.concat());
+        let options = ProofOptions::default().with_fri_queries(16);
+        let proof = fri_prove(evals, &domain, base_tree, &options, &mut prover_transcript);
+
+        // 64 -> 32 -> 16 -> 8 -> 4, each query opens one coset per committed layer
+        assert_eq!(proof.challenges.len(), 4);
+        assert_eq!(proof.layer_caps.len(), 3);
+        assert!(proof.queries.iter().all(|query| query.layers.len() == 4));
+
+        let verify = |proof| {
+            let mut transcript = Transcript::default();
+            transcript.absorb(&base_cap.concat());
+            fri_verify(&base_cap, &domain, 16, proof, &options, &mut transcript)
+        };
+        assert!(verify(&proof));
+
+        let report = |proof| {
+            let mut transcript = Transcript::default();
+            transcript.absorb(&base_cap.concat());
+            fri_verify_with_report(&base_cap, &domain, 16, proof, &options, &mut transcript)
+        };
+        assert_eq!(report(&proof), Ok(()));
+
+        // A folded value inconsistent with the previous layer must be caught
+        let mut tampered = proof.clone();
+        tampered.queries[3].layers[2].values[1] += Fr::from(1u64);
+        assert!(!verify(&tampered));
+        assert!(matches!(
+            report(&tampered),
+            Err(FriFailure::Folding {
+                query: 3,
+                layer: 1,
+                ..
+            } | FriFailure::LayerOpening {
+                query: 3,
+                layer: 2,
+                ..
+            })
+        ));
+
+        // Dropping queries must be caught
+        let mut truncated = proof.clone();
+        truncated.queries.pop();
+        assert!(!verify(&truncated));
+        assert_eq!(
+            report(&truncated),
+            Err(FriFailure::QueryCount {
+                expected: 16,
+                actual: 15
+            })
+        );
+    }
+
+    #[test]
+    fn test_fri_terminal_polynomial() {
+        let mut rng = test_rng();
+        let domain = GeneralEvaluationDomain::<Fr>::new(64).unwrap();
+        let options = ProofOptions::default()
+            .with_fri_remainder_size(16)
+            .with_fri_queries(8);
+
+        let prove_and_verify = |degree_bound: usize, claimed_bound: usize, rng: &mut _| {
+            let poly = DensePolynomial::<Fr>::rand(degree_bound - 1, rng);
+            let evals = domain.fft(&poly.coeffs);
+            let base_tree = MerkleTree::from_field_elements(&evals);
+            let base_cap = base_tree.cap(0);
+
+            let mut transcript = Transcript::default();
+            transcript.absorb(&base_cap.concat());
+            let proof = fri_prove(evals, &domain, base_tree, &options, &mut transcript);
+
+            let mut transcript = Transcript::default();
+            transcript.absorb(&base_cap.concat());
+            let valid = fri_verify(
+                &base_cap,
+                &domain,
+                claimed_bound,
+                &proof,
+                &options,
+                &mut transcript,
+            );
+            (proof, valid)
+        };
+
+        // 64 -> 32 -> 16, a degree < 32 polynomial folds to degree < 8
+        let (proof, valid) = prove_and_verify(32, 32, &mut rng);
+        assert!(valid);
+        assert_eq!(proof.challenges.len(), 2);
+        assert_eq!(proof.final_poly.len(), 8);
+
+        // A polynomial above the claimed bound leaves too many final coefficients
+        let (_, valid) = prove_and_verify(48, 32, &mut rng);
+        assert!(!valid);
+    }
+
+    #[test]
+    fn test_standalone_fri() {
+        let mut rng = test_rng();
+        let domain = GeneralEvaluationDomain::<Fr>::new(128).unwrap();
+        let poly = DensePolynomial::<Fr>::rand(31, &mut rng);
+        let evals = domain.fft(&poly.coeffs);
+
+        let options = ProofOptions::default()
+            .with_fri_remainder_size(16)
+            .with_fri_queries(20);
+        let (commitment, proof) = FriProver::new(options.clone()).prove(evals.clone(), &domain);
+        assert!(FriVerifier::new(&domain, 32, options.clone()).verify(&commitment, &proof));
+
+        // The proof does not hold for a tighter degree bound or another commitment
+        assert!(!FriVerifier::new(&domain, 16, options.clone()).verify(&commitment, &proof));
+        let mut other_evals = evals;
+        other_evals[0] += Fr::from(1u64);
+        let (other_commitment, _) = FriProver::new(options.clone()).prove(other_evals, &domain);
+        assert!(!FriVerifier::new(&domain, 32, options).verify(&other_commitment, &proof));
+    }
+
+    #[test]
+    fn test_batched_fri() {
+        let mut rng = test_rng();
+        let domain = GeneralEvaluationDomain::<Fr>::new(64).unwrap();
+        let evals: Vec<Vec<Fr>> = (0..3)
+            .map(|_| domain.fft(&DensePolynomial::<Fr>::rand(15, &mut rng).coeffs))
+            .collect();
+
+        let options = ProofOptions::default()
+            .with_fri_remainder_size(16)
+            .with_fri_queries(20);
+        let verifier = FriVerifier::new(&domain, 16, options.clone());
+        let (commitment, proof) =
+            FriProver::new(options.clone()).prove_batch(evals.clone(), &domain);
+        assert!(verifier.verify_batch(&commitment, &proof));
+        assert_eq!(proof.openings[0].rows[0].len(), 3);
+
+        // One high-degree polynomial in the batch makes the combination high degree
+        let mut bad_evals = evals.clone();
+        bad_evals[1] = domain.fft(&DensePolynomial::<Fr>::rand(47, &mut rng).coeffs);
+        let (bad_commitment, bad_proof) = FriProver::new(options).prove_batch(bad_evals, &domain);
+        assert!(!verifier.verify_batch(&bad_commitment, &bad_proof));
+
+        // Opened rows must match the combined layer
+        let mut tampered = proof.clone();
+        tampered.openings[0].rows[0][2] += Fr::from(1u64);
+        assert!(!verifier.verify_batch(&commitment, &tampered));
+    }
+
+    #[test]
+    fn test_fri_proof_serde_and_grinding() {
+        let mut rng = test_rng();
+        let domain = GeneralEvaluationDomain::<Fr>::new(64).unwrap();
+        let evals = domain.fft(&DensePolynomial::<Fr>::rand(15, &mut rng).coeffs);
+
+        let options = ProofOptions::default()
+            .with_fri_queries(10)
+            .with_grinding_bits(8);
+        let verifier = FriVerifier::new(&domain, 16, options.clone());
+        let (commitment, proof) = FriProver::new(options).prove(evals, &domain);
+        assert!(verifier.verify(&commitment, &proof));
+
+        // The proof can be stored and verified independently of the prover
+        let encoded = serde_json::to_string(&proof).unwrap();
+        let decoded: FriProof = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decoded.pow_nonce, proof.pow_nonce);
+        assert!(verifier.verify(&commitment, &decoded));
+
+        // Almost every other nonce fails the 8-bit proof-of-work
+        let rejected = (0..64)
+            .filter(|&nonce| {
+                let mut tampered = decoded.clone();
+                tampered.pow_nonce = nonce;
+                !verifier.verify(&commitment, &tampered)
+            })
+            .count();
+        assert!(rejected > 48);
+
+        // No nonce has more zero bits than the digest prefix holds, so the prover
+        // rejects such a proof-of-work instead of searching forever
+        let options = ProofOptions::default().with_grinding_bits(MAX_GRINDING_BITS + 1);
+        let evals = domain.fft(&DensePolynomial::<Fr>::rand(15, &mut rng).coeffs);
+        let tree = MerkleTree::from_field_elements(&evals);
+        let mut transcript = Transcript::new(b"test");
+        assert!(matches!(
+            fri_prove_observed(evals, &domain, tree, &options, &mut transcript, &()),
+            Err(ToyniError::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn test_fri_over_coset() {
+        let mut rng = test_rng();
+        let coset = GeneralEvaluationDomain::<Fr>::new(64)
+            .unwrap()
+            .get_coset(Fr::from(7u64))
+            .unwrap();
+        let poly = DensePolynomial::<Fr>::rand(15, &mut rng);
+        let evals = coset.fft(&poly.coeffs);
+
+        let options = ProofOptions::default().with_fri_queries(10);
+        let (commitment, proof) = FriProver::new(options.clone()).prove(evals, &coset);
+        assert!(FriVerifier::new(&coset, 16, options.clone()).verify(&commitment, &proof));
+
+        // Folding depends on the domain points, so the offset must match the prover's
+        let subgroup = GeneralEvaluationDomain::<Fr>::new(64).unwrap();
+        assert!(!FriVerifier::new(&subgroup, 16, options).verify(&commitment, &proof));
+    }
+
+    #[test]
+    fn test_fri_folding_schedule() {
+        let mut rng = test_rng();
+        let coset = GeneralEvaluationDomain::<Fr>::new(128)
+            .unwrap()
+            .get_coset(Fr::from(7u64))
+            .unwrap();
+        let evals = coset.fft(&DensePolynomial::<Fr>::rand(31, &mut rng).coeffs);
+
+        // 128 -> 32 -> 8 -> 4 commits two layers instead of the five of binary folding
+        let options = ProofOptions::default()
+            .with_fri_folding_schedule(vec![4, 4])
+            .with_fri_queries(12);
+        assert_eq!(fri_folding_factors(128, &options), vec![4, 4, 2]);
+        let verifier = FriVerifier::new(&coset, 32, options.clone());
+        let (commitment, proof) = FriProver::new(options).prove(evals.clone(), &coset);
+        assert_eq!(proof.layer_caps.len(), 2);
+        assert_eq!(proof.queries[0].layers[0].values.len(), 4);
+        assert!(verifier.verify(&commitment, &proof));
+
+        // Every value of an opened coset enters the fold
+        let mut tampered = proof.clone();
+        tampered.queries[0].layers[1].values[3] += Fr::from(1u64);
+        assert!(!verifier.verify(&commitment, &tampered));
+
+        // Folding by 8 matches three binary folds with squared challenges
+        let beta = Fr::rand(&mut rng);
+        let mut expected = evals.clone();
+        let mut domain = coset;
+        let mut challenge = beta;
+        for _ in 0..3 {
+            expected = fri_fold_with_domain(&expected, &domain, challenge);
+            domain = GeneralEvaluationDomain::<Fr>::new(domain.size() / 2)
+                .unwrap()
+                .get_coset(domain.coset_offset().square())
+                .unwrap();
+            challenge.square_in_place();
+        }
+        assert_eq!(fri_fold_by_factor(&evals, &coset, beta, 8), expected);
+    }
+
+    #[test]
+    fn test_fri_fold_matches_even_odd_split() {
+        let mut rng = test_rng();
+        let poly = DensePolynomial::<Fr>::rand(31, &mut rng);
+        let beta = Fr::rand(&mut rng);
+
+        // f_even + β * f_odd, evaluated directly at the squared points
+        let even: Vec<Fr> = poly.coeffs.iter().step_by(2).copied().collect();
+        let odd: Vec<Fr> = poly.coeffs.iter().skip(1).step_by(2).copied().collect();
+        let folded_poly = DensePolynomial::from_coefficients_vec(even)
+            + DensePolynomial::from_coefficients_vec(odd) * beta;
+
+        let subgroup = GeneralEvaluationDomain::<Fr>::new(64).unwrap();
+        let coset = subgroup.get_coset(Fr::from(5u64)).unwrap();
+        for domain in [subgroup, coset] {
+            let folded = fri_fold_with_domain(&domain.fft(&poly.coeffs), &domain, beta);
+            for (i, value) in folded.iter().enumerate() {
+                let x = domain.element(i);
+                assert_eq!(*value, folded_poly.evaluate(&(x * x)));
+            }
+        }
+
+        // Without an offset the shorthand agrees with the domain-aware fold
+        assert_eq!(
+            fri_fold(&subgroup.fft(&poly.coeffs), beta),
+            fri_fold_with_domain(&subgroup.fft(&poly.coeffs), &subgroup, beta)
+        );
+    }
 }
@@ -1,26 +1,56 @@
 #[cfg(test)]
 mod tests {
     use ark_bls12_381::Fr;
-    use ark_ff::Field;
-    use toyni::{prover::StarkProver, verifier::StarkVerifier, vm::{constraints::ConstraintSystem, trace::ExecutionTrace}};
-    use std::collections::HashMap;
+    use ark_ff::{AdditiveGroup, Field, Zero};
+    use std::{
+        collections::HashMap,
+        pin::pin,
+        sync::Mutex,
+        task::{Context, Poll, Waker},
+    };
+    use toyni::{
+        encoding::{CONSTRAINTS_MAGIC, CONSTRAINTS_VERSION, PROOF_MAGIC, PROOF_VERSION},
+        error::ToyniError,
+        hash::Sha256Hasher,
+        math::{
+            fr_ext2::FrExt2,
+            fri::FriFailure,
+            goldilocks::{Goldilocks, GoldilocksExt2},
+        },
+        merkle::SALT_LEN,
+        metrics::ProofMetrics,
+        options::{ProofOptions, SecurityLevel, Soundness},
+        progress::{CancellationToken, ProofObserver, ProofPhase},
+        prover::{ProvingStrategy, StarkProof, StarkProver},
+        transcript::MockOracle,
+        verifier::{StarkVerifier, VerificationFailure},
+        vm::{
+            air::Air,
+            auxiliary::{AuxiliaryTrace, MainColumns},
+            chiplet::{Chiplet, Namespaced},
+            constraints::{BoundaryPosition, ConstraintSystem},
+            dsl::{challenge, col},
+            expr::Expr,
+            permutation::PermutationArgument,
+            table::{TraceFragment, TraceTable},
+            trace::{ExecutionTrace, PaddingStrategy, TraceBuilder, TraceRow},
+        },
+    };
 
     #[test]
     fn test_valid_proof() {
-        let mut trace = ExecutionTrace::new(4, 1);
-        for i in 0..4 {
-            let mut row = HashMap::new();
-            row.insert("x".to_string(), i);
-            trace.insert_column(row);
-        }
+        let trace = TraceBuilder::<1>::new(["x"])
+            .rows((0..4u64).map(|i| [i]))
+            .build()
+            .unwrap();
 
         let mut constraints = ConstraintSystem::default();
         constraints.add_transition_constraint(
             "increment".to_string(),
             vec!["x".to_string()],
             Box::new(|current, next| {
-                let x_n = Fr::from(*current.get("x").unwrap());
-                let x_next = Fr::from(*next.get("x").unwrap());
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
                 x_next - x_n - Fr::ONE
             }),
         );
@@ -28,31 +58,540 @@ mod tests {
             "starts_at_0".to_string(),
             0,
             vec!["x".to_string()],
-            Box::new(|row| Fr::from(*row.get("x").unwrap())),
+            Box::new(|row| *row.get("x").unwrap()),
         );
 
         let prover = StarkProver::new(&trace, &constraints);
-        let proof = prover.generate_proof();
+        let proof = prover.generate_proof().unwrap();
         let verifier = StarkVerifier::new(&constraints, trace.height as usize);
         assert!(verifier.verify(&proof));
     }
 
     #[test]
-    fn test_invalid_proof() {
+    fn test_assertions_prove() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [3 + i]).unwrap();
+        let constraints = |last: u64| {
+            let mut constraints = ConstraintSystem::default();
+            constraints
+                .add_transition_expr(
+                    "increment".to_string(),
+                    vec!["x".to_string()],
+                    Expr::next(0) - Expr::current(0) - Expr::Const(Fr::ONE),
+                )
+                .unwrap();
+            constraints.assert_first("x", Fr::from(3u64));
+            constraints.assert_last("x", Fr::from(last));
+            constraints
+        };
+
+        let proof = StarkProver::new(&trace, &constraints(10))
+            .generate_proof()
+            .unwrap();
+        assert!(StarkVerifier::new(&constraints(10), 8).verify(&proof));
+        // A verifier asserting another final value rejects the proof
+        assert!(!StarkVerifier::new(&constraints(11), 8).verify(&proof));
+    }
+
+    #[test]
+    fn test_expression_constraints_prove() {
+        // Fibonacci over the columns [a, b]: a' = b, b' = a + b
+        let mut rows = vec![[1u64, 1]];
+        for _ in 1..16 {
+            let [a, b] = rows[rows.len() - 1];
+            rows.push([b, a + b]);
+        }
+        let trace = TraceBuilder::<2>::new(["a", "b"])
+            .rows(rows)
+            .build()
+            .unwrap();
+
+        let variables = vec!["a".to_string(), "b".to_string()];
+        let mut constraints = ConstraintSystem::default();
+        constraints
+            .add_transition_expr(
+                "shift".to_string(),
+                variables.clone(),
+                Expr::next(0) - Expr::current(1),
+            )
+            .unwrap();
+        constraints
+            .add_transition_expr(
+                "sum".to_string(),
+                variables.clone(),
+                Expr::next(1) - Expr::current(0) - Expr::current(1),
+            )
+            .unwrap();
+        constraints
+            .add_boundary_expr(
+                "starts_at_1".to_string(),
+                0,
+                variables,
+                Expr::current(0) - Expr::Const(Fr::ONE),
+            )
+            .unwrap();
+
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(StarkVerifier::new(&constraints, 16).verify(&proof));
+    }
+
+    #[test]
+    fn test_dsl_constraints_prove() {
+        // The same Fibonacci constraints written with named columns
+        let mut rows = vec![[1u64, 1]];
+        for _ in 1..16 {
+            let [a, b] = rows[rows.len() - 1];
+            rows.push([b, a + b]);
+        }
+        let trace = TraceBuilder::<2>::new(["a", "b"])
+            .rows(rows)
+            .build()
+            .unwrap();
+
+        let (a, b) = (col("a"), col("b"));
+        let mut constraints = ConstraintSystem::default();
+        constraints
+            .transition(a.next() - b.cur())
+            .transition(b.next() - a.cur() - b.cur())
+            .boundary(BoundaryPosition::First, a.cur() - 1)
+            .unwrap();
+
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(StarkVerifier::new(&constraints, 16).verify(&proof));
+    }
+
+    #[test]
+    fn test_window_constraints_prove() {
+        // Fibonacci in a single column: x[n+2] = x[n+1] + x[n]
+        let mut column = vec![1u64, 1];
+        for i in 2..16 {
+            column.push(column[i - 1] + column[i - 2]);
+        }
+        let trace = ExecutionTrace::from_columns([("x", column.clone())]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints
+            .add_window_expr(
+                "fibonacci".to_string(),
+                vec!["x".to_string()],
+                Expr::Column(0, 2) - Expr::Column(0, 1) - Expr::current(0),
+            )
+            .unwrap();
+        constraints.add_boundary_assertion("starts_at_1".to_string(), "x".to_string(), 0, Fr::ONE);
+
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert_eq!(proof.ood_frame.window.len(), 1);
+        assert!(StarkVerifier::new(&constraints, 16).verify(&proof));
+        let decoded = StarkProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert!(StarkVerifier::new(&constraints, 16).verify(&decoded));
+
+        // A revealed window row must match the commitment
+        let mut tampered = StarkProof::from_bytes(&proof.to_bytes()).unwrap();
+        tampered.ood_frame.window[0][0] += Fr::ONE;
+        assert!(!StarkVerifier::new(&constraints, 16).verify(&tampered));
+        // And the frame must hold as many rows as the longest window
+        let mut truncated = proof;
+        truncated.ood_frame.window.clear();
+        assert!(!StarkVerifier::new(&constraints, 16).verify(&truncated));
+
+        // In zero-knowledge mode every revealed window row takes a blinding row
+        let options = ProofOptions::default().with_zk(true).with_fri_queries(7);
+        assert_eq!(constraints.window(), 3);
+        assert_eq!(options.num_blinding_rows(constraints.window()), 3 + 2 * 7);
+        assert_eq!(options.trace_domain_size(16, constraints.window()), 64);
+        let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        assert_eq!(proof.ood_frame.window.len(), 1);
+        assert!(StarkVerifier::with_options(&constraints, 16, options).verify(&proof));
+
+        column[9] += 1;
+        let invalid = ExecutionTrace::from_columns([("x", column)]).unwrap();
+        assert!(!constraints.is_satisfied(&invalid).unwrap());
+    }
+
+    #[test]
+    fn test_periodic_constraints_prove() {
+        // x counts up by one and resets to zero after every eighth row
+        let trace = ExecutionTrace::from_fn(["x"], 32, |i| [i % 8]).unwrap();
+        let x = || vec!["x".to_string()];
+        let mut constraints = ConstraintSystem::default();
+        for offset in 0..7 {
+            let count = Box::new(|current: &HashMap<_, Fr>, next: &HashMap<_, Fr>| {
+                next["x"] - current["x"] - Fr::ONE
+            });
+            constraints
+                .add_periodic_constraint(format!("count_{}", offset), x(), 8, offset, 1, count)
+                .unwrap();
+        }
+        constraints
+            .add_periodic_constraint(
+                "reset".to_string(),
+                x(),
+                8,
+                7,
+                1,
+                Box::new(|_, next| next["x"]),
+            )
+            .unwrap();
+        constraints.add_boundary_assertion("starts_at_0".to_string(), "x".to_string(), 0, Fr::ZERO);
+
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(StarkVerifier::new(&constraints, 32).verify(&proof));
+
+        // Counting on past the end of a period violates the reset
+        let invalid = ExecutionTrace::from_fn(["x"], 32, |i| [i % 16]).unwrap();
+        let violations = constraints.check(&invalid).unwrap();
+        let rows: Vec<(&str, u64)> = violations
+            .iter()
+            .map(|violation| (violation.constraint.as_str(), violation.row))
+            .collect();
+        assert_eq!(rows, [("reset", 7), ("reset", 23)]);
+    }
+
+    #[test]
+    fn test_selector_groups_prove() {
+        // A machine with two instructions on an accumulator, selected per row
+        let program = [1u64, 1, 0, 1, 0, 0, 1, 1, 0, 1, 1, 0, 1, 0, 0, 1];
+        let mut acc = 3u64;
+        let rows: Vec<[u64; 3]> = program
+            .iter()
+            .map(|&add| {
+                let row = [acc, add, 1 - add];
+                acc = if add == 1 { acc + 5 } else { acc * 3 };
+                row
+            })
+            .collect();
+        let trace = TraceBuilder::<3>::new(["acc", "add", "triple"])
+            .rows(rows)
+            .build()
+            .unwrap();
+
+        let acc = || vec!["acc".to_string()];
+        let mut constraints = ConstraintSystem::default();
+        constraints
+            .add_selector_group("add".to_string())
+            .add_transition_constraint(
+                "add_5".to_string(),
+                acc(),
+                Box::new(|current, next| next["acc"] - current["acc"] - Fr::from(5u64)),
+            );
+        constraints
+            .add_selector_group("triple".to_string())
+            .add_transition_constraint(
+                "triple".to_string(),
+                acc(),
+                Box::new(|current, next| next["acc"] - current["acc"] * Fr::from(3u64)),
+            );
+        // Exactly one instruction per row
+        constraints.add_window_constraint(
+            "one_instruction".to_string(),
+            vec!["add".to_string(), "triple".to_string()],
+            1,
+            1,
+            Box::new(|rows| rows[0]["add"] + rows[0]["triple"] - Fr::ONE),
+        );
+
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(StarkVerifier::new(&constraints, 16).verify(&proof));
+
+        // Setting both selectors to a non-boolean pair breaks the booleanity constraints
+        let mut invalid = trace.clone();
+        invalid.trace[4].insert("add".to_string(), Fr::from(2u64));
+        invalid.trace[4].insert("triple".to_string(), -Fr::ONE);
+        let violated: Vec<String> = constraints
+            .check(&invalid)
+            .unwrap()
+            .into_iter()
+            .map(|violation| violation.constraint)
+            .collect();
+        assert!(violated.contains(&"boolean/add".to_string()));
+        assert!(violated.contains(&"boolean/triple".to_string()));
+        assert!(!violated.contains(&"one_instruction".to_string()));
+    }
+
+    /// Fibonacci over the columns [a, b], proving the value of `a` at a claimed row
+    struct FibonacciAir;
+
+    impl Air for FibonacciAir {
+        /// The row and the value of `a` at that row
+        type PublicInputs = (u64, Fr);
+
+        fn trace_columns(&self) -> Vec<String> {
+            vec!["a".to_string(), "b".to_string()]
+        }
+
+        fn add_constraints(&self, constraints: &mut ConstraintSystem) {
+            let variables = self.trace_columns();
+            constraints
+                .add_transition_expr(
+                    "shift".to_string(),
+                    variables.clone(),
+                    Expr::next(0) - Expr::current(1),
+                )
+                .unwrap();
+            constraints
+                .add_transition_expr(
+                    "sum".to_string(),
+                    variables,
+                    Expr::next(1) - Expr::current(0) - Expr::current(1),
+                )
+                .unwrap();
+        }
+
+        fn add_assertions(&self, constraints: &mut ConstraintSystem, (row, value): &(u64, Fr)) {
+            constraints.add_boundary_assertion("a_0".to_string(), "a".to_string(), 0, Fr::ONE);
+            constraints.add_boundary_assertion("b_0".to_string(), "b".to_string(), 0, Fr::ONE);
+            constraints.add_boundary_assertion("result".to_string(), "a".to_string(), *row, *value);
+        }
+
+        fn public_inputs(&self, (row, value): &(u64, Fr)) -> Vec<Fr> {
+            vec![Fr::from(*row), *value]
+        }
+
+        fn options(&self) -> ProofOptions {
+            ProofOptions::new().with_blowup_factor(4)
+        }
+    }
+
+    #[test]
+    fn test_air_prove() {
+        let mut rows = vec![[1u64, 1]];
+        for _ in 1..16 {
+            let [a, b] = rows[rows.len() - 1];
+            rows.push([b, a + b]);
+        }
+        let fibonacci = TraceBuilder::<2>::new(["a", "b"])
+            .rows(rows)
+            .build()
+            .unwrap();
+        // a at row 10 is the eleventh Fibonacci number
+        let statement = (10, Fr::from(89u64));
+
+        let proof = StarkProver::from_air(&FibonacciAir, &fibonacci, &statement)
+            .unwrap()
+            .generate_proof()
+            .unwrap();
+        assert_eq!(proof.options.blowup_factor, 4);
+        assert!(StarkVerifier::from_air(&FibonacciAir, 16, &statement).verify(&proof));
+        assert!(!StarkVerifier::from_air(&FibonacciAir, 16, &(10, Fr::from(88u64))).verify(&proof));
+
+        // The prover rejects a trace of other columns
+        let other = ExecutionTrace::from_fn(["a", "c"], 16, |_| [0u64, 0]).unwrap();
+        assert!(matches!(
+            StarkProver::from_air(&FibonacciAir, &other, &statement),
+            Err(ToyniError::ColumnSchemaMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_nonzero_boundary_assertions() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [5 + i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+        constraints.add_boundary_assertion(
+            "starts_at_5".to_string(),
+            "x".to_string(),
+            0,
+            Fr::from(5u64),
+        );
+        constraints.add_boundary_assertion(
+            "ends_at_12".to_string(),
+            "x".to_string(),
+            7,
+            Fr::from(12u64),
+        );
+
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        let verifier = StarkVerifier::new(&constraints, trace.height as usize);
+        assert!(verifier.verify(&proof));
+
+        // A wrong target value must be rejected
+        let mut wrong = ConstraintSystem::default();
+        wrong.add_boundary_assertion(
+            "starts_at_6".to_string(),
+            "x".to_string(),
+            0,
+            Fr::from(6u64),
+        );
+        let proof = StarkProver::new(&trace, &wrong).generate_proof().unwrap();
+        let verifier = StarkVerifier::new(&wrong, trace.height as usize);
+        assert!(!verifier.verify(&proof));
+    }
+
+    #[test]
+    fn test_quadratic_constraint() {
+        let trace = ExecutionTrace::from_fn(["x", "y"], 8, |i| [i, i * i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint_with_degree(
+            "y_is_x_squared".to_string(),
+            vec!["x".to_string(), "y".to_string()],
+            2,
+            Box::new(|current, _| {
+                let x = *current.get("x").unwrap();
+                *current.get("y").unwrap() - x * x
+            }),
+        );
+
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        let verifier = StarkVerifier::new(&constraints, trace.height as usize);
+        assert!(verifier.verify(&proof));
+    }
+
+    #[test]
+    fn test_insufficient_blowup() {
+        let trace = ExecutionTrace::from_fn(["x", "y"], 8, |i| [i, i * i * i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint_with_degree(
+            "y_is_x_cubed".to_string(),
+            vec!["x".to_string(), "y".to_string()],
+            3,
+            Box::new(|current, _| {
+                let x = *current.get("x").unwrap();
+                *current.get("y").unwrap() - x * x * x
+            }),
+        );
+
+        assert_eq!(constraints.required_blowup(), 4);
+        let result = StarkProver::new(&trace, &constraints).generate_proof();
+        assert_eq!(
+            result.err(),
+            Some(ToyniError::InsufficientBlowup {
+                constraint: "y_is_x_cubed".to_string(),
+                degree: 3,
+                required: 4,
+                blowup: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_undeclared_constraint_degree() {
+        let trace = ExecutionTrace::from_fn(["x", "y"], 8, |i| [i, i * i]).unwrap();
+
+        // The closure is quadratic but added as linear
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "y_is_x_squared".to_string(),
+            vec!["x".to_string(), "y".to_string()],
+            Box::new(|current, _| current["y"] - current["x"] * current["x"]),
+        );
+        assert_eq!(
+            StarkProver::new(&trace, &constraints)
+                .generate_proof()
+                .err(),
+            Some(ToyniError::UndeclaredConstraintDegree {
+                constraint: "y_is_x_squared".to_string(),
+                declared: 1
+            })
+        );
+
+        // Boundary constraints must be linear
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_boundary_constraint(
+            "square_is_zero".to_string(),
+            0,
+            vec!["x".to_string()],
+            Box::new(|row| row["x"] * row["x"]),
+        );
+        assert!(matches!(
+            constraints.validate_declared_degrees(&trace.variables()),
+            Err(ToyniError::UndeclaredConstraintDegree { declared: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_incomplete_trace() {
         let mut trace = ExecutionTrace::new(4, 1);
-        for i in 0..4 {
+        for i in 0..3 {
             let mut row = HashMap::new();
-            row.insert("x".to_string(), i + 1); // invalid
-            trace.insert_column(row);
+            row.insert("x".to_string(), i);
+            trace.insert_column(row).unwrap();
         }
 
+        let constraints = ConstraintSystem::default();
+        assert_eq!(
+            StarkProver::new(&trace, &constraints)
+                .generate_proof()
+                .err(),
+            Some(ToyniError::TraceTooShort { rows: 3, height: 4 })
+        );
+    }
+
+    #[test]
+    fn test_blowup_and_query_options() {
+        let trace = ExecutionTrace::from_fn(["x", "y"], 8, |i| [i, i * i * i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint_with_degree(
+            "y_is_x_cubed".to_string(),
+            vec!["x".to_string(), "y".to_string()],
+            3,
+            Box::new(|current, _| {
+                let x = *current.get("x").unwrap();
+                *current.get("y").unwrap() - x * x * x
+            }),
+        );
+
+        // A blowup of 4 fits the cubic constraint
+        let options = ProofOptions::default()
+            .with_blowup_factor(4)
+            .with_fri_queries(20);
+        let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        assert_eq!(proof.fri_proof.queries.len(), 20);
+        assert_eq!(proof.trace_openings.len(), 20);
+        assert_eq!(proof.options, options);
+        let verifier =
+            StarkVerifier::with_options(&constraints, trace.height as usize, options.clone());
+        assert!(verifier.verify(&proof));
+
+        // The verifier rejects proofs generated with other options
+        let verifier = StarkVerifier::with_options(
+            &constraints,
+            trace.height as usize,
+            options.with_fri_queries(40),
+        );
+        assert!(!verifier.verify(&proof));
+    }
+
+    #[test]
+    fn test_invalid_proof() {
+        // Invalid: x starts at 1
+        let trace = ExecutionTrace::from_fn(["x"], 4, |i| [i + 1]).unwrap();
+
         let mut constraints = ConstraintSystem::default();
         constraints.add_transition_constraint(
             "increment".to_string(),
             vec!["x".to_string()],
             Box::new(|current, next| {
-                let x_n = Fr::from(*current.get("x").unwrap());
-                let x_next = Fr::from(*next.get("x").unwrap());
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
                 x_next - x_n - Fr::ONE
             }),
         );
@@ -60,31 +599,26 @@ mod tests {
             "starts_at_0".to_string(),
             0,
             vec!["x".to_string()],
-            Box::new(|row| Fr::from(*row.get("x").unwrap())),
+            Box::new(|row| *row.get("x").unwrap()),
         );
 
         let prover = StarkProver::new(&trace, &constraints);
-        let proof = prover.generate_proof();
+        let proof = prover.generate_proof().unwrap();
         let verifier = StarkVerifier::new(&constraints, trace.height as usize);
         assert!(!verifier.verify(&proof));
     }
 
     #[test]
     fn test_larger_trace() {
-        let mut trace = ExecutionTrace::new(8, 1);
-        for i in 0..8 {
-            let mut row = HashMap::new();
-            row.insert("x".to_string(), i);
-            trace.insert_column(row);
-        }
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
 
         let mut constraints = ConstraintSystem::default();
         constraints.add_transition_constraint(
             "increment".to_string(),
             vec!["x".to_string()],
             Box::new(|current, next| {
-                let x_n = Fr::from(*current.get("x").unwrap());
-                let x_next = Fr::from(*next.get("x").unwrap());
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
                 x_next - x_n - Fr::ONE
             }),
         );
@@ -92,24 +626,18 @@ mod tests {
             "starts_at_0".to_string(),
             0,
             vec!["x".to_string()],
-            Box::new(|row| Fr::from(*row.get("x").unwrap())),
+            Box::new(|row| *row.get("x").unwrap()),
         );
 
         let prover = StarkProver::new(&trace, &constraints);
-        let proof = prover.generate_proof();
+        let proof = prover.generate_proof().unwrap();
         let verifier = StarkVerifier::new(&constraints, trace.height as usize);
         assert!(verifier.verify(&proof));
     }
 
     #[test]
     fn test_multiple_variables() {
-        let mut trace = ExecutionTrace::new(4, 2);
-        for i in 0..4 {
-            let mut row = HashMap::new();
-            row.insert("x".to_string(), i);
-            row.insert("y".to_string(), i * 2);
-            trace.insert_column(row);
-        }
+        let trace = ExecutionTrace::from_fn(["x", "y"], 4, |i| [i, i * 2]).unwrap();
 
         let mut constraints = ConstraintSystem::default();
         // x[n+1] = x[n] + 1
@@ -117,8 +645,8 @@ mod tests {
             "increment_x".to_string(),
             vec!["x".to_string(), "y".to_string()],
             Box::new(|current, next| {
-                let x_n = Fr::from(*current.get("x").unwrap());
-                let x_next = Fr::from(*next.get("x").unwrap());
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
                 x_next - x_n - Fr::ONE
             }),
         );
@@ -127,8 +655,8 @@ mod tests {
             "y_is_double_x".to_string(),
             vec!["x".to_string(), "y".to_string()],
             Box::new(|current, _| {
-                let x = Fr::from(*current.get("x").unwrap());
-                let y = Fr::from(*current.get("y").unwrap());
+                let x = *current.get("x").unwrap();
+                let y = *current.get("y").unwrap();
                 y - x * Fr::from(2u64)
             }),
         );
@@ -136,31 +664,27 @@ mod tests {
             "starts_at_0".to_string(),
             0,
             vec!["x".to_string()],
-            Box::new(|row| Fr::from(*row.get("x").unwrap())),
+            Box::new(|row| *row.get("x").unwrap()),
         );
 
         let prover = StarkProver::new(&trace, &constraints);
-        let proof = prover.generate_proof();
+        let proof = prover.generate_proof().unwrap();
         let verifier = StarkVerifier::new(&constraints, trace.height as usize);
         assert!(verifier.verify(&proof));
     }
 
     #[test]
     fn test_zero_values() {
-        let mut trace = ExecutionTrace::new(4, 1);
-        for _ in 0..4 {
-            let mut row = HashMap::new();
-            row.insert("x".to_string(), 0); // All zeros
-            trace.insert_column(row);
-        }
+        // All zeros
+        let trace = ExecutionTrace::from_fn(["x"], 4, |_| [0]).unwrap();
 
         let mut constraints = ConstraintSystem::default();
         constraints.add_transition_constraint(
             "zero_sequence".to_string(),
             vec!["x".to_string()],
             Box::new(|current, next| {
-                let x_n = Fr::from(*current.get("x").unwrap());
-                let x_next = Fr::from(*next.get("x").unwrap());
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
                 x_next - x_n // Should be zero
             }),
         );
@@ -168,24 +692,19 @@ mod tests {
             "starts_at_zero".to_string(),
             0,
             vec!["x".to_string()],
-            Box::new(|row| Fr::from(*row.get("x").unwrap())),
+            Box::new(|row| *row.get("x").unwrap()),
         );
 
         let prover = StarkProver::new(&trace, &constraints);
-        let proof = prover.generate_proof();
+        let proof = prover.generate_proof().unwrap();
         let verifier = StarkVerifier::new(&constraints, trace.height as usize);
         assert!(verifier.verify(&proof));
     }
 
     #[test]
     fn test_complex_constraints() {
-        let mut trace = ExecutionTrace::new(4, 2);
-        for i in 0..4 {
-            let mut row = HashMap::new();
-            row.insert("x".to_string(), i);
-            row.insert("y".to_string(), i * i); // y = x^2
-            trace.insert_column(row);
-        }
+        // y = x^2
+        let trace = ExecutionTrace::from_fn(["x", "y"], 4, |i| [i, i * i]).unwrap();
 
         let mut constraints = ConstraintSystem::default();
         // x[n+1] = x[n] + 1
@@ -193,18 +712,19 @@ mod tests {
             "increment_x".to_string(),
             vec!["x".to_string(), "y".to_string()],
             Box::new(|current, next| {
-                let x_n = Fr::from(*current.get("x").unwrap());
-                let x_next = Fr::from(*next.get("x").unwrap());
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
                 x_next - x_n - Fr::ONE
             }),
         );
         // y[n] = x[n]^2
-        constraints.add_transition_constraint(
+        constraints.add_transition_constraint_with_degree(
             "y_is_x_squared".to_string(),
             vec!["x".to_string(), "y".to_string()],
+            2,
             Box::new(|current, _| {
-                let x = Fr::from(*current.get("x").unwrap());
-                let y = Fr::from(*current.get("y").unwrap());
+                let x = *current.get("x").unwrap();
+                let y = *current.get("y").unwrap();
                 y - x * x
             }),
         );
@@ -212,24 +732,19 @@ mod tests {
             "starts_at_0".to_string(),
             0,
             vec!["x".to_string()],
-            Box::new(|row| Fr::from(*row.get("x").unwrap())),
+            Box::new(|row| *row.get("x").unwrap()),
         );
 
         let prover = StarkProver::new(&trace, &constraints);
-        let proof = prover.generate_proof();
+        let proof = prover.generate_proof().unwrap();
         let verifier = StarkVerifier::new(&constraints, trace.height as usize);
         assert!(verifier.verify(&proof));
     }
 
     #[test]
     fn test_invalid_complex_constraints() {
-        let mut trace = ExecutionTrace::new(4, 2);
-        for i in 0..4 {
-            let mut row = HashMap::new();
-            row.insert("x".to_string(), i);
-            row.insert("y".to_string(), i * i + 1); // y = x^2 + 1 (invalid)
-            trace.insert_column(row);
-        }
+        // y = x^2 + 1 (invalid)
+        let trace = ExecutionTrace::from_fn(["x", "y"], 4, |i| [i, i * i + 1]).unwrap();
 
         let mut constraints = ConstraintSystem::default();
         // x[n+1] = x[n] + 1
@@ -237,18 +752,19 @@ mod tests {
             "increment_x".to_string(),
             vec!["x".to_string(), "y".to_string()],
             Box::new(|current, next| {
-                let x_n = Fr::from(*current.get("x").unwrap());
-                let x_next = Fr::from(*next.get("x").unwrap());
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
                 x_next - x_n - Fr::ONE
             }),
         );
         // y[n] = x[n]^2
-        constraints.add_transition_constraint(
+        constraints.add_transition_constraint_with_degree(
             "y_is_x_squared".to_string(),
             vec!["x".to_string(), "y".to_string()],
+            2,
             Box::new(|current, _| {
-                let x = Fr::from(*current.get("x").unwrap());
-                let y = Fr::from(*current.get("y").unwrap());
+                let x = *current.get("x").unwrap();
+                let y = *current.get("y").unwrap();
                 y - x * x
             }),
         );
@@ -256,12 +772,2128 @@ mod tests {
             "starts_at_0".to_string(),
             0,
             vec!["x".to_string()],
-            Box::new(|row| Fr::from(*row.get("x").unwrap())),
+            Box::new(|row| *row.get("x").unwrap()),
         );
 
         let prover = StarkProver::new(&trace, &constraints);
-        let proof = prover.generate_proof();
+        let proof = prover.generate_proof().unwrap();
         let verifier = StarkVerifier::new(&constraints, trace.height as usize);
         assert!(!verifier.verify(&proof));
     }
+
+    #[test]
+    fn test_mock_oracle_challenges() {
+        let trace = ExecutionTrace::from_fn(["x"], 4, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        // The out-of-domain point is drawn from the same script, so it must avoid the coset
+        // offset 7
+        let oracle = MockOracle::new(vec![Fr::from(11u64)], vec![0, 3, 5]);
+        let prover = StarkProver::new(&trace, &constraints);
+        let proof = prover
+            .generate_proof_with_oracle(&mut oracle.clone())
+            .unwrap();
+        assert!(
+            proof
+                .fri_proof
+                .challenges
+                .iter()
+                .all(|beta| *beta == Fr::from(11u64))
+        );
+
+        let verifier = StarkVerifier::new(&constraints, trace.height as usize);
+        assert!(verifier.verify_with_oracle(&proof, &mut oracle.clone()));
+
+        // A verifier drawing different challenges must reject the proof
+        let mut other_oracle = MockOracle::new(vec![Fr::from(9u64)], vec![0]);
+        assert!(!verifier.verify_with_oracle(&proof, &mut other_oracle));
+
+        // An out-of-domain point on the trace domain is an error, not a panic
+        let mut on_domain = MockOracle::new(vec![Fr::ONE], vec![0]);
+        assert_eq!(
+            prover.generate_proof_with_oracle(&mut on_domain).err(),
+            Some(ToyniError::PointOnDomain)
+        );
+    }
+
+    #[test]
+    fn test_query_positions_follow_transcript() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        // Every query opens the layer 0 coset {p, p + 8} of the position p drawn from the
+        // transcript, on an extended domain of 16 points folded by 2
+        let scripted = [1, 6, 13];
+        let oracle = MockOracle::new(vec![Fr::from(11u64)], scripted.to_vec());
+        let prover = StarkProver::new(&trace, &constraints);
+        let proof = prover
+            .generate_proof_with_oracle(&mut oracle.clone())
+            .unwrap();
+        for (query, openings) in proof.trace_openings.iter().enumerate() {
+            let position = scripted[query % scripted.len()] % 8;
+            let indices: Vec<usize> = openings.iter().map(|opening| opening.index).collect();
+            assert_eq!(indices, vec![position, position + 8]);
+        }
+        let verifier = StarkVerifier::new(&constraints, trace.height as usize);
+        assert!(verifier.verify_with_oracle(&proof, &mut oracle.clone()));
+
+        // Openings at positions the transcript did not select are rejected
+        let mut moved = prover
+            .generate_proof_with_oracle(&mut oracle.clone())
+            .unwrap();
+        moved.trace_openings[0].swap(0, 1);
+        assert!(!verifier.verify_with_oracle(&moved, &mut oracle.clone()));
+
+        // The Fiat-Shamir transcript derives the positions from the commitments, so the
+        // prover cannot choose them and proofs of the same statement open the same rows
+        let first = prover.generate_proof().unwrap();
+        let second = prover.generate_proof().unwrap();
+        let indices = |proof: &StarkProof| -> Vec<usize> {
+            proof
+                .trace_openings
+                .iter()
+                .flatten()
+                .map(|opening| opening.index)
+                .collect()
+        };
+        assert_eq!(indices(&first), indices(&second));
+        assert!(verifier.verify(&first));
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let build_trace = |start: u64| ExecutionTrace::from_fn(["x"], 8, |i| [start + i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        let options = ProofOptions::default().with_zk(true).with_fri_queries(8);
+        let prove = |trace: &ExecutionTrace| {
+            StarkProver::with_options(trace, &constraints, options.clone())
+                .generate_proof()
+                .unwrap()
+        };
+        let mut proofs: Vec<StarkProof> = (0..3).map(|i| prove(&build_trace(i * 10))).collect();
+        let verifier = StarkVerifier::with_options(&constraints, 8, options.clone());
+        assert!(verifier.verify_batch(&proofs));
+        assert!(verifier.verify_batch(&[]));
+
+        // A single invalid proof fails the whole batch
+        let mut invalid = build_trace(0);
+        invalid.trace[7] = TraceRow::from([("x".to_string(), Fr::from(3u64))]);
+        proofs.push(prove(&invalid));
+        assert!(!verifier.verify(&proofs[3]));
+        assert!(!verifier.verify_batch(&proofs));
+
+        // Out-of-domain values that only break the combined equality are caught too
+        proofs.pop();
+        proofs[1].ood_frame.quotient[0] += Fr::ONE;
+        assert!(!verifier.verify_batch(&proofs));
+    }
+
+    #[test]
+    fn test_proof_encoding() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        let options = ProofOptions::default()
+            .with_zk(true)
+            .with_fri_queries(8)
+            .with_security_level(16, Soundness::Proven);
+        let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        let bytes = proof.to_bytes();
+        assert_eq!(&bytes[..4], &PROOF_MAGIC);
+        assert_eq!(bytes[4..6], PROOF_VERSION.to_le_bytes());
+
+        // Decoding round-trips to the same proof and the same bytes
+        let decoded = StarkProof::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.options, options);
+        assert_eq!(decoded.to_bytes(), bytes);
+        let verifier = StarkVerifier::with_options(&constraints, trace.height as usize, options);
+        assert!(verifier.verify(&decoded));
+
+        // Unknown versions, foreign data, truncation and trailing bytes are rejected
+        let mut future = bytes.clone();
+        future[4..6].copy_from_slice(&(PROOF_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            StarkProof::<Fr>::from_bytes(&future).err(),
+            Some(ToyniError::UnsupportedProofVersion(PROOF_VERSION + 1))
+        );
+        let mut foreign = bytes.clone();
+        foreign[0] = b'X';
+        assert!(matches!(
+            StarkProof::<Fr>::from_bytes(&foreign),
+            Err(ToyniError::MalformedProof(_))
+        ));
+        assert!(matches!(
+            StarkProof::<Fr>::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(ToyniError::MalformedProof(_))
+        ));
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(
+            StarkProof::<Fr>::from_bytes(&trailing),
+            Err(ToyniError::MalformedProof(_))
+        ));
+
+        // Field elements must be reduced, so the coset offset cannot be re-encoded
+        // as itself plus the field order
+        let mut unreduced = bytes;
+        let offset = 6 + 8 * 5 + 8 * 2 + 1 + 1 + 8 * 2 + 8 * 2;
+        unreduced[offset..offset + 32].fill(0xff);
+        assert!(matches!(
+            StarkProof::<Fr>::from_bytes(&unreduced),
+            Err(ToyniError::MalformedProof(_))
+        ));
+    }
+
+    #[test]
+    fn test_constraint_system_encoding() {
+        // x counts up, resetting every fourth row, y accumulates x and is published
+        let mut x = Vec::new();
+        let mut y = vec![0u64];
+        for i in 0..16u64 {
+            x.push(i % 4);
+            if i < 15 {
+                y.push(y[i as usize] + i % 4);
+            }
+        }
+        let trace = ExecutionTrace::from_columns([("x", x), ("y", y)]).unwrap();
+        let xy = || vec!["x".to_string(), "y".to_string()];
+
+        let mut constraints = ConstraintSystem::default();
+        for offset in 0..3 {
+            constraints
+                .add_periodic_expr(
+                    format!("count_{}", offset),
+                    xy(),
+                    4,
+                    offset,
+                    Expr::next(0) - Expr::current(0) - Expr::Const(Fr::ONE),
+                )
+                .unwrap();
+        }
+        constraints
+            .add_periodic_expr("reset".to_string(), xy(), 4, 3, Expr::next(0))
+            .unwrap();
+        constraints
+            .add_transition_expr(
+                "accumulate".to_string(),
+                xy(),
+                Expr::next(1) - Expr::current(1) - Expr::current(0),
+            )
+            .unwrap();
+        constraints.add_boundary_assertion("y_0".to_string(), "y".to_string(), 0, Fr::ZERO);
+        constraints.add_public_cell("total".to_string(), "y".to_string(), 15);
+
+        let bytes = constraints.to_bytes().unwrap();
+        assert_eq!(&bytes[..4], &CONSTRAINTS_MAGIC);
+        assert_eq!(bytes[4..6], CONSTRAINTS_VERSION.to_le_bytes());
+        let decoded = ConstraintSystem::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes().unwrap(), bytes);
+        assert_eq!(decoded.num_constraints(), constraints.num_constraints());
+        assert_eq!(decoded.max_degree(), 2);
+        let columns = trace.variables();
+        assert_eq!(decoded.digest(&columns), constraints.digest(&columns));
+
+        // A verifier loading the system from bytes accepts proofs for the original
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert_eq!(proof.public_value("total"), Some(Fr::from(21u64)));
+        assert!(StarkVerifier::new(&decoded, 16).verify(&proof));
+
+        // Closures have no encoding, and malformed bytes are rejected
+        constraints.add_transition_constraint(
+            "closure".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| next["x"] - current["x"]),
+        );
+        assert_eq!(
+            constraints.to_bytes().err(),
+            Some(ToyniError::UnencodableConstraint("closure".to_string()))
+        );
+        assert!(matches!(
+            ConstraintSystem::<Fr>::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(ToyniError::MalformedConstraints(_))
+        ));
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(
+            ConstraintSystem::<Fr>::from_bytes(&trailing),
+            Err(ToyniError::MalformedConstraints(_))
+        ));
+        let mut future = bytes;
+        future[4..6].copy_from_slice(&(CONSTRAINTS_VERSION + 1).to_le_bytes());
+        assert!(ConstraintSystem::<Fr>::from_bytes(&future).is_err());
+    }
+
+    #[test]
+    fn test_constraint_system_decoding_rejects_unbounded_expressions() {
+        // A system holding one transition or window constraint over x, followed by no
+        // boundary constraints, names, periodic columns or public cells
+        let system = |window: bool, expression: Vec<u8>| {
+            let mut bytes = CONSTRAINTS_MAGIC.to_vec();
+            bytes.extend(CONSTRAINTS_VERSION.to_le_bytes());
+            for sequence in [!window, window] {
+                bytes.extend((sequence as u64).to_le_bytes());
+                if sequence {
+                    // The name, then the variables as a sequence of one string
+                    bytes.extend(1u64.to_le_bytes());
+                    bytes.push(b'c');
+                    bytes.extend([1u64, 1].map(u64::to_le_bytes).concat());
+                    bytes.push(b'x');
+                    bytes.extend((expression.len() as u64).to_le_bytes());
+                    bytes.extend(&expression);
+                }
+            }
+            bytes.extend([0; 6 * 8]);
+            bytes
+        };
+        let x = Expr::<Fr>::current(0);
+        let decoded = ConstraintSystem::<Fr>::from_bytes(&system(false, x.to_bytes())).unwrap();
+        assert_eq!(decoded.num_constraints(), 1);
+
+        // A degree overflowing a usize
+        let overflow = (x.clone() * x.clone()).pow(u64::MAX);
+        assert!(matches!(
+            ConstraintSystem::<Fr>::from_bytes(&system(false, overflow.to_bytes())),
+            Err(ToyniError::InvalidExpression(_))
+        ));
+        // A window of more rows than a usize counts
+        let far = Expr::<Fr>::Column(0, usize::MAX);
+        assert!(matches!(
+            ConstraintSystem::<Fr>::from_bytes(&system(true, far.to_bytes())),
+            Err(ToyniError::InvalidExpression(_))
+        ));
+        // Powers nested deeper than any stack holds
+        let depth = 1 << 20;
+        let mut nested = vec![4; depth];
+        nested.extend(x.to_bytes());
+        nested.extend(1u64.to_le_bytes().repeat(depth));
+        assert!(matches!(
+            ConstraintSystem::<Fr>::from_bytes(&system(false, nested)),
+            Err(ToyniError::InvalidExpression(_))
+        ));
+    }
+
+    #[test]
+    fn test_builders_and_public_inputs() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i + 3]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+        constraints.add_boundary_assertion("start".to_string(), "x".to_string(), 0, Fr::from(3));
+
+        let options = ProofOptions::default().with_fri_queries(16);
+        let inputs = vec![Fr::from(3u64)];
+        let proof = StarkProver::builder()
+            .trace(&trace)
+            .constraints(&constraints)
+            .options(options.clone())
+            .public_inputs(inputs.clone())
+            .build()
+            .unwrap()
+            .generate_proof()
+            .unwrap();
+        let verifier = |public_inputs: Vec<Fr>| {
+            StarkVerifier::builder()
+                .constraints(&constraints)
+                .trace_len(8)
+                .options(options.clone())
+                .public_inputs(public_inputs)
+                .build()
+                .unwrap()
+        };
+        assert!(verifier(inputs.clone()).verify(&proof));
+
+        // The builders agree with the constructors
+        let same = StarkProver::with_options(&trace, &constraints, options.clone())
+            .with_public_inputs(inputs.clone())
+            .generate_proof()
+            .unwrap();
+        assert_eq!(same.to_bytes(), proof.to_bytes());
+        let constructed = StarkVerifier::with_options(&constraints, 8, options.clone())
+            .with_public_inputs(inputs);
+        assert!(constructed.verify(&proof));
+
+        // The proof is bound to its public inputs
+        assert!(!verifier(vec![Fr::from(4u64)]).verify(&proof));
+        assert!(!verifier(Vec::new()).verify(&proof));
+
+        // Required settings must be given
+        let missing = StarkProver::builder().constraints(&constraints).build();
+        assert_eq!(missing.err(), Some(ToyniError::IncompleteBuilder("trace")));
+        let missing = StarkVerifier::builder().constraints(&constraints).build();
+        assert_eq!(
+            missing.err(),
+            Some(ToyniError::IncompleteBuilder("trace length"))
+        );
+    }
+
+    #[test]
+    fn test_proof_bound_to_constraints() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [2 * i]).unwrap();
+
+        let step = |name: &str, step: u64| {
+            let mut constraints = ConstraintSystem::default();
+            constraints.add_transition_constraint(
+                name.to_string(),
+                vec!["x".to_string()],
+                Box::new(move |current, next| {
+                    let x_n = *current.get("x").unwrap();
+                    let x_next = *next.get("x").unwrap();
+                    x_next - x_n - Fr::from(step)
+                }),
+            );
+            constraints
+        };
+        let constraints = step("step", 2);
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+
+        // An identically built constraint system accepts the proof
+        assert!(StarkVerifier::new(&step("step", 2), 8).verify(&proof));
+
+        // A different system is rejected even if the trace satisfies it too
+        let mut stricter = step("step", 2);
+        stricter.add_boundary_constraint(
+            "even".to_string(),
+            0,
+            vec!["x".to_string()],
+            Box::new(|row| *row.get("x").unwrap()),
+        );
+        assert!(stricter.is_satisfied(&trace).unwrap());
+        for other in [step("double_step", 2), step("step", 3), stricter] {
+            let verifier = StarkVerifier::new(&other, 8);
+            assert!(!verifier.verify(&proof));
+        }
+    }
+
+    #[test]
+    fn test_proof_security_level() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        let options = ProofOptions::default()
+            .with_blowup_factor(4)
+            .with_fri_max_remainder_degree(1)
+            .with_grinding_bits(4)
+            .with_security_level(64, Soundness::Proven);
+        let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        assert_eq!(
+            proof.security_level(),
+            SecurityLevel {
+                conjectured: 2 * 60 + 4,
+                proven: 64
+            }
+        );
+
+        // The level is read from the embedded options, so it survives encoding
+        let decoded = StarkProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded.security_level(), proof.security_level());
+
+        // A policy can reject proofs below a minimum before verifying them
+        let accepts = |proof: &StarkProof, minimum: usize| {
+            proof.security_level().bits(Soundness::Proven) >= minimum
+                && StarkVerifier::with_options(&constraints, 8, options.clone()).verify(proof)
+        };
+        assert!(accepts(&decoded, 64));
+        assert!(!accepts(&decoded, 80));
+    }
+
+    #[test]
+    fn test_proof_size_estimate() {
+        let trace = ExecutionTrace::from_fn(["x"], 16, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        for options in [
+            ProofOptions::default().with_fri_queries(16),
+            ProofOptions::default()
+                .with_fri_queries(8)
+                .with_merkle_cap_height(2)
+                .with_fri_folding_schedule(vec![4])
+                .with_composition_chunks(2)
+                .with_zk(true),
+        ] {
+            let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+                .generate_proof()
+                .unwrap();
+            let names: usize = proof
+                .trace_variables
+                .iter()
+                .chain(&proof.trace_randomizers)
+                .map(String::len)
+                .sum();
+            let width = proof.ood_frame.current.len();
+            let estimate = options.estimate_proof_size(16, width) + names;
+            let actual = proof.to_bytes().len();
+            assert!(actual <= estimate);
+            assert!(estimate - actual <= 32 * options.fri_remainder_size);
+        }
+    }
+
+    /// Records every notification and cancels once `cancel_at` starts.
+    #[derive(Default)]
+    struct RecordingObserver {
+        events: Mutex<Vec<String>>,
+        cancel_at: Option<ProofPhase>,
+    }
+
+    impl ProofObserver for RecordingObserver {
+        fn on_phase_start(&self, phase: ProofPhase) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("start {:?}", phase));
+        }
+
+        fn on_phase_end(&self, phase: ProofPhase) {
+            self.events.lock().unwrap().push(format!("end {:?}", phase));
+        }
+
+        fn on_fri_progress(&self, folded: usize, total: usize) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("fri {}%", folded * 100 / total));
+        }
+
+        fn is_cancelled(&self) -> bool {
+            let events = self.events.lock().unwrap();
+            self.cancel_at
+                .is_some_and(|phase| events.contains(&format!("start {:?}", phase)))
+        }
+    }
+
+    #[test]
+    fn test_prover_observer() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        // Phases are reported in order, with the FRI rounds in between
+        let options = ProofOptions::default().with_fri_queries(8);
+        let observer = RecordingObserver::default();
+        let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+            .with_observer(&observer)
+            .generate_proof()
+            .unwrap();
+        let verifier =
+            StarkVerifier::with_options(&constraints, trace.height as usize, options.clone());
+        assert!(verifier.verify(&proof));
+        let phase = |event: &str, phase: ProofPhase| format!("{} {:?}", event, phase);
+        assert_eq!(
+            *observer.events.lock().unwrap(),
+            vec![
+                phase("start", ProofPhase::TraceCommitment),
+                phase("end", ProofPhase::TraceCommitment),
+                phase("start", ProofPhase::QuotientCommitment),
+                phase("end", ProofPhase::QuotientCommitment),
+                phase("start", ProofPhase::DeepComposition),
+                phase("end", ProofPhase::DeepComposition),
+                phase("start", ProofPhase::Fri),
+                "fri 50%".to_string(),
+                "fri 100%".to_string(),
+                phase("end", ProofPhase::Fri),
+                phase("start", ProofPhase::TraceOpenings),
+                phase("end", ProofPhase::TraceOpenings),
+            ]
+        );
+
+        // Cancelling stops at the next FRI round or phase
+        let observer = RecordingObserver {
+            cancel_at: Some(ProofPhase::Fri),
+            ..Default::default()
+        };
+        let result = StarkProver::with_options(&trace, &constraints, options.clone())
+            .with_observer(&observer)
+            .generate_proof();
+        assert_eq!(result.err(), Some(ToyniError::Cancelled));
+        assert_eq!(
+            observer.events.lock().unwrap().last(),
+            Some(&phase("start", ProofPhase::Fri))
+        );
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let result = StarkProver::with_options(&trace, &constraints, options)
+            .with_observer(&token)
+            .generate_proof();
+        assert_eq!(result.err(), Some(ToyniError::Cancelled));
+    }
+
+    #[test]
+    fn test_async_proving() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        let options = ProofOptions::default().with_fri_queries(8);
+        let token = CancellationToken::new();
+        let prover =
+            StarkProver::with_options(&trace, &constraints, options.clone()).with_observer(&token);
+
+        // The future can move between worker threads and yields after every phase
+        fn assert_send<T: Send>(_: &T) {}
+        let future = prover.generate_proof_async();
+        assert_send(&future);
+        let mut future = pin!(future);
+        let mut context = Context::from_waker(Waker::noop());
+        let mut yields = 0;
+        let proof = loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(proof) => break proof.unwrap(),
+                Poll::Pending => yields += 1,
+            }
+        };
+        assert_eq!(yields, 5);
+        let verifier = StarkVerifier::with_options(&constraints, trace.height as usize, options);
+        assert!(verifier.verify(&proof));
+
+        // Cancelling while the future is suspended stops it at the next phase
+        let mut future = pin!(prover.generate_proof_async());
+        assert!(future.as_mut().poll(&mut context).is_pending());
+        token.cancel();
+        assert!(matches!(
+            future.as_mut().poll(&mut context),
+            Poll::Ready(Err(ToyniError::Cancelled))
+        ));
+    }
+
+    #[test]
+    fn test_shared_constraint_system() {
+        // One constraint system shared by provers and verifiers on several threads
+        let mut constraints = ConstraintSystem::default();
+        let x = col("x");
+        constraints.transition(x.next() - x.cur() - 1);
+        constraints.assert_first("x", Fr::ZERO);
+        let constraints = &constraints;
+
+        let results = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4u64)
+                .map(|start| {
+                    scope.spawn(move || {
+                        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [start + i]).unwrap();
+                        let proof = StarkProver::new(&trace, constraints)
+                            .generate_proof()
+                            .unwrap();
+                        StarkVerifier::new(constraints, 8).verify(&proof)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        // Only the trace starting at zero satisfies the assertion
+        assert_eq!(results, [true, false, false, false]);
+    }
+
+    #[test]
+    fn test_streaming_strategy() {
+        let trace = ExecutionTrace::from_fn(["x", "y"], 16, |i| [i, i * i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint_with_degree(
+            "square".to_string(),
+            vec!["x".to_string(), "y".to_string()],
+            2,
+            Box::new(|current, next| {
+                let x_next = *next.get("x").unwrap();
+                let y_next = *next.get("y").unwrap();
+                let x_n = *current.get("x").unwrap();
+                (x_next - x_n - Fr::ONE) + (y_next - x_next * x_next)
+            }),
+        );
+        constraints.add_boundary_constraint(
+            "starts_at_0".to_string(),
+            0,
+            vec!["x".to_string()],
+            Box::new(|row| *row.get("x").unwrap()),
+        );
+
+        for options in [
+            ProofOptions::default()
+                .with_blowup_factor(4)
+                .with_fri_max_remainder_degree(1),
+            ProofOptions::default()
+                .with_fri_queries(8)
+                .with_composition_chunks(2)
+                .with_zk(true),
+        ] {
+            // Streaming proofs have the shape of in-memory ones and verify the same way
+            let prove = |strategy| {
+                StarkProver::with_options(&trace, &constraints, options.clone())
+                    .with_strategy(strategy)
+                    .generate_proof()
+                    .unwrap()
+            };
+            let streamed = prove(ProvingStrategy::Streaming);
+            let in_memory = prove(ProvingStrategy::InMemory);
+            assert_eq!(streamed.to_bytes().len(), in_memory.to_bytes().len());
+            let verifier =
+                StarkVerifier::with_options(&constraints, trace.height as usize, options.clone());
+            assert!(verifier.verify(&streamed));
+            assert!(verifier.verify(&in_memory));
+        }
+
+        // Violations are still caught when the extension is streamed
+        let bad_trace =
+            ExecutionTrace::from_fn(["x", "y"], 16, |i| [i, if i == 5 { 26 } else { i * i }])
+                .unwrap();
+        let options = ProofOptions::default()
+            .with_blowup_factor(4)
+            .with_fri_max_remainder_degree(1);
+        let proof = StarkProver::with_options(&bad_trace, &constraints, options.clone())
+            .with_strategy(ProvingStrategy::Streaming)
+            .generate_proof()
+            .unwrap();
+        let verifier =
+            StarkVerifier::with_options(&constraints, bad_trace.height as usize, options);
+        assert!(!verifier.verify(&proof));
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let trace = ExecutionTrace::from_fn(["x", "y"], 64, |i| [i, 2 * i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "double".to_string(),
+            vec!["x".to_string(), "y".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                let y_next = *next.get("y").unwrap();
+                (x_next - x_n - Fr::ONE) + (y_next - x_next - x_next)
+            }),
+        );
+
+        let options = ProofOptions::default()
+            .with_blowup_factor(8)
+            .with_fri_max_remainder_degree(1)
+            .with_fri_queries(16);
+        let in_memory_estimate = options.estimate_prover_memory(64, 2, false);
+        let prove = |options: ProofOptions| -> ProofMetrics {
+            let (proof, metrics) = StarkProver::with_options(&trace, &constraints, options.clone())
+                .generate_proof_with_metrics()
+                .unwrap();
+            // The budget is not part of the proof, so verifiers with or without it accept
+            assert_eq!(proof.options.memory_budget, None);
+            for options in [options.clone(), options.proof_options()] {
+                let verifier = StarkVerifier::with_options(&constraints, 64, options);
+                assert!(verifier.verify(&proof));
+            }
+            metrics
+        };
+
+        // A budget fitting the whole extension keeps it in memory
+        let roomy = prove(options.clone().with_memory_budget(in_memory_estimate));
+        assert_eq!(roomy.strategy, ProvingStrategy::InMemory);
+        let phases: Vec<ProofPhase> = roomy.phases.iter().map(|phase| phase.phase).collect();
+        assert_eq!(
+            phases,
+            vec![
+                ProofPhase::TraceCommitment,
+                ProofPhase::QuotientCommitment,
+                ProofPhase::DeepComposition,
+                ProofPhase::Fri,
+                ProofPhase::TraceOpenings,
+            ]
+        );
+        assert!(roomy.peak_memory() > 0);
+        assert!(roomy.peak_memory() <= in_memory_estimate);
+
+        // A tighter budget streams the extension and holds less
+        let tight = prove(options.clone().with_memory_budget(in_memory_estimate - 1));
+        assert_eq!(tight.strategy, ProvingStrategy::Streaming);
+        let trace_commitment = |metrics: &ProofMetrics| {
+            metrics
+                .phase(ProofPhase::TraceCommitment)
+                .unwrap()
+                .peak_memory
+        };
+        assert!(trace_commitment(&tight) < trace_commitment(&roomy));
+        assert!(tight.peak_memory() < roomy.peak_memory());
+
+        // An explicit strategy overrides the budget
+        let prover = StarkProver::with_options(&trace, &constraints, options.with_memory_budget(0))
+            .with_strategy(ProvingStrategy::InMemory);
+        assert_eq!(prover.strategy(), ProvingStrategy::InMemory);
+    }
+
+    #[test]
+    fn test_tampered_fri_opening() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        let prover = StarkProver::new(&trace, &constraints);
+        let mut proof = prover.generate_proof().unwrap();
+        let verifier = StarkVerifier::new(&constraints, trace.height as usize);
+        assert!(verifier.verify(&proof));
+
+        // Only the queried quotient and layer positions are opened, each with a Merkle path
+        assert!(!proof.fri_proof.queries[0].layers.is_empty());
+        proof.fri_proof.queries[0].layers[0].values[0] += Fr::ONE;
+        assert!(!verifier.verify(&proof));
+    }
+
+    #[test]
+    fn test_verification_report() {
+        let build_trace = |offset: u64| {
+            ExecutionTrace::from_fn(["x"], 8, |i| [if i == 5 { i + offset } else { i }]).unwrap()
+        };
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        let verifier = StarkVerifier::new(&constraints, 8);
+        let proof = StarkProver::new(&build_trace(0), &constraints)
+            .generate_proof()
+            .unwrap();
+        let report = verifier.verify_with_report(&proof);
+        assert!(report.is_valid());
+        assert_eq!(report.to_string(), "proof verified");
+
+        // A trace violating a constraint fails the combined out-of-domain identity
+        let bad_proof = StarkProver::new(&build_trace(1), &constraints)
+            .generate_proof()
+            .unwrap();
+        let report = verifier.verify_with_report(&bad_proof);
+        assert_eq!(report.failure, Some(VerificationFailure::OutOfDomain));
+        assert!(!verifier.verify(&bad_proof));
+
+        // Tampered FRI and trace openings name the query, layer and position
+        let mut tampered = StarkProof::from_bytes(&proof.to_bytes()).unwrap();
+        let layer = &mut tampered.fri_proof.queries[1].layers[0];
+        layer.values[0] += Fr::ONE;
+        let index = layer.index;
+        assert_eq!(
+            verifier.verify_with_report(&tampered).failure,
+            Some(VerificationFailure::Fri(FriFailure::LayerOpening {
+                query: 1,
+                layer: 0,
+                index,
+            }))
+        );
+
+        let mut tampered = StarkProof::from_bytes(&proof.to_bytes()).unwrap();
+        let opening = &mut tampered.trace_openings[2][1];
+        opening.row[0] += Fr::ONE;
+        let position = opening.index;
+        let report = verifier.verify_with_report(&tampered);
+        assert_eq!(
+            report.failure,
+            Some(VerificationFailure::TraceOpening { query: 2, position })
+        );
+        assert!(
+            report
+                .to_string()
+                .contains(&format!("query 2 at position {}", position))
+        );
+
+        // Options are checked before the proof is read
+        let other = StarkVerifier::with_options(
+            &constraints,
+            8,
+            ProofOptions::default().with_fri_queries(7),
+        );
+        assert_eq!(
+            other.verify_with_report(&proof).failure,
+            Some(VerificationFailure::OptionsMismatch)
+        );
+        let invalid = StarkVerifier::new(&constraints, 6);
+        assert!(matches!(
+            invalid.verify_with_report(&proof).failure,
+            Some(VerificationFailure::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn test_trace_commitment() {
+        let build_trace =
+            |offset: u64| ExecutionTrace::from_fn(["x", "y"], 4, |i| [i + offset, i * 2]).unwrap();
+
+        let constraints = ConstraintSystem::default();
+        let trace = build_trace(0);
+        let first = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        let second = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert_eq!(first.trace_commitment, second.trace_commitment);
+
+        let other_trace = build_trace(1);
+        let other = StarkProver::new(&other_trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert_ne!(first.trace_commitment, other.trace_commitment);
+
+        // Without zero-knowledge the prover commits to the same rows as `commit`
+        let options = ProofOptions::default();
+        let commitment = trace
+            .commit::<Sha256Hasher>(options.blowup_factor, options.coset_offset)
+            .unwrap();
+        assert_eq!(first.trace_commitment, vec![commitment.root()]);
+
+        // The prover builds on the trace's cached extension, which follows in-place edits
+        let mut edited = trace.clone();
+        edited.trace[0].insert("x".to_string(), Fr::from(5u64));
+        let proof = StarkProver::new(&edited, &constraints)
+            .generate_proof()
+            .unwrap();
+        let commitment = edited
+            .commit::<Sha256Hasher>(options.blowup_factor, options.coset_offset)
+            .unwrap();
+        assert_eq!(proof.trace_commitment, vec![commitment.root()]);
+        assert_ne!(proof.trace_commitment, first.trace_commitment);
+        assert!(StarkVerifier::new(&constraints, 4).verify(&proof));
+    }
+
+    #[test]
+    fn test_tampered_trace_opening() {
+        let trace = ExecutionTrace::from_fn(["x", "y"], 8, |i| [i, i * i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint_with_degree(
+            "y_is_x_squared".to_string(),
+            vec!["x".to_string(), "y".to_string()],
+            2,
+            Box::new(|current, _| {
+                let x = *current.get("x").unwrap();
+                let y = *current.get("y").unwrap();
+                y - x * x
+            }),
+        );
+
+        let mut proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert_eq!(
+            proof.trace_variables,
+            vec!["x".to_string(), "y".to_string()]
+        );
+        let verifier = StarkVerifier::new(&constraints, trace.height as usize);
+        assert!(verifier.verify(&proof));
+
+        // Opened rows must match the trace commitment
+        proof.trace_openings[0][0].row[1] += Fr::ONE;
+        assert!(!verifier.verify(&proof));
+    }
+
+    #[test]
+    fn test_merkle_cap_options() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        let options = ProofOptions::default().with_merkle_cap_height(2);
+        let root_proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        let cap_proof = StarkProver::with_options(&trace, &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        assert_eq!(cap_proof.trace_commitment.len(), 4);
+        assert_eq!(cap_proof.quotient_commitment.len(), 4);
+        assert!(
+            cap_proof.fri_proof.queries[0].layers[0].proofs[0]
+                .path
+                .len()
+                < root_proof.fri_proof.queries[0].layers[0].proofs[0]
+                    .path
+                    .len()
+        );
+
+        let verifier = StarkVerifier::with_options(&constraints, trace.height as usize, options);
+        assert!(verifier.verify(&cap_proof));
+
+        // The cap height is part of the statement, a mismatching verifier must reject
+        let root_verifier = StarkVerifier::new(&constraints, trace.height as usize);
+        assert!(!root_verifier.verify(&cap_proof));
+    }
+
+    #[test]
+    fn test_coset_offset_option() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        let options = ProofOptions::default().with_coset_offset(Fr::from(5u64));
+        let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        let verifier = StarkVerifier::with_options(&constraints, trace.height as usize, options);
+        assert!(verifier.verify(&proof));
+
+        // An offset inside the extended subgroup would put zeros of Z_H on the domain
+        let bad_options = ProofOptions::default().with_coset_offset(Fr::ONE);
+        let bad_verifier =
+            StarkVerifier::with_options(&constraints, trace.height as usize, bad_options);
+        assert!(!bad_verifier.verify(&proof));
+    }
+
+    #[test]
+    fn test_blinding_rows() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+        constraints.add_boundary_assertion(
+            "ends_at_7".to_string(),
+            "x".to_string(),
+            7,
+            Fr::from(7u64),
+        );
+
+        // Random rows after the trace do not have to satisfy the transitions
+        let options = ProofOptions::default().with_blinding_rows(4);
+        let prover = StarkProver::with_options(&trace, &constraints, options.clone());
+        let proof = prover.generate_proof().unwrap();
+        let verifier =
+            StarkVerifier::with_options(&constraints, trace.height as usize, options.clone());
+        assert!(verifier.verify(&proof));
+
+        // Fresh blinding rows change the trace commitment of the same witness
+        assert_ne!(
+            proof.trace_commitment,
+            prover.generate_proof().unwrap().trace_commitment
+        );
+
+        // The verifier must agree on the number of blinding rows
+        let unblinded = StarkVerifier::new(&constraints, trace.height as usize);
+        assert!(!unblinded.verify(&proof));
+    }
+
+    #[test]
+    fn test_randomizer_columns() {
+        let mut trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+        trace.add_randomizer_column("r".to_string()).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        let prover = StarkProver::new(&trace, &constraints);
+        let proof = prover.generate_proof().unwrap();
+        assert_eq!(proof.trace_variables, vec!["x".to_string()]);
+        assert_eq!(proof.trace_randomizers, vec!["r".to_string()]);
+        assert_eq!(proof.trace_openings[0][0].row.len(), 2);
+        let verifier = StarkVerifier::new(&constraints, trace.height as usize);
+        assert!(verifier.verify(&proof));
+
+        // The randomizer hides the composition of the same witness
+        let other = prover.generate_proof().unwrap();
+        assert_ne!(proof.quotient_commitment, other.quotient_commitment);
+
+        // The randomizer at ω·z enters no constraint, only the DEEP composition binds it
+        let mut tampered = other;
+        tampered.ood_frame.next[1] += Fr::ONE;
+        assert!(!verifier.verify(&tampered));
+    }
+
+    #[test]
+    fn test_zero_knowledge_option() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        let options = ProofOptions::default().with_zk(true);
+        let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        assert!(
+            proof
+                .trace_openings
+                .iter()
+                .flatten()
+                .all(|opening| opening.salt.len() == SALT_LEN)
+        );
+        let verifier =
+            StarkVerifier::with_options(&constraints, trace.height as usize, options.clone());
+        assert!(verifier.verify(&proof));
+
+        // Salts are bound by the trace commitment
+        let mut tampered = proof;
+        tampered.trace_openings[0][0].salt[0] ^= 1;
+        assert!(!verifier.verify(&tampered));
+
+        // Without salts the leaves are plain row hashes
+        let plain = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(plain.trace_openings[0][0].salt.is_empty());
+    }
+
+    #[test]
+    fn test_masked_quotient() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        let options = ProofOptions::default().with_zk(true);
+        let prover = StarkProver::with_options(&trace, &constraints, options.clone());
+        let proof = prover.generate_proof().unwrap();
+        // The mask is committed as the last column of the trace rows
+        assert_eq!(proof.trace_openings[0][0].row.len(), 2);
+        assert_eq!(proof.ood_frame.current.len(), 2);
+        let verifier = StarkVerifier::with_options(&constraints, trace.height as usize, options);
+        assert!(verifier.verify(&proof));
+
+        // The mask randomizes the committed quotient of the same witness
+        let other = prover.generate_proof().unwrap();
+        assert_ne!(proof.quotient_commitment, other.quotient_commitment);
+    }
+
+    #[test]
+    fn test_private_boundary_value() {
+        let build_trace = |output: u64| {
+            let mut trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+            trace
+                .add_constant_column("output".to_string(), output)
+                .unwrap();
+            trace
+        };
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+        constraints.add_private_boundary_constraint(
+            "halts_at_output".to_string(),
+            "x".to_string(),
+            7,
+            "output".to_string(),
+        );
+
+        // The output column is blinded, so its openings do not reveal the value
+        let trace = build_trace(7);
+        let options = ProofOptions::default().with_zk(true);
+        let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        let output = proof
+            .trace_variables
+            .iter()
+            .position(|variable| variable == "output")
+            .unwrap();
+        assert!(
+            proof
+                .trace_openings
+                .iter()
+                .flatten()
+                .all(|opening| opening.row[output] != Fr::from(7u64))
+        );
+        assert_ne!(proof.ood_frame.current[output], Fr::from(7u64));
+        let verifier =
+            StarkVerifier::with_options(&constraints, trace.height as usize, options.clone());
+        assert!(verifier.verify(&proof));
+
+        // A different committed output does not match the halting state
+        let wrong = build_trace(6);
+        let proof = StarkProver::with_options(&wrong, &constraints, options)
+            .generate_proof()
+            .unwrap();
+        assert!(!verifier.verify(&proof));
+
+        // Without blinding the value would leak, so the verifier refuses
+        let unblinded = StarkVerifier::new(&constraints, trace.height as usize);
+        assert!(!unblinded.verify(&proof));
+    }
+
+    #[test]
+    fn test_cancelling_constraints() {
+        // Violates both constraints
+        let trace = ExecutionTrace::from_fn(["x"], 4, |i| [i * 2]).unwrap();
+
+        // The two violations are exact opposites and cancel in a plain sum
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+        constraints.add_transition_constraint(
+            "decrement".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_n - x_next + Fr::ONE
+            }),
+        );
+
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(!constraints.is_satisfied(&trace).unwrap());
+        assert!(
+            proof
+                .ood_frame
+                .quotient
+                .iter()
+                .any(|value| !value.is_zero())
+        );
+        let verifier = StarkVerifier::new(&constraints, trace.height as usize);
+        assert!(!verifier.verify(&proof));
+    }
+
+    #[test]
+    fn test_composition_chunks() {
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [i]).unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| {
+                let x_n = *current.get("x").unwrap();
+                let x_next = *next.get("x").unwrap();
+                x_next - x_n - Fr::ONE
+            }),
+        );
+
+        let options = ProofOptions::default().with_composition_chunks(4);
+        let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        assert_eq!(proof.ood_frame.quotient.len(), 4);
+        assert_eq!(proof.trace_openings[0][0].quotient.len(), 4);
+        let verifier = StarkVerifier::with_options(&constraints, trace.height as usize, options);
+        assert!(verifier.verify(&proof));
+
+        // The verifier must expect the same number of chunks
+        let single = StarkVerifier::new(&constraints, trace.height as usize);
+        assert!(!single.verify(&proof));
+    }
+
+    #[test]
+    fn test_field_element_cells() {
+        // x counts down through zero into negative values, k is wider than 64 bits
+        let wide = Fr::from(u128::MAX).square();
+        let trace =
+            ExecutionTrace::from_fn(["x", "k"], 16, |i| [Fr::from(3u64) - Fr::from(i), wide])
+                .unwrap();
+        assert_eq!(trace.get(15, "x").unwrap(), -Fr::from(12u64));
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "decrement".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| next["x"] - current["x"] + Fr::ONE),
+        );
+        constraints.add_transition_constraint(
+            "constant".to_string(),
+            vec!["k".to_string()],
+            Box::new(|current, next| next["k"] - current["k"]),
+        );
+        constraints.add_boundary_assertion(
+            "end".to_string(),
+            "x".to_string(),
+            15,
+            -Fr::from(12u64),
+        );
+        constraints.add_boundary_assertion("key".to_string(), "k".to_string(), 0, wide);
+        assert!(constraints.is_satisfied(&trace).unwrap());
+
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(StarkVerifier::new(&constraints, 16).verify(&proof));
+
+        // A key reduced to 64 bits no longer satisfies the assertion
+        let truncated = ExecutionTrace::from_fn(["x", "k"], 16, |i| {
+            [Fr::from(3u64) - Fr::from(i), Fr::from(u64::MAX)]
+        })
+        .unwrap();
+        let proof = StarkProver::new(&truncated, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(!StarkVerifier::new(&constraints, 16).verify(&proof));
+    }
+
+    #[test]
+    fn test_alternative_field() {
+        let trace = ExecutionTrace::from_fn(["x"], 16, |i| [3 * i]).unwrap();
+
+        let mut constraints = ConstraintSystem::<Goldilocks>::new();
+        constraints.add_transition_constraint(
+            "step".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| next["x"] - current["x"] - Goldilocks::from(3u64)),
+        );
+        constraints.add_boundary_assertion(
+            "start".to_string(),
+            "x".to_string(),
+            0,
+            Goldilocks::ZERO,
+        );
+
+        let options = ProofOptions::<Goldilocks>::new().with_fri_queries(16);
+        let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        let verifier = StarkVerifier::with_options(&constraints, trace.height as usize, options);
+        assert!(verifier.verify(&proof));
+        assert_eq!(proof.security_level().conjectured, 16);
+
+        // Field elements are encoded with the 8 bytes of the Goldilocks modulus
+        let bytes = proof.to_bytes();
+        let decoded = StarkProof::<Goldilocks>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert!(verifier.verify(&decoded));
+        let width = proof.ood_frame.current.len();
+        let estimate = proof.options.estimate_proof_size(16, width) + "x".len();
+        assert!(bytes.len() <= estimate);
+        assert!(estimate - bytes.len() <= 8 * proof.options.fri_remainder_size);
+
+        let wrong_trace = ExecutionTrace::from_fn(["x"], 16, |i| [2 * i]).unwrap();
+        let proof = StarkProver::new(&wrong_trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(!StarkVerifier::new(&constraints, 16).verify(&proof));
+    }
+
+    #[test]
+    fn test_extension_field_challenges() {
+        let trace_of = |step: u64| ExecutionTrace::from_fn(["x"], 16, |i| [step * i]).unwrap();
+        let trace = trace_of(3);
+
+        let mut constraints = ConstraintSystem::<GoldilocksExt2>::new();
+        constraints.add_transition_constraint(
+            "step".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| next["x"] - current["x"] - GoldilocksExt2::from(3u64)),
+        );
+        constraints.add_boundary_assertion(
+            "start".to_string(),
+            "x".to_string(),
+            0,
+            GoldilocksExt2::ZERO,
+        );
+
+        let options = ProofOptions::<GoldilocksExt2>::new().with_fri_queries(16);
+        let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        let verifier = StarkVerifier::with_options(&constraints, 16, options.clone());
+        assert!(verifier.verify(&proof));
+
+        // The trace is opened over Goldilocks, everything drawn after it over the extension
+        let row: &[Goldilocks] = &proof.trace_openings[0][0].row;
+        assert_eq!(row.len(), 1);
+        let bytes = proof.to_bytes();
+        let decoded = StarkProof::<GoldilocksExt2>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert!(verifier.verify(&decoded));
+        let estimate = options.estimate_proof_size(16, 1) + "x".len();
+        assert!(bytes.len() <= estimate);
+        assert!(estimate - bytes.len() <= 16 * options.fri_remainder_size);
+
+        // The mask takes one column per coefficient of the extension
+        let zk_options = options.clone().with_zk(true);
+        for strategy in [ProvingStrategy::InMemory, ProvingStrategy::Streaming] {
+            let proof = StarkProver::with_options(&trace, &constraints, zk_options.clone())
+                .with_strategy(strategy)
+                .generate_proof()
+                .unwrap();
+            assert_eq!(proof.ood_frame.current.len(), 3);
+            assert!(
+                StarkVerifier::with_options(&constraints, 16, zk_options.clone()).verify(&proof)
+            );
+        }
+
+        let proof = StarkProver::with_options(&trace_of(2), &constraints, options.clone())
+            .generate_proof()
+            .unwrap();
+        assert!(!verifier.verify(&proof));
+    }
+
+    #[test]
+    fn test_extension_challenges_over_fr() {
+        let trace_of = |step: u64| ExecutionTrace::from_fn(["x"], 8, |i| [step * i]).unwrap();
+
+        let mut constraints = ConstraintSystem::<FrExt2>::new();
+        constraints.add_transition_constraint(
+            "step".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| next["x"] - current["x"] - FrExt2::from(5u64)),
+        );
+        constraints.add_boundary_assertion("start".to_string(), "x".to_string(), 0, FrExt2::ZERO);
+
+        let proof = StarkProver::new(&trace_of(5), &constraints)
+            .generate_proof()
+            .unwrap();
+        let verifier = StarkVerifier::new(&constraints, 8);
+        assert!(verifier.verify(&proof));
+
+        // Rows are opened over Fr, while the out-of-domain point lies outside it
+        let row: &[Fr] = &proof.trace_openings[0][0].row;
+        assert_eq!(row.len(), 1);
+        assert!(proof.ood_frame.current[0].c1 != Fr::ZERO);
+        let decoded = StarkProof::<FrExt2>::from_bytes(&proof.to_bytes()).unwrap();
+        assert!(verifier.verify(&decoded));
+
+        let proof = StarkProver::new(&trace_of(4), &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(!verifier.verify(&proof));
+    }
+
+    #[test]
+    fn test_padded_trace() {
+        // x counts up while the selector s is set, then stays on the last row
+        let mut trace = ExecutionTrace::new(5, 2);
+        for i in 0..5u64 {
+            let row = HashMap::from([("x".to_string(), i), ("s".to_string(), u64::from(i < 4))]);
+            trace.insert_column(row).unwrap();
+        }
+        let last_row = trace.last_row().unwrap();
+
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "count".to_string(),
+            vec!["x".to_string(), "s".to_string()],
+            Box::new(|current, next| next["x"] - current["x"] - current["s"]),
+        );
+        constraints.add_boundary_assertion(
+            "end".to_string(),
+            "x".to_string(),
+            last_row,
+            Fr::from(4u64),
+        );
+
+        // The prover pads to eight rows by repeating the last one, which the constraints
+        // accept, and the boundary constraint still applies to the last real row
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert_eq!(trace.padded_height(), 8);
+        let verifier = StarkVerifier::new(&constraints, trace.padded_height() as usize);
+        assert!(verifier.verify(&proof));
+
+        // Dummy rows that break the transition constraints are caught
+        let dummy = HashMap::from([("x".to_string(), Fr::ZERO), ("s".to_string(), Fr::ZERO)]);
+        let proof = StarkProver::new(&trace, &constraints)
+            .with_padding(PaddingStrategy::DummyRow(dummy))
+            .generate_proof()
+            .unwrap();
+        assert!(!verifier.verify(&proof));
+    }
+
+    #[test]
+    fn test_relative_boundary_prove() {
+        // Five rows counting up to 4, padded to eight by repeating the last one
+        let trace = ExecutionTrace::from_fn(["x", "s"], 5, |i| [i, u64::from(i < 4)]).unwrap();
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "count".to_string(),
+            vec!["x".to_string(), "s".to_string()],
+            Box::new(|current, next| next["x"] - current["x"] - current["s"]),
+        );
+        constraints.assert_first("x", Fr::ZERO);
+        // Rows 7 and 4 of the padded trace
+        constraints.assert_last("x", Fr::from(4u64));
+        constraints.assert_at(BoundaryPosition::FromEnd(3), "x", Fr::from(4u64));
+
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert_eq!(trace.padded_height(), 8);
+        assert!(StarkVerifier::new(&constraints, 8).verify(&proof));
+
+        // A position before the first row is rejected rather than wrapped around
+        let mut constraints = ConstraintSystem::default();
+        constraints.assert_at(BoundaryPosition::FromEnd(8), "x", Fr::ZERO);
+        assert!(matches!(
+            StarkProver::new(&trace, &constraints).generate_proof(),
+            Err(ToyniError::InvalidOptions(_))
+        ));
+    }
+
+    /// Counts from `start`, generating each fragment on demand.
+    struct Counter {
+        start: u64,
+        length: usize,
+    }
+
+    impl TraceTable for Counter {
+        fn variables(&self) -> Vec<String> {
+            vec!["x".to_string()]
+        }
+
+        fn length(&self) -> usize {
+            self.length
+        }
+
+        fn fill(&self, fragment: &mut TraceFragment<'_, Fr>) -> Result<(), ToyniError> {
+            let offset = fragment.offset() as u64;
+            for (i, value) in fragment.column_mut(0).iter_mut().enumerate() {
+                *value = Fr::from(self.start + offset + i as u64);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trace_table() {
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "increment".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| next["x"] - current["x"] - Fr::ONE),
+        );
+        constraints.add_boundary_assertion("start".to_string(), "x".to_string(), 0, Fr::from(3u64));
+
+        let table = Counter {
+            start: 3,
+            length: 2048,
+        };
+        let proof = StarkProver::new(&table, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert_eq!(proof.trace_variables, vec!["x"]);
+        let verifier = StarkVerifier::new(&constraints, 2048);
+        assert!(verifier.verify(&proof));
+
+        let table = Counter {
+            start: 4,
+            length: 2048,
+        };
+        let proof = StarkProver::new(&table, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(!verifier.verify(&proof));
+    }
+
+    /// Running product `z` of `(γ - a) / (γ - b)`, which returns to one on the last row
+    /// only if `b` permutes `a`.
+    struct RunningProduct;
+
+    impl AuxiliaryTrace for RunningProduct {
+        fn build(
+            &self,
+            main: &MainColumns<'_, Fr>,
+            challenges: &HashMap<String, Fr>,
+        ) -> Result<HashMap<String, Vec<Fr>>, ToyniError> {
+            let gamma = challenges["gamma"];
+            let (a, b) = (main.column("a")?, main.column("b")?);
+            let mut z = vec![Fr::ONE; main.num_rows()];
+            for i in 1..z.len() {
+                z[i] = z[i - 1] * (gamma - a[i - 1]) / (gamma - b[i - 1]);
+            }
+            Ok(HashMap::from([("z".to_string(), z)]))
+        }
+    }
+
+    #[test]
+    fn test_auxiliary_permutation_argument() {
+        let mut constraints = ConstraintSystem::default();
+        constraints.set_auxiliary_segment(vec!["z".to_string()], vec!["gamma".to_string()]);
+        constraints.add_transition_constraint_with_degree(
+            "running product".to_string(),
+            vec!["a".to_string(), "b".to_string(), "z".to_string()],
+            2,
+            Box::new(|current, next| {
+                let gamma = current["gamma"];
+                next["z"] * (gamma - current["b"]) - current["z"] * (gamma - current["a"])
+            }),
+        );
+        constraints.add_boundary_assertion("z first".to_string(), "z".to_string(), 0, Fr::ONE);
+        constraints.add_boundary_assertion("z last".to_string(), "z".to_string(), 7, Fr::ONE);
+        let verifier = StarkVerifier::new(&constraints, 8);
+
+        let prove = |b: [u64; 8]| {
+            let a = [3u64, 1, 4, 1, 5, 9, 2, 0];
+            let trace = TraceBuilder::<2>::new(["a", "b"])
+                .rows(a.into_iter().zip(b).map(|(a, b)| [a, b]))
+                .build()
+                .unwrap();
+            StarkProver::builder()
+                .trace(&trace)
+                .constraints(&constraints)
+                .auxiliary_trace(&RunningProduct)
+                .build()
+                .unwrap()
+                .generate_proof()
+                .unwrap()
+        };
+
+        let proof = prove([1, 1, 2, 3, 4, 5, 9, 0]);
+        assert_eq!(
+            proof.auxiliary_commitment.len(),
+            proof.trace_commitment.len()
+        );
+        assert!(verifier.verify(&proof));
+        let decoded = StarkProof::<Fr>::from_bytes(&proof.to_bytes()).unwrap();
+        assert!(verifier.verify(&decoded));
+        let estimate =
+            proof.options.estimate_proof_size_with_auxiliary(8, 2, 1) + "a".len() + "b".len();
+        assert_eq!(proof.to_bytes().len(), estimate);
+
+        // The product only returns to one if b permutes a
+        let proof = prove([1, 1, 2, 3, 4, 5, 8, 0]);
+        assert!(!verifier.verify(&proof));
+
+        // Proving requires a builder for the declared segment
+        let trace = TraceBuilder::<2>::new(["a", "b"])
+            .rows([[0u64, 0]; 8])
+            .build()
+            .unwrap();
+        assert!(matches!(
+            StarkProver::new(&trace, &constraints).generate_proof(),
+            Err(ToyniError::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn test_challenge_expressions_prove() {
+        // The running product of test_auxiliary_permutation_argument as an expression
+        let (a, b, z) = (col("a"), col("b"), col("z"));
+        let gamma = || challenge("gamma");
+        let mut constraints = ConstraintSystem::default();
+        constraints.set_auxiliary_segment(vec!["z".to_string()], vec!["gamma".to_string()]);
+        constraints.transition(z.next() * (gamma() - b.cur()) - z.cur() * (gamma() - a.cur()));
+        constraints.assert_first("z", Fr::ONE);
+        constraints.assert_last("z", Fr::ONE);
+        // The challenge does not count towards the degree
+        assert_eq!(constraints.transition_constraints[0].degree, 2);
+
+        let a = [3u64, 1, 4, 1, 5, 9, 2, 0];
+        let b = [1u64, 1, 2, 3, 4, 5, 9, 0];
+        let trace = TraceBuilder::<2>::new(["a", "b"])
+            .rows(a.into_iter().zip(b).map(|(a, b)| [a, b]))
+            .build()
+            .unwrap();
+        let proof = StarkProver::new(&trace, &constraints)
+            .with_auxiliary_trace(&RunningProduct)
+            .generate_proof()
+            .unwrap();
+        // The challenge survives the encoding of the constraint system
+        let decoded = ConstraintSystem::from_bytes(&constraints.to_bytes().unwrap()).unwrap();
+        assert!(StarkVerifier::new(&decoded, 8).verify(&proof));
+
+        // A challenge the segment does not declare is rejected
+        let mut undeclared = ConstraintSystem::default();
+        undeclared.transition(col("a").next() - challenge("delta"));
+        assert!(matches!(
+            StarkProver::new(&trace, &undeclared).generate_proof(),
+            Err(ToyniError::InvalidOptions(_))
+        ));
+
+        // So is a challenge declared without auxiliary columns, which is never drawn
+        let mut without_segment = ConstraintSystem::default();
+        without_segment.set_auxiliary_segment(vec![], vec!["alpha".to_string()]);
+        without_segment.transition(challenge("alpha") * col("a").cur() - col("a").cur());
+        assert!(matches!(
+            StarkProver::new(&trace, &without_segment).generate_proof(),
+            Err(ToyniError::InvalidOptions(reason)) if reason.contains("challenge alpha")
+        ));
+        let verifier = StarkVerifier::new(&without_segment, 8);
+        assert!(matches!(
+            verifier.verify_with_report(&proof).failure,
+            Some(VerificationFailure::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn test_permutation_argument_prove() {
+        // Memory accesses (address, value) in execution order and sorted by address
+        let accesses = [
+            [3u64, 30],
+            [1, 10],
+            [2, 20],
+            [1, 11],
+            [3, 31],
+            [2, 21],
+            [1, 12],
+        ];
+        let prove = |sorted: &[[u64; 2]]| {
+            let mut constraints = ConstraintSystem::default();
+            let argument = constraints
+                .add_permutation_argument(
+                    "memory".to_string(),
+                    vec!["addr".to_string(), "val".to_string()],
+                    vec!["sorted_addr".to_string(), "sorted_val".to_string()],
+                )
+                .unwrap();
+            // Both sets end on the same row, which padding repeats
+            let rows = accesses
+                .iter()
+                .zip(sorted)
+                .map(|(access, sorted)| [access[0], access[1], sorted[0], sorted[1]])
+                .chain([[0u64; 4]]);
+            let trace = TraceBuilder::<4>::new(["addr", "val", "sorted_addr", "sorted_val"])
+                .rows(rows)
+                .build()
+                .unwrap();
+            let proof = StarkProver::new(&trace, &constraints)
+                .with_auxiliary_trace(&argument)
+                .generate_proof()
+                .unwrap();
+            StarkVerifier::new(&constraints, 8).verify(&proof)
+        };
+
+        let mut sorted = accesses;
+        sorted.sort();
+        assert!(prove(&sorted));
+        // Moving a value to another address breaks the permutation
+        sorted[0][0] = 2;
+        assert!(!prove(&sorted));
+    }
+
+    #[test]
+    fn test_lookup_argument_prove() {
+        let table: Vec<Fr> = (0..8u64).map(Fr::from).collect();
+        let mut constraints = ConstraintSystem::default();
+        let argument = constraints
+            .add_lookup_argument("range".to_string(), "x".to_string(), table)
+            .unwrap();
+        // The single-row lookup constraint has degree 3
+        let options = ProofOptions::new().with_blowup_factor(4);
+        let verifier = StarkVerifier::with_options(&constraints, 16, options.clone());
+        let prove = |values: &[u64], multiplicities: &[u64]| {
+            let trace = ExecutionTrace::from_fn(["x", "range/multiplicity"], 16, |i| {
+                [values[i as usize], multiplicities[i as usize]]
+            })
+            .unwrap();
+            let proof = StarkProver::with_options(&trace, &constraints, options.clone())
+                .with_auxiliary_trace(&argument)
+                .generate_proof()
+                .unwrap();
+            verifier.verify(&proof)
+        };
+
+        let values = [5u64, 3, 3, 0, 7, 7, 7, 1, 2, 6, 4, 4, 5, 0, 3, 7];
+        let multiplicities = argument.multiplicities(&values.map(Fr::from)).unwrap();
+        assert!(prove(&values, &multiplicities));
+
+        // A value outside the table cannot be accounted for by any multiplicities
+        let mut outside = values;
+        outside[4] = 8;
+        assert!(!prove(&outside, &multiplicities));
+    }
+
+    #[test]
+    fn test_range_check_prove() {
+        let mut constraints = ConstraintSystem::default();
+        let check = constraints.range_check("x", 32).unwrap();
+        assert!(constraints.range_check("y", 255).is_err());
+        let verifier = StarkVerifier::new(&constraints, 8);
+
+        let values = [0u64, 1, 7, 65_535, 1 << 31, u32::MAX as u64, 12_345, 42];
+        let mut trace = ExecutionTrace::new(8, 33);
+        for value in values {
+            let mut row = check.decompose(value).unwrap();
+            row.insert("x".to_string(), value);
+            trace.insert_column(row).unwrap();
+        }
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(verifier.verify(&proof));
+
+        // 2^32 has no 32-bit decomposition, and its lower bits fail the recomposition
+        assert!(check.decompose(1 << 32).is_err());
+        let mut trace = ExecutionTrace::new(8, 33);
+        for (i, value) in values.into_iter().enumerate() {
+            let mut row = check.decompose(value).unwrap();
+            row.insert("x".to_string(), if i == 0 { 1 << 32 } else { value });
+            trace.insert_column(row).unwrap();
+        }
+        assert!(!constraints.is_satisfied(&trace).unwrap());
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(!verifier.verify(&proof));
+    }
+
+    /// A clock counting up by one
+    struct Clock;
+
+    impl Chiplet for Clock {
+        fn name(&self) -> String {
+            "clock".to_string()
+        }
+
+        fn columns(&self) -> Vec<String> {
+            vec!["clk".to_string()]
+        }
+
+        fn add_constraints(&self, constraints: &mut ConstraintSystem) {
+            constraints.add_transition_constraint(
+                "tick".to_string(),
+                self.columns(),
+                Box::new(|current, next| next["clk"] - current["clk"] - Fr::ONE),
+            );
+            constraints.assert_first("clk", Fr::ZERO);
+        }
+    }
+
+    /// Memory accesses and the same accesses sorted by address
+    struct Memory;
+
+    impl Memory {
+        fn argument(&self) -> PermutationArgument {
+            PermutationArgument::new(
+                "sorted".to_string(),
+                vec!["addr".to_string(), "val".to_string()],
+                vec!["sorted_addr".to_string(), "sorted_val".to_string()],
+            )
+            .unwrap()
+        }
+    }
+
+    impl Chiplet for Memory {
+        fn name(&self) -> String {
+            "memory".to_string()
+        }
+
+        fn columns(&self) -> Vec<String> {
+            ["addr", "val", "sorted_addr", "sorted_val"]
+                .map(String::from)
+                .to_vec()
+        }
+
+        fn add_constraints(&self, constraints: &mut ConstraintSystem) {
+            let argument = self.argument();
+            constraints
+                .add_permutation_argument(argument.name, argument.left, argument.right)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_chiplets_prove() {
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_chiplet(&Clock).unwrap();
+        constraints.add_chiplet(&Memory).unwrap();
+        assert_eq!(constraints.auxiliary_columns, ["memory/sorted/z"]);
+        assert!(matches!(
+            constraints.add_chiplet(&Memory),
+            Err(ToyniError::DuplicateColumn(_))
+        ));
+
+        let accesses = [
+            [3u64, 30],
+            [1, 10],
+            [2, 20],
+            [1, 11],
+            [3, 31],
+            [2, 21],
+            [1, 12],
+            [0, 0],
+        ];
+        let mut sorted = accesses;
+        sorted[..7].sort();
+        let columns = Clock
+            .trace_columns()
+            .into_iter()
+            .chain(Memory.trace_columns());
+        let mut trace = ExecutionTrace::new(8, 5);
+        for (clk, (access, sorted)) in accesses.iter().zip(&sorted).enumerate() {
+            let values = [clk as u64, access[0], access[1], sorted[0], sorted[1]];
+            trace
+                .insert_column(columns.clone().zip(values).collect::<HashMap<_, _>>())
+                .unwrap();
+        }
+
+        let argument = Memory.argument();
+        let auxiliary = Namespaced::new("memory", &argument);
+        let proof = StarkProver::new(&trace, &constraints)
+            .with_auxiliary_trace(&auxiliary)
+            .generate_proof()
+            .unwrap();
+        assert!(StarkVerifier::new(&constraints, 8).verify(&proof));
+    }
+
+    #[test]
+    fn test_periodic_column() {
+        let constants = [1u64, 2, 3, 4];
+        let constraints_with = |pattern: [u64; 4]| {
+            let mut constraints = ConstraintSystem::default();
+            constraints
+                .add_periodic_column("k".to_string(), pattern.map(Fr::from).to_vec())
+                .unwrap();
+            constraints.add_transition_constraint(
+                "add round constant".to_string(),
+                vec!["x".to_string(), "k".to_string()],
+                Box::new(|current, next| next["x"] - current["x"] - current["k"]),
+            );
+            constraints.add_boundary_assertion("start".to_string(), "x".to_string(), 0, Fr::ZERO);
+            constraints
+        };
+        let constraints = constraints_with(constants);
+        let trace_with = |pattern: [u64; 4]| {
+            let rows = (0..16usize).scan(0u64, |x, i| {
+                let row = [*x];
+                *x += pattern[i % 4];
+                Some(row)
+            });
+            TraceBuilder::<1>::new(["x"]).rows(rows).build().unwrap()
+        };
+
+        let trace = trace_with(constants);
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        // The periodic column is neither committed nor revealed
+        assert_eq!(
+            proof.ood_frame.current.len(),
+            1 + proof.options.num_mask_columns()
+        );
+        assert!(StarkVerifier::new(&constraints, 16).verify(&proof));
+
+        // Neither a trace following another pattern nor a verifier expecting one accepts
+        let trace = trace_with([1, 2, 3, 5]);
+        let forged = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(!StarkVerifier::new(&constraints, 16).verify(&forged));
+        assert!(!StarkVerifier::new(&constraints_with([1, 2, 3, 5]), 16).verify(&proof));
+
+        // The pattern must repeat within the trace
+        let mut constraints = constraints_with(constants);
+        constraints
+            .add_periodic_column("c".to_string(), vec![Fr::ONE; 32])
+            .unwrap();
+        let trace = trace_with(constants);
+        assert!(matches!(
+            StarkProver::new(&trace, &constraints).generate_proof(),
+            Err(ToyniError::InvalidOptions(reason)) if reason.contains("periodic column c")
+        ));
+        assert!(!StarkVerifier::new(&constraints, 16).verify(&proof));
+    }
+
+    #[test]
+    fn test_public_cells() {
+        // Doubles the input every step: the trace computes 3·2⁷ = 384 from 3
+        let trace = ExecutionTrace::from_fn(["x"], 8, |i| [3u64 << i]).unwrap();
+        let mut constraints = ConstraintSystem::default();
+        constraints.add_transition_constraint(
+            "double".to_string(),
+            vec!["x".to_string()],
+            Box::new(|current, next| next["x"] - current["x"].double()),
+        );
+        constraints.add_public_cell("input".to_string(), "x".to_string(), 0);
+        constraints.add_public_cell("output".to_string(), "x".to_string(), 7);
+
+        let proof = StarkProver::new(&trace, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert_eq!(proof.public_value("input"), Some(Fr::from(3u64)));
+        assert_eq!(proof.public_value("output"), Some(Fr::from(384u64)));
+        let verifier = StarkVerifier::new(&constraints, 8);
+        assert!(verifier.verify(&proof));
+
+        let decoded = StarkProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded.public_values, proof.public_values);
+        assert!(verifier.verify(&decoded));
+
+        // A claimed output the trace does not hold is rejected
+        let mut forged = StarkProof::from_bytes(&proof.to_bytes()).unwrap();
+        forged.public_values[1].1 = Fr::from(385u64);
+        assert!(!verifier.verify(&forged));
+
+        let mut missing = StarkProof::from_bytes(&proof.to_bytes()).unwrap();
+        missing.public_values.pop();
+        assert_eq!(
+            verifier.verify_with_report(&missing).failure,
+            Some(VerificationFailure::PublicValues)
+        );
+
+        // Renamed or repeated trace columns are rejected before any constraint reads them
+        let mut renamed = StarkProof::from_bytes(&proof.to_bytes()).unwrap();
+        renamed.trace_variables = vec!["y".to_string()];
+        assert!(matches!(
+            verifier.verify_with_report(&renamed).failure,
+            Some(VerificationFailure::TraceVariables(_))
+        ));
+        assert!(!verifier.verify(&renamed));
+        let mut repeated = StarkProof::from_bytes(&proof.to_bytes()).unwrap();
+        repeated.trace_variables = vec!["x".to_string(), "x".to_string()];
+        assert!(matches!(
+            verifier.verify_with_report(&repeated).failure,
+            Some(VerificationFailure::TraceVariables(_))
+        ));
+
+        // A proof for a trace that publishes another output does not verify as this one
+        let other = ExecutionTrace::from_fn(["x"], 8, |i| [5u64 << i]).unwrap();
+        let other = StarkProver::new(&other, &constraints)
+            .generate_proof()
+            .unwrap();
+        assert!(verifier.verify(&other));
+        assert_eq!(other.public_value("output"), Some(Fr::from(640u64)));
+    }
 }